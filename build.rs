@@ -0,0 +1,47 @@
+//! 构建脚本：记录 git 提交与 iroh 相关依赖的实际版本，供 `sendmer version` 输出。
+
+use std::process::Command;
+
+fn main() {
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=Cargo.lock");
+
+    if let Some(hash) = git_hash() {
+        println!("cargo:rustc-env=SENDMER_GIT_HASH={hash}");
+    }
+
+    let lock = std::fs::read_to_string("Cargo.lock").unwrap_or_default();
+    for (package, var) in [
+        ("iroh", "SENDMER_IROH_VERSION"),
+        ("iroh-blobs", "SENDMER_IROH_BLOBS_VERSION"),
+    ] {
+        if let Some(version) = locked_version(&lock, package) {
+            println!("cargo:rustc-env={var}={version}");
+        }
+    }
+}
+
+fn git_hash() -> Option<String> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--short=12", "HEAD"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let hash = String::from_utf8(output.stdout).ok()?.trim().to_string();
+    (!hash.is_empty()).then_some(hash)
+}
+
+/// 在 Cargo.lock 中查找 `package` 的版本。
+fn locked_version(lock: &str, package: &str) -> Option<String> {
+    let name_line = format!("name = \"{package}\"");
+    let mut lines = lock.lines();
+    while let Some(line) = lines.next() {
+        if line.trim() == name_line {
+            let version = lines.next()?.trim().strip_prefix("version = ")?;
+            return Some(version.trim_matches('"').to_string());
+        }
+    }
+    None
+}
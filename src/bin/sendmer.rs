@@ -6,17 +6,26 @@ use clap::error::{ContextKind, ErrorKind};
 use clap::{CommandFactory, Parser};
 use console::style;
 use data_encoding::HEXLOWER;
-use indicatif::HumanBytes;
+use indicatif::{HumanBytes, HumanDuration};
 use n0_future::StreamExt;
 use sendmer::core::args::{
-    Args, Commands, CommonArgs, ReceiveArgs, SendArgs, get_or_create_secret, print_hash,
+    Args, Commands, CommonArgs, Format, HashCommand, ListArgs, ManifestFormat, ReceiveArgs,
+    ReportFormat, SendArgs, ServeArgs, TraceCommand, VersionArgs, cid_string, get_or_create_secret,
+    print_hash,
 };
-use sendmer::core::cli_helper::CliEventEmitter;
-use sendmer::core::results::SenderTransferStatus;
-use sendmer::core::{receiver, sender};
+use sendmer::core::cli_helper::{CliEventEmitter, JsonEventEmitter, render_qr};
+use sendmer::core::code::{self, ShareCode};
+use sendmer::core::error::remediation_hint;
+use sendmer::core::event_rules::{EventRule, RuleEventEmitter};
+use sendmer::core::events::{TransferWarning, WarningCode};
+use sendmer::core::options::{ReceiveRetryPolicy, SymlinkPolicy};
+use sendmer::core::results::{CollectionSummary, SenderTransferStatus, ShareStats, VerifiedEntry};
+use sendmer::core::trace;
+use sendmer::core::{auth, keychain, receiver, sender, serve};
 use sendmer::{AppHandle, ReceiveOptions, SendOptions};
 use std::io::IsTerminal;
 use std::sync::Arc;
+use std::time::Duration;
 
 #[tokio::main]
 async fn main() {
@@ -24,6 +33,9 @@ async fn main() {
 
     if let Err(e) = &res {
         eprintln!("{e}");
+        if let Some(hint) = remediation_hint(e) {
+            eprintln!("hint: {hint}");
+        }
     }
 
     match res {
@@ -52,13 +64,51 @@ pub async fn run() -> anyhow::Result<()> {
         )
     });
 
-    init_tracing(common_args(&args.command).verbose)?;
-    maybe_show_secret(common_args(&args.command))?;
+    let common = common_args(&args.command);
+    init_tracing(common.map_or(0, |common| common.verbose))?;
+    if let Some(common) = common {
+        maybe_show_secret(common)?;
+    }
 
     match args.command {
         Commands::Send(args) => send(args).await,
         Commands::Receive(args) => receive(args).await,
+        Commands::Serve(args) => serve(args).await,
+        Commands::List(args) => list(args).await,
+        Commands::Trace(command) => run_trace(command),
+        Commands::Hash(command) => {
+            run_hash(command);
+            Ok(())
+        }
+        Commands::Version(args) => run_version(&args),
+        #[cfg(feature = "relay-server")]
+        Commands::Relay(args) => run_relay(args).await,
+    }
+}
+
+fn run_version(args: &VersionArgs) -> anyhow::Result<()> {
+    let info = sendmer::core::build_info::BuildInfo::current();
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&info)?);
+    } else {
+        print!("{info}");
     }
+    Ok(())
+}
+
+/// 运行内置 relay 直到收到 Ctrl+C。
+#[cfg(feature = "relay-server")]
+async fn run_relay(args: sendmer::core::args::RelayArgs) -> anyhow::Result<()> {
+    let token = args.token.unwrap_or_else(auth::generate_password);
+    let server = sendmer::core::relay_server::spawn_relay(args.bind, token).await?;
+    println!("relay listening on http://{}", server.addr());
+    println!(
+        "point send/receive at it with --relay {}",
+        server.relay_url("<this-host>")
+    );
+    tokio::signal::ctrl_c().await?;
+    server.shutdown().await?;
+    Ok(())
 }
 
 /// CLI wrapper: call library `start_share` and show minimal output.
@@ -70,24 +120,109 @@ pub async fn run() -> anyhow::Result<()> {
 /// 该函数主要用于命令行程序，不作为库 API 的一部分使用。
 async fn send(args: SendArgs) -> anyhow::Result<()> {
     let opts = send_options(&args);
-    let app_handle = cli_app_handle("[send]", args.common.no_progress);
+    if args.dry_run {
+        anyhow::ensure!(!reads_stdin(&args.paths)?, "--dry-run cannot read stdin");
+        let summary = sender::compute_collection(args.paths.clone(), &opts).await?;
+        if let Some(format) = args.manifest {
+            print_manifest(&summary, format)?;
+            return Ok(());
+        }
+        print_summary(&summary, args.common.format);
+        return Ok(());
+    }
+    let password = opts.password.clone();
+    let code = opts.code.clone();
+    let app_handle = cli_app_handle("[send]", &args.common, args.json)?;
+    let out = CliOutput {
+        ticket_only: args.ticket_only,
+        json: args.json,
+    };
 
-    let res = sender::send(args.path.clone(), opts, app_handle).await?;
+    let res = if reads_stdin(&args.paths)? {
+        let name = args.name.clone().unwrap_or_else(|| "stdin".to_string());
+        sender::send_stdin(name, opts, app_handle).await?
+    } else {
+        sender::send_paths(args.paths.clone(), opts, app_handle).await?
+    };
 
-    println!(
-        "imported {} {}, {}, hash {}",
-        res.entry_type,
-        args.path.display(),
-        HumanBytes(res.size),
-        print_hash(&res.hash, args.common.format)
-    );
+    let paths = args
+        .paths
+        .iter()
+        .map(|path| path.display().to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    out.line(format_args!(
+        "imported {} {paths}, {}, hash {}",
+        res.entry_type(),
+        HumanBytes(res.size()),
+        print_hash(&res.hash(), args.common.format)
+    ));
 
-    println!("to get this data, use");
-    println!("sendmer receive {}", res.ticket);
+    print_warnings(res.warnings());
+    if let Some(format) = args.manifest {
+        print_manifest(&res.manifest(), format)?;
+    }
+    if let Some(password) = &password {
+        out.line(format_args!(
+            "password: {password}, receivers need `--password`"
+        ));
+    }
+    let ticket = res.ticket();
+    #[cfg(feature = "clipboard")]
+    let mut clipboard = None;
+    if let Some(name) = &args.keychain {
+        if let Err(error) = keychain::store_ticket(name, &ticket.to_string()) {
+            if let Err(shutdown_error) = res.stop().await {
+                tracing::warn!(error = %shutdown_error, "failed to shutdown sender");
+            }
+            return Err(error);
+        }
+        out.line(format_args!(
+            "ticket stored in the keychain as {name:?}, to get this data, use"
+        ));
+        out.line(format_args!("sendmer receive --keychain {name}"));
+    } else {
+        if out.ticket_only {
+            println!("{ticket}");
+        } else {
+            out.line("to get this data, use");
+            out.line(format_args!("sendmer receive {ticket}"));
+        }
+        if let Some(code) = &code {
+            out.line("or, with the short code");
+            out.line(format_args!("sendmer receive --code {code}"));
+            out.line(format_args!(
+                "and check that the receiver sees fingerprint {}",
+                code::fingerprint(ticket.ticket.addr().id, ticket.ticket.hash())
+            ));
+        }
+        if args.qr {
+            out.line(render_qr(&format!("sendmer receive {ticket}"))?);
+        }
+        #[cfg(feature = "clipboard")]
+        // 按键提示写到 stdout，`--json` 时会混入 NDJSON，因此不监听按键
+        if is_interactive(&args.common) && !out.ticket_only && !out.json {
+            clipboard = Some(handle_key_press(
+                args.clipboard,
+                ticket.to_string(),
+                args.clipboard_ttl,
+            ));
+        } else if args.clipboard {
+            tracing::warn!("--clipboard is ignored with --json or in non-interactive mode");
+        }
+    }
+    let ticket_printer = spawn_ticket_printer(&res, args.keychain.clone(), code.is_some(), out);
+    let wait_result = wait_for_send_shutdown(&res, out).await;
+    ticket_printer.abort();
+    print_share_stats(&res.stats(), out);
+    let shutdown_result = res.stop().await;
     #[cfg(feature = "clipboard")]
-    maybe_handle_key_press(args.clipboard, res.ticket.to_string());
-    let wait_result = wait_for_send_shutdown(&res).await;
-    let shutdown_result = res.shutdown().await;
+    if let Some(clipboard) = clipboard {
+        clipboard
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .clear_pending();
+    }
     match (wait_result, shutdown_result) {
         (Err(error), Err(shutdown_error)) => {
             tracing::warn!(error = %shutdown_error, "failed to shutdown sender after wait error");
@@ -98,25 +233,376 @@ async fn send(args: SendArgs) -> anyhow::Result<()> {
     }
 }
 
+/// `send` / `receive` 的说明性输出；`--ticket-only` 时写到 stderr，stdout 只保留 ticket，
+/// `--json` 时同样写到 stderr，stdout 只保留 JSON 事件。
+#[derive(Debug, Clone, Copy)]
+struct CliOutput {
+    ticket_only: bool,
+    json: bool,
+}
+
+impl CliOutput {
+    fn line(self, line: impl std::fmt::Display) {
+        if self.ticket_only || self.json {
+            eprintln!("{line}");
+        } else {
+            println!("{line}");
+        }
+    }
+}
+
+/// `serve` 子命令：在同一个 endpoint 上托管多个分享，逐个打印 ticket，直到按下 Ctrl+C。
+async fn serve(args: ServeArgs) -> anyhow::Result<()> {
+    let opts = SendOptions {
+        relay_mode: args.common.relay.clone(),
+        ticket_type: args.ticket_type,
+        magic_ipv4_addr: args.common.magic_ipv4_addr,
+        magic_ipv6_addr: args.common.magic_ipv6_addr,
+        store_dir: Some(args.store_dir.clone()),
+        ..Default::default()
+    };
+    let handle = serve::serve(opts).await?;
+    println!("serving from {}", handle.endpoint_id());
+    for path in &args.paths {
+        let share = match handle.add_share(None, vec![path.clone()]).await {
+            Ok(share) => share,
+            Err(error) => {
+                if let Err(shutdown_error) = handle.shutdown().await {
+                    tracing::warn!(error = %shutdown_error, "failed to shutdown serve endpoint");
+                }
+                return Err(error);
+            }
+        };
+        println!(
+            "{}: {}, hash {}",
+            share.name,
+            HumanBytes(share.size),
+            print_hash(&share.hash, args.common.format)
+        );
+        println!("sendmer receive {}", share.ticket);
+        print_warnings(&share.warnings);
+    }
+    tokio::signal::ctrl_c().await?;
+    handle.shutdown().await
+}
+
 /// CLI wrapper: call library `download` and print the result message.
 ///
 /// 与 `send` 类似，`receive` 在命令行模式下决定是否创建 `CliEventEmitter`，
 /// 调用 `download` 并将结果消息输出到 stdout。
 async fn receive(args: ReceiveArgs) -> anyhow::Result<()> {
-    let opts = receive_options(args.output_dir.clone(), &args.common);
-    let app_handle = cli_app_handle("[recv]", args.common.no_progress);
+    let opts = ReceiveOptions {
+        mirror_dirs: args.mirror.clone(),
+        into_dir: args.into.clone(),
+        extra_tickets: args.extra_tickets.clone(),
+        routes: args.route.clone(),
+        route_dry_run: args.route_dry_run,
+        dns_discovery: args.dns_discovery,
+        dns_origin: args.dns_origin.clone(),
+        static_addrs: args.resolve.clone(),
+        sandboxed_export: args.sandbox,
+        first: args.first.clone(),
+        password: receive_password(args.password.clone())?,
+        stdout: args.stdout,
+        rate_limit: args.limit_rate,
+        retry_policy: ReceiveRetryPolicy {
+            size_fetch_retry_limit: args.retries,
+            backoff: args.retry_backoff,
+            deadline: args.retry_deadline,
+            connect_timeout: args.connect_timeout,
+            stall_timeout: args.stall_timeout,
+            repair_limit: args.repair_attempts,
+            ..ReceiveRetryPolicy::default()
+        },
+        verify_only: args.verify_only,
+        verify_export: args.report.is_some(),
+        checksums: args.checksums,
+        flatten: args.flatten,
+        export_mode: args.export_mode,
+        skip_space_check: args.force,
+        resume: args.resume,
+        retry_failed: args.retry_failed,
+        keep_store: args.keep_store,
+        #[cfg(feature = "chaos")]
+        chaos: args.chaos,
+        ..receive_options(args.output_dir.clone(), &args.common)
+    };
+    let app_handle = cli_app_handle("[recv]", &args.common, args.json)?;
+
+    let ticket = match &args.code {
+        Some(code) => resolve_code(code, args.code_fingerprint.as_deref(), &args.common).await?,
+        None => args.resolve_ticket().await?,
+    };
+    let res = receiver::receive_ticket(ticket, opts, app_handle).await?;
+    if args.stdout {
+        eprintln!("{}", res.message);
+        print_warnings(&res.warnings);
+        return Ok(());
+    }
+    if args.verify_only {
+        print_warnings(&res.warnings);
+        return print_verification(&res.verified, &res.message, args.common.format);
+    }
+    if args.report == Some(ReportFormat::Json) {
+        // stdout 只输出 JSON 报告
+        eprintln!("{} in {:?}", res.message, res.file_path);
+        println!("{}", serde_json::to_string_pretty(&res.verified)?);
+        print_warnings(&res.warnings);
+        return ensure_verified(&res.verified);
+    }
+    let out = CliOutput {
+        ticket_only: false,
+        json: args.json,
+    };
+    out.line(format_args!("{} in {:?}", res.message, res.file_path));
+    if let Some(connection) = &res.connection {
+        out.line(format_args!(
+            "transferred via {} {} (rtt {:?})",
+            connection.via.as_str(),
+            connection.remote,
+            connection.rtt
+        ));
+    }
+    for route in &res.routed {
+        let verb = if args.route_dry_run {
+            "would move"
+        } else {
+            "moved"
+        };
+        out.line(format_args!(
+            "{verb} {} -> {}",
+            route.from.display(),
+            route.to.display()
+        ));
+    }
+    print_warnings(&res.warnings);
+    if args.report == Some(ReportFormat::Table) {
+        print_verified_entries(&res.verified, args.common.format);
+        ensure_verified(&res.verified)?;
+    }
+    Ok(())
+}
+
+/// CLI wrapper: 列出 ticket 中的文件而不下载内容。
+async fn list(args: ListArgs) -> anyhow::Result<()> {
+    let opts = ReceiveOptions {
+        password: receive_password(args.password.clone())?,
+        ..receive_options(None, &args.common)
+    };
+    let ticket = match &args.code {
+        Some(code) => resolve_code(code, args.code_fingerprint.as_deref(), &args.common).await?,
+        None => args
+            .ticket
+            .clone()
+            .ok_or_else(|| anyhow::anyhow!("either a ticket or --code is required"))?,
+    };
+    let summary = receiver::list(ticket, &opts).await?;
+    match args.manifest {
+        Some(format) => print_manifest(&summary, format)?,
+        None => print_summary(&summary, args.common.format),
+    }
+    Ok(())
+}
+
+/// 打印 `--verify-only` 的校验报告；有条目校验失败时返回错误。
+fn print_verification(
+    entries: &[VerifiedEntry],
+    message: &str,
+    format: Format,
+) -> anyhow::Result<()> {
+    print_verified_entries(entries, format);
+    println!("{message}");
+    ensure_verified(entries)
+}
+
+fn print_verified_entries(entries: &[VerifiedEntry], format: Format) {
+    for entry in entries {
+        println!(
+            "{}  {:>10}  {}  {}",
+            print_hash(&entry.hash, format),
+            HumanBytes(entry.size).to_string(),
+            if entry.verified { "OK    " } else { "FAILED" },
+            entry.name
+        );
+    }
+}
 
-    let res = receiver::receive(args.ticket.to_string(), opts, app_handle).await?;
-    println!("{} in {:?}", res.message, res.file_path);
+fn ensure_verified(entries: &[VerifiedEntry]) -> anyhow::Result<()> {
+    let failed = entries.iter().filter(|entry| !entry.verified).count();
+    anyhow::ensure!(failed == 0, "{failed} entries failed verification");
     Ok(())
 }
 
+fn print_warnings(warnings: &[TransferWarning]) {
+    for warning in warnings {
+        eprintln!("warning: {warning}");
+    }
+    let unreadable = warnings
+        .iter()
+        .filter(|warning| warning.code == WarningCode::UnreadableEntry)
+        .count();
+    if unreadable > 0 {
+        eprintln!("skipped {unreadable} unreadable entries");
+    }
+}
+
+fn run_trace(command: TraceCommand) -> anyhow::Result<()> {
+    match command {
+        TraceCommand::Analyze(args) => {
+            let records = trace::read_trace(&args.path)?;
+            let report = trace::analyze(&records, Duration::from_millis(args.stall_ms));
+            print!("{report}");
+            Ok(())
+        }
+    }
+}
+
+fn run_hash(command: HashCommand) {
+    match command {
+        HashCommand::Convert(args) => {
+            println!("hex {}", print_hash(&args.value, Format::Hex));
+            println!("cid {}", cid_string(&args.value));
+        }
+    }
+}
+
+/// `--password` 未带值时从终端读取口令。
+fn receive_password(password: Option<Option<String>>) -> anyhow::Result<Option<String>> {
+    match password {
+        None => Ok(None),
+        Some(Some(password)) => Ok(Some(password)),
+        Some(None) => {
+            eprint!("password: ");
+            std::io::Write::flush(&mut std::io::stderr())?;
+            let mut line = String::new();
+            std::io::stdin().read_line(&mut line)?;
+            let password = line.trim_end_matches(['\r', '\n']).to_string();
+            anyhow::ensure!(!password.is_empty(), "no password given");
+            Ok(Some(password))
+        }
+    }
+}
+
+/// 查找分享码并核对发送端指纹。
+///
+/// 给出 `--code-fingerprint` 时直接比较；否则在终端中显示指纹请用户确认，
+/// 非交互时报错，避免在无人核对的情况下下载到他人发布的内容。
+async fn resolve_code(
+    code: &ShareCode,
+    expected: Option<&str>,
+    common: &CommonArgs,
+) -> anyhow::Result<sendmer::core::ticket::ShareTicket> {
+    let resolved = code.resolve().await?;
+    if let Some(expected) = expected {
+        return resolved.verify(expected);
+    }
+    anyhow::ensure!(
+        is_interactive(common),
+        "cannot confirm the sender of code {code} without a terminal, \
+         pass --code-fingerprint with the fingerprint the sender shows"
+    );
+    eprint!(
+        "sender fingerprint: {}, does it match the one the sender shows? [y/N] ",
+        resolved.fingerprint
+    );
+    std::io::Write::flush(&mut std::io::stderr())?;
+    let mut line = String::new();
+    std::io::stdin().read_line(&mut line)?;
+    anyhow::ensure!(
+        matches!(line.trim().to_ascii_lowercase().as_str(), "y" | "yes"),
+        "fingerprint not confirmed, the code may have been taken by someone else"
+    );
+    Ok(resolved.ticket)
+}
+
+fn print_share_stats(stats: &ShareStats, out: CliOutput) {
+    out.line(format_args!(
+        "served {} download(s) to {} peer(s), {} in {} ({}/s)",
+        stats.completed_downloads,
+        stats.unique_peers,
+        HumanBytes(stats.bytes_served),
+        HumanDuration(stats.duration),
+        HumanBytes(stats.average_throughput() as u64)
+    ));
+}
+
+/// 逐行打印集合条目（hash、大小、名称），最后打印集合 hash 与总大小。
+fn print_summary(summary: &CollectionSummary, format: Format) {
+    for entry in &summary.entries {
+        println!(
+            "{}  {:>10}  {}",
+            print_hash(&entry.hash, format),
+            HumanBytes(entry.size).to_string(),
+            entry.name
+        );
+    }
+    print_warnings(&summary.warnings);
+    println!(
+        "collection {}, {} entries, {}",
+        print_hash(&summary.hash, format),
+        summary.entries.len(),
+        HumanBytes(summary.size)
+    );
+}
+
+fn print_manifest(summary: &CollectionSummary, format: ManifestFormat) -> anyhow::Result<()> {
+    match format {
+        ManifestFormat::Json => println!("{}", serde_json::to_string_pretty(summary)?),
+    }
+    Ok(())
+}
+
+/// `-` 表示从标准输入读取，不能与其他路径混用。
+fn reads_stdin(paths: &[std::path::PathBuf]) -> anyhow::Result<bool> {
+    let stdin = paths.iter().any(|path| path.as_os_str() == "-");
+    anyhow::ensure!(
+        !stdin || paths.len() == 1,
+        "`-` (stdin) cannot be combined with other paths"
+    );
+    Ok(stdin)
+}
+
 fn send_options(args: &SendArgs) -> SendOptions {
+    let (ticket_type, dns_hint) = args.ticket_type.split();
     SendOptions {
         relay_mode: args.common.relay.clone(),
-        ticket_type: args.ticket_type,
+        ticket_type,
         magic_ipv4_addr: args.common.magic_ipv4_addr,
         magic_ipv6_addr: args.common.magic_ipv6_addr,
+        pkarr_publish: args.pkarr_publish,
+        refresh_ticket: args.refresh_ticket,
+        watch: args.watch,
+        keep_snapshots: args.snapshots,
+        compression: args.compress,
+        archive: args.archive,
+        preserve_metadata: args.preserve_metadata,
+        name: args.name.clone(),
+        dns_hint,
+        import_mode: args.import_mode,
+        verify_import: args.verify_import,
+        skip_unreadable: args.skip_unreadable || args.rsync_compat,
+        symlinks: if args.follow_links {
+            SymlinkPolicy::Follow
+        } else {
+            args.symlinks
+        },
+        one_file_system: args.one_file_system,
+        exclude: args.exclude.clone(),
+        gitignore: args.gitignore,
+        max_size: args.max_size,
+        hidden: (args.hidden || args.rsync_compat) && !args.no_hidden,
+        trace_path: args.common.trace.clone(),
+        history: history_path(&args.common),
+        store_dir: args.store_dir.clone(),
+        skip_published: args.skip_published,
+        temp_dir: args.temp_dir.clone(),
+        max_downloads: args.max_downloads,
+        expire: args.expire,
+        rate_limit: args.limit_rate,
+        allow: args.allow.clone(),
+        password: args.password.then(auth::generate_password),
+        code: args.code.then(ShareCode::generate),
+        memory_store: args.mem,
     }
 }
 
@@ -126,19 +612,47 @@ fn receive_options(output_dir: Option<std::path::PathBuf>, common: &CommonArgs)
         relay_mode: common.relay.clone(),
         magic_ipv4_addr: common.magic_ipv4_addr,
         magic_ipv6_addr: common.magic_ipv6_addr,
-        retry_policy: Default::default(),
+        trace_path: common.trace.clone(),
+        history: history_path(common),
+        ..Default::default()
+    }
+}
+
+/// `--history` 的目标文件：显式给出的路径，或数据目录中的默认文件。
+fn history_path(common: &CommonArgs) -> Option<std::path::PathBuf> {
+    match &common.history {
+        None => None,
+        Some(Some(path)) => Some(path.clone()),
+        Some(None) => {
+            let path = sendmer::core::history::default_path();
+            if path.is_none() {
+                eprintln!("warning: no local data directory, pass a file to --history");
+            }
+            path
+        }
     }
 }
 
-fn cli_app_handle(prefix: &'static str, no_progress: bool) -> AppHandle {
-    if no_progress {
+fn cli_app_handle(
+    prefix: &'static str,
+    common: &CommonArgs,
+    json: bool,
+) -> anyhow::Result<AppHandle> {
+    let progress: AppHandle = if json {
+        Some(Arc::new(JsonEventEmitter))
+    } else if common.no_progress {
         None
     } else {
         Some(Arc::new(CliEventEmitter::new(prefix)))
-    }
+    };
+    let rules = EventRule::pair(&common.on, &common.exec_event)?;
+    Ok(RuleEventEmitter::wrap(rules, progress))
 }
 
-async fn wait_for_send_shutdown(res: &sendmer::core::results::SendResult) -> anyhow::Result<()> {
+async fn wait_for_send_shutdown(
+    res: &sendmer::core::results::ShareHandle,
+    out: CliOutput,
+) -> anyhow::Result<()> {
     let mut status_rx = res.subscribe_transfer_status();
 
     loop {
@@ -151,6 +665,17 @@ async fn wait_for_send_shutdown(res: &sendmer::core::results::SendResult) -> any
                 result?;
                 return Ok(());
             }
+            () = res.wait_for_expiry() => {
+                out.line("share expired, stopping");
+                return Ok(());
+            }
+            () = res.wait_for_max_downloads() => {
+                out.line(format_args!(
+                    "reached {} downloads, stopping",
+                    res.completed_downloads()
+                ));
+                return Ok(());
+            }
             changed = status_rx.changed() => {
                 if changed.is_err() {
                     return Ok(());
@@ -169,10 +694,61 @@ async fn wait_for_send_shutdown(res: &sendmer::core::results::SendResult) -> any
     }
 }
 
-fn common_args(command: &Commands) -> &CommonArgs {
+/// 地址变化时打印新的接收命令（仅在启用 `--refresh-ticket` 时会触发）。
+///
+/// 使用 `--keychain` 时改为更新系统凭据存储中的 ticket，不在终端输出；
+/// 使用 `--ticket-only` 时 stdout 上只输出新的 ticket。
+fn spawn_ticket_printer(
+    res: &sendmer::core::results::ShareHandle,
+    keychain_name: Option<String>,
+    code: bool,
+    out: CliOutput,
+) -> tokio::task::JoinHandle<()> {
+    let mut ticket_rx = res.subscribe_ticket();
+    let mut hash = res.hash();
+    tokio::spawn(async move {
+        while ticket_rx.changed().await.is_ok() {
+            let ticket = ticket_rx.borrow_and_update().clone();
+            let content_changed = ticket.hash() != hash;
+            hash = ticket.hash();
+            let change = if content_changed {
+                "shared content changed"
+            } else {
+                "addresses changed"
+            };
+            match &keychain_name {
+                Some(name) => match keychain::store_ticket(name, &ticket.to_string()) {
+                    Ok(()) => out.line(format_args!("{change}, updated keychain entry {name:?}")),
+                    Err(error) => tracing::warn!(error = %error, "failed to update keychain"),
+                },
+                None if out.ticket_only => {
+                    eprintln!("{change}");
+                    println!("{ticket}");
+                }
+                None => {
+                    out.line(format_args!("{change}, to get this data, use"));
+                    out.line(format_args!("sendmer receive {ticket}"));
+                }
+            }
+            if code && content_changed {
+                out.line(format_args!(
+                    "the short code now has fingerprint {}",
+                    code::fingerprint(ticket.addr().id, ticket.hash())
+                ));
+            }
+        }
+    })
+}
+
+fn common_args(command: &Commands) -> Option<&CommonArgs> {
     match command {
-        Commands::Send(args) => &args.common,
-        Commands::Receive(args) => &args.common,
+        Commands::Send(args) => Some(&args.common),
+        Commands::Receive(args) => Some(&args.common),
+        Commands::Serve(args) => Some(&args.common),
+        Commands::List(args) => Some(&args.common),
+        Commands::Trace(_) | Commands::Hash(_) | Commands::Version(_) => None,
+        #[cfg(feature = "relay-server")]
+        Commands::Relay(_) => None,
     }
 }
 
@@ -185,8 +761,10 @@ fn init_tracing(verbose: u8) -> anyhow::Result<()> {
     let env_filter = tracing_subscriber::EnvFilter::try_from_default_env()
         .or_else(|_| tracing_subscriber::EnvFilter::try_new(default_filter))?;
 
+    // 日志写到 stderr，stdout 只留给 ticket、清单与 `receive --stdout` 的数据
     let _ = tracing_subscriber::fmt()
         .with_env_filter(env_filter)
+        .with_writer(std::io::stderr)
         .try_init();
     Ok(())
 }
@@ -199,16 +777,20 @@ fn maybe_show_secret(common: &CommonArgs) -> anyhow::Result<()> {
     Ok(())
 }
 
-#[cfg(feature = "clipboard")]
-fn maybe_handle_key_press(set_clipboard: bool, ticket: String) {
-    if !(std::io::stdin().is_terminal() && std::io::stdout().is_terminal()) {
-        return;
-    }
-    handle_key_press(set_clipboard, ticket);
+/// 是否允许交互（键盘监听、剪贴板、raw mode）。
+///
+/// 显式传入 `--non-interactive` 或 stdin/stdout 不是终端时返回 `false`。
+fn is_interactive(common: &CommonArgs) -> bool {
+    !common.non_interactive && std::io::stdin().is_terminal() && std::io::stdout().is_terminal()
 }
 
+/// 监听按键并按需复制接收命令；返回的剪贴板供退出时清空尚未到期的内容。
 #[cfg(feature = "clipboard")]
-fn handle_key_press(set_clipboard: bool, ticket: String) {
+fn handle_key_press(
+    set_clipboard: bool,
+    ticket: String,
+    clipboard_ttl: Option<Duration>,
+) -> Arc<std::sync::Mutex<Clipboard>> {
     #[cfg(any(unix, windows))]
     use std::io;
 
@@ -229,10 +811,18 @@ fn handle_key_press(set_clipboard: bool, ticket: String) {
         }
     }
 
+    let clipboard = Arc::new(std::sync::Mutex::new(Clipboard {
+        ttl: clipboard_ttl,
+        clear_task: None,
+    }));
     if set_clipboard {
-        add_to_clipboard(&ticket);
+        clipboard
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .copy(&ticket);
     }
 
+    let keyboard_clipboard = clipboard.clone();
     let _keyboard = tokio::task::spawn(async move {
         println!("press c to copy command to clipboard, or use the --clipboard argument");
 
@@ -252,7 +842,10 @@ fn handle_key_press(set_clipboard: bool, ticket: String) {
                     modifiers: KeyModifiers::NONE,
                     kind: KeyEventKind::Press,
                     ..
-                })) => add_to_clipboard(&ticket),
+                })) => keyboard_clipboard
+                    .lock()
+                    .unwrap_or_else(std::sync::PoisonError::into_inner)
+                    .copy(&ticket),
                 Ok(Event::Key(KeyEvent {
                     code: KeyCode::Char('c'),
                     modifiers: KeyModifiers::CONTROL,
@@ -278,6 +871,55 @@ fn handle_key_press(set_clipboard: bool, ticket: String) {
             })
             .await;
     });
+    clipboard
+}
+
+/// 复制接收命令到剪贴板，并在设置了 `ttl` 时到期清空；再次复制会重新计时。
+#[cfg(feature = "clipboard")]
+struct Clipboard {
+    ttl: Option<Duration>,
+    clear_task: Option<tokio::task::JoinHandle<()>>,
+}
+
+#[cfg(feature = "clipboard")]
+impl Clipboard {
+    fn copy(&mut self, ticket: &String) {
+        add_to_clipboard(ticket);
+        let Some(ttl) = self.ttl else {
+            return;
+        };
+        if let Some(task) = self.clear_task.take() {
+            task.abort();
+        }
+        self.clear_task = Some(tokio::spawn(async move {
+            tokio::time::sleep(ttl).await;
+            clear_clipboard();
+        }));
+    }
+
+    /// 分享结束时调用：尚未到期的清空立即执行，避免进程退出后 ticket 一直留在剪贴板里。
+    ///
+    /// OSC 52 只能写入、无法可靠读取剪贴板，因此不能先确认剪贴板里仍是 ticket；
+    /// 若用户在此期间又复制了其他内容，也会一并被清空。
+    fn clear_pending(&mut self) {
+        if let Some(task) = self.clear_task.take()
+            && !task.is_finished()
+        {
+            task.abort();
+            clear_clipboard();
+        }
+    }
+}
+
+/// 通过 OSC 52 写入空内容清空剪贴板；终端不支持时静默无效。
+#[cfg(feature = "clipboard")]
+fn clear_clipboard() {
+    use std::io::stdout;
+
+    use crossterm::{clipboard::CopyToClipboard, execute};
+
+    execute!(stdout(), CopyToClipboard::to_clipboard_from(""))
+        .unwrap_or_else(|e| eprintln!("Failed to clear clipboard: {e}"));
 }
 
 #[cfg(feature = "clipboard")]
@@ -296,8 +938,9 @@ fn add_to_clipboard(ticket: &String) {
 #[cfg(test)]
 mod tests {
     use super::receive_options;
-    use sendmer::core::args::CommonArgs;
-    use sendmer::core::options::RelayModeOption;
+    use clap::Parser;
+    use sendmer::core::args::{Args, Commands, CommonArgs, TraceCommand};
+    use sendmer::core::options::{DiscoveryToggle, ImportModeOption, RelayModeOption};
     use std::path::PathBuf;
 
     fn sample_common_args() -> CommonArgs {
@@ -309,9 +952,24 @@ mod tests {
             no_progress: false,
             relay: RelayModeOption::Default,
             show_secret: false,
+            non_interactive: false,
+            trace: None,
+            history: None,
+            on: Vec::new(),
+            exec_event: Vec::new(),
         }
     }
 
+    #[test]
+    fn non_interactive_flag_disables_interaction() {
+        let common = CommonArgs {
+            non_interactive: true,
+            ..sample_common_args()
+        };
+
+        assert!(!super::is_interactive(&common));
+    }
+
     #[test]
     fn receive_options_keeps_explicit_output_dir() {
         let common = sample_common_args();
@@ -330,4 +988,385 @@ mod tests {
 
         assert!(options.output_dir.is_none());
     }
+
+    #[tokio::test]
+    async fn receive_args_compose_ticket_from_hash_and_node() {
+        let node = iroh::SecretKey::generate(&mut rand::rng()).public();
+        let hash = iroh_blobs::Hash::new(b"demo");
+        let args = Args::try_parse_from([
+            "sendmer",
+            "receive",
+            "--hash",
+            &hash.to_hex(),
+            "--node",
+            &node.to_string(),
+        ])
+        .expect("hash and node should parse");
+
+        let Commands::Receive(args) = args.command else {
+            panic!("expected receive command");
+        };
+        let ticket = args
+            .resolve_ticket()
+            .await
+            .expect("ticket should compose")
+            .ticket;
+        assert_eq!(ticket.hash(), hash);
+        assert_eq!(ticket.addr().id, node);
+    }
+
+    #[tokio::test]
+    async fn receive_reads_ticket_from_file() {
+        let ticket = iroh_blobs::ticket::BlobTicket::new(
+            iroh::EndpointAddr::new(iroh::SecretKey::generate(&mut rand::rng()).public()),
+            iroh_blobs::Hash::new(b"demo"),
+            iroh_blobs::BlobFormat::HashSeq,
+        );
+        let temp_dir = tempfile::tempdir().expect("temp dir");
+        let path = temp_dir.path().join("ticket.txt");
+        std::fs::write(&path, format!("sendmer receive {ticket}\n")).expect("write ticket");
+
+        let args = Args::try_parse_from([
+            "sendmer",
+            "receive",
+            "--ticket-file",
+            path.to_str().expect("utf-8 path"),
+        ])
+        .expect("--ticket-file should parse");
+        let Commands::Receive(args) = args.command else {
+            panic!("expected receive command");
+        };
+        let resolved = args.resolve_ticket().await.expect("ticket should load");
+        assert_eq!(resolved.ticket, ticket);
+    }
+
+    #[test]
+    fn send_dash_reads_stdin_with_name() {
+        let args = Args::try_parse_from(["sendmer", "send", "-", "--name", "dump.sql"])
+            .expect("stdin send should parse");
+        let Commands::Send(args) = args.command else {
+            panic!("expected send command");
+        };
+        assert_eq!(args.name.as_deref(), Some("dump.sql"));
+        assert!(super::reads_stdin(&args.paths).expect("single dash is valid"));
+        assert!(super::reads_stdin(&[PathBuf::from("-"), PathBuf::from("notes.txt")]).is_err());
+    }
+
+    #[test]
+    fn import_mode_defaults_to_reference() {
+        let parse = |extra: &[&str]| {
+            let args = Args::try_parse_from(["sendmer", "send", "docs"].iter().chain(extra))
+                .expect("send should parse");
+            let Commands::Send(args) = args.command else {
+                panic!("expected send command");
+            };
+            args.import_mode
+        };
+        assert_eq!(parse(&[]), ImportModeOption::Reference);
+        assert_eq!(parse(&["--import-mode", "copy"]), ImportModeOption::Copy);
+        assert!(
+            Args::try_parse_from(["sendmer", "send", "docs", "--import-mode", "move"]).is_err()
+        );
+    }
+
+    #[test]
+    fn ticket_only_has_quiet_alias_and_excludes_keychain() {
+        let args = Args::try_parse_from(["sendmer", "send", "docs", "--quiet"])
+            .expect("quiet alias should parse");
+        let Commands::Send(args) = args.command else {
+            panic!("expected send command");
+        };
+        assert!(args.ticket_only);
+        assert!(
+            Args::try_parse_from([
+                "sendmer",
+                "send",
+                "docs",
+                "--ticket-only",
+                "--keychain",
+                "k"
+            ])
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn receive_password_flag_takes_optional_value() {
+        let node = iroh::SecretKey::generate(&mut rand::rng())
+            .public()
+            .to_string();
+        let hash = iroh_blobs::Hash::new(b"demo").to_hex();
+        let receive = ["sendmer", "receive", "--hash", &hash, "--node", &node];
+        let args = Args::try_parse_from(receive.iter().copied().chain(["--password"]))
+            .expect("bare password flag should parse");
+        let Commands::Receive(args) = args.command else {
+            panic!("expected receive command");
+        };
+        assert_eq!(args.password, Some(None));
+
+        let args = Args::try_parse_from(receive.iter().copied().chain(["--password", "secret"]))
+            .expect("password value should parse");
+        let Commands::Receive(args) = args.command else {
+            panic!("expected receive command");
+        };
+        assert_eq!(
+            super::receive_password(args.password).expect("explicit password"),
+            Some("secret".to_string())
+        );
+    }
+
+    #[test]
+    fn skip_published_requires_store_dir_and_history() {
+        let peer = iroh::SecretKey::generate(&mut rand::rng())
+            .public()
+            .to_string();
+        let send = |extra: &[&str]| {
+            let mut argv = vec!["sendmer", "send", "docs", "--skip-published", peer.as_str()];
+            argv.extend_from_slice(extra);
+            Args::try_parse_from(argv)
+        };
+        assert!(send(&["--store-dir", "s"]).is_err());
+        assert!(send(&["--history"]).is_err());
+        let args = send(&["--store-dir", "s", "--history"]).expect("send args should parse");
+        let Commands::Send(args) = args.command else {
+            panic!("expected send command");
+        };
+        assert_eq!(
+            args.skip_published.map(|id| id.to_string()),
+            Some(peer.clone())
+        );
+    }
+
+    #[test]
+    fn serve_args_require_store_dir() {
+        assert!(Args::try_parse_from(["sendmer", "serve", "docs"]).is_err());
+        let args = Args::try_parse_from(["sendmer", "serve", "docs", "photos", "--store-dir", "s"])
+            .expect("serve args should parse");
+        let Commands::Serve(args) = args.command else {
+            panic!("expected serve command");
+        };
+        assert_eq!(args.paths.len(), 2);
+    }
+
+    #[test]
+    fn discovery_flags_reach_receive_options() {
+        let args = Args::try_parse_from([
+            "sendmer",
+            "receive",
+            "--code",
+            "7-brave-otter",
+            "--dns-discovery",
+            "on",
+            "--dns-origin",
+            "dns.example.com",
+        ])
+        .expect("discovery flags should parse");
+        let Commands::Receive(args) = args.command else {
+            panic!("expected receive command");
+        };
+        assert_eq!(args.dns_discovery, DiscoveryToggle::On);
+        assert_eq!(args.dns_origin.as_deref(), Some("dns.example.com"));
+        assert!(
+            Args::try_parse_from(["sendmer", "send", "docs", "--pkarr-publish", "maybe"]).is_err()
+        );
+    }
+
+    #[test]
+    fn keep_partial_is_an_alias_for_resume() {
+        let ticket = iroh_blobs::ticket::BlobTicket::new(
+            iroh::EndpointAddr::new(iroh::SecretKey::generate(&mut rand::rng()).public()),
+            iroh_blobs::Hash::new(b"demo"),
+            iroh_blobs::BlobFormat::HashSeq,
+        )
+        .to_string();
+        let args = Args::try_parse_from(["sendmer", "receive", &ticket, "--keep-partial"])
+            .expect("--keep-partial should parse");
+        let Commands::Receive(args) = args.command else {
+            panic!("expected receive command");
+        };
+        assert!(args.resume);
+    }
+
+    #[test]
+    fn json_conflicts_with_other_stdout_output() {
+        let args = Args::try_parse_from(["sendmer", "send", "docs", "--json"])
+            .expect("--json should parse");
+        let Commands::Send(args) = args.command else {
+            panic!("expected send command");
+        };
+        assert!(args.json);
+
+        assert!(
+            Args::try_parse_from(["sendmer", "send", "docs", "--json", "--ticket-only"]).is_err()
+        );
+
+        let ticket = iroh_blobs::ticket::BlobTicket::new(
+            iroh::EndpointAddr::new(iroh::SecretKey::generate(&mut rand::rng()).public()),
+            iroh_blobs::Hash::new(b"demo"),
+            iroh_blobs::BlobFormat::HashSeq,
+        )
+        .to_string();
+        assert!(Args::try_parse_from(["sendmer", "receive", &ticket, "--json"]).is_ok());
+        assert!(
+            Args::try_parse_from(["sendmer", "receive", &ticket, "--json", "--stdout"]).is_err()
+        );
+    }
+
+    #[test]
+    fn receive_accepts_several_tickets() {
+        let ticket = iroh_blobs::ticket::BlobTicket::new(
+            iroh::EndpointAddr::new(iroh::SecretKey::generate(&mut rand::rng()).public()),
+            iroh_blobs::Hash::new(b"demo"),
+            iroh_blobs::BlobFormat::HashSeq,
+        )
+        .to_string();
+        let args = Args::try_parse_from(["sendmer", "receive", &ticket, &ticket, &ticket])
+            .expect("several tickets should parse");
+        let Commands::Receive(args) = args.command else {
+            panic!("expected receive command");
+        };
+        assert!(args.ticket.is_some());
+        assert_eq!(args.extra_tickets.len(), 2);
+    }
+
+    #[test]
+    fn stdout_conflicts_with_disk_output_flags() {
+        let receive = ["sendmer", "receive", "--code", "7-brave-otter", "--stdout"];
+        let args = Args::try_parse_from(receive).expect("stdout should parse");
+        let Commands::Receive(args) = args.command else {
+            panic!("expected receive command");
+        };
+        assert!(args.stdout);
+        assert!(Args::try_parse_from(receive.iter().copied().chain(["--into", "dl"])).is_err());
+        assert!(Args::try_parse_from(receive.iter().copied().chain(["--flatten"])).is_err());
+    }
+
+    #[test]
+    fn list_requires_a_ticket_or_code() {
+        assert!(Args::try_parse_from(["sendmer", "list"]).is_err());
+        let args = Args::try_parse_from(["sendmer", "list", "--code", "7-brave-otter"])
+            .expect("code should replace the ticket");
+        let Commands::List(args) = args.command else {
+            panic!("expected list command");
+        };
+        assert!(args.ticket.is_none());
+        assert!(args.manifest.is_none());
+    }
+
+    #[test]
+    fn receive_args_accept_code_instead_of_ticket() {
+        let args = Args::try_parse_from(["sendmer", "receive", "--code", "7-brave-otter"])
+            .expect("code should replace the ticket");
+        let Commands::Receive(args) = args.command else {
+            panic!("expected receive command");
+        };
+        assert_eq!(
+            args.code.map(|code| code.to_string()).as_deref(),
+            Some("7-brave-otter")
+        );
+        assert!(args.ticket.is_none());
+        assert!(args.code_fingerprint.is_none());
+
+        let args = Args::try_parse_from([
+            "sendmer",
+            "receive",
+            "--code",
+            "7-brave-otter",
+            "--code-fingerprint",
+            "1a2b-3c4d-5e6f-7a8b",
+        ])
+        .expect("fingerprint goes with the code");
+        let Commands::Receive(args) = args.command else {
+            panic!("expected receive command");
+        };
+        assert_eq!(
+            args.code_fingerprint.as_deref(),
+            Some("1a2b-3c4d-5e6f-7a8b")
+        );
+        let node = iroh::SecretKey::generate(&mut rand::rng())
+            .public()
+            .to_string();
+        let hash = iroh_blobs::Hash::new(b"demo").to_hex();
+        assert!(
+            Args::try_parse_from([
+                "sendmer",
+                "receive",
+                "--hash",
+                &hash,
+                "--node",
+                &node,
+                "--code-fingerprint",
+                "1a2b-3c4d-5e6f-7a8b",
+            ])
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn version_parses_json_flag() {
+        let args = Args::try_parse_from(["sendmer", "version", "--json"]).expect("version parses");
+        let Commands::Version(args) = args.command else {
+            panic!("expected version command");
+        };
+        assert!(args.json);
+    }
+
+    #[test]
+    fn trace_analyze_parses_stall_threshold() {
+        let args = Args::try_parse_from([
+            "sendmer",
+            "trace",
+            "analyze",
+            "transfer.trace",
+            "--stall-ms",
+            "250",
+        ])
+        .expect("trace analyze should parse");
+
+        let Commands::Trace(TraceCommand::Analyze(args)) = args.command else {
+            panic!("expected trace analyze command");
+        };
+        assert_eq!(args.path, PathBuf::from("transfer.trace"));
+        assert_eq!(args.stall_ms, 250);
+    }
+
+    #[cfg(feature = "relay-server")]
+    #[test]
+    fn relay_args_parse_token() {
+        let args = Args::try_parse_from([
+            "sendmer",
+            "relay",
+            "--bind",
+            "127.0.0.1:3340",
+            "--token",
+            "s3cret",
+        ])
+        .expect("relay should parse");
+
+        let Commands::Relay(args) = args.command else {
+            panic!("expected relay command");
+        };
+        assert_eq!(args.bind.port(), 3340);
+        assert_eq!(args.token.as_deref(), Some("s3cret"));
+    }
+
+    #[test]
+    fn receive_args_accept_keychain_instead_of_ticket() {
+        let args = Args::try_parse_from(["sendmer", "receive", "--keychain", "holiday-photos"])
+            .expect("keychain should replace the ticket");
+
+        let Commands::Receive(args) = args.command else {
+            panic!("expected receive command");
+        };
+        assert_eq!(args.keychain.as_deref(), Some("holiday-photos"));
+        assert!(args.ticket.is_none());
+    }
+
+    #[test]
+    fn receive_args_require_node_with_hash() {
+        let hash = iroh_blobs::Hash::new(b"demo");
+        let err = Args::try_parse_from(["sendmer", "receive", "--hash", &hash.to_hex()])
+            .expect_err("hash without node should fail");
+        assert_eq!(err.kind(), clap::error::ErrorKind::MissingRequiredArgument);
+    }
 }
@@ -0,0 +1,151 @@
+//! 归档模式：把要分享的文件打包成一个 tar blob，而不是每个文件一个 blob。
+//!
+//! 对于包含大量小文件的目录，逐个文件的 blob 与请求开销远大于数据本身；
+//! 打包后只需传输一个 blob。集合中另有一个 `.sendmer-archive.tar` 空条目作为标记，
+//! 接收端据此在导出时解包。tar 的读写在阻塞线程中进行，通过管道与异步导入/导出衔接，
+//! 不会在内存或磁盘上保留完整归档。
+
+use crate::core::error::{CatalogError, ErrorKind};
+use crate::core::receiver::{get_export_path, partial_path};
+use crate::core::sender::canonicalized_path_to_string;
+use anyhow::Context;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, DuplexStream};
+use tokio::runtime::Handle;
+
+/// 集合中归档标记条目的名称。
+pub const ARCHIVE_MARKER: &str = ".sendmer-archive.tar";
+const PIPE_SIZE: usize = 256 * 1024;
+
+/// 在阻塞线程中把 `files`（集合内名称与本地路径）写成 tar，返回读取归档数据的一端，
+/// 以及打包任务的句柄；打包失败时归档会被截断，调用方必须检查句柄的结果。
+pub fn pack(
+    files: Vec<(String, PathBuf)>,
+) -> (DuplexStream, tokio::task::JoinHandle<anyhow::Result<()>>) {
+    let (writer, reader) = tokio::io::duplex(PIPE_SIZE);
+    let writer = BlockingIo {
+        inner: writer,
+        handle: Handle::current(),
+    };
+    let packer = tokio::task::spawn_blocking(move || {
+        let mut builder = tar::Builder::new(writer);
+        for (name, path) in files {
+            builder
+                .append_path_with_name(&path, &name)
+                .with_context(|| format!("failed to archive {}", path.display()))?;
+        }
+        builder.into_inner()?.flush()?;
+        Ok(())
+    });
+    (reader, packer)
+}
+
+/// 把 `reader` 中的 tar 归档解包到 `output_dir`；只接受普通文件与目录，
+/// 文件名按与普通导出相同的规则校验，目标已存在时返回 `TargetExists`。
+/// 与普通导出一样，每个文件先解包为 `<name>.part`，完整写入后再重命名。
+pub async fn unpack(
+    reader: impl AsyncRead + Unpin + Send + 'static,
+    output_dir: PathBuf,
+) -> anyhow::Result<()> {
+    let reader = BlockingIo {
+        inner: reader,
+        handle: Handle::current(),
+    };
+    tokio::task::spawn_blocking(move || unpack_blocking(reader, &output_dir)).await?
+}
+
+fn unpack_blocking(reader: impl Read, output_dir: &Path) -> anyhow::Result<()> {
+    let mut archive = tar::Archive::new(reader);
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let name = canonicalized_path_to_string(entry.path()?, true)?;
+        match entry.header().entry_type() {
+            tar::EntryType::Regular => {}
+            tar::EntryType::Directory => continue,
+            other => anyhow::bail!("unsupported archive entry {name} of type {other:?}"),
+        }
+        let target = get_export_path(output_dir, &name)?;
+        if target.exists() {
+            return Err(CatalogError::new(
+                ErrorKind::TargetExists,
+                format!("target {} already exists", target.display()),
+            )
+            .into());
+        }
+        let partial = partial_path(&target);
+        if let Err(error) = entry.unpack(&partial) {
+            let _ = std::fs::remove_file(&partial);
+            return Err(error).with_context(|| format!("failed to unpack {name}"));
+        }
+        std::fs::rename(&partial, &target)
+            .with_context(|| format!("failed to move {name} into place"))?;
+    }
+    Ok(())
+}
+
+/// 在阻塞线程中以同步 IO 的方式读写异步流。
+struct BlockingIo<T> {
+    inner: T,
+    handle: Handle,
+}
+
+impl<T: AsyncWrite + Unpin> Write for BlockingIo<T> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.handle.block_on(self.inner.write(buf))
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.handle.block_on(self.inner.flush())
+    }
+}
+
+impl<T: AsyncRead + Unpin> Read for BlockingIo<T> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.handle.block_on(self.inner.read(buf))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{pack, unpack};
+
+    #[tokio::test]
+    async fn pack_and_unpack_round_trip() {
+        let temp_dir = tempfile::tempdir().expect("temp dir");
+        let source = temp_dir.path().join("a.txt");
+        std::fs::write(&source, b"hello").expect("write source");
+        let files = vec![("docs/nested/a.txt".to_string(), source)];
+
+        let (reader, packer) = pack(files);
+        let output = temp_dir.path().join("out");
+        unpack(reader, output.clone()).await.expect("unpack");
+        packer.await.expect("join").expect("pack");
+
+        let unpacked = std::fs::read(output.join("docs/nested/a.txt")).expect("read unpacked");
+        assert_eq!(unpacked, b"hello");
+        assert!(!output.join("docs/nested/a.txt.part").exists());
+    }
+
+    #[tokio::test]
+    async fn truncated_archive_leaves_no_target_file() {
+        let temp_dir = tempfile::tempdir().expect("temp dir");
+        let source = temp_dir.path().join("a.bin");
+        std::fs::write(&source, vec![7u8; 64 * 1024]).expect("write source");
+
+        let (mut reader, packer) = pack(vec![("a.bin".to_string(), source)]);
+        let mut archive = Vec::new();
+        tokio::io::AsyncReadExt::read_to_end(&mut reader, &mut archive)
+            .await
+            .expect("read archive");
+        packer.await.expect("join").expect("pack");
+        archive.truncate(archive.len() / 2);
+
+        let output = temp_dir.path().join("out");
+        unpack(std::io::Cursor::new(archive), output.clone())
+            .await
+            .expect_err("truncated archive should fail");
+        assert!(!output.join("a.bin").exists());
+        assert!(!output.join("a.bin.part").exists());
+    }
+}
@@ -1,9 +1,10 @@
 //! 命令行参数定义。
 //!
-//! 本文件定义：Args, Commands, SendArgs, ReceiveArgs, CommonArgs, Format。
+//! 本文件定义：Args, Commands, SendArgs, ServeArgs, ReceiveArgs, TraceArgs, HashArgs, VersionArgs, CommonArgs, Format。
 
 use anyhow::Context;
 use clap::{Parser, Subcommand};
+use data_encoding::{BASE32_NOPAD, HEXLOWER_PERMISSIVE};
 use iroh_blobs::ticket::BlobTicket;
 use std::fmt::{Display, Formatter};
 use std::net::{SocketAddrV4, SocketAddrV6};
@@ -11,7 +12,14 @@ use std::path::PathBuf;
 use std::str::FromStr;
 use std::sync::OnceLock;
 
-use super::options::{AddrInfoOptions, RelayModeOption};
+use super::code::ShareCode;
+use super::compression::Compression;
+use super::options::{
+    AddrInfoOptions, BackoffStrategy, DiscoveryToggle, ExportModeOption, ImportModeOption,
+    ImportVerification, RelayModeOption, SymlinkPolicy,
+};
+use super::routing::RouteRule;
+use super::ticket::{ShareTicket, StaticAddr, TicketSource};
 
 static PROCESS_SECRET: OnceLock<iroh::SecretKey> = OnceLock::new();
 
@@ -22,6 +30,8 @@ pub struct Args {
     pub command: Commands,
 }
 
+// 命令行参数只解析一次，不必为缩小体积而装箱
+#[allow(clippy::large_enum_variant)]
 #[derive(Subcommand, Debug)]
 pub enum Commands {
     /// Send a file or directory.
@@ -29,6 +39,72 @@ pub enum Commands {
     /// Receive a file or directory.
     #[clap(visible_alias = "recv")]
     Receive(ReceiveArgs),
+    /// Host several shares from one long-running endpoint and blob store.
+    Serve(ServeArgs),
+    /// List the files in a share without downloading them.
+    List(ListArgs),
+    /// Inspect transfer audit logs written with `--trace`.
+    #[clap(subcommand)]
+    Trace(TraceCommand),
+    /// Work with content hashes.
+    #[clap(subcommand)]
+    Hash(HashCommand),
+    /// Print version and build information.
+    Version(VersionArgs),
+    /// Run a minimal relay server for private or air-gapped networks.
+    #[cfg(feature = "relay-server")]
+    Relay(RelayArgs),
+}
+
+#[derive(Parser, Debug)]
+pub struct VersionArgs {
+    /// Print build information as JSON.
+    #[clap(long)]
+    pub json: bool,
+}
+
+#[cfg(feature = "relay-server")]
+#[derive(Parser, Debug)]
+pub struct RelayArgs {
+    /// Address the relay listens on for HTTP relay connections.
+    #[clap(long, default_value = "0.0.0.0:3340")]
+    pub bind: std::net::SocketAddr,
+
+    /// Shared token clients must put in the relay URL, e.g.
+    /// `--relay http://<host>:3340/?token=<TOKEN>`.
+    ///
+    /// A random token is generated and printed when omitted.
+    #[clap(long, value_name = "TOKEN")]
+    pub token: Option<String>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum HashCommand {
+    /// Print a hash in both the hex and the CID form.
+    Convert(HashArgs),
+}
+
+#[derive(Parser, Debug)]
+pub struct HashArgs {
+    /// Hash in hex, CID or base32 form.
+    #[clap(value_parser = parse_hash)]
+    pub value: iroh_blobs::Hash,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum TraceCommand {
+    /// Report stalls and retransmissions found in a trace log.
+    Analyze(TraceArgs),
+}
+
+#[derive(Parser, Debug)]
+pub struct TraceArgs {
+    /// Path to the trace log.
+    pub path: PathBuf,
+
+    /// Gaps between records of the same stream at least this long count as stalls.
+    #[clap(long, default_value_t = 1000)]
+    pub stall_ms: u64,
 }
 
 #[derive(Parser, Debug)]
@@ -66,15 +142,62 @@ pub struct CommonArgs {
 
     #[clap(long)]
     pub show_secret: bool,
+
+    /// Never prompt, read the keyboard, touch the clipboard or enable raw mode.
+    ///
+    /// Also implied when stdin or stdout is not a terminal, so sendmer is safe
+    /// to run under cron, CI or in pipes.
+    #[clap(long)]
+    pub non_interactive: bool,
+
+    /// Record requested, served and received ranges to a binary audit log.
+    ///
+    /// Meant for diagnosing slow transfers, inspect the file with
+    /// `sendmer trace analyze`.
+    #[clap(long, value_name = "FILE")]
+    pub trace: Option<PathBuf>,
+
+    /// Append a line per send or receive to a local transfer log.
+    ///
+    /// Each line is a JSON record with timestamp, role, hash, peer, size and
+    /// outcome. Defaults to `transfers.log` in the sendmer data directory,
+    /// e.g. `~/.local/share/sendmer/transfers.log`.
+    #[clap(long, value_name = "FILE", num_args = 0..=1)]
+    pub history: Option<Option<PathBuf>>,
+
+    /// Event that triggers the matching `--exec-event` command.
+    ///
+    /// Written as `STATE[:ROLE]`, e.g. `completed:receiver` or `failed`.
+    /// Can be given multiple times, paired with `--exec-event` in order.
+    #[clap(long = "on", value_name = "STATE[:ROLE]")]
+    pub on: Vec<String>,
+
+    /// Shell command to run when the paired `--on` event occurs.
+    ///
+    /// The event is passed in the SENDMER_EVENT, SENDMER_ROLE, SENDMER_STATE
+    /// and SENDMER_MESSAGE environment variables.
+    #[clap(long, value_name = "CMD")]
+    pub exec_event: Vec<String>,
 }
 
 #[derive(Parser, Debug)]
 pub struct SendArgs {
-    /// Path to the file or directory to send.
+    /// Paths to the files or directories to send.
     ///
-    /// The last component of the path will be used as the name of the data
-    /// being shared.
-    pub path: PathBuf,
+    /// The last component of each path is used as its name in the shared
+    /// collection; colliding names get a ` (1)`, ` (2)`... suffix.
+    ///
+    /// Use `-` alone to stream data from stdin as a single file.
+    #[clap(required = true, value_name = "PATH")]
+    pub paths: Vec<PathBuf>,
+
+    /// Top-level name in the share instead of the last path component,
+    /// e.g. share `/tmp/build-1234/out` as `release-v2.1`.
+    ///
+    /// Needs a single path. For data read from stdin (`sendmer send -`) this
+    /// is the file name, `stdin` by default.
+    #[clap(long, value_name = "NAME")]
+    pub name: Option<String>,
 
     /// What type of ticket to use.
     ///
@@ -84,27 +207,338 @@ pub struct SendArgs {
     /// to use the biggest type of ticket that includes both relay and
     /// address information.
     ///
+    /// Use "dns:<host>[:port]" for a short ID ticket carrying a DNS name the
+    /// receiver resolves for direct addresses, e.g. for self-hosted senders.
+    ///
     /// Generally, the more information the higher the likelyhood of
     /// a successful connection, but also the bigger a ticket to connect.
     ///
     /// This is most useful for debugging which methods of connection
     /// establishment work well.
-    #[clap(long, default_value_t = AddrInfoOptions::RelayAndAddresses)]
-    pub ticket_type: AddrInfoOptions,
+    #[clap(long, default_value_t = TicketType::Addr(AddrInfoOptions::RelayAndAddresses))]
+    pub ticket_type: TicketType,
+
+    /// Watch for address changes and print a refreshed ticket when they happen.
+    ///
+    /// Useful for shares that stay up for a long time on a machine that may
+    /// change networks. Also republishes the addresses to discovery.
+    #[clap(long)]
+    pub refresh_ticket: bool,
+
+    /// Publish this node's addresses to pkarr: `auto`, `on` or `off`.
+    ///
+    /// `auto` publishes when the ticket carries no addresses, with
+    /// `--refresh-ticket` and with `--code`, which needs publishing.
+    #[clap(long, value_name = "MODE", default_value_t = DiscoveryToggle::Auto)]
+    pub pkarr_publish: DiscoveryToggle,
+
+    /// Print only the ticket on stdout, for capturing it in scripts.
+    ///
+    /// Everything else goes to stderr. Refreshed tickets are printed on
+    /// their own line as well.
+    #[clap(long, visible_alias = "quiet", conflicts_with_all = ["keychain", "dry_run", "manifest"])]
+    pub ticket_only: bool,
+
+    /// Write newline-delimited JSON events to stdout instead of progress bars.
+    ///
+    /// Each line is an object with `event` (`started`, `progress`,
+    /// `file-names`, `completed`, `failed`, ...), `role` and the event's
+    /// fields. The ticket and other messages go to stderr.
+    #[clap(long, conflicts_with_all = ["ticket_only", "dry_run", "manifest"])]
+    pub json: bool,
+
+    /// Print a manifest listing every entry with its name, hash and size.
+    ///
+    /// Only `json` is supported. Printed before the ticket, or instead of the
+    /// entry list with `--dry-run`.
+    #[clap(long, value_name = "FORMAT")]
+    pub manifest: Option<ManifestFormat>,
+
+    /// Only import and hash the paths, print the collection hash, every
+    /// entry and the total size, then exit without sharing anything.
+    #[clap(long)]
+    pub dry_run: bool,
+
+    /// Keep modification times and unix permissions of shared files.
+    ///
+    /// Receivers apply them to the exported files. Archives made with
+    /// `--archive` always carry them.
+    #[clap(long)]
+    pub preserve_metadata: bool,
+
+    /// Pack everything into a single tar blob instead of one blob per file.
+    ///
+    /// Much faster for directories with many tiny files. Receivers unpack it
+    /// automatically; combine with `--compress` for a compressed archive.
+    #[clap(long)]
+    pub archive: bool,
+
+    /// Compress every file before sharing, e.g. `zstd` or `zstd:19`.
+    ///
+    /// Receivers decompress automatically. Helps text-heavy shares on slow
+    /// links; already compressed data only gets slower to import.
+    #[clap(long, value_name = "ALGO[:LEVEL]")]
+    pub compress: Option<Compression>,
+
+    /// Watch the shared paths and re-import them when they change.
+    ///
+    /// Every change creates a new collection, and the refreshed ticket is
+    /// printed; receivers with the old ticket keep getting the old content.
+    #[clap(long)]
+    pub watch: bool,
+
+    /// With `--watch`, keep the last N versions as timestamped snapshots.
+    ///
+    /// Each snapshot keeps its own ticket working while the share runs; with
+    /// `--store-dir` the snapshots also survive restarts.
+    #[clap(long, value_name = "N", requires = "watch", value_parser = clap::value_parser!(u64).range(1..))]
+    pub snapshots: Option<u64>,
+
+    /// Keep the blob store, node key and ticket in this directory.
+    ///
+    /// Restarting `sendmer send` with the same directory and content reuses
+    /// the stored data and node identity, so the share keeps its ticket.
+    /// The directory is not removed when the share stops.
+    #[clap(long, value_name = "DIR")]
+    pub store_dir: Option<PathBuf>,
+
+    /// Leave out files that were already sent to this receiver, so only new
+    /// or changed files are shared.
+    ///
+    /// Earlier shares are looked up in the `--history` log and their files in
+    /// the `--store-dir` store, so both are required.
+    #[clap(
+        long,
+        value_name = "ENDPOINT_ID",
+        requires_all = ["store_dir", "history"],
+        conflicts_with = "archive"
+    )]
+    pub skip_published: Option<iroh::EndpointId>,
+
+    /// Also publish the share under a short code like `7-brave-otter`.
+    ///
+    /// Receivers use `sendmer receive --code <CODE>` instead of the ticket
+    /// and must confirm the fingerprint printed next to the code.
+    /// Codes are easy to dictate but also easy to guess, prefer the ticket
+    /// for sensitive data.
+    #[clap(long)]
+    pub code: bool,
+
+    /// Protect the share with a generated password.
+    ///
+    /// Receivers must pass it with `sendmer receive --password` before any
+    /// data is served.
+    #[clap(long)]
+    pub password: bool,
+
+    /// Only serve receivers with this endpoint ID. Can be given multiple times.
+    ///
+    /// Other receivers are refused when they connect.
+    #[clap(long, value_name = "ENDPOINT_ID")]
+    pub allow: Vec<iroh::EndpointId>,
+
+    /// Limit outgoing blob data to this many bytes per second, e.g. `500KB` or `5MB`.
+    ///
+    /// The limit is shared by all receivers.
+    #[clap(long, value_name = "RATE", value_parser = parse_rate)]
+    pub limit_rate: Option<u64>,
+
+    /// Refuse to share more than this many bytes in total, e.g. `10GB`.
+    ///
+    /// Checked before anything is copied into the blob store.
+    #[clap(long, value_name = "SIZE", value_parser = parse_size)]
+    pub max_size: Option<u64>,
+
+    /// Stop sharing and remove the temporary data after this long, e.g. `30m` or `2h`.
+    #[clap(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub expire: Option<std::time::Duration>,
+
+    /// Stop sharing after this many receivers finished downloading.
+    #[clap(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    pub max_downloads: Option<u64>,
+
+    /// Directory in which the temporary blob store is created.
+    ///
+    /// Defaults to the system temp directory. It must not be inside a shared path.
+    #[clap(long, value_name = "DIR", conflicts_with = "store_dir")]
+    pub temp_dir: Option<PathBuf>,
+
+    /// Keep the imported data in memory instead of a temporary directory.
+    ///
+    /// Shares smaller than 16 MiB use memory automatically.
+    #[clap(long, conflicts_with = "store_dir")]
+    pub mem: bool,
+
+    /// How files enter the blob store: `reference` the originals or `copy` them.
+    ///
+    /// Use `copy` when the files may change while they are shared; it needs
+    /// as much extra disk space as the files themselves.
+    #[clap(long, value_name = "MODE", default_value_t = ImportModeOption::Reference)]
+    pub import_mode: ImportModeOption,
+
+    /// Check the imported data against its hashes before printing the ticket.
+    ///
+    /// `sample` (the default when no value is given) checks a few random
+    /// files, `all` re-reads every file.
+    #[clap(long, value_name = "MODE", num_args = 0..=1, default_missing_value = "sample")]
+    pub verify_import: Option<ImportVerification>,
+
+    /// Skip files and directories that cannot be read instead of aborting.
+    ///
+    /// Skipped entries are listed as warnings once the import is done.
+    #[clap(long)]
+    pub skip_unreadable: bool,
+
+    /// Follow symlinks and send what they point to, like `rsync -L`.
+    ///
+    /// Same as `--symlinks follow`.
+    #[clap(short = 'L', long, visible_alias = "follow-symlinks")]
+    pub follow_links: bool,
+
+    /// What to do with symlinks: skip them, follow them, or fail the import.
+    ///
+    /// Symlink loops are skipped with a warning when following.
+    #[clap(long, value_name = "POLICY", default_value_t = SymlinkPolicy::Skip, conflicts_with = "follow_links")]
+    pub symlinks: SymlinkPolicy,
+
+    /// Don't cross filesystem boundaries, like `rsync -x`.
+    #[clap(short = 'x', long)]
+    pub one_file_system: bool,
+
+    /// Exclude entries matching a pattern, like `rsync --exclude`.
+    ///
+    /// Patterns without `/` match an entry name at any depth, excluding a
+    /// directory excludes everything below it. Can be given multiple times.
+    #[clap(long, value_name = "PATTERN")]
+    pub exclude: Vec<String>,
+
+    /// Skip entries ignored by `.gitignore` or `.ignore` files in the shared tree.
+    ///
+    /// The `.git` directory is skipped as well.
+    #[clap(long)]
+    pub gitignore: bool,
+
+    /// Include hidden files and directories (names starting with `.`).
+    ///
+    /// They are skipped by default so that `.env`, `.git` or `.DS_Store`
+    /// don't end up in a share by accident. A hidden path given explicitly
+    /// on the command line is always shared.
+    #[clap(long, overrides_with = "no_hidden")]
+    pub hidden: bool,
+
+    /// Skip hidden files and directories (the default), overriding
+    /// `--hidden` or `--rsync-compat`.
+    #[clap(long, overrides_with = "hidden")]
+    pub no_hidden: bool,
+
+    /// Behave like rsync where sendmer's defaults differ.
+    ///
+    /// Currently this implies `--skip-unreadable`, since rsync reports
+    /// unreadable files and keeps going, and `--hidden`, since rsync copies
+    /// dotfiles. Partial transfers are not resumed.
+    #[clap(long)]
+    pub rsync_compat: bool,
+
+    /// Store the ticket in the OS keychain under this name instead of printing it.
+    ///
+    /// Receive it with `sendmer receive --keychain NAME` so the ticket never
+    /// shows up in shell history or terminal scrollback.
+    #[clap(long, value_name = "NAME")]
+    pub keychain: Option<String>,
 
     #[clap(flatten)]
     pub common: CommonArgs,
 
+    /// Also print the receive command as a QR code for phones and GUI clients.
+    #[clap(long)]
+    pub qr: bool,
+
     /// Store the receive command in the clipboard.
     #[cfg(feature = "clipboard")]
     #[clap(short = 'c', long)]
     pub clipboard: bool,
+
+    /// Clear the clipboard this long after copying the receive command, e.g. `5m`.
+    ///
+    /// Keeps the ticket out of long-lived clipboard history. Applies to
+    /// `--clipboard` and to copying with `c`.
+    #[cfg(feature = "clipboard")]
+    #[clap(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub clipboard_ttl: Option<std::time::Duration>,
+}
+
+#[derive(Parser, Debug)]
+pub struct ServeArgs {
+    /// Files or directories to share, each one as a separate share with its
+    /// own ticket, named after the last path component.
+    #[clap(required = true, value_name = "PATH")]
+    pub paths: Vec<PathBuf>,
+
+    /// Directory holding the blob store and node key.
+    ///
+    /// Shares are kept as named tags in the store, so restarting with the
+    /// same directory reuses the data and the endpoint ID.
+    #[clap(long, value_name = "DIR")]
+    pub store_dir: PathBuf,
+
+    /// What type of ticket to use, see `sendmer send --help`.
+    #[clap(long, default_value_t = AddrInfoOptions::RelayAndAddresses)]
+    pub ticket_type: AddrInfoOptions,
+
+    #[clap(flatten)]
+    pub common: CommonArgs,
 }
 
 #[derive(Parser, Debug)]
 pub struct ReceiveArgs {
     /// The ticket to use to connect to the sender.
-    pub ticket: BlobTicket,
+    ///
+    /// Pass `-` to read it from stdin, or an `http(s)://` URL whose response
+    /// contains the ticket, e.g. a pastebin link.
+    #[clap(required_unless_present_any = ["hash", "keychain", "code", "ticket_file"])]
+    pub ticket: Option<TicketSource>,
+
+    /// Read the ticket from this file.
+    #[clap(long, value_name = "FILE", conflicts_with_all = ["ticket", "hash", "keychain", "code"])]
+    pub ticket_file: Option<PathBuf>,
+
+    /// Further tickets for the same content from other senders.
+    ///
+    /// Missing data is fetched from all senders at once; when one fails the
+    /// others take over its share.
+    #[clap(value_name = "MORE_TICKETS", requires = "ticket")]
+    pub extra_tickets: Vec<ShareTicket>,
+
+    /// Fetch the collection with this hash instead of using a ticket.
+    ///
+    /// Accepts both the hex and the cid form. Requires `--node`.
+    #[clap(long, conflicts_with = "ticket", requires = "node", value_parser = parse_hash)]
+    pub hash: Option<iroh_blobs::Hash>,
+
+    /// Endpoint ID of the sender, used together with `--hash`.
+    ///
+    /// Addresses are resolved through discovery.
+    #[clap(long, requires = "hash")]
+    pub node: Option<iroh::EndpointId>,
+
+    /// Read the ticket from the OS keychain entry stored by `send --keychain`.
+    #[clap(long, value_name = "NAME", conflicts_with_all = ["ticket", "hash"])]
+    pub keychain: Option<String>,
+
+    /// Short code printed by `sendmer send --code`, used instead of a ticket.
+    #[clap(long, value_name = "CODE", conflicts_with_all = ["ticket", "hash", "keychain"])]
+    pub code: Option<ShareCode>,
+
+    /// Fingerprint printed by the sender next to `--code`.
+    ///
+    /// Without it the fingerprint is shown for confirmation, which needs a terminal.
+    #[clap(
+        long,
+        value_name = "FINGERPRINT",
+        requires = "code",
+        conflicts_with_all = ["ticket", "ticket_file", "hash", "keychain"]
+    )]
+    pub code_fingerprint: Option<String>,
 
     /// Output directory for received files.
     ///
@@ -112,10 +546,343 @@ pub struct ReceiveArgs {
     #[clap(long)]
     pub output_dir: Option<PathBuf>,
 
+    /// Write everything into this new subdirectory of the output directory.
+    ///
+    /// Fails if it already exists, so different downloads never mix.
+    #[clap(long, value_name = "DIR")]
+    pub into: Option<PathBuf>,
+
+    /// Additional directory that receives a copy of every exported file.
+    ///
+    /// Can be given multiple times. The verified blob data is reused, so no
+    /// second download happens.
+    #[clap(long = "mirror", value_name = "DIR")]
+    pub mirror: Vec<PathBuf>,
+
+    /// Move exported files matching a pattern into another directory.
+    ///
+    /// Written as `PATTERN=>DIR`, e.g. `--route "*.jpg=>~/Pictures"`. Can be
+    /// given multiple times; the first matching rule wins.
+    #[clap(long = "route", value_name = "PATTERN=>DIR")]
+    pub route: Vec<RouteRule>,
+
+    /// Only print where `--route` rules would move files, without downloading
+    /// anything but the share listing.
+    #[clap(long, requires = "route")]
+    pub route_dry_run: bool,
+
+    /// Look up the sender's addresses via DNS: `auto`, `on` or `off`.
+    ///
+    /// `auto` looks them up only when the ticket carries no addresses.
+    #[clap(long, value_name = "MODE", default_value_t = DiscoveryToggle::Auto)]
+    pub dns_discovery: DiscoveryToggle,
+
+    /// DNS origin domain used for address lookups instead of the n0 default.
+    #[clap(long, value_name = "DOMAIN")]
+    pub dns_origin: Option<String>,
+
+    /// Connect to an endpoint at a fixed address, bypassing pkarr and DNS.
+    ///
+    /// Written as `NODE_ID=IP:PORT`. Can be given multiple times.
+    #[clap(long, value_name = "NODE_ID=IP:PORT")]
+    pub resolve: Vec<StaticAddr>,
+
+    /// Fetch and export entries matching this pattern before the rest.
+    ///
+    /// Supports `*`, `**` and `?`, e.g. `--first "docs/**"`. Can be given
+    /// multiple times.
+    #[clap(long, value_name = "PATTERN")]
+    pub first: Vec<String>,
+
+    /// Password for a share created with `sendmer send --password`.
+    ///
+    /// Prompts for it when no value is given.
+    #[clap(long, value_name = "PASSWORD", num_args = 0..=1)]
+    pub password: Option<Option<String>>,
+
+    /// Write files through a handle to the output directory so nothing can
+    /// escape it. Requires the `sandbox` feature.
+    #[clap(long)]
+    pub sandbox: bool,
+
+    /// How many times to try each request before giving up.
+    #[clap(long, value_name = "N", default_value_t = 3, value_parser = clap::value_parser!(u32).range(1..))]
+    pub retries: u32,
+
+    /// How the wait between retries grows: `constant`, `linear` or `exponential`.
+    #[clap(long, value_name = "STRATEGY", default_value_t = BackoffStrategy::Linear)]
+    pub retry_backoff: BackoffStrategy,
+
+    /// Stop retrying once this much time has passed since the first attempt, e.g. `5m`.
+    #[clap(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub retry_deadline: Option<std::time::Duration>,
+
+    /// Give up on a connection attempt that takes longer than this, e.g. `30s`.
+    #[clap(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub connect_timeout: Option<std::time::Duration>,
+
+    /// Fail (and retry) a transfer that receives no data for this long, e.g. `1m`.
+    #[clap(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub stall_timeout: Option<std::time::Duration>,
+
+    /// Re-request data that fails verification up to this many times before giving up.
+    #[clap(long, value_name = "N", default_value_t = 3)]
+    pub repair_attempts: u32,
+
+    /// Limit incoming blob data to this many bytes per second, e.g. `500KB` or `5MB`.
+    #[clap(long, value_name = "RATE", value_parser = parse_rate)]
+    pub limit_rate: Option<u64>,
+
+    /// Download and blake3-verify everything, then print a report instead
+    /// of writing any files.
+    #[clap(
+        long,
+        conflicts_with_all = ["output_dir", "into", "mirror", "route", "first", "flatten", "sandbox", "stdout"]
+    )]
+    pub verify_only: bool,
+
+    /// Print a per-file report after export: `table` or `json`.
+    ///
+    /// Every exported file is read back and blake3-verified.
+    #[clap(long, value_name = "FORMAT", conflicts_with_all = ["stdout", "verify_only"])]
+    pub report: Option<ReportFormat>,
+
+    /// Write newline-delimited JSON events to stdout instead of progress bars.
+    ///
+    /// Each line is an object with `event` (`started`, `progress`,
+    /// `file-names`, `completed`, `failed`, ...), `role` and the event's
+    /// fields. The final summary goes to stderr.
+    #[clap(long, conflicts_with_all = ["stdout", "verify_only", "report"])]
+    pub json: bool,
+
+    /// Write a `BLAKE3SUMS` file (as printed by `b3sum`) into the output
+    /// directory after export.
+    #[clap(long, conflicts_with_all = ["stdout", "verify_only"])]
+    pub checksums: bool,
+
+    /// Write the received file to stdout instead of the output directory.
+    ///
+    /// Only works for shares with a single file, e.g.
+    /// `sendmer receive <ticket> --stdout | tar x`.
+    #[clap(
+        long,
+        conflicts_with_all = ["output_dir", "into", "mirror", "route", "first", "flatten", "sandbox"]
+    )]
+    pub stdout: bool,
+
+    /// Drop all directories and write every file directly into the output
+    /// directory. Fails before writing if two files share a name.
+    #[clap(long)]
+    pub flatten: bool,
+
+    /// How files leave the temporary store: `copy` or `reference`.
+    ///
+    /// `reference` moves the downloaded data into place instead of copying it
+    /// when the temp directory is on the same filesystem as the output,
+    /// falling back to a copy otherwise. Only the last `--mirror` directory
+    /// is referenced.
+    #[clap(
+        long,
+        value_name = "MODE",
+        default_value_t = ExportModeOption::Copy,
+        conflicts_with_all = ["stdout", "verify_only", "sandbox", "resume", "retry_failed", "keep_store"]
+    )]
+    pub export_mode: ExportModeOption,
+
+    /// Start the download even if the temp or output directory looks too
+    /// small for it.
+    #[clap(long)]
+    pub force: bool,
+
+    /// Keep partial data when the download fails or is interrupted, and
+    /// continue from it when receiving the same ticket again.
+    ///
+    /// Without it, the temporary store is removed whenever a receive fails.
+    #[clap(long, visible_alias = "keep-partial")]
+    pub resume: bool,
+
+    /// Only fetch and export files that are missing from the output directory
+    /// or fail verification, e.g. the ones `--report` marked as failed or an
+    /// interrupted receive never wrote. Implies `--resume`.
+    #[clap(
+        long,
+        conflicts_with_all = ["stdout", "verify_only", "first", "route", "route_dry_run"]
+    )]
+    pub retry_failed: bool,
+
+    /// Keep the downloaded blobs in a store in the user cache directory.
+    ///
+    /// Later receives share that store, so content that was already received
+    /// (or files that only need exporting again) is not downloaded twice.
+    #[clap(long)]
+    pub keep_store: bool,
+
+    /// Inject failures into the download, e.g. `drop=0.1,latency=200ms,disconnect-after=50MB`.
+    #[cfg(feature = "chaos")]
+    #[clap(long, hide = true, value_name = "SPEC")]
+    pub chaos: Option<crate::core::chaos::ChaosConfig>,
+
+    #[clap(flatten)]
+    pub common: CommonArgs,
+}
+
+#[derive(Parser, Debug)]
+pub struct ListArgs {
+    /// The ticket to use to connect to the sender.
+    #[clap(required_unless_present = "code")]
+    pub ticket: Option<ShareTicket>,
+
+    /// Short code printed by `sendmer send --code`, used instead of a ticket.
+    #[clap(long, value_name = "CODE", conflicts_with = "ticket")]
+    pub code: Option<ShareCode>,
+
+    /// Fingerprint printed by the sender next to `--code`.
+    ///
+    /// Without it the fingerprint is shown for confirmation, which needs a terminal.
+    #[clap(
+        long,
+        value_name = "FINGERPRINT",
+        requires = "code",
+        conflicts_with = "ticket"
+    )]
+    pub code_fingerprint: Option<String>,
+
+    /// Password for a share created with `sendmer send --password`.
+    ///
+    /// Prompts for it when no value is given.
+    #[clap(long, value_name = "PASSWORD", num_args = 0..=1)]
+    pub password: Option<Option<String>>,
+
+    /// Print the listing as a machine-readable manifest instead.
+    ///
+    /// Only `json` is supported.
+    #[clap(long, value_name = "FORMAT")]
+    pub manifest: Option<ManifestFormat>,
+
     #[clap(flatten)]
     pub common: CommonArgs,
 }
 
+impl ReceiveArgs {
+    /// 返回要使用的 ticket：显式给出（或从标准输入、文件、URL 读取）的 ticket、
+    /// 系统凭据存储中的 ticket，或由 `--hash` 与 `--node` 组合而成。
+    pub async fn resolve_ticket(&self) -> anyhow::Result<ShareTicket> {
+        if let Some(name) = &self.keychain {
+            return ShareTicket::try_from(super::keychain::load_ticket(name)?.as_str());
+        }
+        if let Some(path) = &self.ticket_file {
+            return TicketSource::File(path.clone()).resolve().await;
+        }
+        match (&self.ticket, self.hash, self.node) {
+            (Some(ticket), _, _) => ticket.resolve().await,
+            (None, Some(hash), Some(node)) => Ok(BlobTicket::new(
+                iroh::EndpointAddr::new(node),
+                hash,
+                iroh_blobs::BlobFormat::HashSeq,
+            )
+            .into()),
+            _ => anyhow::bail!(
+                "either a ticket, --ticket-file, --keychain or --hash together with --node is required"
+            ),
+        }
+    }
+}
+
+/// `--ticket-type` 的取值：地址信息选项，或带 DNS 提示的 ID ticket。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TicketType {
+    Addr(AddrInfoOptions),
+    Dns(String),
+}
+
+impl TicketType {
+    /// 拆分为库使用的 ticket 类型与可选的 DNS 提示。
+    pub fn split(&self) -> (AddrInfoOptions, Option<String>) {
+        match self {
+            Self::Addr(options) => (*options, None),
+            Self::Dns(hint) => (AddrInfoOptions::Id, Some(hint.clone())),
+        }
+    }
+}
+
+impl FromStr for TicketType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(hint) = s.strip_prefix("dns:") {
+            anyhow::ensure!(!hint.is_empty(), "dns ticket type requires a host name");
+            return Ok(Self::Dns(hint.to_string()));
+        }
+        AddrInfoOptions::from_str(s)
+            .map(Self::Addr)
+            .map_err(|_| anyhow::anyhow!("invalid ticket type {s:?}"))
+    }
+}
+
+impl Display for TicketType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Addr(options) => write!(f, "{options}"),
+            Self::Dns(hint) => write!(f, "dns:{hint}"),
+        }
+    }
+}
+
+/// `--manifest` 的输出格式。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ManifestFormat {
+    Json,
+}
+
+impl FromStr for ManifestFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "json" => Ok(Self::Json),
+            _ => Err(anyhow::anyhow!("invalid manifest format, expected json")),
+        }
+    }
+}
+
+impl Display for ManifestFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Json => write!(f, "json"),
+        }
+    }
+}
+
+/// `receive --report` 的输出格式。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    Table,
+    Json,
+}
+
+impl FromStr for ReportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "table" => Ok(Self::Table),
+            "json" => Ok(Self::Json),
+            _ => Err(anyhow::anyhow!(
+                "invalid report format, expected table or json"
+            )),
+        }
+    }
+}
+
+impl Display for ReportFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Table => write!(f, "table"),
+            Self::Json => write!(f, "json"),
+        }
+    }
+}
+
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 pub enum Format {
     #[default]
@@ -144,6 +911,9 @@ impl Display for Format {
     }
 }
 
+/// CIDv1 前缀：版本 1、raw 编解码、blake3 multihash、32 字节摘要。
+const CID_PREFIX: [u8; 4] = [0x01, 0x55, 0x1e, 0x20];
+
 pub fn print_hash(hash: &iroh_blobs::Hash, format: Format) -> String {
     match format {
         Format::Hex => hash.to_hex(),
@@ -151,6 +921,98 @@ pub fn print_hash(hash: &iroh_blobs::Hash, format: Format) -> String {
     }
 }
 
+/// 以 multibase base32（`b` 前缀）编码的 CIDv1 形式表示 hash。
+pub fn cid_string(hash: &iroh_blobs::Hash) -> String {
+    let mut bytes = CID_PREFIX.to_vec();
+    bytes.extend_from_slice(hash.as_bytes());
+    format!("b{}", BASE32_NOPAD.encode(&bytes).to_ascii_lowercase())
+}
+
+/// 解析 hex、CID 或 base32 形式的 hash，并校验长度与 multibase 前缀。
+pub fn parse_hash(value: &str) -> anyhow::Result<iroh_blobs::Hash> {
+    let value = value.trim();
+    let decoded = match value.len() {
+        64 => HEXLOWER_PERMISSIVE
+            .decode(value.as_bytes())
+            .map_err(|_| anyhow::anyhow!("invalid hex hash {value:?}"))?,
+        52 => BASE32_NOPAD
+            .decode(value.to_ascii_uppercase().as_bytes())
+            .map_err(|_| anyhow::anyhow!("invalid base32 hash {value:?}"))?,
+        59 => {
+            let Some(encoded) = value.strip_prefix('b') else {
+                anyhow::bail!("unsupported multibase prefix in {value:?}, expected base32 (b)");
+            };
+            let cid = BASE32_NOPAD
+                .decode(encoded.to_ascii_uppercase().as_bytes())
+                .map_err(|_| anyhow::anyhow!("invalid CID {value:?}"))?;
+            anyhow::ensure!(
+                cid.starts_with(&CID_PREFIX),
+                "CID {value:?} is not a raw blake3 CIDv1"
+            );
+            cid[CID_PREFIX.len()..].to_vec()
+        }
+        len => anyhow::bail!(
+            "hash {value:?} has length {len}, expected 64 (hex), 59 (CID) or 52 (base32)"
+        ),
+    };
+    let bytes: [u8; 32] = decoded
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("hash {value:?} does not decode to 32 bytes"))?;
+    Ok(iroh_blobs::Hash::from_bytes(bytes))
+}
+
+/// 解析 `512`、`64KB`、`5MB`、`1GB` 形式的字节数（以 1024 为基数，`K`/`M`/`G` 亦可）。
+pub fn parse_size(value: &str) -> anyhow::Result<u64> {
+    let upper = value.trim().to_ascii_uppercase();
+    let (number, unit) = upper
+        .find(|c: char| !c.is_ascii_digit())
+        .map_or((upper.as_str(), ""), |index| upper.split_at(index));
+    let multiplier: u64 = match unit {
+        "" | "B" => 1,
+        "K" | "KB" => 1 << 10,
+        "M" | "MB" => 1 << 20,
+        "G" | "GB" => 1 << 30,
+        _ => anyhow::bail!("invalid size unit in {value:?}"),
+    };
+    number
+        .parse::<u64>()
+        .with_context(|| format!("invalid size {value:?}"))?
+        .checked_mul(multiplier)
+        .ok_or_else(|| anyhow::anyhow!("size {value:?} is too large"))
+}
+
+/// 解析 `--limit-rate` 的速率，格式同 [`parse_size`]；不接受 0。
+pub fn parse_rate(value: &str) -> anyhow::Result<u64> {
+    let rate = parse_size(value)?;
+    anyhow::ensure!(
+        rate > 0,
+        "rate {value:?} must be greater than zero, omit --limit-rate for no limit"
+    );
+    Ok(rate)
+}
+
+/// 解析 `90s`、`30m`、`2h`、`1d` 形式的时长；不带单位时按秒计算。
+pub fn parse_duration(value: &str) -> anyhow::Result<std::time::Duration> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number = number
+        .parse::<u64>()
+        .with_context(|| format!("invalid duration {value:?}"))?;
+    let seconds = match unit {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => anyhow::bail!("invalid duration unit in {value:?}, expected s, m, h or d"),
+    };
+    Ok(std::time::Duration::from_secs(
+        number.saturating_mul(seconds),
+    ))
+}
+
 pub fn get_or_create_secret() -> anyhow::Result<iroh::SecretKey> {
     std::env::var("IROH_SECRET").map_or_else(
         |_| Ok(PROCESS_SECRET.get_or_init(new_secret_key).clone()),
@@ -161,3 +1023,64 @@ pub fn get_or_create_secret() -> anyhow::Result<iroh::SecretKey> {
 fn new_secret_key() -> iroh::SecretKey {
     iroh::SecretKey::generate(&mut rand::rng())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        Format, cid_string, parse_duration, parse_hash, parse_rate, parse_size, print_hash,
+    };
+
+    #[test]
+    fn parse_size_accepts_units() {
+        assert_eq!(parse_size("512").unwrap(), 512);
+        assert_eq!(parse_size("64KB").unwrap(), 64 * 1024);
+        assert_eq!(parse_size("5m").unwrap(), 5 * 1024 * 1024);
+        assert!(parse_size("5TB").is_err());
+    }
+
+    #[test]
+    fn parse_rate_rejects_zero() {
+        assert_eq!(parse_rate("500KB").unwrap(), 500 * 1024);
+        assert!(parse_rate("0").is_err());
+        assert!(parse_rate("0MB").is_err());
+    }
+
+    #[test]
+    fn parse_duration_accepts_units() {
+        use std::time::Duration;
+
+        assert_eq!(parse_duration("45").unwrap(), Duration::from_secs(45));
+        assert_eq!(parse_duration("30m").unwrap(), Duration::from_secs(30 * 60));
+        assert_eq!(parse_duration("2h").unwrap(), Duration::from_secs(2 * 3600));
+        assert_eq!(parse_duration("1d").unwrap(), Duration::from_secs(86400));
+        assert!(parse_duration("10w").is_err());
+        assert!(parse_duration("m").is_err());
+    }
+
+    #[test]
+    fn parse_hash_accepts_hex_and_cid_forms() {
+        let hash = iroh_blobs::Hash::new(b"demo");
+
+        let cid = cid_string(&hash);
+        assert!(cid.starts_with("bafkr4"));
+        assert_eq!(parse_hash(&cid).expect("cid should parse"), hash);
+        assert_eq!(parse_hash(&hash.to_hex()).expect("hex should parse"), hash);
+        assert_eq!(
+            parse_hash(&hash.to_hex().to_uppercase()).expect("upper hex should parse"),
+            hash
+        );
+        // `--format cid` 保持原有输出，CIDv1 形式只由 `hash convert` 打印
+        assert_eq!(print_hash(&hash, Format::Cid), hash.to_string());
+    }
+
+    #[test]
+    fn parse_hash_rejects_bad_length_and_multibase() {
+        let err = parse_hash("abc").expect_err("short value should fail");
+        assert!(err.to_string().contains("expected 64"));
+
+        let cid = cid_string(&iroh_blobs::Hash::new(b"demo"));
+        let wrong_base = format!("z{}", &cid[1..]);
+        let err = parse_hash(&wrong_base).expect_err("non-base32 multibase should fail");
+        assert!(err.to_string().contains("multibase"));
+    }
+}
@@ -0,0 +1,201 @@
+//! 口令保护的分享。
+//!
+//! 接收端在请求任何 blob 之前，先通过独立的 ALPN 向发送端提交口令；发送端校验通过后
+//! 记录该接收端的 endpoint ID，之后只放行这些 endpoint 的 blob 连接。
+
+use crate::core::connect::connect;
+use crate::core::error::{CatalogError, ErrorKind};
+use data_encoding::BASE32_NOPAD;
+use iroh::endpoint::Connection;
+use iroh::protocol::{AcceptError, ProtocolHandler};
+use iroh::{Endpoint, EndpointAddr, EndpointId};
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+
+/// 口令握手使用的 ALPN。
+pub const AUTH_ALPN: &[u8] = b"sendmer/auth/0";
+
+const MAX_PASSWORD_LEN: usize = 1024;
+const ACCEPTED: u8 = 1;
+
+/// 生成一个随机口令（16 个小写 base32 字符）。
+pub fn generate_password() -> String {
+    BASE32_NOPAD
+        .encode(&rand::random::<[u8; 10]>())
+        .to_ascii_lowercase()
+}
+
+/// 发送端的口令校验器，同时记录已通过校验的接收端。
+#[derive(Debug, Clone)]
+pub struct PasswordGate {
+    password_hash: blake3::Hash,
+    authorized: Arc<Mutex<HashSet<EndpointId>>>,
+}
+
+impl PasswordGate {
+    pub fn new(password: &str) -> Self {
+        Self {
+            password_hash: blake3::hash(password.as_bytes()),
+            authorized: Arc::default(),
+        }
+    }
+
+    /// 该接收端是否已提交过正确的口令。
+    pub fn is_authorized(&self, peer: &EndpointId) -> bool {
+        self.authorized
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .contains(peer)
+    }
+
+    /// 校验口令；比较的是哈希值，`blake3::Hash` 的相等比较是常数时间的。
+    fn verify(&self, peer: EndpointId, candidate: &[u8]) -> bool {
+        let accepted = blake3::hash(candidate) == self.password_hash;
+        if accepted {
+            self.authorized
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner)
+                .insert(peer);
+        }
+        accepted
+    }
+
+    async fn handle(&self, connection: Connection) -> anyhow::Result<()> {
+        let peer = connection.remote_id();
+        let (mut send, mut recv) = connection.accept_bi().await?;
+        let candidate = recv.read_to_end(MAX_PASSWORD_LEN).await?;
+        let accepted = self.verify(peer, &candidate);
+        if !accepted {
+            tracing::warn!(%peer, "rejected wrong share password");
+        }
+        send.write_all(&[u8::from(accepted)]).await?;
+        send.finish()?;
+        // 等待接收端读取结果后主动关闭连接
+        connection.closed().await;
+        Ok(())
+    }
+}
+
+impl ProtocolHandler for PasswordGate {
+    async fn accept(&self, connection: Connection) -> Result<(), AcceptError> {
+        self.handle(connection)
+            .await
+            .map_err(|error| AcceptError::from_err(std::io::Error::other(error)))
+    }
+}
+
+/// 接收端：向 `addr` 提交口令，口令错误时返回 [`ErrorKind::WrongPassword`] 分类的错误。
+pub async fn authenticate(
+    endpoint: &Endpoint,
+    addr: &EndpointAddr,
+    password: &str,
+) -> anyhow::Result<()> {
+    let connection = connect(endpoint, addr, AUTH_ALPN).await?;
+    let (mut send, mut recv) = connection.open_bi().await?;
+    send.write_all(password.as_bytes()).await?;
+    send.finish()?;
+    let reply = recv.read_to_end(1).await?;
+    connection.close(0u32.into(), b"done");
+    if reply != [ACCEPTED] {
+        return Err(CatalogError::new(
+            ErrorKind::WrongPassword,
+            "the sender rejected the password",
+        )
+        .into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PasswordGate, authenticate, generate_password};
+    use crate::core::error::{ErrorKind, classify};
+    use crate::core::options::{RelayModeOption, SendOptions};
+    use crate::core::sender::send;
+    use iroh::{Endpoint, EndpointAddr};
+    use iroh_blobs::format::collection::Collection;
+    use iroh_blobs::store::mem::MemStore;
+
+    #[test]
+    fn password_gate_authorizes_only_correct_password() {
+        let gate = PasswordGate::new("open sesame");
+        let good = iroh::SecretKey::generate(&mut rand::rng()).public();
+        let bad = iroh::SecretKey::generate(&mut rand::rng()).public();
+
+        assert!(!gate.verify(bad, b"open sesame!"));
+        assert!(gate.verify(good, b"open sesame"));
+        assert!(gate.is_authorized(&good));
+        assert!(!gate.is_authorized(&bad));
+    }
+
+    #[tokio::test]
+    async fn protected_share_serves_only_the_right_password() {
+        let temp_dir = tempfile::tempdir().expect("temp dir");
+        let source = temp_dir.path().join("secret.txt");
+        std::fs::write(&source, b"top secret").expect("write source");
+        // 关闭 relay 后 ticket 未必带上直连地址，改为绑定已知的本地端口
+        let bind_addr = std::net::UdpSocket::bind("127.0.0.1:0")
+            .and_then(|socket| socket.local_addr())
+            .expect("pick a local port");
+        let std::net::SocketAddr::V4(bind_addr) = bind_addr else {
+            unreachable!("bound to an IPv4 address");
+        };
+        let share = send(
+            source,
+            SendOptions {
+                relay_mode: RelayModeOption::Disabled,
+                magic_ipv4_addr: Some(bind_addr),
+                password: Some("open sesame".to_string()),
+                ..SendOptions::default()
+            },
+            None,
+        )
+        .await
+        .expect("start share");
+        let ticket = share.ticket().resolve().await.expect("resolve ticket");
+        let addr = EndpointAddr::new(ticket.addr().id).with_ip_addr(bind_addr.into());
+        // 进程内的发送端共用同一个密钥，接收端需要自己的 endpoint
+        let client = Endpoint::builder()
+            .relay_mode(iroh::RelayMode::Disabled)
+            .bind()
+            .await
+            .expect("bind client");
+        let store = MemStore::new();
+
+        let error = authenticate(&client, &addr, "open sesame!")
+            .await
+            .expect_err("wrong password must be rejected");
+        assert_eq!(classify(&error), Some(ErrorKind::WrongPassword));
+        authenticate(&client, &addr, "open sesame")
+            .await
+            .expect("right password should be accepted");
+        let connection = client
+            .connect(addr.clone(), iroh_blobs::protocol::ALPN)
+            .await
+            .expect("connect");
+        store
+            .remote()
+            .fetch(connection, ticket.hash_and_format())
+            .await
+            .expect("authorized peer should be served");
+        let collection = Collection::load(ticket.hash(), &*store)
+            .await
+            .expect("load collection");
+        let (name, hash) = collection.iter().next().expect("one entry");
+        assert_eq!(name, "secret.txt");
+        assert_eq!(
+            store.get_bytes(*hash).await.expect("read blob").as_ref(),
+            b"top secret"
+        );
+
+        client.close().await;
+        share.stop().await.expect("stop share");
+    }
+
+    #[test]
+    fn generated_passwords_are_distinct() {
+        let password = generate_password();
+        assert_eq!(password.len(), 16);
+        assert_ne!(password, generate_password());
+    }
+}
@@ -0,0 +1,151 @@
+//! 同步（阻塞）API：在内部创建 tokio 运行时，供不使用 async 的命令行工具与脚本调用，
+//! 用法与 `reqwest::blocking` 类似。
+//!
+//! 这些函数会阻塞当前线程，不能在 async 上下文中调用（tokio 会因嵌套 `block_on` 而 panic）。
+
+use crate::core::error::Error;
+use crate::core::events::AppHandle;
+use crate::core::options::{ReceiveOptions, SendOptions};
+use crate::core::receiver;
+use crate::core::results::{ReceiveResult, ShareHandle, ShareStats};
+use crate::core::sender;
+use crate::core::ticket::ShareTicket;
+use std::path::PathBuf;
+use tokio::runtime::Runtime;
+
+/// 运行中的分享，持有其运行时；分享在后台线程上继续对外提供数据。
+///
+/// 被丢弃时与 [`Share::stop`] 一样停止分享并清理临时存储。
+pub struct Share {
+    handle: Option<ShareHandle>,
+    runtime: Runtime,
+}
+
+impl Share {
+    /// 供接收端使用的 ticket。
+    pub fn ticket(&self) -> ShareTicket {
+        self.handle().ticket()
+    }
+
+    /// 完成的下载次数与传输字节数等统计。
+    pub fn stats(&self) -> ShareStats {
+        self.handle().stats()
+    }
+
+    /// 阻塞直到分享到期或达到 `max_downloads`；两者都未设置时永不返回。
+    pub fn wait(&self) {
+        let handle = self.handle();
+        self.runtime.block_on(async {
+            tokio::select! {
+                () = handle.wait_for_expiry() => {}
+                () = handle.wait_for_max_downloads() => {}
+            }
+        });
+    }
+
+    /// 停止分享并删除临时存储。
+    pub fn stop(mut self) -> Result<(), Error> {
+        self.handle.take().map_or_else(
+            || Ok(()),
+            |handle| self.runtime.block_on(handle.stop()).map_err(Error::from),
+        )
+    }
+
+    const fn handle(&self) -> &ShareHandle {
+        self.handle
+            .as_ref()
+            .expect("share handle is only taken when the share stops")
+    }
+}
+
+impl Drop for Share {
+    fn drop(&mut self) {
+        if let Some(handle) = self.handle.take()
+            && let Err(error) = self.runtime.block_on(handle.stop())
+        {
+            tracing::warn!(error = %error, "failed to stop share");
+        }
+    }
+}
+
+/// 阻塞版本的 [`sender::send`]：导入 `path` 并开始分享。
+pub fn send_path(
+    path: impl Into<PathBuf>,
+    options: SendOptions,
+    app_handle: AppHandle,
+) -> Result<Share, Error> {
+    let runtime = runtime()?;
+    let handle = runtime.block_on(sender::send(path.into(), options, app_handle))?;
+    Ok(Share {
+        handle: Some(handle),
+        runtime,
+    })
+}
+
+/// 阻塞版本的 [`receiver::receive_ticket`]：下载并导出 ticket 指向的数据后返回。
+pub fn receive_ticket(
+    ticket: impl Into<ShareTicket>,
+    options: ReceiveOptions,
+    app_handle: AppHandle,
+) -> Result<ReceiveResult, Error> {
+    runtime()?.block_on(receiver::receive_ticket(ticket, options, app_handle))
+}
+
+fn runtime() -> Result<Runtime, Error> {
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .map_err(|error| anyhow::Error::from(error).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{receive_ticket, send_path};
+    use crate::core::options::{ReceiveOptions, ReceiveRetryPolicy, RelayModeOption, SendOptions};
+    use iroh_blobs::{BlobFormat, Hash, ticket::BlobTicket};
+    use std::time::Duration;
+
+    #[test]
+    fn send_path_shares_a_file_until_stopped() {
+        let temp_dir = tempfile::tempdir().expect("temp dir");
+        let file = temp_dir.path().join("hello.txt");
+        std::fs::write(&file, b"hello").expect("write file");
+        let store_parent = temp_dir.path().join("stores");
+        std::fs::create_dir_all(&store_parent).expect("create store parent");
+        let options = SendOptions {
+            relay_mode: RelayModeOption::Disabled,
+            temp_dir: Some(store_parent.clone()),
+            ..Default::default()
+        };
+
+        let share = send_path(&file, options, None).expect("send file");
+        assert_eq!(share.stats().completed_downloads, 0);
+        assert_eq!(share.ticket().ticket.format(), BlobFormat::HashSeq);
+        share.stop().expect("stop share");
+        let leftover = std::fs::read_dir(&store_parent)
+            .expect("read stores")
+            .count();
+        assert_eq!(leftover, 0);
+    }
+
+    #[test]
+    fn receive_ticket_fails_for_an_unreachable_sender() {
+        let temp_dir = tempfile::tempdir().expect("temp dir");
+        // 本地没有监听该端口，连接必然失败
+        let addr = iroh::EndpointAddr::new(iroh::SecretKey::generate(&mut rand::rng()).public())
+            .with_ip_addr("127.0.0.1:9".parse().expect("socket addr"));
+        let ticket = BlobTicket::new(addr, Hash::new(b"missing"), BlobFormat::HashSeq);
+        let options = ReceiveOptions {
+            output_dir: Some(temp_dir.path().to_path_buf()),
+            relay_mode: RelayModeOption::Disabled,
+            retry_policy: ReceiveRetryPolicy {
+                size_fetch_retry_limit: 1,
+                connect_timeout: Some(Duration::from_secs(2)),
+                ..ReceiveRetryPolicy::default()
+            },
+            ..ReceiveOptions::default()
+        };
+
+        assert!(receive_ticket(ticket, options, None).is_err());
+    }
+}
@@ -0,0 +1,90 @@
+//! 版本与构建信息：crate 版本、git 提交、启用的 feature、iroh 依赖版本及协议能力，
+//! 便于提交问题和双方核对兼容性。
+
+use serde::Serialize;
+use std::fmt::{Display, Formatter};
+
+/// 当前二进制的构建信息。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BuildInfo {
+    pub version: &'static str,
+    pub git_hash: Option<&'static str>,
+    pub features: Vec<&'static str>,
+    pub iroh_version: Option<&'static str>,
+    pub iroh_blobs_version: Option<&'static str>,
+    /// 支持的协议能力（ALPN、ticket 与 hash 格式）
+    pub capabilities: Vec<String>,
+}
+
+impl BuildInfo {
+    pub fn current() -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION"),
+            git_hash: option_env!("SENDMER_GIT_HASH"),
+            features: enabled_features(),
+            iroh_version: option_env!("SENDMER_IROH_VERSION"),
+            iroh_blobs_version: option_env!("SENDMER_IROH_BLOBS_VERSION"),
+            capabilities: vec![
+                format!(
+                    "alpn:{}",
+                    String::from_utf8_lossy(iroh_blobs::protocol::ALPN)
+                ),
+                "ticket:blob".to_string(),
+                "ticket:dns-hint".to_string(),
+                "hash:hex".to_string(),
+                "hash:cid".to_string(),
+            ],
+        }
+    }
+}
+
+fn enabled_features() -> Vec<&'static str> {
+    [
+        ("clipboard", cfg!(feature = "clipboard")),
+        ("keychain", cfg!(feature = "keychain")),
+        ("ticket-url", cfg!(feature = "ticket-url")),
+        ("relay-server", cfg!(feature = "relay-server")),
+        ("chaos", cfg!(feature = "chaos")),
+        ("sandbox", cfg!(feature = "sandbox")),
+    ]
+    .into_iter()
+    .filter_map(|(name, enabled)| enabled.then_some(name))
+    .collect()
+}
+
+impl Display for BuildInfo {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "sendmer {} ({})",
+            self.version,
+            self.git_hash.unwrap_or("unknown commit")
+        )?;
+        writeln!(f, "features: {}", self.features.join(", "))?;
+        writeln!(f, "iroh: {}", self.iroh_version.unwrap_or("unknown"))?;
+        writeln!(
+            f,
+            "iroh-blobs: {}",
+            self.iroh_blobs_version.unwrap_or("unknown")
+        )?;
+        writeln!(f, "capabilities: {}", self.capabilities.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BuildInfo;
+
+    #[test]
+    fn build_info_serializes_to_json() {
+        let info = BuildInfo::current();
+        let json = serde_json::to_value(&info).expect("build info should serialize");
+        assert_eq!(json["version"], env!("CARGO_PKG_VERSION"));
+        assert!(json["features"].is_array());
+        assert!(
+            info.capabilities
+                .iter()
+                .any(|capability| capability.starts_with("alpn:"))
+        );
+    }
+}
@@ -0,0 +1,138 @@
+//! 传输故障注入（需启用 `chaos` feature），用于在集成测试或复现弱网问题时
+//! 演练重试与续传逻辑。
+//!
+//! 配置形如 `drop=0.1,latency=200ms,disconnect-after=50MB`：
+//! - `drop`：每个进度项触发断开的概率
+//! - `latency`：每个进度项之前额外等待的时间（支持 `ms` 与 `s`）
+//! - `disconnect-after`：接收到指定字节数后断开（支持 `KB`、`MB`、`GB`，以 1024 为基数）
+//!
+//! 断开通过提前结束下载流实现，效果与连接中途断开相同。
+
+use crate::core::args::parse_size;
+use iroh_blobs::api::remote::GetProgressItem;
+use n0_future::{Stream, StreamExt, stream};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// 故障注入配置。
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ChaosConfig {
+    pub drop: f64,
+    pub latency: Duration,
+    pub disconnect_after: Option<u64>,
+}
+
+impl ChaosConfig {
+    fn should_disconnect(&self, offset: u64) -> bool {
+        self.disconnect_after.is_some_and(|limit| offset >= limit)
+            || (self.drop > 0.0 && rand::random::<f64>() < self.drop)
+    }
+}
+
+impl FromStr for ChaosConfig {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut config = Self::default();
+        for part in s.split(',').map(str::trim).filter(|part| !part.is_empty()) {
+            let (key, value) = part
+                .split_once('=')
+                .ok_or_else(|| anyhow::anyhow!("chaos option {part:?} must look like key=value"))?;
+            match key {
+                "drop" => {
+                    let drop = value.parse::<f64>()?;
+                    anyhow::ensure!(
+                        (0.0..=1.0).contains(&drop),
+                        "chaos drop must be between 0 and 1"
+                    );
+                    config.drop = drop;
+                }
+                "latency" => config.latency = parse_duration(value)?,
+                "disconnect-after" => config.disconnect_after = Some(parse_size(value)?),
+                _ => anyhow::bail!("unknown chaos option {key:?}"),
+            }
+        }
+        Ok(config)
+    }
+}
+
+fn parse_duration(value: &str) -> anyhow::Result<Duration> {
+    if let Some(ms) = value.strip_suffix("ms") {
+        return Ok(Duration::from_millis(ms.parse()?));
+    }
+    let secs = value.strip_suffix('s').unwrap_or(value);
+    // 负数、NaN 与无穷大在这里报错，而不是在 `from_secs_f64` 中 panic
+    Duration::try_from_secs_f64(secs.parse()?)
+        .map_err(|_| anyhow::anyhow!("invalid chaos latency {value:?}"))
+}
+
+/// 按 `config` 为下载流注入延迟与断开；`config` 为 `None` 时原样透传。
+pub fn inject<S>(
+    inner: S,
+    config: Option<ChaosConfig>,
+) -> impl Stream<Item = GetProgressItem> + Unpin + Send
+where
+    S: Stream<Item = GetProgressItem> + Unpin + Send,
+{
+    Box::pin(stream::unfold(Some(inner), move |inner| async move {
+        let mut inner = inner?;
+        let item = inner.next().await?;
+        if let (Some(config), GetProgressItem::Progress(offset)) = (config, &item) {
+            if !config.latency.is_zero() {
+                tokio::time::sleep(config.latency).await;
+            }
+            if config.should_disconnect(*offset) {
+                tracing::warn!(offset, "chaos: dropping download stream");
+                return None;
+            }
+        }
+        Some((item, Some(inner)))
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ChaosConfig, inject};
+    use iroh_blobs::api::remote::GetProgressItem;
+    use n0_future::{StreamExt, stream};
+    use std::time::Duration;
+
+    #[test]
+    fn chaos_config_parses_all_options() {
+        let config: ChaosConfig = "drop=0.1,latency=200ms,disconnect-after=50MB"
+            .parse()
+            .expect("valid chaos config");
+        assert_eq!(config.drop, 0.1);
+        assert_eq!(config.latency, Duration::from_millis(200));
+        assert_eq!(config.disconnect_after, Some(50 * 1024 * 1024));
+
+        let err = "jitter=5ms"
+            .parse::<ChaosConfig>()
+            .expect_err("unknown option should fail");
+        assert!(err.to_string().contains("unknown chaos option"));
+
+        for latency in ["-1", "inf", "NaN", "-0.5s"] {
+            assert!(
+                format!("latency={latency}").parse::<ChaosConfig>().is_err(),
+                "latency {latency:?} should be rejected"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn inject_disconnects_after_limit() {
+        let items = stream::iter([
+            GetProgressItem::Progress(1024),
+            GetProgressItem::Progress(4096),
+            GetProgressItem::Progress(8192),
+        ]);
+        let config = ChaosConfig {
+            disconnect_after: Some(4096),
+            ..Default::default()
+        };
+
+        let seen = inject(items, Some(config)).collect::<Vec<_>>().await;
+        assert_eq!(seen.len(), 1);
+        assert!(matches!(seen[0], GetProgressItem::Progress(1024)));
+    }
+}
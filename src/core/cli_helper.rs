@@ -2,10 +2,11 @@
 //!
 //! 本模块包含用于向外部 `EventEmitter` 发射事件的便捷函数，
 //! 以及一个命令行环境下的事件发射器实现 `CliEventEmitter`，
-//! 用于在控制台显示文件传输进度条。
+//! 用于在控制台显示文件传输进度条；`JsonEventEmitter` 则把事件逐行以 JSON 写到 stdout。
 
 use crate::core::events::{EventEmitter, TransferEvent};
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use std::io::Write;
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
@@ -61,6 +62,7 @@ impl EventEmitter for CliEventEmitter {
                 processed,
                 total,
                 speed,
+                eta_secs,
                 ..
             } => {
                 let mut guard = self.pb.lock().unwrap_or_else(|error| error.into_inner());
@@ -79,7 +81,7 @@ impl EventEmitter for CliEventEmitter {
                 if let Some(pb) = guard.as_ref() {
                     pb.set_length(*total);
                     pb.set_position(*processed);
-                    pb.set_message(human_bytes_per_sec(*speed));
+                    pb.set_message(progress_message(*speed, *eta_secs));
                 }
             }
 
@@ -105,14 +107,62 @@ impl EventEmitter for CliEventEmitter {
                 }
                 eprintln!("Transfer failed: {message}");
             }
-            TransferEvent::FileNames { .. } => {
-                // skipping
+            TransferEvent::ImportProgress { .. }
+            | TransferEvent::FileNames { .. }
+            | TransferEvent::TicketRefreshed { .. }
+            | TransferEvent::Warning { .. }
+            | TransferEvent::PeerConnected { .. }
+            | TransferEvent::PeerDisconnected { .. }
+            | TransferEvent::Connected { .. } => {
+                // skipping; warnings are printed from the result once the transfer ends
             }
         }
     }
 }
 
+/// 以换行分隔的 JSON（NDJSON）输出事件的发射器，供脚本和其他程序解析。
+///
+/// 每个事件一行，写到 stdout，格式为 [`TransferEvent`] 的序列化结果：
+/// `event`（同 [`TransferEvent::state`]）、`role`、`transfer_id` 以及该事件的字段。
+pub struct JsonEventEmitter;
+
+impl EventEmitter for JsonEventEmitter {
+    fn emit(&self, event: &TransferEvent) {
+        let Ok(line) = serde_json::to_string(event) else {
+            return;
+        };
+        let mut stdout = std::io::stdout().lock();
+        // stdout 被关闭（如管道另一端已退出）时丢弃事件，不影响传输
+        if writeln!(stdout, "{line}").is_ok() {
+            let _ = stdout.flush();
+        }
+    }
+}
+
+/// 把 `text` 渲染为可在终端中扫描的二维码（每个字符表示上下两个模块）。
+pub fn render_qr(text: &str) -> anyhow::Result<String> {
+    let code = qrcode::QrCode::new(text.as_bytes())?;
+    Ok(code
+        .render::<qrcode::render::unicode::Dense1x2>()
+        .dark_color(qrcode::render::unicode::Dense1x2::Light)
+        .light_color(qrcode::render::unicode::Dense1x2::Dark)
+        .quiet_zone(true)
+        .build())
+}
+
 /// 将字节每秒速率格式化为人类可读的字符串。
+/// 进度条右侧的说明：当前速度，以及可用时的预计剩余时间。
+fn progress_message(speed: f64, eta_secs: Option<f64>) -> String {
+    let speed = human_bytes_per_sec(speed);
+    match eta_secs.filter(|eta| eta.is_finite() && *eta > 0.0) {
+        Some(eta) => format!(
+            "{speed}, ETA {}",
+            indicatif::HumanDuration(Duration::from_secs_f64(eta.ceil()))
+        ),
+        None => speed,
+    }
+}
+
 fn human_bytes_per_sec(speed: f64) -> String {
     if speed <= 0.0 {
         return "0 B/s".to_string();
@@ -129,7 +179,62 @@ fn human_bytes_per_sec(speed: f64) -> String {
 
 #[cfg(test)]
 mod tests {
-    use super::human_bytes_per_sec;
+    use super::{human_bytes_per_sec, progress_message, render_qr};
+    use crate::core::events::{Role, TransferEvent, WarningCode};
+
+    #[test]
+    fn events_serialize_as_flat_json_objects() {
+        let progress = serde_json::to_value(TransferEvent::Progress {
+            role: Role::Receiver,
+            transfer_id: 3,
+            processed: 512,
+            total: 1024,
+            speed: 256.0,
+            eta_secs: None,
+        })
+        .expect("event should serialize");
+        assert_eq!(
+            progress,
+            serde_json::json!({
+                "event": "progress",
+                "role": "receiver",
+                "transfer_id": 3,
+                "processed": 512,
+                "total": 1024,
+                "speed": 256.0,
+                "eta_secs": null,
+            })
+        );
+
+        let started = serde_json::to_value(TransferEvent::Started {
+            role: Role::Sender,
+            transfer_id: 3,
+        })
+        .expect("event should serialize");
+        assert_eq!(
+            started,
+            serde_json::json!({ "event": "started", "role": "sender", "transfer_id": 3 })
+        );
+
+        let warning = serde_json::to_value(TransferEvent::Warning {
+            role: Role::Sender,
+            transfer_id: 3,
+            code: WarningCode::SkippedSymlink,
+            detail: "docs/link".to_string(),
+        })
+        .expect("event should serialize");
+        assert_eq!(warning["event"], "warning");
+        assert_eq!(warning["code"], WarningCode::SkippedSymlink.as_str());
+    }
+
+    #[test]
+    fn render_qr_produces_square_block() {
+        let qr = render_qr("sendmer receive blobexample").expect("qr should render");
+        let lines = qr.lines().collect::<Vec<_>>();
+        assert!(lines.len() > 10);
+        let width = lines[0].chars().count();
+        assert!(lines.iter().all(|line| line.chars().count() == width));
+    }
 
     #[test]
     fn human_bytes_per_sec_formats_zero() {
@@ -140,4 +245,14 @@ mod tests {
     fn human_bytes_per_sec_formats_kilobytes_once() {
         assert_eq!(human_bytes_per_sec(2048.0), "2.0 KB/s");
     }
+
+    #[test]
+    fn progress_message_appends_eta_when_known() {
+        assert_eq!(progress_message(2048.0, None), "2.0 KB/s");
+        assert_eq!(progress_message(2048.0, Some(0.0)), "2.0 KB/s");
+        assert_eq!(
+            progress_message(2048.0, Some(29.2)),
+            "2.0 KB/s, ETA 30 seconds"
+        );
+    }
 }
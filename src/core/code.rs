@@ -0,0 +1,299 @@
+//! 口述友好的短分享码（形如 `7-brave-otter`），用于代替很长的 ticket。
+//!
+//! 发送端照常使用随机的节点密钥，另外用分享码派生出一个查找密钥，在 pkarr 上以它
+//! 发布一条会合记录，内容为发送端的 endpoint ID 与集合 hash；接收端用同样的分享码
+//! 算出查找 ID，通过 DNS 查到该记录后拼出完整 ticket。
+//!
+//! 分享码只有约 2^20 种，任何人都能离线算出所有查找密钥并覆盖会合记录，
+//! 因此记录本身不可信：双方需要核对由 endpoint ID 与 hash 算出的 [`fingerprint`]，
+//! 一致后接收端才开始下载。被覆盖的记录最多导致核对失败，而不会下载到他人的内容。
+
+use crate::core::ticket::ShareTicket;
+use iroh::discovery::pkarr::PkarrPublisher;
+use iroh::discovery::{Discovery, EndpointData, UserData, dns::DnsDiscovery};
+use iroh::{EndpointId, SecretKey};
+use iroh_blobs::{BlobFormat, Hash, ticket::BlobTicket};
+use n0_future::StreamExt;
+use rand::seq::IndexedRandom;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+const LOOKUP_KEY_CONTEXT: &str = "sendmer 2025 share code lookup v2";
+const FINGERPRINT_CONTEXT: &str = "sendmer 2025 share code fingerprint v2";
+const USER_DATA_PREFIX: &str = "sendmer:";
+/// 指纹的字节数；64 位足以让伪造者无法暴力搜索出指纹相同的节点密钥
+const FINGERPRINT_BYTES: usize = 8;
+const MAX_CHANNEL: u16 = 99;
+
+const ADJECTIVES: &[&str] = &[
+    "able", "agile", "amber", "ample", "azure", "basic", "bold", "brave", "brief", "bright",
+    "brisk", "busy", "calm", "candid", "clean", "clear", "clever", "cool", "cozy", "crisp",
+    "curly", "dainty", "dapper", "dear", "deep", "eager", "early", "easy", "elder", "epic", "fair",
+    "fancy", "fast", "fine", "firm", "fluffy", "fond", "free", "fresh", "frosty", "funny",
+    "gentle", "giant", "glad", "golden", "grand", "great", "green", "happy", "hardy", "hasty",
+    "honest", "humble", "icy", "jolly", "keen", "kind", "large", "lively", "loyal", "lucky",
+    "mellow", "merry", "mighty", "modest", "neat", "nimble", "noble", "odd", "plain", "polite",
+    "proud", "quick", "quiet", "rapid", "rare", "ready", "rosy", "royal", "rusty", "safe", "shiny",
+    "silent", "silver", "simple", "sleepy", "smart", "snowy", "solid", "spicy", "steady", "sunny",
+    "sweet", "swift", "tidy", "tiny", "vivid", "warm", "wild", "witty",
+];
+
+const NOUNS: &[&str] = &[
+    "acorn", "anchor", "apple", "arrow", "badger", "bamboo", "beacon", "beaver", "berry", "bison",
+    "breeze", "brook", "cactus", "camel", "canyon", "cedar", "cherry", "cloud", "comet", "coral",
+    "cricket", "dolphin", "dragon", "eagle", "ember", "falcon", "fern", "finch", "forest", "fox",
+    "garden", "gecko", "glacier", "harbor", "hazel", "heron", "hill", "island", "jaguar", "kettle",
+    "koala", "lagoon", "lantern", "lemon", "lily", "lion", "lotus", "maple", "meadow", "melon",
+    "meteor", "mango", "moose", "nectar", "nebula", "oak", "ocean", "olive", "orchid", "otter",
+    "owl", "panda", "parrot", "pebble", "pepper", "pine", "planet", "plum", "pony", "puffin",
+    "quartz", "rabbit", "raven", "reef", "river", "robin", "rocket", "saddle", "salmon", "sparrow",
+    "spruce", "squid", "star", "stone", "summit", "thunder", "tiger", "tulip", "turtle", "valley",
+    "violet", "walnut", "whale", "willow", "wolf", "yak", "zebra", "zephyr", "lynx", "mole",
+];
+
+/// 形如 `7-brave-otter` 的分享码。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShareCode {
+    channel: u16,
+    adjective: &'static str,
+    noun: &'static str,
+}
+
+impl ShareCode {
+    /// 随机生成一个分享码。
+    pub fn generate() -> Self {
+        let mut rng = rand::rng();
+        Self {
+            channel: rand::random_range(1..=MAX_CHANNEL),
+            adjective: ADJECTIVES.choose(&mut rng).copied().unwrap_or("brave"),
+            noun: NOUNS.choose(&mut rng).copied().unwrap_or("otter"),
+        }
+    }
+
+    /// 由分享码派生的查找密钥，只用于签名会合记录。
+    ///
+    /// 任何知道（或猜到）分享码的人都能算出它，不能作为发送端的身份。
+    fn lookup_key(&self) -> SecretKey {
+        SecretKey::from_bytes(&blake3::derive_key(
+            LOOKUP_KEY_CONTEXT,
+            self.to_string().as_bytes(),
+        ))
+    }
+
+    /// 会合记录在 pkarr 中的查找 ID。
+    pub fn lookup_id(&self) -> EndpointId {
+        self.lookup_key().public()
+    }
+
+    /// 以查找密钥发布会合记录，指向发送端 `endpoint_id` 上的集合 `hash`。
+    ///
+    /// 返回的发布器被丢弃后停止定期重新发布。
+    pub fn publish(&self, endpoint_id: EndpointId, hash: Hash) -> anyhow::Result<CodePublisher> {
+        let publisher = CodePublisher {
+            publisher: PkarrPublisher::n0_dns().build(self.lookup_key()),
+            endpoint_id,
+        };
+        publisher.update(hash)?;
+        Ok(publisher)
+    }
+
+    /// 通过 DNS 查找分享码对应的会合记录。
+    ///
+    /// 记录可能被他人覆盖：调用方必须让用户把 [`ResolvedCode::fingerprint`]
+    /// 与发送端显示的指纹核对一致（或使用 [`ResolvedCode::verify`]）后再下载。
+    pub async fn resolve(&self) -> anyhow::Result<ResolvedCode> {
+        let discovery = DnsDiscovery::n0_dns().build();
+        let mut items = discovery
+            .resolve(self.lookup_id())
+            .ok_or_else(|| anyhow::anyhow!("discovery is not available"))?;
+        while let Some(item) = items.next().await {
+            let item = match item {
+                Ok(item) => item,
+                Err(error) => {
+                    tracing::debug!(error = %error, "code lookup failed");
+                    continue;
+                }
+            };
+            let Some((endpoint_id, hash)) = item.user_data().as_ref().and_then(parse_record) else {
+                continue;
+            };
+            // 发送端的地址通过它自己的 pkarr 记录解析
+            let addr = iroh::EndpointAddr::new(endpoint_id);
+            return Ok(ResolvedCode {
+                ticket: BlobTicket::new(addr, hash, BlobFormat::HashSeq).into(),
+                fingerprint: fingerprint(endpoint_id, hash),
+            });
+        }
+        anyhow::bail!(
+            "no share found for code {self}, check the code and that the sender is still running"
+        )
+    }
+}
+
+/// 分享码会合记录的发布器，丢弃后停止重新发布。
+#[derive(Debug, Clone)]
+pub struct CodePublisher {
+    publisher: PkarrPublisher,
+    endpoint_id: EndpointId,
+}
+
+impl CodePublisher {
+    /// 让会合记录指向新的集合 `hash`（例如 `--watch` 重新导入之后）。
+    pub fn update(&self, hash: Hash) -> anyhow::Result<()> {
+        let data = EndpointData::new([]).with_user_data(Some(record(self.endpoint_id, hash)?));
+        self.publisher.update_endpoint_data(&data);
+        Ok(())
+    }
+}
+
+/// 由分享码查到、尚待核对的分享。
+#[derive(Debug, Clone)]
+pub struct ResolvedCode {
+    pub ticket: ShareTicket,
+    /// 由记录中的 endpoint ID 与 hash 算出的指纹，应与发送端显示的一致
+    pub fingerprint: String,
+}
+
+impl ResolvedCode {
+    /// 与发送端显示的指纹比较（忽略大小写与分隔符），一致时返回 ticket。
+    pub fn verify(self, expected: &str) -> anyhow::Result<ShareTicket> {
+        anyhow::ensure!(
+            normalize_fingerprint(expected) == normalize_fingerprint(&self.fingerprint),
+            "fingerprint mismatch: the code points to {}, not {expected}; \
+             someone else may have published under this code, do not use it",
+            self.fingerprint
+        );
+        Ok(self.ticket)
+    }
+}
+
+/// 发送端 endpoint ID 与集合 hash 的短指纹，形如 `1a2b-3c4d-5e6f-7a8b`，供双方口头核对。
+pub fn fingerprint(endpoint_id: EndpointId, hash: Hash) -> String {
+    let mut input = Vec::with_capacity(64);
+    input.extend_from_slice(endpoint_id.as_bytes());
+    input.extend_from_slice(hash.as_bytes());
+    let digest = blake3::derive_key(FINGERPRINT_CONTEXT, &input);
+    digest[..FINGERPRINT_BYTES]
+        .chunks(2)
+        .map(|pair| format!("{:02x}{:02x}", pair[0], pair[1]))
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+fn normalize_fingerprint(fingerprint: &str) -> String {
+    fingerprint
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+fn record(endpoint_id: EndpointId, hash: Hash) -> anyhow::Result<UserData> {
+    UserData::try_from(format!("{USER_DATA_PREFIX}{endpoint_id}:{}", hash.to_hex()))
+        .map_err(|error| anyhow::anyhow!("invalid discovery user data: {error}"))
+}
+
+fn parse_record(user_data: &UserData) -> Option<(EndpointId, Hash)> {
+    let user_data = user_data.to_string();
+    let (endpoint_id, hash) = user_data.strip_prefix(USER_DATA_PREFIX)?.split_once(':')?;
+    Some((endpoint_id.parse().ok()?, Hash::from_str(hash).ok()?))
+}
+
+impl Display for ShareCode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-{}-{}", self.channel, self.adjective, self.noun)
+    }
+}
+
+impl FromStr for ShareCode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let normalized = s.trim().to_ascii_lowercase();
+        let invalid =
+            || anyhow::anyhow!("invalid code {s:?}, expected something like 7-brave-otter");
+        let mut parts = normalized.split(['-', ' ']).filter(|part| !part.is_empty());
+        let (Some(channel), Some(adjective), Some(noun), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(invalid());
+        };
+        let channel = channel
+            .parse::<u16>()
+            .ok()
+            .filter(|channel| (1..=MAX_CHANNEL).contains(channel))
+            .ok_or_else(invalid)?;
+        let adjective = ADJECTIVES
+            .iter()
+            .find(|word| **word == adjective)
+            .ok_or_else(invalid)?;
+        let noun = NOUNS
+            .iter()
+            .find(|word| **word == noun)
+            .ok_or_else(invalid)?;
+        Ok(Self {
+            channel,
+            adjective,
+            noun,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ShareCode, fingerprint, parse_record, record};
+
+    #[test]
+    fn share_code_round_trips_and_normalizes() {
+        let code = ShareCode::generate();
+        let parsed: ShareCode = code.to_string().parse().expect("generated code parses");
+        assert_eq!(parsed, code);
+        assert_eq!(parsed.lookup_id(), code.lookup_id());
+
+        let spoken: ShareCode = " 7 Brave Otter ".parse().expect("spaces and case are fine");
+        assert_eq!(spoken.to_string(), "7-brave-otter");
+        assert!("7-brave".parse::<ShareCode>().is_err());
+        assert!("0-brave-otter".parse::<ShareCode>().is_err());
+        assert!("7-brave-unicorn".parse::<ShareCode>().is_err());
+    }
+
+    #[test]
+    fn record_carries_endpoint_and_hash() {
+        let endpoint_id = iroh::SecretKey::generate(&mut rand::rng()).public();
+        let hash = iroh_blobs::Hash::new(b"demo");
+        let user_data = record(endpoint_id, hash).expect("record fits in user data");
+        assert_eq!(parse_record(&user_data), Some((endpoint_id, hash)));
+    }
+
+    #[test]
+    fn fingerprint_binds_endpoint_and_hash() {
+        let endpoint_id = iroh::SecretKey::generate(&mut rand::rng()).public();
+        let other_id = iroh::SecretKey::generate(&mut rand::rng()).public();
+        let hash = iroh_blobs::Hash::new(b"demo");
+        let expected = fingerprint(endpoint_id, hash);
+        assert_eq!(expected.len(), 19);
+        assert_ne!(expected, fingerprint(other_id, hash));
+        assert_ne!(
+            expected,
+            fingerprint(endpoint_id, iroh_blobs::Hash::new(b"other"))
+        );
+
+        let resolved = |id| super::ResolvedCode {
+            ticket: iroh_blobs::ticket::BlobTicket::new(
+                iroh::EndpointAddr::new(id),
+                hash,
+                iroh_blobs::BlobFormat::HashSeq,
+            )
+            .into(),
+            fingerprint: fingerprint(id, hash),
+        };
+        let ticket = resolved(endpoint_id)
+            .verify(&expected.to_ascii_uppercase().replace('-', " "))
+            .expect("same fingerprint");
+        assert_eq!(ticket.ticket.addr().id, endpoint_id);
+        let err = resolved(other_id)
+            .verify(&expected)
+            .expect_err("forged record should be rejected");
+        assert!(err.to_string().contains("fingerprint mismatch"));
+    }
+}
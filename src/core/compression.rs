@@ -0,0 +1,183 @@
+//! 可选的 blob 压缩。
+//!
+//! 发送端在导入时压缩每个文件，并在集合中附加一个名为
+//! `.sendmer-compressed.<算法>` 的空条目作为标记；接收端据此识别压缩方式，
+//! 导出时自动解压，标记条目本身不会被导出。
+//! 压缩后的数据仍按 blob hash 校验，hash 对应的是压缩后的内容。
+
+use async_compression::Level;
+use async_compression::tokio::bufread::{ZstdDecoder, ZstdEncoder};
+use bytes::Bytes;
+use iroh_blobs::{Hash, api::Store, format::collection::Collection};
+use n0_future::Stream;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use tokio::io::{AsyncRead, AsyncReadExt, BufReader};
+
+/// 集合中压缩标记条目名称的前缀，后接算法名。
+const MARKER_PREFIX: &str = ".sendmer-compressed.";
+const DEFAULT_ZSTD_LEVEL: i32 = 3;
+const MAX_ZSTD_LEVEL: i32 = 22;
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// 压缩算法及级别。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Zstd { level: i32 },
+}
+
+impl Compression {
+    const fn algorithm(self) -> &'static str {
+        match self {
+            Self::Zstd { .. } => "zstd",
+        }
+    }
+
+    /// 写入集合的标记条目名称。
+    pub(crate) fn marker_name(self) -> String {
+        format!("{MARKER_PREFIX}{}", self.algorithm())
+    }
+
+    /// 把 `reader` 中的数据压缩后按块输出，供 `add_stream` 导入。
+    pub(crate) fn compress<R>(self, reader: R) -> impl Stream<Item = std::io::Result<Bytes>>
+    where
+        R: AsyncRead + Unpin + Send + 'static,
+    {
+        match self {
+            Self::Zstd { level } => read_chunks(ZstdEncoder::with_quality(
+                BufReader::new(reader),
+                Level::Precise(level),
+            )),
+        }
+    }
+
+    /// 读取 `hash` 对应 blob 解压后的内容。
+    pub(crate) fn decompress(
+        self,
+        db: &Store,
+        hash: Hash,
+    ) -> impl AsyncRead + Unpin + Send + use<> {
+        match self {
+            Self::Zstd { .. } => ZstdDecoder::new(BufReader::new(db.reader(hash))),
+        }
+    }
+}
+
+/// 以固定大小的块读取 `reader`。
+pub(crate) fn read_chunks<R>(reader: R) -> impl Stream<Item = std::io::Result<Bytes>>
+where
+    R: AsyncRead + Unpin + Send + 'static,
+{
+    n0_future::stream::unfold(Some(reader), |reader| async move {
+        let mut reader = reader?;
+        let mut buf = vec![0u8; CHUNK_SIZE];
+        match reader.read(&mut buf).await {
+            Ok(0) => None,
+            Ok(n) => {
+                buf.truncate(n);
+                Some((Ok(Bytes::from(buf)), Some(reader)))
+            }
+            Err(error) => Some((Err(error), None)),
+        }
+    })
+}
+
+/// 去掉集合中的压缩标记条目，返回其余条目与识别出的压缩方式。
+pub(crate) fn split_collection(
+    collection: &Collection,
+) -> anyhow::Result<(Collection, Option<Compression>)> {
+    let mut compression = None;
+    let mut entries = Vec::new();
+    for (name, hash) in collection.iter() {
+        match name.strip_prefix(MARKER_PREFIX) {
+            Some("zstd") => {
+                compression = Some(Compression::Zstd {
+                    level: DEFAULT_ZSTD_LEVEL,
+                });
+            }
+            Some(algorithm) => anyhow::bail!(
+                "the share is compressed with unsupported algorithm {algorithm:?}, upgrade sendmer"
+            ),
+            None => entries.push((name.clone(), *hash)),
+        }
+    }
+    Ok((entries.into_iter().collect(), compression))
+}
+
+impl Display for Compression {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Zstd { level } => write!(f, "zstd:{level}"),
+        }
+    }
+}
+
+impl FromStr for Compression {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (algorithm, level) = match s.split_once(':') {
+            Some((algorithm, level)) => (algorithm, Some(level)),
+            None => (s, None),
+        };
+        anyhow::ensure!(
+            algorithm.eq_ignore_ascii_case("zstd"),
+            "unsupported compression {algorithm:?}, expected zstd[:level]"
+        );
+        let level = match level {
+            Some(level) => level
+                .parse::<i32>()
+                .ok()
+                .filter(|level| (1..=MAX_ZSTD_LEVEL).contains(level))
+                .ok_or_else(|| {
+                    anyhow::anyhow!("invalid zstd level {level:?}, expected 1 to {MAX_ZSTD_LEVEL}")
+                })?,
+            None => DEFAULT_ZSTD_LEVEL,
+        };
+        Ok(Self::Zstd { level })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Compression, split_collection};
+    use iroh_blobs::{Hash, format::collection::Collection};
+
+    #[test]
+    fn compression_parses_algorithm_and_level() {
+        assert_eq!(
+            "zstd".parse::<Compression>().expect("default level"),
+            Compression::Zstd { level: 3 }
+        );
+        assert_eq!(
+            "zstd:19".parse::<Compression>().expect("explicit level"),
+            Compression::Zstd { level: 19 }
+        );
+        assert!("zstd:0".parse::<Compression>().is_err());
+        assert!("gzip".parse::<Compression>().is_err());
+    }
+
+    #[test]
+    fn split_collection_strips_marker() {
+        let marker = Compression::Zstd { level: 9 }.marker_name();
+        let collection: Collection = [
+            (marker, Hash::new(b"")),
+            ("docs/a.txt".to_string(), Hash::new(b"a")),
+        ]
+        .into_iter()
+        .collect();
+        let (entries, compression) = split_collection(&collection).expect("known marker");
+        assert!(matches!(compression, Some(Compression::Zstd { .. })));
+        assert_eq!(
+            entries
+                .iter()
+                .map(|(name, _)| name.as_str())
+                .collect::<Vec<_>>(),
+            ["docs/a.txt"]
+        );
+
+        let unknown: Collection =
+            std::iter::once((".sendmer-compressed.lz4".to_string(), Hash::new(b""))).collect();
+        assert!(split_collection(&unknown).is_err());
+    }
+}
@@ -0,0 +1,253 @@
+//! 接收端连接建立：当 ticket 同时包含直连地址与 relay 时并发竞速（happy eyeballs）。
+//!
+//! 直连尝试先行启动，relay 尝试在短暂延迟后跟进，先成功者胜出；
+//! 之后由 iroh 在后台继续尝试打洞升级为直连。
+
+use crate::core::error::{CatalogError, ErrorKind};
+use crate::core::events::ConnectionVia;
+use crate::core::options::{AddrInfoOptions, apply_options};
+use crate::core::results::ConnectionInfo;
+use iroh::endpoint::{Connection, ConnectionType};
+use iroh::{Endpoint, EndpointAddr, EndpointId, Watcher};
+use std::time::Duration;
+use tokio::select;
+
+/// 直连尝试相对 relay 尝试的领先时间。
+const DIRECT_HEAD_START: Duration = Duration::from_millis(250);
+
+/// 建立连接时实际使用的地址路径，仅用于日志。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ConnectPath {
+    /// 通过 ticket 中的直连 IP 地址建立
+    Direct,
+    /// 通过 ticket 中的 relay 地址建立
+    Relay,
+    /// ticket 不含地址信息，通过 discovery 解析后建立
+    Discovered,
+}
+
+impl ConnectPath {
+    const fn as_str(self) -> &'static str {
+        match self {
+            Self::Direct => "direct",
+            Self::Relay => "relay",
+            Self::Discovered => "discovered",
+        }
+    }
+}
+
+/// 连接到 `addr`，在地址类型混合时对直连与 relay 进行竞速。
+///
+/// 两条路径都失败时，返回的错误同时包含两者的原因。
+pub async fn connect(
+    endpoint: &Endpoint,
+    addr: &EndpointAddr,
+    alpn: &[u8],
+) -> anyhow::Result<Connection> {
+    let Some((direct_addr, relay_addr)) = split_addr(addr) else {
+        let path = single_path(addr);
+        let connection = endpoint
+            .connect(addr.clone(), alpn)
+            .await
+            .map_err(|error| connect_failed(&error))?;
+        tracing::info!(path = path.as_str(), "connected to sender");
+        return Ok(connection);
+    };
+
+    let direct = endpoint.connect(direct_addr, alpn);
+    let relay = async {
+        tokio::time::sleep(DIRECT_HEAD_START).await;
+        endpoint.connect(relay_addr, alpn).await
+    };
+    tokio::pin!(direct, relay);
+
+    let mut direct_error = None;
+    let mut relay_error = None;
+    loop {
+        select! {
+            result = &mut direct, if direct_error.is_none() => match result {
+                Ok(connection) => {
+                    tracing::info!(path = "direct", "connected to sender");
+                    return Ok(connection);
+                }
+                Err(error) => {
+                    tracing::debug!(error = %error, "direct connection attempt failed");
+                    direct_error = Some(error);
+                }
+            },
+            result = &mut relay, if relay_error.is_none() => match result {
+                Ok(connection) => {
+                    tracing::info!(path = "relay", "connected to sender");
+                    return Ok(connection);
+                }
+                Err(error) => {
+                    tracing::debug!(error = %error, "relay connection attempt failed");
+                    relay_error = Some(error);
+                }
+            },
+        }
+
+        if let (Some(direct), Some(relay)) = (&direct_error, &relay_error) {
+            return Err(both_paths_failed(direct, relay));
+        }
+    }
+}
+
+/// 与 [`connect`] 相同，但超过 `timeout` 仍未连上时返回 [`ErrorKind::ConnectFailed`]。
+pub async fn connect_within(
+    endpoint: &Endpoint,
+    addr: &EndpointAddr,
+    alpn: &[u8],
+    timeout: Option<Duration>,
+) -> anyhow::Result<Connection> {
+    let Some(timeout) = timeout else {
+        return connect(endpoint, addr, alpn).await;
+    };
+    tokio::time::timeout(timeout, connect(endpoint, addr, alpn))
+        .await
+        .map_err(|_| connect_failed(&format!("timed out after {timeout:?}")))?
+}
+
+/// 查询与 `remote` 的连接当前所走的路径及 `connection` 测得的往返时延。
+///
+/// iroh 会在连接建立后继续尝试打洞，因此同一连接的路径可能从 relay 变为直连；
+/// 尚未确定路径时返回 `None`。
+pub fn connection_info(
+    endpoint: &Endpoint,
+    remote: EndpointId,
+    connection: &Connection,
+) -> Option<ConnectionInfo> {
+    let conn_type = endpoint.conn_type(remote)?.get();
+    let (via, remote) = classify_conn_type(&conn_type)?;
+    Some(ConnectionInfo {
+        via,
+        remote,
+        rtt: connection.rtt(),
+    })
+}
+
+/// 同时使用两种路径（`Mixed`）时数据仍经 relay 转发，按 relay 计。
+fn classify_conn_type(conn_type: &ConnectionType) -> Option<(ConnectionVia, String)> {
+    match conn_type {
+        ConnectionType::Direct(addr) => Some((ConnectionVia::Direct, addr.to_string())),
+        ConnectionType::Relay(url) | ConnectionType::Mixed(_, url) => {
+            Some((ConnectionVia::Relay, url.to_string()))
+        }
+        ConnectionType::None => None,
+    }
+}
+
+fn connect_failed(error: &impl std::fmt::Display) -> anyhow::Error {
+    CatalogError::new(
+        ErrorKind::ConnectFailed,
+        format!("failed to connect to sender: {error}"),
+    )
+    .into()
+}
+
+fn both_paths_failed(
+    direct: &impl std::fmt::Display,
+    relay: &impl std::fmt::Display,
+) -> anyhow::Error {
+    connect_failed(&format!("direct: {direct}; relay: {relay}"))
+}
+
+/// 将同时包含直连地址和 relay 的地址拆分为两份，分别只保留一种。
+///
+/// 只包含一种地址（或不含地址）时返回 `None`，无需竞速。
+fn split_addr(addr: &EndpointAddr) -> Option<(EndpointAddr, EndpointAddr)> {
+    if addr.ip_addrs().next().is_none() || addr.relay_urls().next().is_none() {
+        return None;
+    }
+
+    let mut direct = addr.clone();
+    apply_options(&mut direct, AddrInfoOptions::Addresses);
+    let mut relay = addr.clone();
+    apply_options(&mut relay, AddrInfoOptions::Relay);
+    Some((direct, relay))
+}
+
+fn single_path(addr: &EndpointAddr) -> ConnectPath {
+    if addr.ip_addrs().next().is_some() {
+        ConnectPath::Direct
+    } else if addr.relay_urls().next().is_some() {
+        ConnectPath::Relay
+    } else {
+        ConnectPath::Discovered
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ConnectPath, both_paths_failed, classify_conn_type, single_path, split_addr};
+    use crate::core::error::{ErrorKind, classify};
+    use crate::core::events::ConnectionVia;
+    use iroh::endpoint::ConnectionType;
+    use iroh::{EndpointAddr, RelayUrl, SecretKey, TransportAddr};
+    use std::str::FromStr;
+
+    fn node_addr() -> EndpointAddr {
+        EndpointAddr::new(SecretKey::generate(&mut rand::rng()).public())
+    }
+
+    fn relay_url() -> RelayUrl {
+        RelayUrl::from_str("https://relay.example").expect("valid relay url")
+    }
+
+    #[test]
+    fn split_addr_separates_direct_and_relay() {
+        let addr = node_addr()
+            .with_relay_url(relay_url())
+            .with_ip_addr("127.0.0.1:7777".parse().expect("valid socket addr"));
+
+        let (direct, relay) = split_addr(&addr).expect("mixed addr should split");
+
+        assert!(
+            direct
+                .addrs
+                .iter()
+                .all(|addr| matches!(addr, TransportAddr::Ip(_)))
+        );
+        assert!(
+            relay
+                .addrs
+                .iter()
+                .all(|addr| matches!(addr, TransportAddr::Relay(_)))
+        );
+        assert_eq!(direct.addrs.len() + relay.addrs.len(), addr.addrs.len());
+    }
+
+    #[test]
+    fn split_addr_skips_single_kind_addresses() {
+        let relay_only = node_addr().with_relay_url(relay_url());
+        assert!(split_addr(&relay_only).is_none());
+        assert_eq!(single_path(&relay_only), ConnectPath::Relay);
+
+        let id_only = node_addr();
+        assert!(split_addr(&id_only).is_none());
+        assert_eq!(single_path(&id_only), ConnectPath::Discovered);
+    }
+
+    #[test]
+    fn mixed_connections_are_reported_as_relayed() {
+        let addr = "127.0.0.1:7777".parse().expect("valid socket addr");
+        assert_eq!(
+            classify_conn_type(&ConnectionType::Direct(addr)),
+            Some((ConnectionVia::Direct, "127.0.0.1:7777".to_string()))
+        );
+        assert_eq!(
+            classify_conn_type(&ConnectionType::Mixed(addr, relay_url())),
+            Some((ConnectionVia::Relay, relay_url().to_string()))
+        );
+        assert_eq!(classify_conn_type(&ConnectionType::None), None);
+    }
+
+    #[test]
+    fn both_failures_are_reported() {
+        let error = both_paths_failed(&"no route to host", &"relay unreachable");
+        assert_eq!(classify(&error), Some(ErrorKind::ConnectFailed));
+        let message = error.to_string();
+        assert!(message.contains("direct: no route to host"), "{message}");
+        assert!(message.contains("relay: relay unreachable"), "{message}");
+    }
+}
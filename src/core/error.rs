@@ -0,0 +1,264 @@
+//! 常见失败模式的错误目录：为错误附加分类，并给出一行补救提示。
+//!
+//! 库代码在已知的失败点构造 [`CatalogError`]，上层（例如 CLI）通过
+//! [`remediation_hint`] 在错误链中查找分类并展示对应提示。
+//! 公开的收发入口返回 [`Error`]，调用方可以直接按失败模式分支。
+
+use iroh_blobs::get::GetError;
+use std::fmt::{Display, Formatter};
+
+/// 已知失败模式的分类。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// ticket 无法解析
+    InvalidTicket,
+    /// 导出目标已存在
+    TargetExists,
+    /// 无法连接到发送端
+    ConnectFailed,
+    /// 磁盘空间不足
+    NoSpace,
+    /// 权限不足
+    PermissionDenied,
+    /// 分享口令错误
+    WrongPassword,
+    /// 传输过程中发送端断开或停止分享
+    ProviderGone,
+    /// 收到的数据未通过校验
+    VerificationFailed,
+    /// 写出接收到的文件失败
+    ExportIo,
+    /// 传输被取消
+    Cancelled,
+}
+
+impl ErrorKind {
+    /// 返回面向用户的一行补救提示。
+    pub const fn hint(&self) -> &'static str {
+        match self {
+            Self::InvalidTicket => {
+                "copy the complete ticket printed by `sendmer send`, it starts with `blob`"
+            }
+            Self::TargetExists => {
+                "remove the existing file or choose another location with --output-dir"
+            }
+            Self::ConnectFailed => {
+                "make sure the sender is still running, or ask for a ticket with more address info (--ticket-type relay-and-addresses)"
+            }
+            Self::NoSpace => {
+                "free up disk space in the output and temp directories, then retry; \
+                 `sendmer receive --force` skips the free space check"
+            }
+            Self::PermissionDenied => "check the permissions of the files and directories involved",
+            Self::WrongPassword => {
+                "ask the sender for the password printed by `sendmer send --password`"
+            }
+            Self::ProviderGone => {
+                "ask the sender to keep `sendmer send` running, then retry; data already received is kept with --resume"
+            }
+            Self::VerificationFailed => {
+                "the data was corrupted in transit, retry or raise --repair-attempts"
+            }
+            Self::ExportIo => "check that the output directory is writable and on a healthy disk",
+            Self::Cancelled => "run the command again to restart the transfer",
+        }
+    }
+}
+
+/// 带有分类的错误。
+#[derive(Debug)]
+pub struct CatalogError {
+    kind: ErrorKind,
+    message: String,
+}
+
+impl CatalogError {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+        }
+    }
+
+    pub const fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+}
+
+impl Display for CatalogError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for CatalogError {}
+
+/// 公开收发 API 返回的错误，按失败模式区分。
+///
+/// 每个变体包装完整的错误链，`Display` 与 `source` 均委托给它。
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum Error {
+    #[error(transparent)]
+    InvalidTicket(anyhow::Error),
+    #[error(transparent)]
+    TargetExists(anyhow::Error),
+    #[error(transparent)]
+    ConnectFailed(anyhow::Error),
+    #[error(transparent)]
+    NoSpace(anyhow::Error),
+    #[error(transparent)]
+    PermissionDenied(anyhow::Error),
+    #[error(transparent)]
+    WrongPassword(anyhow::Error),
+    #[error(transparent)]
+    ProviderGone(anyhow::Error),
+    #[error(transparent)]
+    VerificationFailed(anyhow::Error),
+    #[error(transparent)]
+    ExportIo(anyhow::Error),
+    #[error(transparent)]
+    Cancelled(anyhow::Error),
+    /// 未归类的失败
+    #[error(transparent)]
+    Other(anyhow::Error),
+}
+
+impl Error {
+    /// 错误的分类；未归类的失败返回 `None`。
+    pub const fn kind(&self) -> Option<ErrorKind> {
+        match self {
+            Self::InvalidTicket(_) => Some(ErrorKind::InvalidTicket),
+            Self::TargetExists(_) => Some(ErrorKind::TargetExists),
+            Self::ConnectFailed(_) => Some(ErrorKind::ConnectFailed),
+            Self::NoSpace(_) => Some(ErrorKind::NoSpace),
+            Self::PermissionDenied(_) => Some(ErrorKind::PermissionDenied),
+            Self::WrongPassword(_) => Some(ErrorKind::WrongPassword),
+            Self::ProviderGone(_) => Some(ErrorKind::ProviderGone),
+            Self::VerificationFailed(_) => Some(ErrorKind::VerificationFailed),
+            Self::ExportIo(_) => Some(ErrorKind::ExportIo),
+            Self::Cancelled(_) => Some(ErrorKind::Cancelled),
+            Self::Other(_) => None,
+        }
+    }
+}
+
+impl From<anyhow::Error> for Error {
+    fn from(error: anyhow::Error) -> Self {
+        let error = match error.downcast::<Self>() {
+            Ok(error) => return error,
+            Err(error) => error,
+        };
+        let Some(kind) = classify(&error) else {
+            return Self::Other(error);
+        };
+        match kind {
+            ErrorKind::InvalidTicket => Self::InvalidTicket(error),
+            ErrorKind::TargetExists => Self::TargetExists(error),
+            ErrorKind::ConnectFailed => Self::ConnectFailed(error),
+            ErrorKind::NoSpace => Self::NoSpace(error),
+            ErrorKind::PermissionDenied => Self::PermissionDenied(error),
+            ErrorKind::WrongPassword => Self::WrongPassword(error),
+            ErrorKind::ProviderGone => Self::ProviderGone(error),
+            ErrorKind::VerificationFailed => Self::VerificationFailed(error),
+            ErrorKind::ExportIo => Self::ExportIo(error),
+            ErrorKind::Cancelled => Self::Cancelled(error),
+        }
+    }
+}
+
+/// 在错误链中查找分类：优先使用 [`CatalogError`] 与 [`Error`]，
+/// 其次识别下载错误与底层 IO 错误。
+pub fn classify(error: &anyhow::Error) -> Option<ErrorKind> {
+    error.chain().find_map(|cause| {
+        if let Some(error) = cause.downcast_ref::<CatalogError>() {
+            return Some(error.kind());
+        }
+        if let Some(error) = cause.downcast_ref::<Error>() {
+            return error.kind();
+        }
+        if let Some(error) = cause.downcast_ref::<GetError>() {
+            return classify_get(error);
+        }
+        cause.downcast_ref::<std::io::Error>().and_then(classify_io)
+    })
+}
+
+/// 返回错误对应的补救提示（若已知）。
+pub fn remediation_hint(error: &anyhow::Error) -> Option<&'static str> {
+    classify(error).map(|kind| kind.hint())
+}
+
+const fn classify_get(error: &GetError) -> Option<ErrorKind> {
+    match error {
+        GetError::InitialNext { .. }
+        | GetError::ConnectedNext { .. }
+        | GetError::AtBlobHeaderNext { .. } => Some(ErrorKind::ProviderGone),
+        GetError::Decode { .. } => Some(ErrorKind::VerificationFailed),
+        GetError::IrpcSend { .. }
+        | GetError::AtClosingNext { .. }
+        | GetError::BadRequest { .. }
+        | GetError::LocalFailure { .. } => None,
+    }
+}
+
+fn classify_io(error: &std::io::Error) -> Option<ErrorKind> {
+    match error.kind() {
+        std::io::ErrorKind::StorageFull => Some(ErrorKind::NoSpace),
+        std::io::ErrorKind::PermissionDenied => Some(ErrorKind::PermissionDenied),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CatalogError, Error, ErrorKind, classify, remediation_hint};
+
+    #[test]
+    fn classify_finds_catalog_error_behind_context() {
+        let error = anyhow::Error::new(CatalogError::new(ErrorKind::TargetExists, "exists"))
+            .context("error: exists");
+        assert_eq!(classify(&error), Some(ErrorKind::TargetExists));
+        assert!(remediation_hint(&error).is_some_and(|hint| hint.contains("--output-dir")));
+    }
+
+    #[test]
+    fn classify_recognizes_io_errors() {
+        let error = anyhow::Error::new(std::io::Error::from(std::io::ErrorKind::StorageFull));
+        assert_eq!(classify(&error), Some(ErrorKind::NoSpace));
+    }
+
+    #[test]
+    fn error_from_anyhow_picks_the_matching_variant() {
+        let error = Error::from(
+            anyhow::Error::new(CatalogError::new(
+                ErrorKind::Cancelled,
+                "Operation cancelled",
+            ))
+            .context("error: Operation cancelled"),
+        );
+        assert!(matches!(error, Error::Cancelled(_)));
+        assert_eq!(error.to_string(), "error: Operation cancelled");
+
+        let error = Error::from(anyhow::anyhow!("boom"));
+        assert!(matches!(error, Error::Other(_)));
+        assert_eq!(error.kind(), None);
+    }
+
+    #[test]
+    fn classify_sees_through_converted_errors() {
+        let error = Error::from(anyhow::Error::new(CatalogError::new(
+            ErrorKind::WrongPassword,
+            "wrong password",
+        )));
+        assert_eq!(
+            classify(&anyhow::Error::new(error)),
+            Some(ErrorKind::WrongPassword)
+        );
+    }
+
+    #[test]
+    fn classify_ignores_unknown_errors() {
+        assert_eq!(classify(&anyhow::anyhow!("boom")), None);
+    }
+}
@@ -0,0 +1,275 @@
+//! 带背压控制的事件发射器：在独立线程中投递事件，避免慢速前端拖慢传输。
+//!
+//! [`EventEmitter::emit`] 在传输的热循环中被同步调用。[`QueuedEventEmitter`]
+//! 只把事件放入有界队列后立即返回：队尾已有同角色、同类型的进度事件（`Progress`、
+//! `ImportProgress`）时直接合并为最新值，队列已满时丢弃新的进度事件；
+//! 其余状态变化事件始终入队，不会丢失。
+//!
+//! 发射器被丢弃时不等待后台线程：线程投递完已入队的事件后自行退出，
+//! 丢弃可以发生在 tokio 工作线程上而不会阻塞运行时。需要确认事件已送达时
+//! （例如进程即将退出）调用 [`QueuedEventEmitter::flush`]。
+
+use crate::core::events::{EventEmitter, TransferEvent};
+use std::collections::VecDeque;
+use std::sync::{Arc, Condvar, Mutex};
+use tokio::sync::watch;
+
+/// 默认队列容量，超过后新的进度事件会被丢弃。
+pub const DEFAULT_QUEUE_CAPACITY: usize = 64;
+
+#[derive(Default)]
+struct Queue {
+    events: VecDeque<TransferEvent>,
+    /// 累计入队的事件数（合并的进度事件不重复计数）
+    enqueued: u64,
+    dropped: u64,
+    closed: bool,
+}
+
+struct Shared {
+    queue: Mutex<Queue>,
+    ready: Condvar,
+    /// 累计投递的事件数
+    delivered: watch::Sender<u64>,
+}
+
+/// 在后台线程中把事件转发给内部发射器。
+pub struct QueuedEventEmitter {
+    shared: Arc<Shared>,
+    capacity: usize,
+    /// 后台线程是否已启动；未启动时事件不会被投递，`flush` 立即返回
+    running: bool,
+}
+
+impl QueuedEventEmitter {
+    pub fn new(inner: Arc<dyn EventEmitter>, capacity: usize) -> Self {
+        let shared = Arc::new(Shared {
+            queue: Mutex::new(Queue::default()),
+            ready: Condvar::new(),
+            delivered: watch::Sender::new(0),
+        });
+        let worker_shared = shared.clone();
+        // 线程分离运行，队列关闭且清空后退出
+        let running = match std::thread::Builder::new()
+            .name("sendmer-events".to_string())
+            .spawn(move || deliver(&worker_shared, inner.as_ref()))
+        {
+            Ok(_) => true,
+            Err(error) => {
+                tracing::warn!(error = %error, "failed to start event delivery thread");
+                false
+            }
+        };
+        Self {
+            shared,
+            capacity: capacity.max(1),
+            running,
+        }
+    }
+
+    /// 以默认容量包装 `inner`。
+    pub fn wrap(inner: Arc<dyn EventEmitter>) -> Arc<dyn EventEmitter> {
+        Arc::new(Self::new(inner, DEFAULT_QUEUE_CAPACITY))
+    }
+
+    /// 因队列已满而被丢弃的进度事件数量。
+    pub fn dropped(&self) -> u64 {
+        self.shared.queue.lock().map_or(0, |queue| queue.dropped)
+    }
+
+    /// 等待此前入队的事件全部投递给内部发射器。
+    pub async fn flush(&self) {
+        if !self.running {
+            return;
+        }
+        let Ok(target) = self.shared.queue.lock().map(|queue| queue.enqueued) else {
+            return;
+        };
+        let mut delivered = self.shared.delivered.subscribe();
+        // 后台线程异常退出时发送端仍由 `Shared` 持有，这里不会提前返回
+        let _ = delivered.wait_for(|delivered| *delivered >= target).await;
+    }
+}
+
+impl EventEmitter for QueuedEventEmitter {
+    fn emit(&self, event: &TransferEvent) {
+        let Ok(mut queue) = self.shared.queue.lock() else {
+            return;
+        };
+        if let TransferEvent::Progress { role, .. } | TransferEvent::ImportProgress { role, .. } =
+            event
+        {
+            // 只与队尾合并，保证进度不会越过其后的状态变化事件
+            if let Some(last) = queue.events.back_mut()
+                && std::mem::discriminant(last) == std::mem::discriminant(event)
+                && last.role() == *role
+            {
+                *last = event.clone();
+                return;
+            }
+            if queue.events.len() >= self.capacity {
+                queue.dropped += 1;
+                return;
+            }
+        }
+        queue.events.push_back(event.clone());
+        queue.enqueued += 1;
+        drop(queue);
+        self.shared.ready.notify_one();
+    }
+}
+
+impl Drop for QueuedEventEmitter {
+    /// 关闭队列后立即返回；后台线程仍会投递已入队的事件，最后的完成/失败事件不会丢失。
+    fn drop(&mut self) {
+        if let Ok(mut queue) = self.shared.queue.lock() {
+            queue.closed = true;
+        }
+        self.shared.ready.notify_one();
+    }
+}
+
+fn deliver(shared: &Shared, inner: &dyn EventEmitter) {
+    loop {
+        let event = {
+            let Ok(mut queue) = shared.queue.lock() else {
+                return;
+            };
+            loop {
+                if let Some(event) = queue.events.pop_front() {
+                    break event;
+                }
+                if queue.closed {
+                    return;
+                }
+                queue = match shared.ready.wait(queue) {
+                    Ok(queue) => queue,
+                    Err(_) => return,
+                };
+            }
+        };
+        inner.emit(&event);
+        shared.delivered.send_modify(|delivered| *delivered += 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::QueuedEventEmitter;
+    use crate::core::events::{EventEmitter, Role, TransferEvent};
+    use std::sync::{Arc, Condvar, Mutex};
+    use std::time::Duration;
+
+    /// 阻塞在第一个事件上直到被放行，模拟卡住的前端。
+    #[derive(Default)]
+    struct GatedEmitter {
+        states: Mutex<Vec<&'static str>>,
+        open: Mutex<bool>,
+        opened: Condvar,
+        delivered: Condvar,
+    }
+
+    impl GatedEmitter {
+        fn open(&self) {
+            *self.open.lock().expect("lock") = true;
+            self.opened.notify_all();
+        }
+
+        /// 等到最后投递的是 `state` 事件，超时则 panic。
+        fn wait_for_last(&self, state: &str) -> Vec<&'static str> {
+            let (states, timeout) = self
+                .delivered
+                .wait_timeout_while(
+                    self.states.lock().expect("lock"),
+                    Duration::from_secs(5),
+                    |states| states.last() != Some(&state),
+                )
+                .expect("lock");
+            let delivered = states.clone();
+            drop(states);
+            assert!(!timeout.timed_out(), "no {state} event: {delivered:?}");
+            delivered
+        }
+    }
+
+    impl EventEmitter for GatedEmitter {
+        fn emit(&self, event: &TransferEvent) {
+            let open = self.open.lock().expect("lock");
+            drop(self.opened.wait_while(open, |open| !*open).expect("lock"));
+            self.states.lock().expect("lock").push(event.state());
+            self.delivered.notify_all();
+        }
+    }
+
+    fn progress(processed: u64) -> TransferEvent {
+        TransferEvent::Progress {
+            role: Role::Receiver,
+            transfer_id: 0,
+            processed,
+            total: 1000,
+            speed: 0.0,
+            eta_secs: None,
+        }
+    }
+
+    #[test]
+    fn stalled_emitter_coalesces_progress_and_keeps_state_events() {
+        let inner = Arc::new(GatedEmitter::default());
+        let emitter = QueuedEventEmitter::new(inner.clone(), 4);
+
+        emitter.emit(&TransferEvent::Started {
+            role: Role::Receiver,
+            transfer_id: 0,
+        });
+        for processed in 0..1000 {
+            emitter.emit(&progress(processed));
+        }
+        emitter.emit(&TransferEvent::Completed {
+            role: Role::Receiver,
+            transfer_id: 0,
+        });
+        // 前端卡住期间所有事件都已入队，丢弃发射器也不等待投递
+        drop(emitter);
+        inner.open();
+
+        let states = inner.wait_for_last("completed");
+        assert_eq!(states.first(), Some(&"started"));
+        let progress = states.iter().filter(|state| **state == "progress").count();
+        assert!(
+            (1..=2).contains(&progress),
+            "progress should be coalesced: {states:?}"
+        );
+        assert_eq!(states.len(), progress + 2);
+    }
+
+    #[tokio::test]
+    async fn flush_waits_for_queued_events() {
+        let inner = Arc::new(GatedEmitter::default());
+        let emitter = QueuedEventEmitter::new(inner.clone(), 4);
+        emitter.emit(&TransferEvent::Started {
+            role: Role::Receiver,
+            transfer_id: 0,
+        });
+        emitter.emit(&progress(1));
+        emitter.emit(&TransferEvent::Failed {
+            role: Role::Receiver,
+            transfer_id: 0,
+            message: "lost".to_string(),
+        });
+
+        let flush = emitter.flush();
+        tokio::pin!(flush);
+        assert!(
+            tokio::time::timeout(Duration::from_millis(50), &mut flush)
+                .await
+                .is_err(),
+            "flush should wait for the stalled emitter"
+        );
+        inner.open();
+        flush.await;
+        assert_eq!(
+            *inner.states.lock().expect("lock"),
+            ["started", "progress", "failed"]
+        );
+    }
+}
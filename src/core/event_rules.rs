@@ -0,0 +1,351 @@
+//! 基于规则的事件发射器：当特定 [`TransferEvent`] 发生时执行外部命令。
+//!
+//! 规则的匹配条件形如 `<state>[:<role>]`，例如 `completed:receiver`、`failed`；
+//! `state` 可写作 `*` 以匹配任意状态。命令通过系统 shell 执行，事件信息通过
+//! `SENDMER_EVENT`、`SENDMER_ROLE`、`SENDMER_STATE`、`SENDMER_TRANSFER_ID`、`SENDMER_MESSAGE`
+//! 环境变量传入。
+//!
+//! 命令在一个后台线程中依次执行，不会为每个事件各启动一个线程；进度类事件
+//! （`progress`、`import-progress`）每条规则每 [`PROGRESS_COMMAND_INTERVAL`] 最多触发一次。
+
+use crate::core::events::{AppHandle, EventEmitter, Role, TransferEvent};
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, mpsc};
+use std::time::{Duration, Instant};
+
+/// 同一条规则由进度类事件触发命令的最短间隔。
+pub const PROGRESS_COMMAND_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 事件匹配条件。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventFilter {
+    /// 事件状态（见 [`TransferEvent::state`]），`None` 表示任意状态
+    pub state: Option<String>,
+    /// 事件角色，`None` 表示任意角色
+    pub role: Option<Role>,
+}
+
+impl EventFilter {
+    pub fn matches(&self, event: &TransferEvent) -> bool {
+        self.state
+            .as_deref()
+            .is_none_or(|state| state == event.state())
+            && self.role.is_none_or(|role| role == event.role())
+    }
+}
+
+impl FromStr for EventFilter {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (state, role) = match s.split_once(':') {
+            Some((state, role)) => (state, Some(role)),
+            None => (s, None),
+        };
+        anyhow::ensure!(
+            !state.is_empty(),
+            "event filter needs a state, e.g. completed"
+        );
+        let role = match role {
+            None | Some("*") => None,
+            Some("sender") => Some(Role::Sender),
+            Some("receiver") => Some(Role::Receiver),
+            Some(role) => anyhow::bail!("invalid role {role:?}, expected sender or receiver"),
+        };
+        Ok(Self {
+            state: (state != "*").then(|| state.to_string()),
+            role,
+        })
+    }
+}
+
+/// 一条规则：匹配条件与要执行的 shell 命令。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventRule {
+    pub filter: EventFilter,
+    pub command: String,
+}
+
+impl EventRule {
+    /// 将 `--on` 与 `--exec-event` 按出现顺序一一配对。
+    pub fn pair(filters: &[String], commands: &[String]) -> anyhow::Result<Vec<Self>> {
+        anyhow::ensure!(
+            filters.len() == commands.len(),
+            "every --on needs exactly one matching --exec-event"
+        );
+        filters
+            .iter()
+            .zip(commands)
+            .map(|(filter, command)| {
+                Ok(Self {
+                    filter: filter.parse()?,
+                    command: command.clone(),
+                })
+            })
+            .collect()
+    }
+}
+
+/// 在转发事件给内部发射器的同时，执行命中规则的命令。
+pub struct RuleEventEmitter {
+    rules: Vec<EventRule>,
+    inner: AppHandle,
+    /// 交给后台线程执行的命令；线程未能启动时为 `None`
+    commands: Option<mpsc::Sender<std::process::Command>>,
+    throttle: ProgressThrottle,
+}
+
+impl RuleEventEmitter {
+    pub fn new(rules: Vec<EventRule>, inner: AppHandle) -> Self {
+        let (commands, queued) = mpsc::channel();
+        // 发射器被丢弃后通道关闭，线程执行完已排队的命令后退出
+        let commands = match std::thread::Builder::new()
+            .name("sendmer-event-rules".to_string())
+            .spawn(move || run_commands(&queued))
+        {
+            Ok(_) => Some(commands),
+            Err(error) => {
+                tracing::warn!(error = %error, "failed to start event command thread");
+                None
+            }
+        };
+        Self {
+            rules,
+            inner,
+            commands,
+            throttle: ProgressThrottle::new(PROGRESS_COMMAND_INTERVAL),
+        }
+    }
+
+    /// 有规则时包装 `inner`，否则原样返回。
+    pub fn wrap(rules: Vec<EventRule>, inner: AppHandle) -> AppHandle {
+        if rules.is_empty() {
+            inner
+        } else {
+            Some(Arc::new(Self::new(rules, inner)))
+        }
+    }
+}
+
+impl EventEmitter for RuleEventEmitter {
+    fn emit(&self, event: &TransferEvent) {
+        crate::core::events::emit_event(&self.inner, event);
+        let Some(commands) = &self.commands else {
+            return;
+        };
+        let progress = matches!(
+            event,
+            TransferEvent::Progress { .. } | TransferEvent::ImportProgress { .. }
+        );
+        for (index, rule) in self.rules.iter().enumerate() {
+            if !rule.filter.matches(event)
+                || (progress && !self.throttle.allow(index, Instant::now()))
+            {
+                continue;
+            }
+            let _ = commands.send(event_command(&rule.command, event));
+        }
+    }
+}
+
+/// 每条规则记录上次由进度事件触发命令的时间，间隔不足时跳过。
+struct ProgressThrottle {
+    interval: Duration,
+    last: Mutex<HashMap<usize, Instant>>,
+}
+
+impl ProgressThrottle {
+    fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            last: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn allow(&self, rule: usize, now: Instant) -> bool {
+        let mut last = self.last.lock().unwrap_or_else(|error| error.into_inner());
+        if last
+            .get(&rule)
+            .is_some_and(|last| now.duration_since(*last) < self.interval)
+        {
+            return false;
+        }
+        last.insert(rule, now);
+        true
+    }
+}
+
+/// 依次执行排队的命令，等每个命令结束后再执行下一个。
+fn run_commands(queued: &mpsc::Receiver<std::process::Command>) {
+    for mut process in queued {
+        match process.status() {
+            Ok(status) if !status.success() => {
+                tracing::debug!(%status, "event command exited unsuccessfully");
+            }
+            Ok(_) => {}
+            Err(error) => tracing::warn!(error = %error, "failed to run event command"),
+        }
+    }
+}
+
+fn event_command(command: &str, event: &TransferEvent) -> std::process::Command {
+    let mut process = shell_command(command);
+    process
+        .env("SENDMER_EVENT", event.event_name())
+        .env("SENDMER_ROLE", event.role().as_str())
+        .env("SENDMER_STATE", event.state())
+        .env("SENDMER_TRANSFER_ID", event.transfer_id().to_string())
+        .env("SENDMER_MESSAGE", event_message(event));
+    process
+}
+
+fn shell_command(command: &str) -> std::process::Command {
+    #[cfg(windows)]
+    {
+        let mut process = std::process::Command::new("cmd");
+        process.arg("/C").arg(command);
+        process
+    }
+    #[cfg(not(windows))]
+    {
+        let mut process = std::process::Command::new("sh");
+        process.arg("-c").arg(command);
+        process
+    }
+}
+
+fn event_message(event: &TransferEvent) -> String {
+    match event {
+        TransferEvent::Failed { message, .. } => message.clone(),
+        TransferEvent::Warning { code, detail, .. } => format!("{}: {detail}", code.as_str()),
+        TransferEvent::TicketRefreshed { ticket, .. } => ticket.clone(),
+        TransferEvent::FileNames { file_names, .. } => file_names.join("\n"),
+        TransferEvent::PeerConnected { endpoint_id, .. }
+        | TransferEvent::PeerDisconnected { endpoint_id, .. } => endpoint_id.to_string(),
+        TransferEvent::Connected { via, remote, .. } => format!("{} {remote}", via.as_str()),
+        TransferEvent::Started { .. }
+        | TransferEvent::Progress { .. }
+        | TransferEvent::ImportProgress { .. }
+        | TransferEvent::Completed { .. } => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EventFilter, EventRule, ProgressThrottle};
+    use crate::core::events::{Role, TransferEvent};
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn event_filter_matches_state_and_role() {
+        let filter: EventFilter = "completed:receiver".parse().expect("valid filter");
+        assert!(filter.matches(&TransferEvent::Completed {
+            role: Role::Receiver,
+            transfer_id: 0,
+        }));
+        assert!(!filter.matches(&TransferEvent::Completed {
+            role: Role::Sender,
+            transfer_id: 0,
+        }));
+        assert!(!filter.matches(&TransferEvent::Started {
+            role: Role::Receiver,
+            transfer_id: 0,
+        }));
+
+        let any_failure: EventFilter = "failed".parse().expect("valid filter");
+        assert!(any_failure.matches(&TransferEvent::Failed {
+            role: Role::Sender,
+            transfer_id: 0,
+            message: "boom".to_string(),
+        }));
+    }
+
+    #[test]
+    fn event_filter_matches_peer_events() {
+        let filter: EventFilter = "peer-connected:sender".parse().expect("valid filter");
+        let endpoint_id = iroh::SecretKey::generate(&mut rand::rng()).public();
+        let event = TransferEvent::PeerConnected {
+            role: Role::Sender,
+            transfer_id: 0,
+            endpoint_id,
+            connection_id: 1,
+        };
+        assert!(filter.matches(&event));
+        assert_eq!(super::event_message(&event), endpoint_id.to_string());
+        assert!(!filter.matches(&TransferEvent::PeerDisconnected {
+            role: Role::Sender,
+            transfer_id: 0,
+            endpoint_id,
+            connection_id: 1,
+        }));
+    }
+
+    #[test]
+    fn event_rules_require_matching_commands() {
+        let filters = vec!["completed".to_string(), "failed".to_string()];
+        let err = EventRule::pair(&filters, &["true".to_string()])
+            .expect_err("missing command should fail");
+        assert!(err.to_string().contains("--exec-event"));
+
+        let err = "done:nobody"
+            .parse::<EventFilter>()
+            .expect_err("unknown role should fail");
+        assert!(err.to_string().contains("invalid role"));
+    }
+
+    #[test]
+    fn progress_throttle_limits_each_rule_separately() {
+        let throttle = ProgressThrottle::new(Duration::from_secs(1));
+        let start = Instant::now();
+        assert!(throttle.allow(0, start));
+        assert!(!throttle.allow(0, start + Duration::from_millis(100)));
+        assert!(throttle.allow(1, start + Duration::from_millis(100)));
+        assert!(throttle.allow(0, start + Duration::from_secs(1)));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn matching_events_run_commands_in_order() {
+        use super::RuleEventEmitter;
+        use crate::core::events::EventEmitter;
+
+        let temp_dir = tempfile::tempdir().expect("temp dir");
+        let log = temp_dir.path().join("events.log");
+        let rules = EventRule::pair(
+            &["*:receiver".to_string()],
+            &[format!("echo \"$SENDMER_STATE\" >> '{}'", log.display())],
+        )
+        .expect("valid rules");
+        let emitter = RuleEventEmitter::new(rules, None);
+        emitter.emit(&TransferEvent::Started {
+            role: Role::Receiver,
+            transfer_id: 0,
+        });
+        for processed in 0..100 {
+            emitter.emit(&TransferEvent::Progress {
+                role: Role::Receiver,
+                transfer_id: 0,
+                processed,
+                total: 100,
+                speed: 0.0,
+                eta_secs: None,
+            });
+        }
+        emitter.emit(&TransferEvent::Completed {
+            role: Role::Receiver,
+            transfer_id: 0,
+        });
+        drop(emitter);
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let lines = loop {
+            let content = std::fs::read_to_string(&log).unwrap_or_default();
+            if content.ends_with("completed\n") || Instant::now() > deadline {
+                break content.lines().map(str::to_string).collect::<Vec<_>>();
+            }
+            std::thread::sleep(Duration::from_millis(20));
+        };
+        assert_eq!(lines, ["started", "progress", "completed"]);
+    }
+}
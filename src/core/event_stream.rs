@@ -0,0 +1,148 @@
+//! 以异步流的形式消费传输事件，无需实现 [`EventEmitter`]。
+//!
+//! [`event_stream`] 返回一个把事件转发到通道的 [`AppHandle`] 与对应的 [`EventStream`]；
+//! 所有发射器被丢弃（传输结束、分享停止）后流随之结束。
+//! [`send_with_events`] 与 [`receive_with_events`] 在后台任务中启动传输并直接返回事件流。
+
+use crate::core::error::Error;
+use crate::core::events::{AppHandle, EventEmitter, TransferEvent};
+use crate::core::options::{ReceiveOptions, SendOptions};
+use crate::core::receiver::receive_ticket;
+use crate::core::results::{ReceiveResult, ShareHandle};
+use crate::core::sender::send;
+use crate::core::ticket::ShareTicket;
+use n0_future::Stream;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+/// 把事件转发到通道的发射器。
+///
+/// 通道不设上限：进度事件本身已按时间节流，接收端消费不及时也不会拖慢传输。
+struct ChannelEmitter {
+    events: mpsc::UnboundedSender<TransferEvent>,
+}
+
+impl EventEmitter for ChannelEmitter {
+    fn emit(&self, event: &TransferEvent) {
+        // 事件流已被丢弃时忽略
+        let _ = self.events.send(event.clone());
+    }
+}
+
+/// 传输事件流，所有对应的发射器被丢弃后结束。
+pub struct EventStream {
+    events: mpsc::UnboundedReceiver<TransferEvent>,
+}
+
+impl Stream for EventStream {
+    type Item = TransferEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.events.poll_recv(cx)
+    }
+}
+
+/// 创建一对事件发射器句柄与事件流。
+pub fn event_stream() -> (AppHandle, EventStream) {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let emitter: Arc<dyn EventEmitter> = Arc::new(ChannelEmitter { events: tx });
+    (Some(emitter), EventStream { events: rx })
+}
+
+/// 在后台任务中开始分享 `path`，返回事件流与得到 [`ShareHandle`] 的任务句柄。
+///
+/// 导入进度等事件在分享就绪前即可从流中读取；流在分享停止后结束。
+pub fn send_with_events(
+    path: PathBuf,
+    options: SendOptions,
+) -> (EventStream, JoinHandle<Result<ShareHandle, Error>>) {
+    let (app_handle, events) = event_stream();
+    let task = tokio::spawn(send(path, options, app_handle));
+    (events, task)
+}
+
+/// 在后台任务中开始接收，返回事件流与得到 [`ReceiveResult`] 的任务句柄。
+///
+/// 流在接收结束（成功或失败）后结束，之后等待任务句柄即可取得结果。
+pub fn receive_with_events(
+    ticket: impl Into<ShareTicket>,
+    options: ReceiveOptions,
+) -> (EventStream, JoinHandle<Result<ReceiveResult, Error>>) {
+    let (app_handle, events) = event_stream();
+    let task = tokio::spawn(receive_ticket(ticket.into(), options, app_handle));
+    (events, task)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{event_stream, receive_with_events};
+    use crate::core::events::{Role, TransferEvent, emit_event};
+    use crate::core::options::{ReceiveOptions, ReceiveRetryPolicy, RelayModeOption};
+    use iroh_blobs::{BlobFormat, Hash, ticket::BlobTicket};
+    use n0_future::StreamExt;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn stream_yields_events_and_ends_when_handle_is_dropped() {
+        let (app_handle, mut events) = event_stream();
+        emit_event(
+            &app_handle,
+            &TransferEvent::Started {
+                role: Role::Receiver,
+                transfer_id: 0,
+            },
+        );
+        emit_event(
+            &app_handle,
+            &TransferEvent::Completed {
+                role: Role::Receiver,
+                transfer_id: 0,
+            },
+        );
+        drop(app_handle);
+
+        let mut states = Vec::new();
+        while let Some(event) = events.next().await {
+            states.push(event.state());
+        }
+        assert_eq!(states, ["started", "completed"]);
+    }
+
+    #[tokio::test]
+    async fn receive_stream_ends_with_failed_event_when_sender_is_unreachable() {
+        let temp_dir = tempfile::tempdir().expect("temp dir");
+        // 本地没有监听该端口，连接必然失败
+        let addr = iroh::EndpointAddr::new(iroh::SecretKey::generate(&mut rand::rng()).public())
+            .with_ip_addr("127.0.0.1:9".parse().expect("socket addr"));
+        let ticket = BlobTicket::new(addr, Hash::new(b"missing"), BlobFormat::HashSeq);
+        let options = ReceiveOptions {
+            output_dir: Some(temp_dir.path().to_path_buf()),
+            relay_mode: RelayModeOption::Disabled,
+            retry_policy: ReceiveRetryPolicy {
+                size_fetch_retry_limit: 1,
+                connect_timeout: Some(Duration::from_secs(2)),
+                ..ReceiveRetryPolicy::default()
+            },
+            ..ReceiveOptions::default()
+        };
+
+        let (mut events, task) = receive_with_events(ticket, options);
+        let mut states = Vec::new();
+        while let Some(event) = events.next().await {
+            states.push(event.state());
+        }
+        assert_eq!(states.last(), Some(&"failed"), "events: {states:?}");
+        assert_eq!(
+            states.iter().filter(|state| **state == "started").count(),
+            1,
+            "events: {states:?}"
+        );
+        task.await
+            .expect("receive task")
+            .expect_err("unreachable sender should fail the receive");
+    }
+}
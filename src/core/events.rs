@@ -2,7 +2,11 @@
 //!
 //! 本文件定义：事件发射器 trait、传输事件枚举、角色枚举。
 
+use iroh::EndpointId;
+use serde::{Serialize, Serializer};
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
 
 /// 事件发射器接口。
 ///
@@ -18,6 +22,26 @@ pub trait EventEmitter: Send + Sync {
     fn emit(&self, event: &TransferEvent);
 }
 
+/// 由闭包实现的事件发射器，小型程序无需为此定义结构体。
+pub struct FnEmitter<F>(pub F);
+
+impl<F> EventEmitter for FnEmitter<F>
+where
+    F: Fn(&TransferEvent) + Send + Sync,
+{
+    fn emit(&self, event: &TransferEvent) {
+        (self.0)(event);
+    }
+}
+
+/// 把闭包包装为可放入 [`AppHandle`] 的发射器，例如 `send(path, options, Some(on_event(|e| ...)))`。
+pub fn on_event<F>(f: F) -> Arc<dyn EventEmitter>
+where
+    F: Fn(&TransferEvent) + Send + Sync + 'static,
+{
+    Arc::new(FnEmitter(f))
+}
+
 /// 传输过程中对外发送的统一事件模型。
 ///
 /// 该枚举用于描述一次传输在某个角色（发送端 / 接收端）下的
@@ -27,34 +51,183 @@ pub trait EventEmitter: Send + Sync {
 /// - 这是**通知型事件**，不参与错误控制流
 /// - 不用于 `Result` / `anyhow`
 /// - payload 直接体现在枚举字段中
-#[derive(Debug, Clone)]
+/// - 每个事件都带有 `transfer_id`：同一次 `send` / `receive` 调用发出的事件相同，
+///   共享一个发射器的并发传输据此区分
+///
+/// 序列化为 JSON 对象：`event` 为 [`TransferEvent::state`]，其余为各字段，
+/// 可直接转发给 webview 或作为 NDJSON 输出。
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "kebab-case")]
 pub enum TransferEvent {
     /// 传输开始
-    Started { role: Role },
+    Started { role: Role, transfer_id: u64 },
 
     /// 传输进度更新
     Progress {
         role: Role,
+        transfer_id: u64,
         /// 已处理字节数
         processed: u64,
         /// 总字节数
         total: u64,
         /// 当前速度（字节 / 秒）
         speed: f64,
+        /// 预计剩余秒数，按近期速度的滑动平均估算；速度未知时为 `None`
+        eta_secs: Option<f64>,
+    },
+
+    /// 分享开始前导入（哈希、复制）文件的进度（仅由发送端发出）
+    ImportProgress {
+        role: Role,
+        transfer_id: u64,
+        /// 已导入的字节数
+        processed: u64,
+        /// 待导入的总字节数
+        total: u64,
+        /// 预计剩余秒数，估算方式同 `Progress`
+        eta_secs: Option<f64>,
     },
 
     /// 传输完成
-    Completed { role: Role },
+    Completed { role: Role, transfer_id: u64 },
 
     /// 传输失败
     Failed {
         role: Role,
+        transfer_id: u64,
         /// 用于展示的错误信息
         message: String,
     },
 
     /// 特殊事件：文件名列表
-    FileNames { role: Role, file_names: Vec<String> },
+    FileNames {
+        role: Role,
+        transfer_id: u64,
+        file_names: Vec<String>,
+    },
+
+    /// 地址变化后重新生成的 ticket
+    TicketRefreshed {
+        role: Role,
+        transfer_id: u64,
+        /// 新的 ticket 字符串
+        ticket: String,
+    },
+
+    /// 非致命问题：某些条目被跳过或被改动，但传输继续进行
+    Warning {
+        role: Role,
+        transfer_id: u64,
+        code: WarningCode,
+        /// 受影响的路径或补充说明
+        detail: String,
+    },
+
+    /// 接收端建立了连接（仅由发送端发出）
+    PeerConnected {
+        role: Role,
+        transfer_id: u64,
+        /// 接收端的 endpoint ID
+        #[serde(serialize_with = "serialize_display")]
+        endpoint_id: EndpointId,
+        /// 连接 ID，同一接收端的多条连接据此区分
+        connection_id: u64,
+    },
+
+    /// 接收端的连接已关闭（仅由发送端发出）
+    PeerDisconnected {
+        role: Role,
+        transfer_id: u64,
+        #[serde(serialize_with = "serialize_display")]
+        endpoint_id: EndpointId,
+        connection_id: u64,
+    },
+
+    /// 已连接到发送端，报告连接所走的网络路径（仅由接收端发出）
+    Connected {
+        role: Role,
+        transfer_id: u64,
+        /// 直连（打洞成功）或经 relay 转发
+        via: ConnectionVia,
+        /// 对端的直连地址或 relay URL
+        remote: String,
+        /// 测得的往返时延，序列化为毫秒数 `rtt_ms`
+        #[serde(rename = "rtt_ms", serialize_with = "serialize_millis")]
+        rtt: Duration,
+    },
+}
+
+/// 连接所走的网络路径。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConnectionVia {
+    /// 与对端直接通信
+    Direct,
+    /// 经 relay 服务器转发
+    Relay,
+}
+
+impl ConnectionVia {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Direct => "direct",
+            Self::Relay => "relay",
+        }
+    }
+}
+
+/// 非致命问题的分类，序列化为 [`WarningCode::as_str`] 的形式。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum WarningCode {
+    /// 跳过了符号链接
+    SkippedSymlink,
+    /// 跟随符号链接时发现成环，已跳过
+    SymlinkLoop,
+    /// 跳过了设备文件、FIFO、socket 等特殊文件
+    SkippedSpecialFile,
+    /// 条目无法读取而被跳过
+    UnreadableEntry,
+    /// 重复的输出目录被忽略
+    DuplicateOutputDir,
+    /// 之前已发送给同一接收端的文件被省略
+    SkippedPublished,
+}
+
+impl WarningCode {
+    /// 稳定的字符串形式，便于前端匹配。
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::SkippedSymlink => "skipped-symlink",
+            Self::SymlinkLoop => "symlink-loop",
+            Self::SkippedSpecialFile => "skipped-special-file",
+            Self::UnreadableEntry => "unreadable-entry",
+            Self::DuplicateOutputDir => "duplicate-output-dir",
+            Self::SkippedPublished => "skipped-published",
+        }
+    }
+}
+
+/// 传输过程中收集到的一条警告。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransferWarning {
+    pub code: WarningCode,
+    pub detail: String,
+}
+
+impl TransferWarning {
+    pub fn new(code: WarningCode, detail: impl Into<String>) -> Self {
+        Self {
+            code,
+            detail: detail.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for TransferWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.code.as_str(), self.detail)
+    }
 }
 
 impl TransferEvent {
@@ -63,20 +236,65 @@ impl TransferEvent {
         match self {
             Self::Started { .. } => "started",
             Self::Progress { .. } => "progress",
+            Self::ImportProgress { .. } => "import-progress",
             Self::Completed { .. } => "completed",
             Self::Failed { .. } => "failed",
             Self::FileNames { .. } => "file-names",
+            Self::TicketRefreshed { .. } => "ticket-refreshed",
+            Self::Warning { .. } => "warning",
+            Self::PeerConnected { .. } => "peer-connected",
+            Self::PeerDisconnected { .. } => "peer-disconnected",
+            Self::Connected { .. } => "connected",
         }
     }
 
     /// 返回事件所属角色
     pub const fn role(&self) -> Role {
         match self {
-            Self::Started { role }
-            | Self::Completed { role }
+            Self::Started { role, .. }
+            | Self::Completed { role, .. }
             | Self::Failed { role, .. }
             | Self::Progress { role, .. }
-            | Self::FileNames { role, .. } => *role,
+            | Self::ImportProgress { role, .. }
+            | Self::FileNames { role, .. }
+            | Self::TicketRefreshed { role, .. }
+            | Self::Warning { role, .. }
+            | Self::PeerConnected { role, .. }
+            | Self::PeerDisconnected { role, .. }
+            | Self::Connected { role, .. } => *role,
+        }
+    }
+
+    /// 返回事件所属传输的 ID
+    pub const fn transfer_id(&self) -> u64 {
+        match self {
+            Self::Started { transfer_id, .. }
+            | Self::Completed { transfer_id, .. }
+            | Self::Failed { transfer_id, .. }
+            | Self::Progress { transfer_id, .. }
+            | Self::ImportProgress { transfer_id, .. }
+            | Self::FileNames { transfer_id, .. }
+            | Self::TicketRefreshed { transfer_id, .. }
+            | Self::Warning { transfer_id, .. }
+            | Self::PeerConnected { transfer_id, .. }
+            | Self::PeerDisconnected { transfer_id, .. }
+            | Self::Connected { transfer_id, .. } => *transfer_id,
+        }
+    }
+
+    const fn transfer_id_mut(&mut self) -> &mut u64 {
+        match self {
+            Self::Started { transfer_id, .. }
+            | Self::Completed { transfer_id, .. }
+            | Self::Failed { transfer_id, .. }
+            | Self::Progress { transfer_id, .. }
+            | Self::ImportProgress { transfer_id, .. }
+            | Self::FileNames { transfer_id, .. }
+            | Self::TicketRefreshed { transfer_id, .. }
+            | Self::Warning { transfer_id, .. }
+            | Self::PeerConnected { transfer_id, .. }
+            | Self::PeerDisconnected { transfer_id, .. }
+            | Self::Connected { transfer_id, .. } => transfer_id,
         }
     }
 
@@ -97,7 +315,8 @@ impl TransferEvent {
 ///
 /// 用于区分事件来自哪一侧，
 /// 前端与 CLI 可以据此展示不同视角的状态。
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Role {
     /// 数据发送方
     Sender,
@@ -115,11 +334,50 @@ impl Role {
     }
 }
 
+fn serialize_display<S: Serializer>(
+    value: &impl std::fmt::Display,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(value)
+}
+
+fn serialize_millis<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_f64(duration.as_secs_f64() * 1000.0)
+}
+
 /// 应用层句柄：可选包装的共享 `EventEmitter`。
 ///
 /// 使用 `None` 表示不发射任何事件（例如在测试或禁止进度时）。
 pub type AppHandle = Option<Arc<dyn EventEmitter>>;
 
+/// 为一次新的传输分配 ID，进程内唯一且从 1 开始递增。
+pub fn next_transfer_id() -> u64 {
+    static NEXT_TRANSFER_ID: AtomicU64 = AtomicU64::new(1);
+    NEXT_TRANSFER_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// 为经过的事件填写 `transfer_id` 的发射器。
+///
+/// 传输入口用它包装调用方的发射器，库内部构造事件时 `transfer_id` 一律为 0。
+pub(crate) struct TransferIdEmitter {
+    inner: Arc<dyn EventEmitter>,
+    transfer_id: u64,
+}
+
+impl TransferIdEmitter {
+    pub(crate) fn wrap(inner: Arc<dyn EventEmitter>, transfer_id: u64) -> Arc<dyn EventEmitter> {
+        Arc::new(Self { inner, transfer_id })
+    }
+}
+
+impl EventEmitter for TransferIdEmitter {
+    fn emit(&self, event: &TransferEvent) {
+        let mut event = event.clone();
+        *event.transfer_id_mut() = self.transfer_id;
+        self.inner.emit(&event);
+    }
+}
+
 /// 安全地向前端发送事件。
 ///
 /// 若未配置事件发射器或发送失败，将被忽略。
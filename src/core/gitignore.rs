@@ -0,0 +1,106 @@
+//! 导入目录时遵循 `.gitignore` / `.ignore` 规则。
+//!
+//! 规则只从被分享的目录本身及其子目录中读取；越深的目录中的规则优先级越高，
+//! 与 git 的行为一致。`.git` 目录始终被跳过。
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+const IGNORE_FILES: [&str; 2] = [".gitignore", ".ignore"];
+
+/// 按目录缓存已解析的忽略规则。
+pub struct IgnoreStack {
+    root: PathBuf,
+    matchers: HashMap<PathBuf, Option<Gitignore>>,
+}
+
+impl IgnoreStack {
+    pub fn new(root: PathBuf) -> Self {
+        Self {
+            root,
+            matchers: HashMap::new(),
+        }
+    }
+
+    /// 判断 `path` 是否被忽略；`path` 必须位于根目录之下。
+    pub fn is_ignored(&mut self, path: &Path, is_dir: bool) -> bool {
+        if is_dir && path.file_name().is_some_and(|name| name == ".git") {
+            return true;
+        }
+        let Some(parent) = path.parent() else {
+            return false;
+        };
+        let directories = parent
+            .ancestors()
+            .take_while(|dir| dir.starts_with(&self.root))
+            .map(Path::to_path_buf)
+            .collect::<Vec<_>>();
+        // 从最深的目录开始查找，第一条命中的规则（忽略或白名单）生效
+        for dir in directories {
+            let matched = self
+                .matcher(dir)
+                .map(|matcher| matcher.matched(path, is_dir));
+            match matched {
+                Some(matched) if matched.is_ignore() => return true,
+                Some(matched) if matched.is_whitelist() => return false,
+                _ => {}
+            }
+        }
+        false
+    }
+
+    fn matcher(&mut self, dir: PathBuf) -> Option<&Gitignore> {
+        self.matchers
+            .entry(dir)
+            .or_insert_with_key(|dir| load_matcher(dir))
+            .as_ref()
+    }
+}
+
+fn load_matcher(dir: &Path) -> Option<Gitignore> {
+    let mut builder = GitignoreBuilder::new(dir);
+    let mut found = false;
+    for file in IGNORE_FILES {
+        let path = dir.join(file);
+        if !path.is_file() {
+            continue;
+        }
+        found = true;
+        if let Some(error) = builder.add(&path) {
+            tracing::warn!(path = %path.display(), error = %error, "invalid ignore file");
+        }
+    }
+    if !found {
+        return None;
+    }
+    builder
+        .build()
+        .inspect_err(
+            |error| tracing::warn!(dir = %dir.display(), error = %error, "invalid ignore rules"),
+        )
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::IgnoreStack;
+
+    #[test]
+    fn ignore_stack_applies_nested_rules() {
+        let temp_dir = tempfile::tempdir().expect("temp dir");
+        let root = temp_dir.path().join("project");
+        let nested = root.join("src");
+        std::fs::create_dir_all(&nested).expect("create dirs");
+        std::fs::write(root.join(".gitignore"), "target/\n*.log\n").expect("write gitignore");
+        std::fs::write(nested.join(".ignore"), "!keep.log\n").expect("write ignore");
+
+        let mut stack = IgnoreStack::new(root.clone());
+        assert!(stack.is_ignored(&root.join("target"), true));
+        assert!(stack.is_ignored(&root.join("debug.log"), false));
+        assert!(stack.is_ignored(&nested.join("other.log"), false));
+        assert!(!stack.is_ignored(&nested.join("keep.log"), false));
+        assert!(!stack.is_ignored(&nested.join("main.rs"), false));
+        assert!(stack.is_ignored(&root.join(".git"), true));
+    }
+}
@@ -0,0 +1,145 @@
+//! 本地传输记录：以 JSON Lines 的形式追加记录每次发送、接收的结果。
+//!
+//! 需要显式启用（`SendOptions::history` / `ReceiveOptions::history`）。发送端在每个接收端的
+//! 连接关闭时记录一条，接收端在下载结束（成功或失败）时记录一条。文件只追加不改写；
+//! 写入失败只记录日志，不影响传输本身。
+
+use crate::core::events::Role;
+use anyhow::Context;
+use iroh::EndpointId;
+use iroh_blobs::Hash;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 默认的记录文件路径：`<本地数据目录>/sendmer/transfers.log`，
+/// 例如 Linux 上的 `~/.local/share/sendmer/transfers.log`。
+pub fn default_path() -> Option<PathBuf> {
+    dirs::data_local_dir().map(|dir| dir.join("sendmer").join("transfers.log"))
+}
+
+/// 一次传输的结果。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Outcome {
+    Completed,
+    Failed,
+}
+
+/// 记录文件中的一行。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransferRecord {
+    /// 自 unix 纪元起的秒数
+    pub timestamp: u64,
+    /// `sender` 或 `receiver`
+    pub role: String,
+    /// 集合 hash（hex）
+    pub hash: String,
+    /// 对端的 endpoint ID；发送端未能识别接收端时为空
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub peer: Option<String>,
+    /// 实际传输的数据字节数
+    pub size: u64,
+    pub outcome: Outcome,
+    /// 失败原因
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+impl TransferRecord {
+    pub(crate) fn new(
+        role: Role,
+        hash: Hash,
+        peer: Option<EndpointId>,
+        size: u64,
+        outcome: Outcome,
+    ) -> Self {
+        Self {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_secs()),
+            role: role.as_str().to_string(),
+            hash: hash.to_hex(),
+            peer: peer.map(|peer| peer.to_string()),
+            size,
+            outcome,
+            message: None,
+        }
+    }
+
+    pub(crate) fn with_message(mut self, message: impl Into<String>) -> Self {
+        self.message = Some(message.into());
+        self
+    }
+}
+
+/// 把 `record` 追加到 `path`，必要时创建文件及其父目录；失败时只记录警告。
+pub(crate) fn append(path: &Path, record: &TransferRecord) {
+    if let Err(error) = try_append(path, record) {
+        tracing::warn!(error = %error, path = %path.display(), "failed to write transfer history");
+    }
+}
+
+fn try_append(path: &Path, record: &TransferRecord) -> anyhow::Result<()> {
+    if let Some(parent) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut line = serde_json::to_vec(record)?;
+    line.push(b'\n');
+    // 整行一次写入，配合追加模式避免并发写入的记录相互穿插
+    std::fs::File::options()
+        .create(true)
+        .append(true)
+        .open(path)?
+        .write_all(&line)?;
+    Ok(())
+}
+
+/// 读取 `path` 中的全部记录。
+pub fn read(path: &Path) -> anyhow::Result<Vec<TransferRecord>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| serde_json::from_str(line).context("invalid transfer history line"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Outcome, TransferRecord, append, read};
+    use crate::core::events::Role;
+    use iroh_blobs::Hash;
+
+    #[test]
+    fn records_are_appended_as_json_lines() {
+        let temp_dir = tempfile::tempdir().expect("temp dir");
+        let path = temp_dir.path().join("nested").join("transfers.log");
+        let hash = Hash::new(b"collection");
+        let peer = iroh::SecretKey::generate(&mut rand::rng()).public();
+
+        append(
+            &path,
+            &TransferRecord::new(Role::Sender, hash, Some(peer), 42, Outcome::Completed),
+        );
+        append(
+            &path,
+            &TransferRecord::new(Role::Receiver, hash, None, 0, Outcome::Failed)
+                .with_message("connection lost"),
+        );
+
+        let records = read(&path).expect("read history");
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].role, "sender");
+        assert_eq!(records[0].hash, hash.to_hex());
+        assert_eq!(records[0].peer, Some(peer.to_string()));
+        assert_eq!(records[0].size, 42);
+        assert_eq!(records[1].outcome, Outcome::Failed);
+        assert_eq!(records[1].message.as_deref(), Some("connection lost"));
+    }
+}
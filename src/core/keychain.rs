@@ -0,0 +1,45 @@
+//! 将 ticket 存入系统凭据存储（macOS Keychain、Windows 凭据管理器、Secret Service）。
+//!
+//! 需要启用 `keychain` feature；未启用时所有操作都返回错误。
+
+#[cfg(feature = "keychain")]
+const KEYCHAIN_SERVICE: &str = "sendmer";
+
+/// 以 `name` 为键保存 ticket，已存在时覆盖。
+#[cfg(feature = "keychain")]
+pub fn store_ticket(name: &str, ticket: &str) -> anyhow::Result<()> {
+    keyring::Entry::new(KEYCHAIN_SERVICE, name)?.set_password(ticket)?;
+    Ok(())
+}
+
+/// 读取以 `name` 为键保存的 ticket。
+#[cfg(feature = "keychain")]
+pub fn load_ticket(name: &str) -> anyhow::Result<String> {
+    match keyring::Entry::new(KEYCHAIN_SERVICE, name)?.get_password() {
+        Ok(ticket) => Ok(ticket),
+        Err(keyring::Error::NoEntry) => anyhow::bail!("no ticket named {name:?} in the keychain"),
+        Err(error) => Err(error.into()),
+    }
+}
+
+#[cfg(not(feature = "keychain"))]
+pub fn store_ticket(_name: &str, _ticket: &str) -> anyhow::Result<()> {
+    anyhow::bail!("sendmer was built without keychain support, enable the `keychain` feature")
+}
+
+#[cfg(not(feature = "keychain"))]
+pub fn load_ticket(_name: &str) -> anyhow::Result<String> {
+    anyhow::bail!("sendmer was built without keychain support, enable the `keychain` feature")
+}
+
+#[cfg(all(test, not(feature = "keychain")))]
+mod tests {
+    use super::{load_ticket, store_ticket};
+
+    #[test]
+    fn keychain_reports_missing_feature() {
+        let err = load_ticket("demo").expect_err("keychain should be unavailable");
+        assert!(err.to_string().contains("keychain"));
+        assert!(store_ticket("demo", "ticket").is_err());
+    }
+}
@@ -0,0 +1,130 @@
+//! 文件元数据（修改时间、unix 权限）的保存与恢复。
+//!
+//! 发送端启用 `--preserve-metadata` 时，把每个文件的元数据序列化为 JSON，
+//! 作为名为 `.sendmer-metadata.json` 的条目附加到集合中；接收端导出文件后据此恢复
+//! 修改时间与权限，该条目本身不会被导出。归档模式下 tar 已经携带这些信息，无需该条目。
+//! 非 unix 平台上只保存和恢复修改时间。
+
+use anyhow::Context;
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// 集合中元数据条目的名称。
+pub const METADATA_ENTRY: &str = ".sendmer-metadata.json";
+
+/// 单个文件的元数据。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileMetadata {
+    /// 修改时间，自 unix 纪元起的秒数
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    mtime_secs: Option<u64>,
+    #[serde(default)]
+    mtime_nanos: u32,
+    /// unix 权限位
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    mode: Option<u32>,
+}
+
+/// 以集合内名称为键的元数据表。
+pub type MetadataMap = BTreeMap<String, FileMetadata>;
+
+impl FileMetadata {
+    fn from_std(metadata: &std::fs::Metadata) -> Self {
+        let mtime = metadata
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok());
+        #[cfg(unix)]
+        let mode = {
+            use std::os::unix::fs::PermissionsExt;
+            Some(metadata.permissions().mode() & 0o7777)
+        };
+        #[cfg(not(unix))]
+        let mode = None;
+        Self {
+            mtime_secs: mtime.map(|mtime| mtime.as_secs()),
+            mtime_nanos: mtime.map_or(0, |mtime| mtime.subsec_nanos()),
+            mode,
+        }
+    }
+
+    fn modified(self) -> Option<SystemTime> {
+        let secs = self.mtime_secs?;
+        UNIX_EPOCH.checked_add(Duration::new(secs, self.mtime_nanos.min(999_999_999)))
+    }
+}
+
+/// 读取 `files`（集合内名称与本地路径）的元数据并编码为元数据条目的内容。
+pub fn collect<'a>(files: impl IntoIterator<Item = (&'a str, &'a Path)>) -> anyhow::Result<Bytes> {
+    let mut map = MetadataMap::new();
+    for (name, path) in files {
+        let metadata = std::fs::metadata(path)
+            .with_context(|| format!("failed to read metadata of {}", path.display()))?;
+        map.insert(name.to_string(), FileMetadata::from_std(&metadata));
+    }
+    Ok(serde_json::to_vec(&map)?.into())
+}
+
+/// 解码元数据条目的内容。
+pub fn decode(bytes: &[u8]) -> anyhow::Result<MetadataMap> {
+    serde_json::from_slice(bytes).with_context(|| format!("invalid {METADATA_ENTRY} in the share"))
+}
+
+/// 把 `metadata` 应用到已导出的文件上。
+///
+/// 先设置修改时间再设置权限，避免只读权限导致无法打开文件写入时间。
+pub fn apply(path: &Path, metadata: FileMetadata) -> anyhow::Result<()> {
+    if let Some(modified) = metadata.modified() {
+        std::fs::File::options()
+            .write(true)
+            .open(path)?
+            .set_modified(modified)?;
+    }
+    #[cfg(unix)]
+    if let Some(mode) = metadata.mode {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{apply, collect, decode};
+
+    #[test]
+    fn metadata_round_trips_onto_another_file() {
+        let temp_dir = tempfile::tempdir().expect("temp dir");
+        let source = temp_dir.path().join("source.sh");
+        std::fs::write(&source, b"#!/bin/sh").expect("write source");
+        let modified = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_600_000_000);
+        std::fs::File::options()
+            .write(true)
+            .open(&source)
+            .and_then(|file| file.set_modified(modified))
+            .expect("set source mtime");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&source, std::fs::Permissions::from_mode(0o750))
+                .expect("set source mode");
+        }
+
+        let encoded = collect([("docs/source.sh", source.as_path())]).expect("collect");
+        let map = decode(&encoded).expect("decode");
+        let target = temp_dir.path().join("target.sh");
+        std::fs::write(&target, b"#!/bin/sh").expect("write target");
+        apply(&target, map["docs/source.sh"]).expect("apply");
+
+        let metadata = std::fs::metadata(&target).expect("target metadata");
+        assert_eq!(metadata.modified().expect("mtime"), modified);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(metadata.permissions().mode() & 0o7777, 0o750);
+        }
+    }
+}
@@ -2,14 +2,43 @@
 //!
 //! 该模块导出内部子模块：`send`, `receive`, `progress`, `types`，
 //! 并提供给上层 crate 使用的库 API（见 `src/lib.rs` 的 pub re-export）。
+mod archive;
 pub mod args;
+pub mod auth;
+pub mod blocking;
+pub mod build_info;
+#[cfg(feature = "chaos")]
+pub mod chaos;
 pub mod cli_helper;
+pub mod code;
+pub mod compression;
+mod connect;
 mod endpoint;
+pub mod error;
+pub mod event_queue;
+pub mod event_rules;
+pub mod event_stream;
 pub mod events;
+mod gitignore;
+pub mod history;
+pub mod keychain;
+mod metadata;
 pub mod options;
 mod progress;
+mod rate_limit;
 pub mod receiver;
+#[cfg(feature = "relay-server")]
+pub mod relay_server;
 pub mod results;
+pub mod routing;
+mod sandbox;
 pub mod sender;
+pub mod serve;
 mod storage;
+pub mod ticket;
+pub mod trace;
+pub mod transferer;
 pub mod types;
+pub mod updates;
+mod verify;
+mod watch;
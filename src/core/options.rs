@@ -1,6 +1,6 @@
 //! 发送和接收选项定义。
 //!
-//! 本文件定义：SendOptions, ReceiveOptions, RelayModeOption, AddrInfoOptions。
+//! 本文件定义：SendOptions, ReceiveOptions, RelayModeOption, AddrInfoOptions, ImportModeOption, ExportModeOption, DiscoveryToggle, BackoffStrategy。
 
 use iroh::RelayUrl;
 use std::net::{SocketAddrV4, SocketAddrV6};
@@ -11,13 +11,289 @@ pub struct SendOptions {
     pub ticket_type: AddrInfoOptions,
     pub magic_ipv4_addr: Option<SocketAddrV4>,
     pub magic_ipv6_addr: Option<SocketAddrV6>,
+    /// 是否把本机地址发布到 pkarr；`Auto` 时仅在 ticket 不含地址、刷新 ticket
+    /// 或使用分享码时发布。
+    pub pkarr_publish: DiscoveryToggle,
+    /// 周期性检查本机地址，变化时重新生成 ticket 并发布到 discovery。
+    pub refresh_ticket: bool,
+    /// 把所有文件打包为一个 tar blob 分享，适合包含大量小文件的目录。
+    pub archive: bool,
+    /// 覆盖集合中的顶层名称（默认取路径的最后一个组件），只能用于单个路径；
+    /// 对标准输入无效，其名称由 `send_stdin` 的参数指定。
+    pub name: Option<String>,
+    /// 在集合中保存文件的修改时间与权限，接收端导出时恢复。
+    pub preserve_metadata: bool,
+    /// 导入时压缩每个文件，接收端导出时自动解压。
+    pub compression: Option<crate::core::compression::Compression>,
+    /// 监听分享的路径，内容变化时重新导入并生成新的 ticket。
+    pub watch: bool,
+    /// 监听模式下以带时间戳的 tag 保留最近 N 个快照，它们的 ticket 在分享期间都有效。
+    pub keep_snapshots: Option<u64>,
+    /// 附加在 ticket 上的 DNS 提示（`host` 或 `host:port`），接收端解析它得到直连地址。
+    pub dns_hint: Option<String>,
+    /// 文件导入存储的方式：尽量引用源文件，或复制一份稳定的副本。
+    pub import_mode: ImportModeOption,
+    /// 导入完成后、生成 ticket 之前对存储的数据进行完整性抽查。
+    pub verify_import: Option<ImportVerification>,
+    /// 跳过无权限读取的文件与目录并记录警告，而不是中止导入。
+    pub skip_unreadable: bool,
+    /// 导入时遇到符号链接的处理方式。
+    pub symlinks: SymlinkPolicy,
+    /// 不跨越文件系统边界。
+    pub one_file_system: bool,
+    /// 排除匹配这些通配符模式的条目（rsync `--exclude` 语义）。
+    pub exclude: Vec<String>,
+    /// 遵循 `.gitignore` / `.ignore` 规则。
+    pub gitignore: bool,
+    /// 要分享的文件总大小上限（字节），超过时在导入前报错。
+    pub max_size: Option<u64>,
+    /// 包含以 `.` 开头的隐藏文件与目录；默认跳过，直接指定的路径本身不受影响。
+    pub hidden: bool,
+    /// 分块级审计日志的输出路径，用于排查慢传输。
+    pub trace_path: Option<std::path::PathBuf>,
+    /// 传输记录文件；设置后每个接收端的下载结果都会追加一条记录。
+    pub history: Option<std::path::PathBuf>,
+    /// 持久化的 blob 存储目录；同时保存节点密钥与 ticket，重启后复用同一个分享。
+    /// 未设置时使用临时目录并在分享结束后删除。
+    pub store_dir: Option<std::path::PathBuf>,
+    /// 省略之前已完整发送给该接收端的文件，只分享新增或改变的部分。
+    /// 需要 `store_dir` 与 `history`：已发送的集合从传输记录中查找，其条目从持久化存储中读取。
+    pub skip_published: Option<iroh::EndpointId>,
+    /// 临时 blob 存储所在的父目录，默认使用系统临时目录。
+    pub temp_dir: Option<std::path::PathBuf>,
+    /// 以短分享码发布分享，接收端可用分享码代替 ticket。
+    pub code: Option<crate::core::code::ShareCode>,
+    /// 分享口令；接收端必须先提交相同的口令才能下载。
+    pub password: Option<String>,
+    /// 只允许这些接收端下载；为空时不限制。
+    pub allow: Vec<iroh::EndpointId>,
+    /// 发送速率上限（字节/秒），所有接收端共享。
+    pub rate_limit: Option<u64>,
+    /// 分享的有效期，从 ticket 生成时开始计算。
+    pub expire: Option<std::time::Duration>,
+    /// 完成这么多次下载后自动结束分享。
+    pub max_downloads: Option<u64>,
+    /// 始终使用内存存储；未设置时仅在总大小较小时自动使用。
+    pub memory_store: bool,
 }
 
+/// 导入时遇到符号链接的处理方式。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SymlinkPolicy {
+    /// 跳过符号链接并记录警告
+    #[default]
+    Skip,
+    /// 跟随符号链接导入其指向的内容，链接成环时跳过并记录警告
+    Follow,
+    /// 遇到符号链接时中止导入
+    Error,
+}
+
+impl std::str::FromStr for SymlinkPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "skip" => Ok(Self::Skip),
+            "follow" => Ok(Self::Follow),
+            "error" => Ok(Self::Error),
+            _ => anyhow::bail!("invalid symlink policy {s:?}, expected skip, follow or error"),
+        }
+    }
+}
+
+impl std::fmt::Display for SymlinkPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Skip => f.write_str("skip"),
+            Self::Follow => f.write_str("follow"),
+            Self::Error => f.write_str("error"),
+        }
+    }
+}
+
+/// 文件导入存储的方式。
+///
+/// 引用方式不复制数据，但分享期间源文件被修改会导致接收端校验失败；
+/// 复制方式占用额外的磁盘空间，换取分享内容不受源文件变化影响。
+/// 压缩、归档与内存存储总是复制数据，不受该选项影响。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ImportModeOption {
+    /// 尽可能引用源文件，无法引用时复制
+    #[default]
+    Reference,
+    /// 总是把文件复制到存储中
+    Copy,
+}
+
+impl From<ImportModeOption> for iroh_blobs::api::blobs::ImportMode {
+    fn from(value: ImportModeOption) -> Self {
+        match value {
+            ImportModeOption::Reference => Self::TryReference,
+            ImportModeOption::Copy => Self::Copy,
+        }
+    }
+}
+
+impl std::str::FromStr for ImportModeOption {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "reference" => Ok(Self::Reference),
+            "copy" => Ok(Self::Copy),
+            _ => anyhow::bail!("invalid import mode {s:?}, expected copy or reference"),
+        }
+    }
+}
+
+impl std::fmt::Display for ImportModeOption {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Reference => f.write_str("reference"),
+            Self::Copy => f.write_str("copy"),
+        }
+    }
+}
+
+/// 接收端把文件从临时存储导出到输出目录的方式。
+///
+/// 引用方式在临时存储与输出目录位于同一文件系统时直接移动数据文件，
+/// 避免大文件再复制一份；跨文件系统或数据内联在存储中时自动退回复制。
+/// 有多个输出目录时只有最后一个目录使用引用方式，其余目录仍然复制。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExportModeOption {
+    /// 总是复制数据
+    #[default]
+    Copy,
+    /// 尽可能从存储中移动数据文件，无法移动时复制
+    Reference,
+}
+
+impl From<ExportModeOption> for iroh_blobs::api::blobs::ExportMode {
+    fn from(value: ExportModeOption) -> Self {
+        match value {
+            ExportModeOption::Copy => Self::Copy,
+            ExportModeOption::Reference => Self::TryReference,
+        }
+    }
+}
+
+impl std::str::FromStr for ExportModeOption {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "copy" => Ok(Self::Copy),
+            "reference" => Ok(Self::Reference),
+            _ => anyhow::bail!("invalid export mode {s:?}, expected copy or reference"),
+        }
+    }
+}
+
+impl std::fmt::Display for ExportModeOption {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Copy => f.write_str("copy"),
+            Self::Reference => f.write_str("reference"),
+        }
+    }
+}
+
+/// 地址发现（pkarr 发布、DNS 解析）的开关。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DiscoveryToggle {
+    /// 按场景自动决定
+    #[default]
+    Auto,
+    /// 始终启用
+    On,
+    /// 始终关闭
+    Off,
+}
+
+impl DiscoveryToggle {
+    /// 结合自动模式下的默认值 `auto`，返回是否启用。
+    pub const fn enabled(self, auto: bool) -> bool {
+        match self {
+            Self::Auto => auto,
+            Self::On => true,
+            Self::Off => false,
+        }
+    }
+}
+
+impl std::str::FromStr for DiscoveryToggle {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(Self::Auto),
+            "on" => Ok(Self::On),
+            "off" => Ok(Self::Off),
+            _ => anyhow::bail!("invalid discovery setting {s:?}, expected auto, on or off"),
+        }
+    }
+}
+
+impl std::fmt::Display for DiscoveryToggle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Auto => f.write_str("auto"),
+            Self::On => f.write_str("on"),
+            Self::Off => f.write_str("off"),
+        }
+    }
+}
+
+/// 导入后完整性校验的范围。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportVerification {
+    /// 随机抽查一部分文件
+    Sample,
+    /// 校验全部文件
+    All,
+}
+
+impl std::str::FromStr for ImportVerification {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sample" => Ok(Self::Sample),
+            "all" => Ok(Self::All),
+            _ => anyhow::bail!("invalid import verification {s:?}, expected sample or all"),
+        }
+    }
+}
+
+impl std::fmt::Display for ImportVerification {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Sample => f.write_str("sample"),
+            Self::All => f.write_str("all"),
+        }
+    }
+}
+
+/// 接收端的重试策略，同时用于大小查询与数据下载。
 #[derive(Debug, Clone, Copy)]
 pub struct ReceiveRetryPolicy {
+    /// 每个请求最多尝试的次数（含第一次）
     pub size_fetch_retry_limit: u32,
     pub size_fetch_chunk_size: u64,
+    /// 重试等待的基准时长（毫秒）
     pub size_fetch_backoff_ms: u64,
+    /// 等待时长随尝试次数增长的方式
+    pub backoff: BackoffStrategy,
+    /// 从第一次尝试起算的总时限，超过后不再重试
+    pub deadline: Option<std::time::Duration>,
+    /// 单次建立连接的时限
+    pub connect_timeout: Option<std::time::Duration>,
+    /// 传输过程中持续收不到数据的时限，超过即视为本次尝试失败
+    pub stall_timeout: Option<std::time::Duration>,
+    /// 收到的数据校验失败时，立即重新请求损坏部分的最多次数；不计入 `size_fetch_retry_limit`
+    pub repair_limit: u32,
 }
 
 impl Default for ReceiveRetryPolicy {
@@ -26,6 +302,78 @@ impl Default for ReceiveRetryPolicy {
             size_fetch_retry_limit: 3,
             size_fetch_chunk_size: 1024 * 1024 * 32,
             size_fetch_backoff_ms: 250,
+            backoff: BackoffStrategy::Linear,
+            deadline: None,
+            connect_timeout: None,
+            stall_timeout: None,
+            repair_limit: 3,
+        }
+    }
+}
+
+impl ReceiveRetryPolicy {
+    /// 第 `attempt` 次尝试（从 1 开始）失败后应等待的时长；
+    /// 次数用完或等待后会超过总时限时返回 `None`。
+    pub fn next_delay(
+        &self,
+        attempt: u32,
+        elapsed: std::time::Duration,
+    ) -> Option<std::time::Duration> {
+        if attempt >= self.size_fetch_retry_limit {
+            return None;
+        }
+        let base = self.size_fetch_backoff_ms;
+        let millis = match self.backoff {
+            BackoffStrategy::Constant => base,
+            BackoffStrategy::Linear => base.saturating_mul(u64::from(attempt)),
+            BackoffStrategy::Exponential => base.saturating_mul(
+                1u64.checked_shl(attempt.saturating_sub(1))
+                    .unwrap_or(u64::MAX),
+            ),
+        };
+        let delay = std::time::Duration::from_millis(millis);
+        if let Some(deadline) = self.deadline
+            && elapsed + delay >= deadline
+        {
+            return None;
+        }
+        Some(delay)
+    }
+}
+
+/// 重试等待时长的增长方式。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BackoffStrategy {
+    /// 每次等待相同的时长
+    Constant,
+    /// 等待时长与尝试次数成正比
+    #[default]
+    Linear,
+    /// 每次等待时长翻倍
+    Exponential,
+}
+
+impl std::str::FromStr for BackoffStrategy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "constant" => Ok(Self::Constant),
+            "linear" => Ok(Self::Linear),
+            "exponential" => Ok(Self::Exponential),
+            _ => anyhow::bail!(
+                "invalid backoff strategy {s:?}, expected constant, linear or exponential"
+            ),
+        }
+    }
+}
+
+impl std::fmt::Display for BackoffStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Constant => f.write_str("constant"),
+            Self::Linear => f.write_str("linear"),
+            Self::Exponential => f.write_str("exponential"),
         }
     }
 }
@@ -33,10 +381,70 @@ impl Default for ReceiveRetryPolicy {
 #[derive(Debug, Default)]
 pub struct ReceiveOptions {
     pub output_dir: Option<std::path::PathBuf>,
+    /// 额外的镜像输出目录，导出时会同时写入这些目录。
+    pub mirror_dirs: Vec<std::path::PathBuf>,
+    /// 在每个输出目录下新建的子目录，所有文件写入其中；该目录已存在时报错。
+    pub into_dir: Option<std::path::PathBuf>,
     pub relay_mode: RelayModeOption,
     pub magic_ipv4_addr: Option<SocketAddrV4>,
     pub magic_ipv6_addr: Option<SocketAddrV6>,
     pub retry_policy: ReceiveRetryPolicy,
+    /// 导出后按文件名模式移动文件的路由规则。
+    pub routes: Vec<crate::core::routing::RouteRule>,
+    /// 仅计算路由计划，不实际移动文件。
+    pub route_dry_run: bool,
+    /// 分块级审计日志的输出路径，用于排查慢传输。
+    pub trace_path: Option<std::path::PathBuf>,
+    /// 传输记录文件；设置后下载结束时追加一条记录。
+    pub history: Option<std::path::PathBuf>,
+    /// 是否通过 DNS 解析发送端地址；`Auto` 时仅在 ticket 不含任何地址时启用。
+    pub dns_discovery: DiscoveryToggle,
+    /// DNS 解析使用的 origin 域名，默认使用 n0 的 DNS 服务。
+    pub dns_origin: Option<String>,
+    /// 提供同一内容的其他发送端的 ticket；与主 ticket 的发送端并发下载，
+    /// 某个发送端出错时由其余发送端接手。
+    pub extra_tickets: Vec<crate::core::ticket::ShareTicket>,
+    /// 静态地址覆盖：命中的 endpoint 只使用这些地址，不再走 pkarr/DNS。
+    pub static_addrs: Vec<crate::core::ticket::StaticAddr>,
+    /// 通过目录句柄导出（cap-std），防止写入逃逸出输出目录；需要 `sandbox` feature。
+    pub sandboxed_export: bool,
+    /// 优先下载并导出的条目模式（`*`、`**`、`?` 通配符）。
+    pub first: Vec<String>,
+    /// 发送端要求的分享口令。
+    pub password: Option<String>,
+    /// 下载速率上限（字节/秒）。
+    pub rate_limit: Option<u64>,
+    /// 只下载到临时存储并重新计算每个条目的 blake3，不导出任何文件；
+    /// 校验结果见 `ReceiveResult::verified`。
+    pub verify_only: bool,
+    /// 导出后重新读取主输出目录中的文件并计算 blake3，结果见 `ReceiveResult::verified`。
+    pub verify_export: bool,
+    /// 导出后在主输出目录中写入 `BLAKE3SUMS`（`b3sum` 格式）；隐含 `verify_export`。
+    pub checksums: bool,
+    /// 把只有一个条目的集合写到标准输出，而不是导出到输出目录；
+    /// 此时 `ReceiveResult::file_path` 为 `-`。
+    pub stdout: bool,
+    /// 不保留目录结构，所有文件直接写入输出目录；文件名冲突时报错。
+    pub flatten: bool,
+    /// 导出方式；`resume` 或 `keep_store` 时总是复制，以免保留的存储引用已移走的文件。
+    pub export_mode: ExportModeOption,
+    /// 接收后保留 blob 存储：所有接收共用用户缓存目录中的一个存储，
+    /// 之后接收内容有重叠的分享（或重新导出到其他目录）时复用已校验的数据。
+    pub keep_store: bool,
+    /// 跳过下载前对临时目录与输出目录剩余空间的检查。
+    pub skip_space_check: bool,
+    /// 取消令牌：触发后停止下载，关闭临时存储并按失败流程清理，
+    /// `receive` 返回取消错误；不设置时只能通过 Ctrl+C 取消。
+    pub cancel: Option<tokio_util::sync::CancellationToken>,
+    /// 续传：使用按集合 hash 固定的临时存储，下载失败或中断时保留已校验的数据，
+    /// 再次接收同一个 ticket 时跳过这部分数据；只在成功后清理。
+    pub resume: bool,
+    /// 只重新获取并导出主输出目录中缺失或内容与集合不符的条目，
+    /// 例如 `--report` 中标记为失败的文件或被中断的接收未写出的文件；隐含 `resume`。
+    pub retry_failed: bool,
+    /// 故障注入配置，用于演练重试与续传。
+    #[cfg(feature = "chaos")]
+    pub chaos: Option<crate::core::chaos::ChaosConfig>,
 }
 
 pub trait EndpointOptions: BindAddressOptions {
@@ -182,7 +590,8 @@ pub fn apply_options(addr: &mut iroh::EndpointAddr, opts: AddrInfoOptions) {
 
 #[cfg(test)]
 mod tests {
-    use super::ReceiveRetryPolicy;
+    use super::{BackoffStrategy, ReceiveRetryPolicy};
+    use std::time::Duration;
 
     #[test]
     fn receive_retry_policy_defaults_match_receiver_expectations() {
@@ -190,5 +599,33 @@ mod tests {
         assert_eq!(policy.size_fetch_retry_limit, 3);
         assert_eq!(policy.size_fetch_chunk_size, 1024 * 1024 * 32);
         assert_eq!(policy.size_fetch_backoff_ms, 250);
+        assert_eq!(policy.repair_limit, 3);
+    }
+
+    #[test]
+    fn next_delay_follows_strategy_attempts_and_deadline() {
+        let mut policy = ReceiveRetryPolicy {
+            size_fetch_retry_limit: 4,
+            ..ReceiveRetryPolicy::default()
+        };
+        assert_eq!(
+            policy.next_delay(2, Duration::ZERO),
+            Some(Duration::from_millis(500))
+        );
+        assert_eq!(policy.next_delay(4, Duration::ZERO), None);
+
+        policy.backoff = BackoffStrategy::Exponential;
+        assert_eq!(
+            policy.next_delay(3, Duration::ZERO),
+            Some(Duration::from_millis(1000))
+        );
+        policy.backoff = BackoffStrategy::Constant;
+        assert_eq!(
+            policy.next_delay(3, Duration::ZERO),
+            Some(Duration::from_millis(250))
+        );
+
+        policy.deadline = Some(Duration::from_secs(1));
+        assert_eq!(policy.next_delay(1, Duration::from_millis(900)), None);
     }
 }
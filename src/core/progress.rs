@@ -1,9 +1,12 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::{Duration, Instant};
 
-use crate::core::events::{AppHandle, Role, TransferEvent, emit_event};
+use crate::core::events::{AppHandle, Role, TransferEvent, TransferWarning, emit_event};
+use crate::core::results::ConnectionInfo;
 use crate::core::types::EntryType;
+use iroh::EndpointId;
 use tokio::sync::{Mutex, watch};
 
 pub struct ProgressTracker {
@@ -11,14 +14,58 @@ pub struct ProgressTracker {
     last_emit: Instant,
     current: u64,
     total: u64,
+    rate: RateEstimator,
 }
 
 pub struct ProgressSnapshot {
     pub current: u64,
     pub total: u64,
     pub speed: f64,
+    pub eta_secs: Option<f64>,
 }
 
+/// 以指数加权滑动平均估算近期速度，据此计算剩余时间。
+///
+/// 与自开始以来的平均速度相比，它能更快反映网络或磁盘速度的变化。
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RateEstimator {
+    last: Option<(Instant, u64)>,
+    rate: Option<f64>,
+}
+
+impl RateEstimator {
+    /// 新样本所占的权重
+    const SMOOTHING: f64 = 0.3;
+
+    /// 记录 `now` 时已处理 `processed` 字节。
+    pub fn update(&mut self, now: Instant, processed: u64) {
+        if let Some((last_time, last_processed)) = self.last {
+            let elapsed = now.saturating_duration_since(last_time).as_secs_f64();
+            if elapsed <= 0.0 {
+                return;
+            }
+            let sample = processed.saturating_sub(last_processed) as f64 / elapsed;
+            self.rate = Some(
+                self.rate
+                    .map_or(sample, |rate| Self::SMOOTHING.mul_add(sample - rate, rate)),
+            );
+        }
+        self.last = Some((now, processed));
+    }
+
+    /// 剩余 `total - processed` 字节所需的秒数；已完成时为 0，速度未知或为 0 时为 `None`。
+    pub fn eta_secs(&self, processed: u64, total: u64) -> Option<f64> {
+        let remaining = total.saturating_sub(processed);
+        if remaining == 0 {
+            return Some(0.0);
+        }
+        self.rate
+            .filter(|rate| *rate > 0.0)
+            .map(|rate| remaining as f64 / rate)
+    }
+}
+
+/// 按角色构造并发出事件；`transfer_id` 由传输入口包装的发射器填写。
 #[derive(Clone)]
 pub struct TransferEventEmitter {
     app_handle: AppHandle,
@@ -33,18 +80,36 @@ impl TransferEventEmitter {
     pub fn emit_started(&self) {
         emit_event(
             &self.app_handle,
-            &TransferEvent::Started { role: self.role },
+            &TransferEvent::Started {
+                role: self.role,
+                transfer_id: 0,
+            },
         );
     }
 
-    pub fn emit_progress(&self, processed: u64, total: u64, speed: f64) {
+    pub fn emit_progress(&self, processed: u64, total: u64, speed: f64, eta_secs: Option<f64>) {
         emit_event(
             &self.app_handle,
             &TransferEvent::Progress {
                 role: self.role,
+                transfer_id: 0,
                 processed,
                 total,
                 speed,
+                eta_secs,
+            },
+        );
+    }
+
+    pub fn emit_import_progress(&self, processed: u64, total: u64, eta_secs: Option<f64>) {
+        emit_event(
+            &self.app_handle,
+            &TransferEvent::ImportProgress {
+                role: self.role,
+                transfer_id: 0,
+                processed,
+                total,
+                eta_secs,
             },
         );
     }
@@ -52,7 +117,10 @@ impl TransferEventEmitter {
     pub fn emit_completed(&self) {
         emit_event(
             &self.app_handle,
-            &TransferEvent::Completed { role: self.role },
+            &TransferEvent::Completed {
+                role: self.role,
+                transfer_id: 0,
+            },
         );
     }
 
@@ -61,6 +129,7 @@ impl TransferEventEmitter {
             &self.app_handle,
             &TransferEvent::Failed {
                 role: self.role,
+                transfer_id: 0,
                 message: message.into(),
             },
         );
@@ -71,10 +140,71 @@ impl TransferEventEmitter {
             &self.app_handle,
             &TransferEvent::FileNames {
                 role: self.role,
+                transfer_id: 0,
                 file_names,
             },
         );
     }
+
+    pub fn emit_ticket_refreshed(&self, ticket: String) {
+        emit_event(
+            &self.app_handle,
+            &TransferEvent::TicketRefreshed {
+                role: self.role,
+                transfer_id: 0,
+                ticket,
+            },
+        );
+    }
+
+    pub fn emit_warning(&self, warning: &TransferWarning) {
+        emit_event(
+            &self.app_handle,
+            &TransferEvent::Warning {
+                role: self.role,
+                transfer_id: 0,
+                code: warning.code,
+                detail: warning.detail.clone(),
+            },
+        );
+    }
+
+    pub fn emit_peer_connected(&self, endpoint_id: EndpointId, connection_id: u64) {
+        emit_event(
+            &self.app_handle,
+            &TransferEvent::PeerConnected {
+                role: self.role,
+                transfer_id: 0,
+                endpoint_id,
+                connection_id,
+            },
+        );
+    }
+
+    pub fn emit_peer_disconnected(&self, endpoint_id: EndpointId, connection_id: u64) {
+        emit_event(
+            &self.app_handle,
+            &TransferEvent::PeerDisconnected {
+                role: self.role,
+                transfer_id: 0,
+                endpoint_id,
+                connection_id,
+            },
+        );
+    }
+
+    pub fn emit_connected(&self, connection: &ConnectionInfo) {
+        emit_event(
+            &self.app_handle,
+            &TransferEvent::Connected {
+                role: self.role,
+                transfer_id: 0,
+                via: connection.via,
+                remote: connection.remote.clone(),
+                rtt: connection.rtt,
+            },
+        );
+    }
 }
 
 impl ProgressTracker {
@@ -85,6 +215,7 @@ impl ProgressTracker {
             last_emit: now,
             current: 0,
             total: 0,
+            rate: RateEstimator::default(),
         }
     }
 
@@ -104,6 +235,7 @@ impl ProgressTracker {
             current: self.current,
             total: self.total,
             speed,
+            eta_secs: self.rate.eta_secs(self.current, self.total),
         }
     }
 
@@ -115,11 +247,59 @@ impl ProgressTracker {
         }
 
         self.last_emit = Instant::now();
+        self.rate.update(self.last_emit, current);
 
         Some(self.snapshot())
     }
 }
 
+/// 导入阶段的进度：多个文件并行导入时累计已处理的字节数，并以节流的方式发出
+/// `ImportProgress` 事件；达到总量时总会发出最后一次事件。
+pub struct ImportProgress {
+    emitter: TransferEventEmitter,
+    total: u64,
+    processed: AtomicU64,
+    /// 上次发出事件的时间与速度估算
+    last_emit: std::sync::Mutex<(Instant, RateEstimator)>,
+}
+
+impl ImportProgress {
+    pub fn new(app_handle: AppHandle, total: u64) -> Self {
+        Self {
+            emitter: TransferEventEmitter::new(app_handle, Role::Sender),
+            total,
+            processed: AtomicU64::new(0),
+            last_emit: std::sync::Mutex::new((Instant::now(), RateEstimator::default())),
+        }
+    }
+
+    /// 记录又有 `bytes` 字节导入完成。
+    pub fn advance(&self, bytes: u64) {
+        if bytes == 0 {
+            return;
+        }
+        let processed = self
+            .processed
+            .fetch_add(bytes, Ordering::Relaxed)
+            .saturating_add(bytes)
+            .min(self.total);
+        let mut last_emit = self
+            .last_emit
+            .lock()
+            .unwrap_or_else(|error| error.into_inner());
+        let (last_time, rate) = &mut *last_emit;
+        if processed < self.total && last_time.elapsed() < Duration::from_millis(200) {
+            return;
+        }
+        *last_time = Instant::now();
+        rate.update(*last_time, processed);
+        let eta_secs = rate.eta_secs(processed, self.total);
+        drop(last_emit);
+        self.emitter
+            .emit_import_progress(processed, self.total, eta_secs);
+    }
+}
+
 /// Transfer ID combining connection and request IDs
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct TransferId {
@@ -142,6 +322,7 @@ pub struct TransferInfo {
     pub start_time: Instant,
     pub total_size: u64,
     pub last_progress_emit: Instant,
+    pub rate: RateEstimator,
 }
 
 /// Provider-side progress tracker for managing multiple concurrent transfers
@@ -178,6 +359,7 @@ impl ProviderProgressTracker {
             start_time: Instant::now(),
             total_size,
             last_progress_emit: Instant::now(),
+            rate: RateEstimator::default(),
         };
         self.transfer_states.insert(id, info);
         self.active_requests += 1;
@@ -186,7 +368,11 @@ impl ProviderProgressTracker {
     }
 
     /// Update progress for a transfer, potentially returning progress event data
-    pub fn on_progress(&mut self, id: TransferId, offset: u64) -> Option<(u64, u64, f64)> {
+    pub fn on_progress(
+        &mut self,
+        id: TransferId,
+        offset: u64,
+    ) -> Option<(u64, u64, f64, Option<f64>)> {
         let info = self.transfer_states.get_mut(&id)?;
 
         // Throttle progress emissions
@@ -195,6 +381,7 @@ impl ProviderProgressTracker {
         }
 
         info.last_progress_emit = Instant::now();
+        info.rate.update(info.last_progress_emit, offset);
 
         let processed = offset;
         let total = info.total_size;
@@ -205,7 +392,12 @@ impl ProviderProgressTracker {
             0.0
         };
 
-        Some((processed, total, speed))
+        Some((
+            processed,
+            total,
+            speed,
+            info.rate.eta_secs(processed, total),
+        ))
     }
 
     /// Record that a request has completed.
@@ -296,6 +488,101 @@ pub enum CompletionStatus {
     MoreRequestsArrivingSoon,
 }
 
+/// 按连接统计完成的下载次数。
+///
+/// 接收端可能在同一连接上发出多个请求，因此只有当连接关闭、其上的请求全部结束、
+/// 且至少有一个请求成功而没有请求中止时，才记为一次完整的下载。
+#[derive(Debug, Default)]
+pub struct DownloadCounter {
+    connections: HashMap<u64, ConnectionDownloads>,
+    completed: u64,
+}
+
+#[derive(Debug, Default)]
+struct ConnectionDownloads {
+    active: usize,
+    succeeded: usize,
+    aborted: bool,
+    closed: bool,
+}
+
+impl DownloadCounter {
+    pub fn on_request_started(&mut self, connection: u64) {
+        self.connections.entry(connection).or_default().active += 1;
+    }
+
+    /// 记录请求结束；返回完成的下载次数是否因此增加。
+    pub fn on_request_finished(&mut self, connection: u64, succeeded: bool) -> bool {
+        let Some(state) = self.connections.get_mut(&connection) else {
+            return false;
+        };
+        state.active = state.active.saturating_sub(1);
+        if succeeded {
+            state.succeeded += 1;
+        } else {
+            state.aborted = true;
+        }
+        self.settle(connection)
+    }
+
+    /// 记录连接关闭；返回完成的下载次数是否因此增加。
+    pub fn on_connection_closed(&mut self, connection: u64) -> bool {
+        let Some(state) = self.connections.get_mut(&connection) else {
+            return false;
+        };
+        state.closed = true;
+        self.settle(connection)
+    }
+
+    pub const fn completed(&self) -> u64 {
+        self.completed
+    }
+
+    fn settle(&mut self, connection: u64) -> bool {
+        if self
+            .connections
+            .get(&connection)
+            .is_none_or(|state| !state.closed || state.active > 0)
+        {
+            return false;
+        }
+        let Some(state) = self.connections.remove(&connection) else {
+            return false;
+        };
+        if state.succeeded > 0 && !state.aborted {
+            self.completed += 1;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// 一次分享的传输统计，由 provider 事件汇总而来。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ShareStats {
+    /// 完成的下载次数，计数规则见 [`DownloadCounter`]
+    pub completed_downloads: u64,
+    /// 连接过的不同接收端数量
+    pub unique_peers: u64,
+    /// 已发送的数据字节数，不含协议开销，中止的请求也计入已发送的部分
+    pub bytes_served: u64,
+    /// 分享持续的时间
+    pub duration: Duration,
+}
+
+impl ShareStats {
+    /// 整个分享期间的平均吞吐量（字节 / 秒）。
+    pub fn average_throughput(&self) -> f64 {
+        let secs = self.duration.as_secs_f64();
+        if secs > 0.0 {
+            self.bytes_served as f64 / secs
+        } else {
+            0.0
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SenderTransferStatus {
     Idle,
@@ -309,10 +596,14 @@ pub struct SenderProgressReporter {
     emitter: TransferEventEmitter,
     state: Arc<Mutex<SenderProgressState>>,
     status_tx: watch::Sender<SenderTransferStatus>,
+    downloads_tx: Option<watch::Sender<u64>>,
+    stats_tx: Option<watch::Sender<ShareStats>>,
 }
 
 struct SenderProgressState {
     tracker: ProviderProgressTracker,
+    downloads: DownloadCounter,
+    peers: HashSet<EndpointId>,
     has_emitted_started: bool,
 }
 
@@ -326,10 +617,56 @@ impl SenderProgressReporter {
             emitter: TransferEventEmitter::new(app_handle, Role::Sender),
             state: Arc::new(Mutex::new(SenderProgressState {
                 tracker: ProviderProgressTracker::new(entry_type),
+                downloads: DownloadCounter::default(),
+                peers: HashSet::new(),
                 has_emitted_started: false,
             })),
             status_tx,
+            downloads_tx: None,
+            stats_tx: None,
+        }
+    }
+
+    /// 把完成的下载次数发布到 `downloads_tx`。
+    pub fn with_downloads(mut self, downloads_tx: watch::Sender<u64>) -> Self {
+        self.downloads_tx = Some(downloads_tx);
+        self
+    }
+
+    /// 把传输统计发布到 `stats_tx`；其中的 `duration` 由读取方填写。
+    pub fn with_stats(mut self, stats_tx: watch::Sender<ShareStats>) -> Self {
+        self.stats_tx = Some(stats_tx);
+        self
+    }
+
+    /// 记录一个已放行的接收端连接。
+    pub async fn on_peer_connected(&self, peer: EndpointId) {
+        let mut state = self.state.lock().await;
+        if state.peers.insert(peer) {
+            let unique_peers = state.peers.len() as u64;
+            drop(state);
+            self.update_stats(|stats| stats.unique_peers = unique_peers);
+        }
+    }
+
+    fn update_stats(&self, update: impl FnOnce(&mut ShareStats)) {
+        if let Some(stats_tx) = &self.stats_tx {
+            stats_tx.send_modify(update);
+        }
+    }
+
+    pub async fn on_connection_closed(&self, connection: u64) {
+        let mut state = self.state.lock().await;
+        if state.downloads.on_connection_closed(connection) {
+            self.publish_downloads(&state.downloads);
+        }
+    }
+
+    fn publish_downloads(&self, downloads: &DownloadCounter) {
+        if let Some(downloads_tx) = &self.downloads_tx {
+            downloads_tx.send_replace(downloads.completed());
         }
+        self.update_stats(|stats| stats.completed_downloads = downloads.completed());
     }
 
     pub async fn on_request_received(&self, transfer_id: TransferId, total_file_size: u64) {
@@ -338,6 +675,7 @@ impl SenderProgressReporter {
             state
                 .tracker
                 .on_request_started(transfer_id, total_file_size);
+            state.downloads.on_request_started(transfer_id.connection);
             if state.has_emitted_started {
                 false
             } else {
@@ -361,15 +699,23 @@ impl SenderProgressReporter {
             iroh_blobs::provider::events::RequestUpdate::Started(_) => {}
             iroh_blobs::provider::events::RequestUpdate::Progress(m) => {
                 let mut state = self.state.lock().await;
-                if let Some((processed, total, speed)) =
+                if let Some((processed, total, speed, eta_secs)) =
                     state.tracker.on_progress(transfer_id, m.end_offset)
                 {
-                    self.emitter.emit_progress(processed, total, speed);
+                    self.emitter
+                        .emit_progress(processed, total, speed, eta_secs);
                 }
             }
-            iroh_blobs::provider::events::RequestUpdate::Completed(_) => {
+            iroh_blobs::provider::events::RequestUpdate::Completed(m) => {
+                self.update_stats(|stats| stats.bytes_served += m.stats.payload_bytes_sent);
                 let quiet_period = {
                     let mut state = self.state.lock().await;
+                    if state
+                        .downloads
+                        .on_request_finished(transfer_id.connection, true)
+                    {
+                        self.publish_downloads(&state.downloads);
+                    }
                     match state.tracker.on_request_completed(transfer_id) {
                         CompletionStatus::Completed => {
                             self.emitter.emit_completed();
@@ -396,9 +742,13 @@ impl SenderProgressReporter {
                     }
                 }
             }
-            iroh_blobs::provider::events::RequestUpdate::Aborted(_) => {
+            iroh_blobs::provider::events::RequestUpdate::Aborted(m) => {
+                self.update_stats(|stats| stats.bytes_served += m.stats.payload_bytes_sent);
                 let should_emit_failed = {
                     let mut state = self.state.lock().await;
+                    state
+                        .downloads
+                        .on_request_finished(transfer_id.connection, false);
                     state.tracker.on_request_aborted(transfer_id)
                 };
 
@@ -427,21 +777,29 @@ impl ReceiverProgressReporter {
     }
 
     pub fn emit_initial_progress(&self) {
-        self.emitter.emit_progress(0, self.tracker.total, 0.0);
+        self.emitter.emit_progress(0, self.tracker.total, 0.0, None);
     }
 
     pub fn on_progress(&mut self, current: u64) {
         if let Some(snapshot) = self.tracker.update(current) {
-            self.emitter
-                .emit_progress(snapshot.current, snapshot.total, snapshot.speed);
+            self.emitter.emit_progress(
+                snapshot.current,
+                snapshot.total,
+                snapshot.speed,
+                snapshot.eta_secs,
+            );
         }
     }
 
     pub fn emit_completed_progress(&mut self) {
         self.tracker.current = self.tracker.total;
         let snapshot = self.tracker.snapshot();
-        self.emitter
-            .emit_progress(snapshot.current, snapshot.total, snapshot.speed);
+        self.emitter.emit_progress(
+            snapshot.current,
+            snapshot.total,
+            snapshot.speed,
+            snapshot.eta_secs,
+        );
     }
 
     pub fn emit_failed(&self, message: impl Into<String>) {
@@ -452,8 +810,8 @@ impl ReceiverProgressReporter {
 #[cfg(test)]
 mod tests {
     use super::{
-        CompletionStatus, ProviderProgressTracker, SenderProgressReporter, SenderTransferStatus,
-        TransferId,
+        CompletionStatus, DownloadCounter, ImportProgress, ProviderProgressTracker, RateEstimator,
+        SenderProgressReporter, SenderTransferStatus, ShareStats, TransferId,
     };
     use crate::core::events::{EventEmitter, Role, TransferEvent};
     use crate::core::types::EntryType;
@@ -463,7 +821,7 @@ mod tests {
     };
     use std::sync::{Arc, Mutex as StdMutex};
     use std::thread::sleep;
-    use std::time::Duration;
+    use std::time::{Duration, Instant};
 
     #[derive(Default)]
     struct RecordingEmitter {
@@ -482,6 +840,44 @@ mod tests {
         }
     }
 
+    #[test]
+    fn import_progress_throttles_and_always_reports_completion() {
+        let emitter = Arc::new(RecordingEmitter::default());
+        let progress = ImportProgress::new(Some(emitter.clone()), 100);
+
+        progress.advance(40);
+        assert!(emitter.events().is_empty());
+        progress.advance(80);
+
+        let events = emitter.events();
+        assert_eq!(events.len(), 1);
+        assert!(matches!(
+            events[0],
+            TransferEvent::ImportProgress {
+                role: Role::Sender,
+                processed: 100,
+                total: 100,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn rate_estimator_smooths_samples_into_an_eta() {
+        let start = Instant::now();
+        let mut rate = RateEstimator::default();
+        assert_eq!(rate.eta_secs(0, 1000), None);
+
+        rate.update(start, 0);
+        rate.update(start + Duration::from_secs(1), 100);
+        assert_eq!(rate.eta_secs(100, 1000), Some(9.0));
+
+        // 速度翻倍后只按权重靠近新样本：100 + 0.3 * (200 - 100) = 130
+        rate.update(start + Duration::from_secs(2), 300);
+        assert_eq!(rate.eta_secs(300, 950), Some(5.0));
+        assert_eq!(rate.eta_secs(950, 950), Some(0.0));
+    }
+
     #[test]
     fn file_transfer_completes_after_quiet_period() {
         let mut tracker = ProviderProgressTracker::new(EntryType::File);
@@ -591,13 +987,18 @@ mod tests {
         let events = sink.events();
         assert!(matches!(
             events.first(),
-            Some(TransferEvent::Started { role: Role::Sender })
+            Some(TransferEvent::Started {
+                role: Role::Sender,
+                ..
+            })
         ));
-        assert!(
-            events
-                .iter()
-                .any(|event| matches!(event, TransferEvent::Completed { role: Role::Sender }))
-        );
+        assert!(events.iter().any(|event| matches!(
+            event,
+            TransferEvent::Completed {
+                role: Role::Sender,
+                ..
+            }
+        )));
     }
 
     #[tokio::test]
@@ -625,11 +1026,64 @@ mod tests {
         let events = sink.events();
         assert!(events.iter().any(|event| matches!(
             event,
-            TransferEvent::Failed { role: Role::Sender, message }
+            TransferEvent::Failed { role: Role::Sender, message, .. }
                 if message == "transfer aborted"
         )));
     }
 
+    #[tokio::test]
+    async fn sender_progress_reporter_collects_share_stats() {
+        let (status_tx, _status_rx) = tokio::sync::watch::channel(SenderTransferStatus::Idle);
+        let (stats_tx, stats_rx) = tokio::sync::watch::channel(ShareStats::default());
+        let reporter =
+            SenderProgressReporter::new(None, EntryType::File, status_tx).with_stats(stats_tx);
+        let peer = iroh::SecretKey::generate(&mut rand::rng()).public();
+        let id = TransferId::new(12, 1);
+
+        reporter.on_peer_connected(peer).await;
+        reporter.on_peer_connected(peer).await;
+        reporter.on_request_received(id, 128).await;
+        reporter
+            .on_request_update(
+                id,
+                RequestUpdate::Completed(TransferCompleted {
+                    stats: transfer_stats(128),
+                }),
+            )
+            .await;
+        reporter.on_connection_closed(id.connection).await;
+
+        let stats = *stats_rx.borrow();
+        assert_eq!(stats.unique_peers, 1);
+        assert_eq!(stats.completed_downloads, 1);
+        assert_eq!(stats.bytes_served, 128);
+
+        let stats = ShareStats {
+            duration: Duration::from_secs(2),
+            ..stats
+        };
+        assert!((stats.average_throughput() - 64.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn download_counter_counts_closed_connections_once() {
+        let mut counter = DownloadCounter::default();
+
+        counter.on_request_started(1);
+        counter.on_request_started(1);
+        assert!(!counter.on_request_finished(1, true));
+        // 连接关闭早于第二个请求的完成事件
+        assert!(!counter.on_connection_closed(1));
+        assert!(counter.on_request_finished(1, true));
+        assert_eq!(counter.completed(), 1);
+
+        counter.on_request_started(2);
+        assert!(!counter.on_request_finished(2, false));
+        assert!(!counter.on_connection_closed(2));
+        assert!(!counter.on_connection_closed(3));
+        assert_eq!(counter.completed(), 1);
+    }
+
     fn transfer_stats(payload_bytes_sent: u64) -> Box<TransferStats> {
         Box::new(TransferStats {
             payload_bytes_sent,
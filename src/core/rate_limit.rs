@@ -0,0 +1,59 @@
+//! 限速：所有连接共享同一个速率上限，避免大文件传输占满带宽。
+//!
+//! 发送端通过 iroh-blobs 的节流事件（`ThrottleMode::Intercept`）实现：每发送一块数据前，
+//! 提供者都会等待我们放行，我们按块大小把放行时间依次向后排。
+//! 接收端在处理下载进度流时按已收到的字节数等待，读取暂停后下载也随之放慢。
+
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// 按字节速率放行数据块。
+#[derive(Debug)]
+pub struct RateLimiter {
+    bytes_per_second: u64,
+    next_slot: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    pub fn new(bytes_per_second: u64) -> Self {
+        Self {
+            bytes_per_second: bytes_per_second.max(1),
+            next_slot: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// 为 `size` 字节预留发送时间，返回该块可以发送的时刻。
+    fn reserve(&self, size: u64) -> Instant {
+        let cost = Duration::from_secs_f64(size as f64 / self.bytes_per_second as f64);
+        let mut next_slot = self
+            .next_slot
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        let start = (*next_slot).max(Instant::now());
+        *next_slot = start + cost;
+        start
+    }
+
+    /// 等待直到可以发送 `size` 字节。
+    pub async fn acquire(&self, size: u64) {
+        tokio::time::sleep_until(self.reserve(size)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RateLimiter;
+    use std::time::Duration;
+
+    #[test]
+    fn rate_limiter_spaces_out_chunks() {
+        let limiter = RateLimiter::new(1024);
+
+        let first = limiter.reserve(512);
+        let second = limiter.reserve(1024);
+        let third = limiter.reserve(1);
+        assert_eq!(second - first, Duration::from_millis(500));
+        assert_eq!(third - first, Duration::from_millis(1500));
+    }
+}
@@ -2,118 +2,510 @@
 //!
 //! 主要导出 `download`，它负责建立连接、跟踪进度并将文件导出到目标目录。
 
+use crate::core::archive::{ARCHIVE_MARKER, unpack};
+use crate::core::auth::authenticate;
+use crate::core::compression::{Compression, split_collection};
+use crate::core::connect::{connect_within, connection_info};
 use crate::core::endpoint::base_endpoint_builder;
-use crate::core::events::AppHandle;
-use crate::core::options::{ReceiveOptions, ReceiveRetryPolicy};
+use crate::core::error::{CatalogError, Error, ErrorKind, classify};
+use crate::core::event_queue::{DEFAULT_QUEUE_CAPACITY, QueuedEventEmitter};
+use crate::core::events::{
+    AppHandle, EventEmitter, Role, TransferIdEmitter, TransferWarning, WarningCode,
+    next_transfer_id,
+};
+use crate::core::history::{self, Outcome, TransferRecord};
+use crate::core::metadata::{self, METADATA_ENTRY, MetadataMap};
+use crate::core::options::{ExportModeOption, ReceiveOptions, ReceiveRetryPolicy};
 use crate::core::progress::{ReceiverProgressReporter, TransferEventEmitter};
-use crate::core::results::ReceiveResult;
-use crate::core::storage::{load_fs_store, unique_temp_dir};
+use crate::core::rate_limit::RateLimiter;
+use crate::core::results::{
+    CollectionEntry, CollectionSummary, ConnectionInfo, ReceiveResult, VerifiedEntry,
+};
+use crate::core::routing::{RouteRule, RoutedFile, apply_routes, glob_match, plan_routes};
+use crate::core::storage::{disk_space, load_fs_store, unique_temp_dir};
+use crate::core::ticket::ShareTicket;
+use crate::core::trace::{TraceKind, TraceLog};
+use crate::core::verify::{hash_file, hash_reader, rehash_blob_with_size};
+use anyhow::Context;
 use iroh::{Endpoint, discovery::dns::DnsDiscovery};
 use iroh_blobs::{
+    HashAndFormat,
     api::{
         Store,
-        blobs::{ExportMode, ExportOptions, ExportProgressItem},
+        blobs::{ExportOptions, ExportProgressItem},
         remote::GetProgressItem,
     },
     format::collection::Collection,
-    get::{GetError, request::get_hash_seq_and_sizes},
+    get::{GetError, fsm::DecodeError, request::get_hash_seq_and_sizes},
+    hashseq::HashSeq,
+    protocol::{ChunkRanges, GetRequest},
+    store::{fs::FsStore, mem::MemStore},
     ticket::BlobTicket,
 };
 use n0_future::StreamExt;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::path::{Path, PathBuf};
-use std::str::FromStr;
 use std::sync::Arc as StdArc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::io::{AsyncRead, AsyncWriteExt};
 use tokio::select;
+use tokio::task::JoinSet;
 use tracing::info;
 use tracing::log::trace;
 
 // event helpers provided by `core::progress`
 
 const RECEIVE_TEMP_DIR_PREFIX: &str = ".sendmer-recv-";
+/// 导出中的文件名后缀，写完后重命名为最终文件名。
+const PARTIAL_SUFFIX: &str = ".part";
 
 /// 下载并导出由 `ticket_str` 指定的数据到本地目录。
 ///
 /// - `ticket_str`：连接票据字符串。
 /// - `options`：接收选项（输出目录、转发模式等）。
 /// - `app_handle`：可选的事件发射器句柄，用于 UI/CLI 上报进度与文件名等信息。
+///
+/// 已经解析过 ticket 的调用方可以直接使用 [`receive_ticket`]。
 pub async fn receive(
     ticket_str: String,
     options: ReceiveOptions,
     app_handle: AppHandle,
+) -> Result<ReceiveResult, Error> {
+    let ticket = ShareTicket::try_from(ticket_str.as_str())?;
+    receive_ticket(ticket, options, app_handle).await
+}
+
+/// 与 [`receive`] 相同，但接收已解析的 ticket（`BlobTicket` 或 `ShareTicket`）。
+///
+/// 失败时返回的 [`Error`] 区分 ticket 无效、连接失败、发送端中途断开、校验失败、
+/// 导出失败与取消等情况。
+pub async fn receive_ticket(
+    ticket: impl Into<ShareTicket>,
+    options: ReceiveOptions,
+    app_handle: AppHandle,
+) -> Result<ReceiveResult, Error> {
+    let transfer_id = next_transfer_id();
+    let queue = app_handle.map(|inner| {
+        StdArc::new(QueuedEventEmitter::new(
+            TransferIdEmitter::wrap(inner, transfer_id),
+            DEFAULT_QUEUE_CAPACITY,
+        ))
+    });
+    let app_handle = queue
+        .clone()
+        .map(|queue| -> StdArc<dyn EventEmitter> { queue });
+    let result = receive_resolved(ticket.into(), options, app_handle, transfer_id).await;
+    // 调用方拿到结果后可能立即退出，先等最后的完成/失败事件送达
+    if let Some(queue) = queue {
+        queue.flush().await;
+    }
+    result.map_err(Error::from)
+}
+
+async fn receive_resolved(
+    ticket: ShareTicket,
+    options: ReceiveOptions,
+    app_handle: AppHandle,
+    transfer_id: u64,
 ) -> anyhow::Result<ReceiveResult> {
-    let ticket = BlobTicket::from_str(&ticket_str)?;
+    let ticket = ticket.resolve_with(&options.static_addrs).await?;
     info!(
+        transfer_id,
         hash = %ticket.hash(),
         relay_addrs = ticket.addr().relay_urls().count(),
         ip_addrs = ticket.addr().ip_addrs().count(),
         "starting receive"
     );
+    // 先完成不涉及临时存储的检查，之后的任何失败都经过 `cleanup_failed_receive`
+    let (mut output_dirs, warnings) =
+        resolve_output_dirs(options.output_dir.clone(), options.mirror_dirs.clone())?;
+    if let Some(into) = &options.into_dir {
+        output_dirs = into_subdirs(&output_dirs, into)?;
+    }
     let context = ReceiveContext::prepare(ticket, &options).await?;
-    let output_dir = resolve_output_dir(options.output_dir)?;
+    emit_receive_warnings(&app_handle, &warnings);
 
     let artifacts = select! {
-        x = receive_once(&context, &output_dir, app_handle.clone()) => match x {
+        x = receive_once(&context, &output_dirs, app_handle.clone()) => match x {
             Ok(artifacts) => artifacts,
             Err(error) => {
                 tracing::error!(error = %error, "download operation failed");
                 let message = receive_failed_message(&error);
+                record_history(options.history.as_deref(), &context, 0, Some(&message));
                 emit_receive_failed(&app_handle, message.clone());
                 let error = finalize_failed_receive(
-                    anyhow::anyhow!(message),
+                    error.context(message),
                     cleanup_failed_receive(&context).await,
                 );
                 return Err(error);
             }
         },
-        _ = tokio::signal::ctrl_c() => {
+        () = cancel_requested(options.cancel.as_ref()) => {
             tracing::warn!("operation cancelled by user");
             let message = receive_cancelled_message();
+            record_history(options.history.as_deref(), &context, 0, Some(message));
             emit_receive_failed(&app_handle, message);
             let error = finalize_failed_receive(
-                anyhow::anyhow!(message),
+                CatalogError::new(ErrorKind::Cancelled, message).into(),
                 cleanup_failed_receive(&context).await,
             );
             return Err(error);
         }
     };
 
-    let result = finish_receive(&context, artifacts).await?;
+    record_history(
+        options.history.as_deref(),
+        &context,
+        artifacts.payload_size,
+        None,
+    );
+    let result = finish_receive(&context, artifacts, warnings, transfer_id).await?;
     info!(output = %result.file_path.display(), message = %result.message, "receive completed");
     Ok(result)
 }
 
-/// 将集合中的各个 blob 导出到 `output_dir`。
+/// 等待取消请求：Ctrl+C，或调用方的取消令牌被触发。
+async fn cancel_requested(token: Option<&tokio_util::sync::CancellationToken>) {
+    match token {
+        Some(token) => select! {
+            _ = tokio::signal::ctrl_c() => {}
+            () = token.cancelled() => {}
+        },
+        None => {
+            let _ = tokio::signal::ctrl_c().await;
+        }
+    }
+}
+
+/// 列出 ticket 指向的集合中的文件名与大小，不下载文件内容。
 ///
-/// 该函数会为每个条目创建目标路径并通过 `db.export_with_opts` 执行导出流。
-async fn export(db: &Store, collection: Collection, output_dir: &Path) -> anyhow::Result<()> {
-    for (name, hash) in collection.iter() {
-        let target = get_export_path(output_dir, name)?;
-        if target.exists() {
-            anyhow::bail!("target {} already exists", target.display());
-        }
-        let mut stream = db
-            .export_with_opts(ExportOptions {
-                hash: *hash,
-                target,
-                mode: ExportMode::Copy,
-            })
-            .stream()
-            .await;
+/// 只请求 hash seq、各子 blob 的大小与集合元数据 blob，数据暂存在内存中。
+/// 文件大小为传输大小：使用 `--compress` 分享的集合给出的是压缩后的大小，
+/// `--archive` 分享的集合只有一个归档条目。
+pub async fn list(
+    ticket: impl Into<ShareTicket>,
+    options: &ReceiveOptions,
+) -> anyhow::Result<CollectionSummary> {
+    let ticket = ticket.into().resolve_with(&options.static_addrs).await?;
+    let endpoint = bind_endpoint(&ticket, options).await?;
+    let db = MemStore::new();
+    let summary = fetch_summary(&endpoint, &db, &ticket, options).await;
+    // 无论成功与否都关闭存储与端点
+    let shutdown = db.shutdown().await;
+    endpoint.close().await;
+    let summary = summary?;
+    shutdown?;
+    Ok(summary)
+}
 
-        while let Some(item) = stream.next().await {
-            match item {
-                ExportProgressItem::Size(_size) => {
-                    // Skip progress updates for library version
+/// 只下载集合元数据 blob 并生成清单。
+async fn fetch_summary(
+    endpoint: &Endpoint,
+    db: &Store,
+    ticket: &BlobTicket,
+    options: &ReceiveOptions,
+) -> anyhow::Result<CollectionSummary> {
+    let hash = ticket.hash();
+    let addr = ticket.addr().clone();
+    if let Some(password) = &options.password {
+        authenticate(endpoint, &addr, password).await?;
+    }
+    let (_hash_seq, sizes) =
+        get_sizes_with_retries(endpoint, &addr, &hash, options.retry_policy).await?;
+
+    let request = GetRequest::builder()
+        .root(ChunkRanges::all())
+        .child(0, ChunkRanges::all())
+        .build(hash);
+    let connection = connect_within(
+        endpoint,
+        &addr,
+        iroh_blobs::protocol::ALPN,
+        options.retry_policy.connect_timeout,
+    )
+    .await?;
+    db.remote()
+        .execute_get(connection, request)
+        .await
+        .map_err(show_get_error)?;
+    let full = Collection::load(hash, db).await?;
+    summarize_collection(hash, &full, &sizes)
+}
+
+/// 按 `sizes`（hash seq 中各子 blob 的大小，第 0 个为集合元数据 blob）生成集合清单。
+fn summarize_collection(
+    hash: iroh_blobs::Hash,
+    full: &Collection,
+    sizes: &[u64],
+) -> anyhow::Result<CollectionSummary> {
+    let blob_sizes = full
+        .iter()
+        .zip(sizes.iter().skip(1))
+        .map(|((_, hash), size)| (*hash, *size))
+        .collect::<HashMap<_, _>>();
+    let (collection, _encoding) = decode_collection(full)?;
+    let entries = collection
+        .iter()
+        .map(|(name, hash)| CollectionEntry {
+            name: name.clone(),
+            hash: *hash,
+            size: blob_sizes.get(hash).copied().unwrap_or_default(),
+        })
+        .collect::<Vec<_>>();
+    Ok(CollectionSummary {
+        hash,
+        size: entries.iter().map(|entry| entry.size).sum(),
+        entries,
+        warnings: Vec::new(),
+    })
+}
+
+/// 启用传输记录时追加本次接收的结果；`failure` 为失败原因。
+fn record_history(
+    history: Option<&Path>,
+    context: &ReceiveContext,
+    size: u64,
+    failure: Option<&str>,
+) {
+    let Some(path) = history else {
+        return;
+    };
+    let outcome = if failure.is_some() {
+        Outcome::Failed
+    } else {
+        Outcome::Completed
+    };
+    let mut record = TransferRecord::new(
+        Role::Receiver,
+        context.ticket.hash(),
+        Some(context.addr.id),
+        size,
+        outcome,
+    );
+    if let Some(message) = failure {
+        record = record.with_message(message);
+    }
+    history::append(path, &record);
+}
+
+/// 将集合中的各个 blob 导出到 `output_dirs` 中的每个目录。
+///
+/// 该函数会为每个条目、每个目标目录创建目标路径并通过 `db.export_with_opts`
+/// 执行导出流；多个目录复用同一份已校验的 blob 数据。
+/// 每个文件先写到同目录下的 `<name>.part`，写完后再重命名为目标文件名，
+/// 中断的接收不会留下看似完整的截断文件。目标文件已存在且内容与条目一致时
+/// （例如上次导出中途被打断）跳过该文件，内容不同时报错；写入任何文件之前
+/// 先检查所有输出目录中的全部目标，不会因为后面的冲突留下只导出了一部分的目录。
+///
+/// `export_mode` 为引用方式时，只有最后一个输出目录从存储中移动数据，
+/// 之前的目录仍从存储复制。
+///
+/// 集合经过压缩时导出的是解压后的内容；归档集合则把归档解包到各个目录。
+/// 集合携带元数据条目时，非沙箱导出的文件会恢复修改时间与权限。
+async fn export(
+    db: &Store,
+    collection: Collection,
+    output_dirs: &[PathBuf],
+    sandboxed: bool,
+    encoding: CollectionEncoding,
+    export_mode: ExportModeOption,
+) -> anyhow::Result<()> {
+    let CollectionEncoding {
+        compression,
+        archive,
+        metadata,
+        flatten,
+    } = encoding;
+    if archive {
+        anyhow::ensure!(
+            !sandboxed,
+            "archived shares cannot be received with --sandbox"
+        );
+        for (_, hash) in collection.iter() {
+            for output_dir in output_dirs {
+                let reader: Box<dyn AsyncRead + Unpin + Send> = match compression {
+                    Some(compression) => Box::new(compression.decompress(db, *hash)),
+                    None => Box::new(db.reader(*hash)),
+                };
+                unpack(reader, output_dir.clone()).await?;
+            }
+        }
+        return Ok(());
+    }
+    if sandboxed {
+        for output_dir in output_dirs {
+            for (name, _) in collection.iter() {
+                let target = output_dir.join(name);
+                if target.symlink_metadata().is_ok() {
+                    return Err(target_exists(&target));
                 }
-                ExportProgressItem::CopyProgress(_offset) => {
-                    // Skip progress updates for library version
+            }
+        }
+        for output_dir in output_dirs {
+            crate::core::sandbox::export_collection(db, &collection, output_dir, compression)
+                .await?;
+        }
+        return Ok(());
+    }
+    let mut metadata = match metadata {
+        Some(hash) => metadata::decode(&db.get_bytes(hash).await?)?,
+        None => MetadataMap::new(),
+    };
+    if flatten {
+        metadata = metadata
+            .into_iter()
+            .map(|(name, file_metadata)| (flat_name(&name).to_string(), file_metadata))
+            .collect();
+    }
+    let mut exported = HashSet::new();
+    for (name, hash) in collection.iter() {
+        for output_dir in output_dirs {
+            let target = get_export_path(output_dir, name)?;
+            if target.exists() {
+                if !already_exported(db, *hash, &target, compression).await? {
+                    return Err(target_exists(&target));
                 }
-                ExportProgressItem::Done => {
-                    // Export completed
+                exported.insert(target);
+            }
+        }
+    }
+    for (name, hash) in collection.iter() {
+        for (index, output_dir) in output_dirs.iter().enumerate() {
+            let mode = if index + 1 == output_dirs.len() {
+                export_mode
+            } else {
+                ExportModeOption::Copy
+            };
+            let target = get_export_path(output_dir, name)?;
+            if exported.contains(&target) {
+                info!(path = %target.display(), "skipping file that was already exported");
+                continue;
+            }
+            if target.exists() {
+                return Err(target_exists(&target));
+            }
+            let partial = partial_path(&target);
+            // 上次中断留下的临时文件
+            remove_if_exists(&partial).await?;
+            let result = match compression {
+                Some(compression) => {
+                    export_decompressed_blob(db, *hash, partial.clone(), name, compression).await
                 }
-                ExportProgressItem::Error(cause) => {
-                    anyhow::bail!("error exporting {}: {}", name, cause);
+                None => export_blob(db, *hash, partial.clone(), name, mode).await,
+            };
+            if let Err(error) = result {
+                if let Err(cleanup_error) = remove_if_exists(&partial).await {
+                    tracing::warn!(error = %cleanup_error, "failed to remove {}", partial.display());
                 }
+                return Err(error);
+            }
+            tokio::fs::rename(&partial, &target)
+                .await
+                .with_context(|| format!("failed to move {name} into place"))?;
+            if let Some(file_metadata) = metadata.get(name) {
+                metadata::apply(&target, *file_metadata)
+                    .with_context(|| format!("failed to restore metadata of {name}"))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn target_exists(target: &Path) -> anyhow::Error {
+    CatalogError::new(
+        ErrorKind::TargetExists,
+        format!("target {} already exists", target.display()),
+    )
+    .into()
+}
+
+/// 判断已存在的 `target` 是否就是该条目导出后的内容；先比较大小，一致时再比较 blake3。
+async fn already_exported(
+    db: &Store,
+    hash: iroh_blobs::Hash,
+    target: &Path,
+    compression: Option<Compression>,
+) -> anyhow::Result<bool> {
+    let metadata = tokio::fs::metadata(target).await?;
+    if !metadata.is_file() {
+        return Ok(false);
+    }
+    let expected = match compression {
+        Some(compression) => hash_reader(compression.decompress(db, hash)).await?,
+        None => {
+            let size = db
+                .remote()
+                .local(HashAndFormat::raw(hash))
+                .await?
+                .local_bytes();
+            (hash, size)
+        }
+    };
+    if expected.1 != metadata.len() {
+        return Ok(false);
+    }
+    Ok(hash_file(target).await? == expected)
+}
+
+/// 导出过程中使用的临时文件：与目标同目录，文件名追加 [`PARTIAL_SUFFIX`]。
+pub(crate) fn partial_path(target: &Path) -> PathBuf {
+    let mut name = target.file_name().unwrap_or_default().to_os_string();
+    name.push(PARTIAL_SUFFIX);
+    target.with_file_name(name)
+}
+
+async fn remove_if_exists(path: &Path) -> std::io::Result<()> {
+    match tokio::fs::remove_file(path).await {
+        Err(error) if error.kind() != std::io::ErrorKind::NotFound => Err(error),
+        _ => Ok(()),
+    }
+}
+
+async fn export_decompressed_blob(
+    db: &Store,
+    hash: iroh_blobs::Hash,
+    target: PathBuf,
+    name: &str,
+    compression: Compression,
+) -> anyhow::Result<()> {
+    let mut reader = compression.decompress(db, hash);
+    let mut file = tokio::fs::File::create_new(&target).await?;
+    tokio::io::copy(&mut reader, &mut file)
+        .await
+        .map_err(|error| anyhow::anyhow!("error decompressing {name}: {error}"))?;
+    file.sync_all().await?;
+    Ok(())
+}
+
+async fn export_blob(
+    db: &Store,
+    hash: iroh_blobs::Hash,
+    target: PathBuf,
+    name: &str,
+    mode: ExportModeOption,
+) -> anyhow::Result<()> {
+    let mut stream = db
+        .export_with_opts(ExportOptions {
+            hash,
+            target,
+            mode: mode.into(),
+        })
+        .stream()
+        .await;
+
+    while let Some(item) = stream.next().await {
+        match item {
+            ExportProgressItem::Size(_size) => {
+                // Skip progress updates for library version
+            }
+            ExportProgressItem::CopyProgress(_offset) => {
+                // Skip progress updates for library version
+            }
+            ExportProgressItem::Done => {
+                // Export completed
+            }
+            ExportProgressItem::Error(cause) => {
+                anyhow::bail!("error exporting {}: {}", name, cause);
             }
         }
     }
@@ -127,17 +519,66 @@ struct ReceiveContext {
     iroh_data_dir: PathBuf,
     db: Store,
     retry_policy: ReceiveRetryPolicy,
+    routes: Vec<RouteRule>,
+    route_dry_run: bool,
+    trace: Option<TraceLog>,
+    sandboxed_export: bool,
+    first: Vec<String>,
+    password: Option<String>,
+    /// 失败时保留临时存储以便续传
+    resume: bool,
+    /// 只重新获取缺失或校验失败的条目
+    retry_failed: bool,
+    flatten: bool,
+    export_mode: ExportModeOption,
+    /// 完成或失败后都保留共享的 blob 存储
+    keep_store: bool,
+    /// 跳过下载前的磁盘空间检查
+    skip_space_check: bool,
+    /// 把唯一条目写到标准输出而不是导出到磁盘
+    stdout: bool,
+    /// 只下载并校验，不导出
+    verify_only: bool,
+    /// 下载限速，`--first` 与后续下载、多个发送端共享
+    rate_limiter: Option<StdArc<RateLimiter>>,
+    /// 提供同一内容的所有发送端，第一个为主 ticket 的发送端
+    sources: Vec<iroh::EndpointAddr>,
+    /// 导出后重新校验导出的文件
+    verify_export: bool,
+    /// 导出后写入 `BLAKE3SUMS`
+    checksums: bool,
+    #[cfg(feature = "chaos")]
+    chaos: Option<crate::core::chaos::ChaosConfig>,
+}
+
+/// 集合中标记条目描述的数据编码方式。
+#[derive(Debug, Clone, Copy, Default)]
+struct CollectionEncoding {
+    compression: Option<Compression>,
+    /// 集合中唯一的数据条目是 tar 归档
+    archive: bool,
+    /// 元数据条目的 hash
+    metadata: Option<iroh_blobs::Hash>,
+    /// 条目名称已去掉目录部分，元数据需要按文件名查找
+    flatten: bool,
 }
 
 struct ReceiveArtifacts {
     total_files: u64,
     payload_size: u64,
+    resumed_bytes: u64,
+    verified: Vec<VerifiedEntry>,
     root_item_path: PathBuf,
+    routed: Vec<RoutedFile>,
+    connection: Option<ConnectionInfo>,
 }
 
 struct DownloadOutcome {
     total_files: u64,
     payload_size: u64,
+    /// 本地已有、未重新传输的数据字节数
+    resumed_bytes: u64,
+    connection: Option<ConnectionInfo>,
 }
 
 struct DownloadPlan {
@@ -148,6 +589,25 @@ struct DownloadPlan {
 impl ReceiveContext {
     async fn prepare(ticket: BlobTicket, options: &ReceiveOptions) -> anyhow::Result<Self> {
         let addr = ticket.addr().clone();
+        let mut sources = vec![addr.clone()];
+        for extra in &options.extra_tickets {
+            let extra = extra.clone().resolve_with(&options.static_addrs).await?;
+            anyhow::ensure!(
+                extra.hash() == ticket.hash(),
+                "ticket for {} does not share the same content as {}",
+                extra.hash(),
+                ticket.hash()
+            );
+            if !sources.iter().any(|source| source.id == extra.addr().id) {
+                sources.push(extra.addr().clone());
+            }
+        }
+        let trace = options
+            .trace_path
+            .as_deref()
+            .map(TraceLog::create)
+            .transpose()?;
+        // 创建临时存储之后不再有会失败的步骤，失败时不会遗留存储目录
         let (endpoint, iroh_data_dir, db) = prepare_env(&ticket, options).await?;
         Ok(Self {
             ticket,
@@ -156,6 +616,32 @@ impl ReceiveContext {
             iroh_data_dir,
             db,
             retry_policy: options.retry_policy,
+            routes: options.routes.clone(),
+            route_dry_run: options.route_dry_run,
+            trace,
+            sandboxed_export: options.sandboxed_export,
+            first: options.first.clone(),
+            password: options.password.clone(),
+            resume: options.resume || options.retry_failed,
+            retry_failed: options.retry_failed,
+            skip_space_check: options.skip_space_check,
+            keep_store: options.keep_store,
+            export_mode: if options.resume || options.retry_failed || options.keep_store {
+                ExportModeOption::Copy
+            } else {
+                options.export_mode
+            },
+            flatten: options.flatten,
+            stdout: options.stdout,
+            verify_only: options.verify_only,
+            rate_limiter: options
+                .rate_limit
+                .map(|rate| StdArc::new(RateLimiter::new(rate))),
+            sources,
+            verify_export: options.verify_export || options.checksums,
+            checksums: options.checksums,
+            #[cfg(feature = "chaos")]
+            chaos: options.chaos,
         })
     }
 
@@ -163,9 +649,70 @@ impl ReceiveContext {
         self.ticket.hash_and_format()
     }
 
-    async fn load_collection(&self) -> anyhow::Result<Collection> {
-        Collection::load(self.hash_and_format().hash, &self.db).await
+    /// 加载集合并去掉压缩、归档、元数据等标记条目，同时返回集合的编码方式。
+    ///
+    /// 启用 `flatten` 时条目名称只保留文件名。
+    async fn load_collection(&self) -> anyhow::Result<(Collection, CollectionEncoding)> {
+        let collection = Collection::load(self.hash_and_format().hash, &self.db).await?;
+        let (collection, mut encoding) = decode_collection(&collection)?;
+        if !self.flatten {
+            return Ok((collection, encoding));
+        }
+        anyhow::ensure!(
+            !encoding.archive,
+            "archived shares cannot be received with --flatten"
+        );
+        encoding.flatten = true;
+        Ok((flatten_collection(&collection)?, encoding))
+    }
+}
+
+/// 去掉集合中的压缩、归档、元数据等标记条目，同时返回集合的编码方式。
+fn decode_collection(collection: &Collection) -> anyhow::Result<(Collection, CollectionEncoding)> {
+    let (collection, compression) = split_collection(collection)?;
+    let archive = collection.iter().any(|(name, _)| name == ARCHIVE_MARKER);
+    let metadata = collection
+        .iter()
+        .find(|(name, _)| name == METADATA_ENTRY)
+        .map(|(_, hash)| *hash);
+    let collection = filter_collection(&collection, |name| {
+        name != ARCHIVE_MARKER && name != METADATA_ENTRY
+    });
+    Ok((
+        collection,
+        CollectionEncoding {
+            compression,
+            archive,
+            metadata,
+            flatten: false,
+        },
+    ))
+}
+
+/// `--flatten` 时条目导出的文件名：去掉所有目录部分。
+fn flat_name(name: &str) -> &str {
+    name.rsplit('/').next().unwrap_or(name)
+}
+
+/// 把条目名称替换为文件名；两个条目落到同一个文件名时报错，不覆盖任何文件。
+fn flatten_collection(collection: &Collection) -> anyhow::Result<Collection> {
+    let mut seen = HashMap::new();
+    for (name, _) in collection.iter() {
+        if let Some(previous) = seen.insert(flat_name(name), name) {
+            return Err(CatalogError::new(
+                ErrorKind::TargetExists,
+                format!(
+                    "--flatten would write both {previous} and {name} to {}",
+                    flat_name(name)
+                ),
+            )
+            .into());
+        }
     }
+    Ok(collection
+        .iter()
+        .map(|(name, hash)| (flat_name(name).to_string(), *hash))
+        .collect())
 }
 
 impl DownloadPlan {
@@ -179,27 +726,217 @@ impl DownloadPlan {
 
 async fn receive_once(
     context: &ReceiveContext,
-    output_dir: &Path,
+    output_dirs: &[PathBuf],
     app_handle: AppHandle,
 ) -> anyhow::Result<ReceiveArtifacts> {
     trace!("load done!");
 
+    if let Some(password) = &context.password {
+        authenticate(&context.endpoint, &context.addr, password).await?;
+    }
     let event_emitter =
         TransferEventEmitter::new(app_handle.clone(), crate::core::events::Role::Receiver);
-    let download = download_missing_data(context, app_handle).await?;
-    let collection = context.load_collection().await?;
+    // 之后的各个下载阶段都不再单独发出 `Started`
+    event_emitter.emit_started();
+    if context.stdout {
+        ensure_single_entry(context, &app_handle).await?;
+    }
+    if context.retry_failed {
+        return retry_failed_entries(context, output_dirs, &app_handle).await;
+    }
+    if !context.routes.is_empty() {
+        let (routed, sizes) =
+            plan_routes_before_download(context, &output_dirs[0], &app_handle).await?;
+        if context.route_dry_run {
+            let (collection, _encoding) = context.load_collection().await?;
+            let plan = DownloadPlan::from_sizes(&sizes);
+            event_emitter.emit_completed();
+            return Ok(ReceiveArtifacts {
+                total_files: plan.total_files,
+                payload_size: plan.payload_size,
+                resumed_bytes: 0,
+                verified: Vec::new(),
+                root_item_path: resolve_root_item_path(&output_dirs[0], &collection)?,
+                routed,
+                connection: None,
+            });
+        }
+    }
+    let exported_first = download_and_export_first(context, output_dirs, &app_handle).await?;
+    // 只写到标准输出或只校验时不会写入输出目录
+    let export_dirs = if context.stdout || context.verify_only {
+        &[][..]
+    } else {
+        output_dirs
+    };
+    let download = download_missing_data(context, export_dirs, app_handle).await?;
+    let (collection, encoding) = context.load_collection().await?;
     emit_collection_file_names(&event_emitter, &collection);
-    let root_item_path = resolve_root_item_path(output_dir, &collection)?;
-    export(&context.db, collection, output_dir).await?;
+    if context.stdout {
+        write_to_stdout(&context.db, &collection, encoding).await?;
+        event_emitter.emit_completed();
+        return Ok(ReceiveArtifacts {
+            total_files: download.total_files,
+            payload_size: download.payload_size,
+            resumed_bytes: download.resumed_bytes,
+            verified: Vec::new(),
+            root_item_path: PathBuf::from("-"),
+            routed: Vec::new(),
+            connection: download.connection,
+        });
+    }
+    if context.verify_only {
+        let verified = verify_entries(&context.db, &collection).await?;
+        event_emitter.emit_completed();
+        return Ok(ReceiveArtifacts {
+            total_files: download.total_files,
+            payload_size: download.payload_size,
+            resumed_bytes: download.resumed_bytes,
+            verified,
+            root_item_path: PathBuf::new(),
+            routed: Vec::new(),
+            connection: download.connection,
+        });
+    }
+    let root_item_path = resolve_root_item_path(&output_dirs[0], &collection)?;
+    let file_names = collect_file_names(&collection);
+    let remaining = filter_collection(&collection, |name| !exported_first.contains(name));
+    export(
+        &context.db,
+        remaining,
+        output_dirs,
+        context.sandboxed_export,
+        encoding,
+        context.export_mode,
+    )
+    .await
+    .map_err(export_failed)?;
+    // 在路由移动文件之前校验，此时文件仍位于集合中的路径
+    let verified = if context.verify_export {
+        verify_exported(&context.db, &collection, &output_dirs[0], encoding).await?
+    } else {
+        Vec::new()
+    };
+    if context.checksums {
+        write_checksums(&output_dirs[0], &verified)?;
+    }
+    let routed = route_exported_files(context, &file_names, &output_dirs[0])?;
     event_emitter.emit_completed();
 
     Ok(ReceiveArtifacts {
         total_files: download.total_files,
         payload_size: download.payload_size,
+        resumed_bytes: download.resumed_bytes,
+        verified,
         root_item_path,
+        routed,
+        connection: download.connection,
     })
 }
 
+/// `--retry-failed`：只重新获取并导出主输出目录中缺失或内容不符的条目。
+///
+/// 内容不符的文件先被删除再重新导出；续传存储中已有的数据不会重新下载。
+async fn retry_failed_entries(
+    context: &ReceiveContext,
+    output_dirs: &[PathBuf],
+    app_handle: &AppHandle,
+) -> anyhow::Result<ReceiveArtifacts> {
+    let event_emitter =
+        TransferEventEmitter::new(app_handle.clone(), crate::core::events::Role::Receiver);
+    let sizes = download_collection_listing(context, app_handle).await?;
+    let (collection, encoding) = context.load_collection().await?;
+    anyhow::ensure!(
+        !encoding.archive,
+        "archived shares have no per-file hashes, receive them again with --resume instead"
+    );
+    let root_item_path = resolve_root_item_path(&output_dirs[0], &collection)?;
+    let failed = failed_entries(&context.db, &collection, &output_dirs[0], encoding).await?;
+    for name in &failed {
+        let path = get_export_path(&output_dirs[0], name)?;
+        if path.exists() {
+            tracing::warn!(path = %path.display(), "replacing file that failed verification");
+            for dir in output_dirs {
+                remove_if_exists(&get_export_path(dir, name)?).await?;
+            }
+        }
+    }
+    let (exported, payload_size) = download_and_export_selected(
+        context,
+        output_dirs,
+        app_handle,
+        (&collection, encoding),
+        &sizes,
+        |name| failed.contains(name),
+    )
+    .await?;
+    info!(entries = exported.len(), "re-exported failed entries");
+    event_emitter.emit_completed();
+    Ok(ReceiveArtifacts {
+        total_files: exported.len() as u64,
+        payload_size,
+        resumed_bytes: 0,
+        verified: Vec::new(),
+        root_item_path,
+        routed: Vec::new(),
+        connection: None,
+    })
+}
+
+/// 主输出目录中缺失或内容与集合不符的条目名称。
+///
+/// 压缩条目要用存储中的数据算出解压后的 hash，数据不在本地时按失败处理。
+async fn failed_entries(
+    db: &Store,
+    collection: &Collection,
+    output_dir: &Path,
+    encoding: CollectionEncoding,
+) -> anyhow::Result<HashSet<String>> {
+    let mut failed = HashSet::new();
+    for (name, hash) in collection.iter() {
+        let path = get_export_path(output_dir, name)?;
+        let intact = match hash_file(&path).await {
+            Ok((actual, _)) => match encoding.compression {
+                None => actual == *hash,
+                Some(compression) if db.has(*hash).await? => {
+                    actual == hash_reader(compression.decompress(db, *hash)).await?.0
+                }
+                Some(_) => false,
+            },
+            Err(_) => false,
+        };
+        if !intact {
+            failed.insert(name.clone());
+        }
+    }
+    Ok(failed)
+}
+
+/// 为未归类的导出失败加上 [`ErrorKind::ExportIo`] 分类；已归类的失败（如目标已存在）保持不变。
+fn export_failed(error: anyhow::Error) -> anyhow::Error {
+    if classify(&error).is_some() {
+        return error;
+    }
+    CatalogError::new(
+        ErrorKind::ExportIo,
+        format!("failed to export received files: {error:#}"),
+    )
+    .into()
+}
+
+/// 导出后的路由阶段；dry-run 时只返回计划而不移动文件。
+fn route_exported_files(
+    context: &ReceiveContext,
+    file_names: &[String],
+    output_dir: &Path,
+) -> anyhow::Result<Vec<RoutedFile>> {
+    let routed = plan_routes(file_names, output_dir, &context.routes)?;
+    if !context.route_dry_run {
+        apply_routes(&routed)?;
+    }
+    Ok(routed)
+}
+
 fn emit_collection_file_names(emitter: &TransferEventEmitter, collection: &Collection) {
     let file_names = collect_file_names(collection);
     if !file_names.is_empty() {
@@ -229,6 +966,15 @@ fn emit_receive_failed(app_handle: &AppHandle, message: impl Into<String>) {
     emitter.emit_failed(message);
 }
 
+fn emit_receive_warnings(app_handle: &AppHandle, warnings: &[TransferWarning]) {
+    let emitter =
+        TransferEventEmitter::new(app_handle.clone(), crate::core::events::Role::Receiver);
+    for warning in warnings {
+        tracing::warn!(%warning, "receive warning");
+        emitter.emit_warning(warning);
+    }
+}
+
 fn finalize_failed_receive(
     primary_error: anyhow::Error,
     cleanup_result: anyhow::Result<()>,
@@ -241,67 +987,734 @@ fn finalize_failed_receive(
 
 async fn cleanup_failed_receive(context: &ReceiveContext) -> anyhow::Result<()> {
     let shutdown_result = context.db.shutdown().await.map_err(anyhow::Error::from);
+    if context.keep_store {
+        return shutdown_result;
+    }
+    if context.resume {
+        info!(
+            path = %context.iroh_data_dir.display(),
+            "keeping partial download, rerun with --resume to continue"
+        );
+        return shutdown_result;
+    }
     let cleanup_result = remove_temp_receive_dir(&context.iroh_data_dir).await;
     finalize_cleanup(shutdown_result, cleanup_result)
 }
 
-async fn finish_receive(
+async fn finish_receive(
+    context: &ReceiveContext,
+    artifacts: ReceiveArtifacts,
+    warnings: Vec<TransferWarning>,
+    transfer_id: u64,
+) -> anyhow::Result<ReceiveResult> {
+    if context.keep_store {
+        // 持久 tag 保证数据不会被存储的垃圾回收删除
+        context
+            .db
+            .tags()
+            .set(
+                kept_store_tag(context.ticket.hash()),
+                context.hash_and_format(),
+            )
+            .await?;
+        context.db.shutdown().await?;
+    } else {
+        let shutdown_result = context.db.shutdown().await.map_err(anyhow::Error::from);
+        let cleanup_result = remove_temp_receive_dir(&context.iroh_data_dir).await;
+        finalize_cleanup(shutdown_result, cleanup_result)?;
+    }
+
+    let verb = if context.verify_only {
+        "Verified"
+    } else if context.route_dry_run {
+        "Would download"
+    } else {
+        "Downloaded"
+    };
+    let mut message = format!(
+        "{verb} {} files, {} bytes",
+        artifacts.total_files, artifacts.payload_size
+    );
+    if artifacts.resumed_bytes > 0 {
+        message.push_str(&format!(
+            " ({} bytes resumed from a previous attempt)",
+            artifacts.resumed_bytes
+        ));
+    }
+    Ok(ReceiveResult {
+        transfer_id,
+        message,
+        file_path: artifacts.root_item_path,
+        routed: artifacts.routed,
+        warnings,
+        resumed_bytes: artifacts.resumed_bytes,
+        verified: artifacts.verified,
+        connection: artifacts.connection,
+    })
+}
+
+async fn remove_temp_receive_dir(path: &Path) -> anyhow::Result<()> {
+    match tokio::fs::remove_dir_all(path).await {
+        Ok(()) => Ok(()),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(error) => Err(error.into()),
+    }
+}
+
+async fn download_missing_data(
+    context: &ReceiveContext,
+    output_dirs: &[PathBuf],
+    app_handle: AppHandle,
+) -> anyhow::Result<DownloadOutcome> {
+    let hash_and_format = context.hash_and_format();
+    let local = context.db.remote().local(hash_and_format).await?;
+    if local.is_complete() {
+        let total_files = completed_local_total_files_from_children(local.children())?;
+        return Ok(DownloadOutcome {
+            total_files,
+            payload_size: 0,
+            resumed_bytes: 0,
+            connection: None,
+        });
+    }
+
+    let (hash_seq, sizes) = get_sizes_with_retries(
+        &context.endpoint,
+        &context.addr,
+        &context.ticket.hash(),
+        context.retry_policy,
+    )
+    .await?;
+    let plan = DownloadPlan::from_sizes(&sizes);
+    let resumed_bytes = if context.resume || context.keep_store {
+        resumed_payload_bytes(local.local_bytes(), &sizes, plan.payload_size)
+    } else {
+        0
+    };
+    if resumed_bytes > 0 {
+        info!(
+            resumed_bytes,
+            payload_size = plan.payload_size,
+            "resuming download, skipping data already on disk"
+        );
+    }
+    if !context.skip_space_check {
+        check_disk_space(
+            &context.iroh_data_dir,
+            plan.payload_size - resumed_bytes,
+            output_dirs,
+            plan.payload_size,
+            context.export_mode,
+        )?;
+    }
+    // 进度只统计本次实际需要传输的数据
+    let missing_plan = DownloadPlan {
+        total_files: plan.total_files,
+        payload_size: plan.payload_size - resumed_bytes,
+    };
+    let connection = if context.sources.len() > 1 {
+        download_from_sources(context, &hash_seq, &missing_plan, &app_handle).await?;
+        None
+    } else {
+        download_with_retries(context, &sizes, &plan, missing_plan, &app_handle).await?
+    };
+
+    Ok(DownloadOutcome {
+        total_files: plan.total_files,
+        payload_size: plan.payload_size,
+        resumed_bytes,
+        connection,
+    })
+}
+
+fn disk_space_unknown(path: &Path) {
+    tracing::warn!(path = %path.display(), "cannot query free disk space, skipping the check");
+}
+
+/// 下载前检查临时存储与各输出目录所在文件系统是否有足够的剩余空间。
+///
+/// 位于同一文件系统的需求会累加；以引用方式导出到与临时存储同一文件系统的
+/// 最后一个输出目录时数据直接移动，不额外占用空间。压缩或归档分享解包后的
+/// 大小无法预知，按传输大小估算。无法查询剩余空间的路径不做检查，只记录警告。
+fn check_disk_space(
+    temp_dir: &Path,
+    download_size: u64,
+    output_dirs: &[PathBuf],
+    export_size: u64,
+    export_mode: ExportModeOption,
+) -> anyhow::Result<()> {
+    let mut needs = Vec::new();
+    let temp_space = disk_space(temp_dir);
+    match temp_space {
+        Some(space) => needs.push((space, download_size, temp_dir)),
+        None => disk_space_unknown(temp_dir),
+    }
+    for (index, output_dir) in output_dirs.iter().enumerate() {
+        let Some(space) = disk_space(output_dir) else {
+            disk_space_unknown(output_dir);
+            continue;
+        };
+        let moved = export_mode == ExportModeOption::Reference
+            && index + 1 == output_dirs.len()
+            && temp_space.is_some_and(|temp_space| temp_space.device == space.device);
+        if moved {
+            continue;
+        }
+        match needs
+            .iter_mut()
+            .find(|(other, _, _)| other.device == space.device)
+        {
+            Some((_, bytes, _)) => *bytes = bytes.saturating_add(export_size),
+            None => needs.push((space, export_size, output_dir.as_path())),
+        }
+    }
+    for (space, bytes, path) in needs {
+        if bytes > space.available {
+            return Err(CatalogError::new(
+                ErrorKind::NoSpace,
+                format!(
+                    "not enough disk space at {}: {} needed, {} available",
+                    path.display(),
+                    indicatif::HumanBytes(bytes),
+                    indicatif::HumanBytes(space.available)
+                ),
+            )
+            .into());
+        }
+    }
+    Ok(())
+}
+
+/// 按重试策略下载缺失的数据；每次重试前重新计算本地仍缺失的部分，已收到的数据不会重新下载。
+/// 成功时返回最后一次连接所走的路径。
+///
+/// 校验失败的数据不会写入存储，因此数据损坏时重新请求缺失部分即只获取损坏的范围；
+/// 这类修复立即进行，次数由 `repair_limit` 单独限制。
+async fn download_with_retries(
+    context: &ReceiveContext,
+    sizes: &[u64],
+    plan: &DownloadPlan,
+    mut missing_plan: DownloadPlan,
+    app_handle: &AppHandle,
+) -> anyhow::Result<Option<ConnectionInfo>> {
+    let hash_and_format = context.hash_and_format();
+    let started = std::time::Instant::now();
+    let mut attempt = 0;
+    let mut repairs = 0;
+    loop {
+        let missing = context.db.remote().local(hash_and_format).await?.missing();
+        let error = match execute_download(context, missing, &missing_plan, app_handle).await {
+            Ok(connection) => return Ok(connection),
+            Err(error) => error,
+        };
+        if is_corrupted_data(&error) && repairs < context.retry_policy.repair_limit {
+            repairs += 1;
+            tracing::warn!(
+                repairs,
+                error = %error,
+                "received data failed verification, re-requesting the damaged ranges"
+            );
+        } else {
+            attempt += 1;
+            let Some(delay) = context.retry_policy.next_delay(attempt, started.elapsed()) else {
+                return Err(error);
+            };
+            tracing::warn!(attempt, error = %error, "download failed, retrying in {delay:?}");
+            tokio::time::sleep(delay).await;
+        }
+        let local_bytes = context
+            .db
+            .remote()
+            .local(hash_and_format)
+            .await?
+            .local_bytes();
+        missing_plan.payload_size =
+            plan.payload_size - resumed_payload_bytes(local_bytes, sizes, plan.payload_size);
+    }
+}
+
+/// 下载是否因收到的数据未通过 blake3 校验而失败。
+///
+/// 读取失败、数据缺失等其他解码错误不算在内。
+fn is_corrupted_data(error: &anyhow::Error) -> bool {
+    matches!(
+        error.downcast_ref::<GetError>(),
+        Some(GetError::Decode {
+            source: DecodeError::LeafHashMismatch { .. } | DecodeError::ParentHashMismatch { .. },
+            ..
+        })
+    )
+}
+
+/// 从多个发送端并发下载缺失的数据。
+///
+/// hash seq 与每个缺失的子 blob 各作为一个任务放入共享队列，每个发送端一个 worker
+/// 依次领取任务。某个发送端出错时任务放回队列，由其余发送端接手，出错的发送端不再使用；
+/// 所有发送端都失败时返回最后一个错误。
+async fn download_from_sources(
+    context: &ReceiveContext,
+    hash_seq: &HashSeq,
+    plan: &DownloadPlan,
+    app_handle: &AppHandle,
+) -> anyhow::Result<()> {
+    let root = context.ticket.hash();
+    let mut jobs = VecDeque::new();
+    if !context
+        .db
+        .remote()
+        .local(HashAndFormat::raw(root))
+        .await?
+        .is_complete()
+    {
+        jobs.push_back(GetRequest::builder().root(ChunkRanges::all()).build(root));
+    }
+    for hash in hash_seq.iter() {
+        let local = context.db.remote().local(HashAndFormat::raw(hash)).await?;
+        if !local.is_complete() {
+            jobs.push_back(local.missing());
+        }
+    }
+    let jobs = StdArc::new(std::sync::Mutex::new(jobs));
+    let received = StdArc::new(AtomicU64::new(0));
+    let mut reporter = ReceiverProgressReporter::new(app_handle.clone(), plan.payload_size);
+    reporter.emit_initial_progress();
+    let mut ticker = tokio::time::interval(std::time::Duration::from_millis(200));
+
+    let mut healthy = context.sources.clone();
+    let mut last_error = None;
+    // 健康的发送端领完队列就会退出；若此后其他发送端出错放回了任务，再启动一轮
+    while !lock_jobs(&jobs).is_empty() && !healthy.is_empty() {
+        let mut workers = JoinSet::new();
+        for addr in std::mem::take(&mut healthy) {
+            let source = SourceWorker {
+                endpoint: context.endpoint.clone(),
+                db: context.db.clone(),
+                addr: addr.clone(),
+                password: context.password.clone(),
+                jobs: jobs.clone(),
+                received: received.clone(),
+                rate_limiter: context.rate_limiter.clone(),
+                retry_policy: context.retry_policy,
+            };
+            workers.spawn(async move { (addr, source.run().await) });
+        }
+        loop {
+            select! {
+                joined = workers.join_next() => match joined {
+                    None => break,
+                    Some(Ok((addr, Ok(())))) => healthy.push(addr),
+                    Some(Ok((addr, Err(error)))) => {
+                        tracing::warn!(
+                            source = %addr.id,
+                            error = %error,
+                            "sender failed, continuing with the remaining senders"
+                        );
+                        last_error = Some(error);
+                    }
+                    Some(Err(error)) => last_error = Some(error.into()),
+                },
+                _ = ticker.tick() => reporter.on_progress(received.load(Ordering::Relaxed)),
+            }
+        }
+    }
+
+    let remaining = lock_jobs(&jobs).len();
+    if remaining > 0 {
+        let error = last_error
+            .unwrap_or_else(|| anyhow::anyhow!("no sender available"))
+            .context(format!(
+                "{remaining} blobs could not be fetched from any sender"
+            ));
+        reporter.emit_failed(receive_failed_message(&error));
+        return Err(error);
+    }
+    reporter.emit_completed_progress();
+    Ok(())
+}
+
+fn lock_jobs(
+    jobs: &std::sync::Mutex<VecDeque<GetRequest>>,
+) -> std::sync::MutexGuard<'_, VecDeque<GetRequest>> {
+    jobs.lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+}
+
+/// 多发送端下载中负责一个发送端的 worker。
+struct SourceWorker {
+    endpoint: Endpoint,
+    db: Store,
+    addr: iroh::EndpointAddr,
+    password: Option<String>,
+    jobs: StdArc<std::sync::Mutex<VecDeque<GetRequest>>>,
+    received: StdArc<AtomicU64>,
+    rate_limiter: Option<StdArc<RateLimiter>>,
+    retry_policy: ReceiveRetryPolicy,
+}
+
+impl SourceWorker {
+    /// 领取任务直到队列为空；出错时把当前任务放回队列并返回错误。
+    async fn run(self) -> anyhow::Result<()> {
+        if let Some(password) = &self.password {
+            authenticate(&self.endpoint, &self.addr, password).await?;
+        }
+        let connection = connect_within(
+            &self.endpoint,
+            &self.addr,
+            iroh_blobs::protocol::ALPN,
+            self.retry_policy.connect_timeout,
+        )
+        .await?;
+        loop {
+            let Some(request) = lock_jobs(&self.jobs).pop_front() else {
+                return Ok(());
+            };
+            if let Err(error) = self.fetch(&connection, request.clone()).await {
+                lock_jobs(&self.jobs).push_back(request);
+                return Err(error);
+            }
+        }
+    }
+
+    async fn fetch(
+        &self,
+        connection: &iroh::endpoint::Connection,
+        request: GetRequest,
+    ) -> anyhow::Result<()> {
+        let mut stream = self
+            .db
+            .remote()
+            .execute_get(connection.clone(), request)
+            .stream();
+        let mut last_offset = 0;
+        while let Some(item) = next_progress(&mut stream, self.retry_policy.stall_timeout).await? {
+            match item {
+                GetProgressItem::Progress(offset) => {
+                    let delta = offset.saturating_sub(last_offset);
+                    last_offset = offset;
+                    if let Some(rate_limiter) = &self.rate_limiter {
+                        rate_limiter.acquire(delta).await;
+                    }
+                    self.received.fetch_add(delta, Ordering::Relaxed);
+                }
+                GetProgressItem::Done(_) => return Ok(()),
+                GetProgressItem::Error(cause) => return Err(show_get_error(cause).into()),
+            }
+        }
+        Err(CatalogError::new(
+            ErrorKind::ProviderGone,
+            format!(
+                "download stream from {} ended before completion",
+                self.addr.id
+            ),
+        )
+        .into())
+    }
+}
+
+/// 本地已有的数据字节数，不含 hash seq 本身（每个子 blob 占 32 字节）。
+fn resumed_payload_bytes(local_bytes: u64, sizes: &[u64], payload_size: u64) -> u64 {
+    let hash_seq_size = sizes.len() as u64 * 32;
+    local_bytes.saturating_sub(hash_seq_size).min(payload_size)
+}
+
+/// 按 `--first` 模式先下载并导出匹配的条目，返回已导出的条目名。
+///
+/// 先请求 hash seq 与元数据 blob 以获得文件名，再只请求匹配条目对应的子 blob；
+/// 其余数据随后由 [`download_missing_data`] 照常补齐。
+async fn download_and_export_first(
+    context: &ReceiveContext,
+    output_dirs: &[PathBuf],
+    app_handle: &AppHandle,
+) -> anyhow::Result<HashSet<String>> {
+    if context.first.is_empty() {
+        return Ok(HashSet::new());
+    }
+    let hash_and_format = context.hash_and_format();
+    if context
+        .db
+        .remote()
+        .local(hash_and_format)
+        .await?
+        .is_complete()
+    {
+        return Ok(HashSet::new());
+    }
+
+    let sizes = download_collection_listing(context, app_handle).await?;
+    let (collection, encoding) = context.load_collection().await?;
+    let (names, _) = download_and_export_selected(
+        context,
+        output_dirs,
+        app_handle,
+        (&collection, encoding),
+        &sizes,
+        |name| {
+            context
+                .first
+                .iter()
+                .any(|pattern| glob_match(pattern, name))
+        },
+    )
+    .await?;
+    info!(entries = names.len(), "exported priority entries");
+    Ok(names)
+}
+
+/// 只下载 `select` 选中的条目（以及元数据条目）并导出它们，需要先下载集合列表；
+/// 返回导出的条目名称与下载的字节数。
+async fn download_and_export_selected(
+    context: &ReceiveContext,
+    output_dirs: &[PathBuf],
+    app_handle: &AppHandle,
+    (collection, encoding): (&Collection, CollectionEncoding),
+    sizes: &[u64],
+    select: impl Fn(&str) -> bool,
+) -> anyhow::Result<(HashSet<String>, u64)> {
+    let hash = context.hash_and_format().hash;
+    // 标记条目已从 `collection` 中去掉（`--flatten` 时名称也已改变），
+    // 子 blob 的序号要按完整集合中的 hash 计算
+    let full = Collection::load(hash, &context.db).await?;
+    let Some(selection) = select_children(&full, collection, encoding, sizes, select) else {
+        return Ok((HashSet::new(), 0));
+    };
+    let plan = DownloadPlan {
+        total_files: selection.names.len() as u64,
+        payload_size: selection.payload_size,
+    };
+    let mut request = GetRequest::builder();
+    for child in &selection.children {
+        request = request.child(*child, ChunkRanges::all());
+    }
+    execute_download(context, request.build(hash), &plan, app_handle).await?;
+
+    let names = selection.names.into_iter().collect::<HashSet<_>>();
+    let selected = filter_collection(collection, |name| names.contains(name));
+    export(
+        &context.db,
+        selected,
+        output_dirs,
+        context.sandboxed_export,
+        encoding,
+        context.export_mode,
+    )
+    .await
+    .map_err(export_failed)?;
+    Ok((names, plan.payload_size))
+}
+
+/// 部分下载要请求的子 blob。
+#[derive(Debug, PartialEq, Eq)]
+struct ChildSelection {
+    /// 选中的条目名称
+    names: Vec<String>,
+    /// 要请求的子 blob 序号（hash seq 中的位置，集合元数据 blob 占 0），升序
+    children: Vec<u64>,
+    /// 这些子 blob 的总字节数
+    payload_size: u64,
+}
+
+/// 在完整集合 `full` 中找到 `collection` 里被 `select` 选中的条目对应的子 blob；
+/// 导出时需要元数据条目才能恢复修改时间与权限，因此一并请求。没有选中任何条目时返回 `None`。
+fn select_children(
+    full: &Collection,
+    collection: &Collection,
+    encoding: CollectionEncoding,
+    sizes: &[u64],
+    select: impl Fn(&str) -> bool,
+) -> Option<ChildSelection> {
+    let children = full
+        .iter()
+        .enumerate()
+        .map(|(index, (_, hash))| (*hash, index as u64 + 1))
+        .collect::<HashMap<_, _>>();
+    let (mut wanted, names): (Vec<_>, Vec<_>) = collection
+        .iter()
+        .filter(|(name, _)| select(name))
+        .filter_map(|(name, hash)| children.get(hash).map(|child| (*child, name.clone())))
+        .unzip();
+    if names.is_empty() {
+        return None;
+    }
+    if let Some(metadata) = encoding.metadata
+        && let Some(child) = children.get(&metadata)
+    {
+        wanted.push(*child);
+    }
+    wanted.sort_unstable();
+    wanted.dedup();
+    let payload_size = wanted
+        .iter()
+        .filter_map(|child| {
+            usize::try_from(*child)
+                .ok()
+                .and_then(|child| sizes.get(child))
+        })
+        .sum();
+    Some(ChildSelection {
+        names,
+        children: wanted,
+        payload_size,
+    })
+}
+
+/// 只下载 hash seq 与集合元数据 blob，使得 [`ReceiveContext::load_collection`] 可用；
+/// 返回各子 blob 的大小。
+async fn download_collection_listing(
+    context: &ReceiveContext,
+    app_handle: &AppHandle,
+) -> anyhow::Result<StdArc<[u64]>> {
+    let hash = context.ticket.hash();
+    let (_hash_seq, sizes) = get_sizes_with_retries(
+        &context.endpoint,
+        &context.addr,
+        &hash,
+        context.retry_policy,
+    )
+    .await?;
+    let metadata = GetRequest::builder()
+        .root(ChunkRanges::all())
+        .child(0, ChunkRanges::all())
+        .build(hash);
+    let metadata_plan = DownloadPlan {
+        total_files: 0,
+        payload_size: sizes.first().copied().unwrap_or_default(),
+    };
+    execute_download(context, metadata, &metadata_plan, app_handle).await?;
+    Ok(sizes)
+}
+
+/// `--route`：在下载数据之前先取得集合清单并计算路由计划，目标冲突时直接失败；
+/// 同时返回集合中各个 blob 的大小，供 `--route-dry-run` 报告将要下载的数据量。
+async fn plan_routes_before_download(
     context: &ReceiveContext,
-    artifacts: ReceiveArtifacts,
-) -> anyhow::Result<ReceiveResult> {
-    let shutdown_result = context.db.shutdown().await.map_err(anyhow::Error::from);
-    let cleanup_result = remove_temp_receive_dir(&context.iroh_data_dir).await;
-    finalize_cleanup(shutdown_result, cleanup_result)?;
+    output_dir: &Path,
+    app_handle: &AppHandle,
+) -> anyhow::Result<(Vec<RoutedFile>, StdArc<[u64]>)> {
+    let sizes = download_collection_listing(context, app_handle).await?;
+    let (collection, _encoding) = context.load_collection().await?;
+    let routed = plan_routes(
+        &collect_file_names(&collection),
+        output_dir,
+        &context.routes,
+    )?;
+    Ok((routed, sizes))
+}
 
-    Ok(ReceiveResult {
-        message: format!(
-            "Downloaded {} files, {} bytes",
-            artifacts.total_files, artifacts.payload_size
-        ),
-        file_path: artifacts.root_item_path,
-    })
+/// `--stdout` 只支持单个条目：在下载数据之前先取得集合清单并检查条目数。
+async fn ensure_single_entry(
+    context: &ReceiveContext,
+    app_handle: &AppHandle,
+) -> anyhow::Result<()> {
+    let local = context.db.remote().local(context.hash_and_format()).await?;
+    if !local.is_complete() {
+        download_collection_listing(context, app_handle).await?;
+    }
+    let (collection, _encoding) = context.load_collection().await?;
+    let entries = collection.iter().count();
+    anyhow::ensure!(
+        entries == 1,
+        "--stdout needs a share with exactly one file, this one has {entries}"
+    );
+    Ok(())
 }
 
-async fn remove_temp_receive_dir(path: &Path) -> anyhow::Result<()> {
-    match tokio::fs::remove_dir_all(path).await {
-        Ok(()) => Ok(()),
-        Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(()),
-        Err(error) => Err(error.into()),
+/// 重新读取存储中的每个条目并计算 blake3，与集合中记录的 hash 比对。
+async fn verify_entries(db: &Store, collection: &Collection) -> anyhow::Result<Vec<VerifiedEntry>> {
+    let mut verified = Vec::new();
+    for (name, hash) in collection.iter() {
+        let (actual, size) = rehash_blob_with_size(db, *hash).await?;
+        verified.push(VerifiedEntry {
+            name: name.clone(),
+            hash: *hash,
+            size,
+            verified: actual == *hash,
+        });
     }
+    Ok(verified)
 }
 
-async fn download_missing_data(
-    context: &ReceiveContext,
-    app_handle: AppHandle,
-) -> anyhow::Result<DownloadOutcome> {
-    let emitter =
-        TransferEventEmitter::new(app_handle.clone(), crate::core::events::Role::Receiver);
-    let hash_and_format = context.hash_and_format();
-    let local = context.db.remote().local(hash_and_format).await?;
-    if local.is_complete() {
-        let total_files = completed_local_total_files_from_children(local.children())?;
-        emitter.emit_started();
-        return Ok(DownloadOutcome {
-            total_files,
-            payload_size: 0,
+/// 重新读取主输出目录中导出的文件并计算 blake3。
+///
+/// 未压缩的条目与集合中的 hash 比对；压缩的条目与存储中解压后的内容比对。
+/// 报告中的 hash 总是导出文件本身的 hash。归档集合没有逐文件的 hash，返回空列表。
+async fn verify_exported(
+    db: &Store,
+    collection: &Collection,
+    output_dir: &Path,
+    encoding: CollectionEncoding,
+) -> anyhow::Result<Vec<VerifiedEntry>> {
+    if encoding.archive {
+        tracing::warn!("archived shares have no per-file hashes, skipping export verification");
+        return Ok(Vec::new());
+    }
+    let mut verified = Vec::new();
+    for (name, hash) in collection.iter() {
+        let (actual, size) = hash_file(&get_export_path(output_dir, name)?).await?;
+        let expected = match encoding.compression {
+            Some(compression) => hash_reader(compression.decompress(db, *hash)).await?.0,
+            None => *hash,
+        };
+        verified.push(VerifiedEntry {
+            name: name.clone(),
+            hash: actual,
+            size,
+            verified: actual == expected,
         });
     }
+    Ok(verified)
+}
 
-    emitter.emit_started();
-    let (_hash_seq, sizes) = get_sizes_with_retries(
-        &context.endpoint,
-        &context.addr,
-        &context.ticket.hash(),
-        context.retry_policy,
-    )
-    .await?;
-    let plan = DownloadPlan::from_sizes(&sizes);
-    execute_download(context, local.missing(), &plan, &app_handle).await?;
+/// 校验和文件的名称，格式与 `b3sum` 的输出相同，可用 `b3sum --check` 检查。
+const CHECKSUMS_FILE: &str = "BLAKE3SUMS";
 
-    Ok(DownloadOutcome {
-        total_files: plan.total_files,
-        payload_size: plan.payload_size,
-    })
+/// 在 `output_dir` 中写入 `BLAKE3SUMS`；文件已存在时报错，不覆盖其他下载的校验和。
+fn write_checksums(output_dir: &Path, entries: &[VerifiedEntry]) -> anyhow::Result<()> {
+    let path = output_dir.join(CHECKSUMS_FILE);
+    let mut file = match std::fs::File::create_new(&path) {
+        Ok(file) => file,
+        Err(error) if error.kind() == std::io::ErrorKind::AlreadyExists => {
+            return Err(target_exists(&path));
+        }
+        Err(error) => return Err(error.into()),
+    };
+    std::io::Write::write_all(&mut file, checksums_content(entries).as_bytes())?;
+    Ok(())
+}
+
+fn checksums_content(entries: &[VerifiedEntry]) -> String {
+    entries
+        .iter()
+        .map(|entry| format!("{}  {}\n", entry.hash.to_hex(), entry.name))
+        .collect()
+}
+
+/// 把唯一条目的内容写到标准输出；压缩的条目会先解压，归档条目输出 tar 流本身。
+async fn write_to_stdout(
+    db: &Store,
+    collection: &Collection,
+    encoding: CollectionEncoding,
+) -> anyhow::Result<()> {
+    let Some((name, hash)) = collection.iter().next() else {
+        anyhow::bail!("collection is empty")
+    };
+    let mut reader: Box<dyn AsyncRead + Unpin + Send> = match encoding.compression {
+        Some(compression) => Box::new(compression.decompress(db, *hash)),
+        None => Box::new(db.reader(*hash)),
+    };
+    let mut stdout = tokio::io::stdout();
+    tokio::io::copy(&mut reader, &mut stdout)
+        .await
+        .with_context(|| format!("failed to write {name} to stdout"))?;
+    stdout.flush().await?;
+    Ok(())
+}
+
+fn filter_collection(collection: &Collection, keep: impl Fn(&String) -> bool) -> Collection {
+    collection
+        .iter()
+        .filter(|(name, _)| keep(name))
+        .map(|(name, hash)| (name.clone(), *hash))
+        .collect()
 }
 
 const fn completed_local_total_files(children: u64) -> u64 {
@@ -319,14 +1732,43 @@ async fn execute_download(
     missing: iroh_blobs::protocol::GetRequest,
     plan: &DownloadPlan,
     app_handle: &AppHandle,
-) -> anyhow::Result<()> {
-    let connection = context
-        .endpoint
-        .connect(context.addr.clone(), iroh_blobs::protocol::ALPN)
-        .await?;
-    let get = context.db.remote().execute_get(connection, missing);
-    let mut stream = get.stream();
-    process_get_stream(&mut stream, plan.payload_size, app_handle).await
+) -> anyhow::Result<Option<ConnectionInfo>> {
+    let connection = connect_within(
+        &context.endpoint,
+        &context.addr,
+        iroh_blobs::protocol::ALPN,
+        context.retry_policy.connect_timeout,
+    )
+    .await?;
+    let connected = connection_info(&context.endpoint, context.addr.id, &connection);
+    if let Some(connected) = &connected {
+        info!(via = connected.via.as_str(), remote = %connected.remote, rtt = ?connected.rtt, "connected to sender");
+        TransferEventEmitter::new(app_handle.clone(), crate::core::events::Role::Receiver)
+            .emit_connected(connected);
+    }
+    if let Some(trace) = &context.trace {
+        trace.record(TraceKind::Requested, 0, 0, 0);
+    }
+    let get = context.db.remote().execute_get(connection.clone(), missing);
+    let stream = get.stream();
+    #[cfg(feature = "chaos")]
+    let stream = crate::core::chaos::inject(stream, context.chaos);
+    let mut stream = stream;
+    let result = process_get_stream(
+        &mut stream,
+        plan.payload_size,
+        app_handle,
+        context.trace.as_ref(),
+        context.rate_limiter.as_deref(),
+        context.retry_policy.stall_timeout,
+    )
+    .await;
+    if let Some(trace) = &context.trace {
+        trace.flush();
+    }
+    result?;
+    // 传输期间可能已从 relay 升级为直连，以结束时的路径为准
+    Ok(connection_info(&context.endpoint, context.addr.id, &connection).or(connected))
 }
 
 fn collect_file_names(collection: &Collection) -> Vec<String> {
@@ -364,8 +1806,49 @@ fn resolve_output_dir(output_dir: Option<PathBuf>) -> anyhow::Result<PathBuf> {
     Ok(resolved)
 }
 
-fn size_fetch_backoff(attempt: u32, retry_policy: ReceiveRetryPolicy) -> std::time::Duration {
-    std::time::Duration::from_millis(retry_policy.size_fetch_backoff_ms * u64::from(attempt))
+/// 解析主输出目录并追加镜像目录，返回的列表中第一个元素始终为主输出目录。
+///
+/// 重复的目录会被忽略，并为每个被忽略的目录返回一条警告。
+fn resolve_output_dirs(
+    output_dir: Option<PathBuf>,
+    mirror_dirs: Vec<PathBuf>,
+) -> anyhow::Result<(Vec<PathBuf>, Vec<TransferWarning>)> {
+    let primary = resolve_output_dir(output_dir)?;
+    let mut output_dirs = vec![primary];
+    let mut warnings = Vec::new();
+    for mirror in mirror_dirs {
+        if output_dirs.contains(&mirror) {
+            warnings.push(TransferWarning::new(
+                WarningCode::DuplicateOutputDir,
+                mirror.display().to_string(),
+            ));
+        } else {
+            output_dirs.push(mirror);
+        }
+    }
+    Ok((output_dirs, warnings))
+}
+
+/// 把每个输出目录替换为其下的 `into` 子目录；子目录已存在时报错，避免与其他下载混在一起。
+///
+/// 子目录在导出时创建，下载失败不会留下空目录。
+fn into_subdirs(output_dirs: &[PathBuf], into: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    anyhow::ensure!(
+        into.components()
+            .all(|component| matches!(component, std::path::Component::Normal(_))),
+        "--into must be a relative directory name, got {}",
+        into.display()
+    );
+    output_dirs
+        .iter()
+        .map(|output_dir| {
+            let target = output_dir.join(into);
+            if target.exists() {
+                return Err(target_exists(&target));
+            }
+            Ok(target)
+        })
+        .collect()
 }
 
 fn finalize_cleanup(
@@ -433,7 +1916,7 @@ fn log_get_error_misc(e: &GetError) {
 }
 
 /// 根据集合内的名称生成导出路径，同时验证每个路径组件的合法性。
-fn get_export_path(root: &Path, name: &str) -> anyhow::Result<PathBuf> {
+pub(crate) fn get_export_path(root: &Path, name: &str) -> anyhow::Result<PathBuf> {
     if root.exists() {
         anyhow::ensure!(
             root.is_dir(),
@@ -471,13 +1954,18 @@ async fn prepare_env(
     ticket: &BlobTicket,
     options: &ReceiveOptions,
 ) -> anyhow::Result<(Endpoint, PathBuf, Store)> {
-    let mut builder = base_endpoint_builder(options, vec![])?;
+    let endpoint = bind_endpoint(ticket, options).await?;
 
-    if ticket.addr().relay_urls().next().is_none() && ticket.addr().ip_addrs().next().is_none() {
-        builder = builder.discovery(DnsDiscovery::n0_dns());
+    if options.keep_store {
+        let iroh_data_dir = kept_store_dir();
+        let db = load_resumable_store(&iroh_data_dir).await?;
+        return Ok((endpoint, iroh_data_dir, db.into()));
+    }
+    if options.resume || options.retry_failed {
+        let iroh_data_dir = resume_store_dir(ticket.hash());
+        let db = load_resumable_store(&iroh_data_dir).await?;
+        return Ok((endpoint, iroh_data_dir, db.into()));
     }
-    let endpoint = builder.bind().await?;
-
     let iroh_data_dir = unique_temp_dir(&format!(
         "{RECEIVE_TEMP_DIR_PREFIX}{}-",
         ticket.hash().to_hex()
@@ -486,6 +1974,66 @@ async fn prepare_env(
     Ok((endpoint, iroh_data_dir, db.into()))
 }
 
+/// 按接收选项创建 endpoint；ticket 不含地址时按 `dns_discovery` 启用 DNS 解析。
+async fn bind_endpoint(ticket: &BlobTicket, options: &ReceiveOptions) -> anyhow::Result<Endpoint> {
+    let mut builder = base_endpoint_builder(options, vec![])?;
+
+    let addressless =
+        ticket.addr().relay_urls().next().is_none() && ticket.addr().ip_addrs().next().is_none();
+    // `--resolve` 给出了发送端地址时只连接这些地址，不再查询 DNS
+    let overridden = options
+        .static_addrs
+        .iter()
+        .any(|entry| entry.id == ticket.addr().id);
+    if !overridden && options.dns_discovery.enabled(addressless) {
+        builder = builder.discovery(
+            options
+                .dns_origin
+                .as_ref()
+                .map_or_else(DnsDiscovery::n0_dns, |origin| {
+                    DnsDiscovery::builder(origin.clone())
+                }),
+        );
+    }
+    Ok(builder.bind().await?)
+}
+
+/// 续传使用的临时存储目录，只由集合 hash 决定，重复接收同一个 ticket 时复用。
+fn resume_store_dir(hash: iroh_blobs::Hash) -> PathBuf {
+    std::env::temp_dir().join(format!("{RECEIVE_TEMP_DIR_PREFIX}{}", hash.to_hex()))
+}
+
+/// `keep_store` 使用的共享存储目录：用户缓存目录下的 `sendmer/receive-store`，
+/// 没有缓存目录时放在系统临时目录中。
+fn kept_store_dir() -> PathBuf {
+    dirs::cache_dir().map_or_else(
+        || std::env::temp_dir().join(format!("{RECEIVE_TEMP_DIR_PREFIX}store")),
+        |dir| dir.join("sendmer").join("receive-store"),
+    )
+}
+
+fn kept_store_tag(hash: iroh_blobs::Hash) -> String {
+    format!("sendmer-recv-{}", hash.to_hex())
+}
+
+/// 打开续传存储；已有数据无法加载（例如上次写入时被强制终止而损坏）时丢弃并重新开始。
+///
+/// 存储只保留经过 BLAKE3 校验的分块，能加载的数据可以直接复用。
+async fn load_resumable_store(path: &Path) -> anyhow::Result<FsStore> {
+    match load_fs_store(path).await {
+        Ok(db) => Ok(db),
+        Err(error) => {
+            tracing::warn!(
+                error = %error,
+                path = %path.display(),
+                "discarding unreadable partial download"
+            );
+            remove_temp_receive_dir(path).await?;
+            load_fs_store(path).await
+        }
+    }
+}
+
 // Helper: get sizes with retries and reconnects
 async fn get_sizes_with_retries(
     endpoint: &Endpoint,
@@ -494,9 +2042,14 @@ async fn get_sizes_with_retries(
     retry_policy: ReceiveRetryPolicy,
 ) -> anyhow::Result<(iroh_blobs::hashseq::HashSeq, StdArc<[u64]>)> {
     let mut last_err: Option<GetError> = None;
-    let mut connection = endpoint
-        .connect(addr.clone(), iroh_blobs::protocol::ALPN)
-        .await?;
+    let mut connection = connect_within(
+        endpoint,
+        addr,
+        iroh_blobs::protocol::ALPN,
+        retry_policy.connect_timeout,
+    )
+    .await?;
+    let started = std::time::Instant::now();
     for attempt in 1..=retry_policy.size_fetch_retry_limit {
         match get_hash_seq_and_sizes(&connection, hash, retry_policy.size_fetch_chunk_size, None)
             .await
@@ -505,10 +2058,11 @@ async fn get_sizes_with_retries(
             Err(e) => {
                 tracing::error!("Attempt {attempt} to get sizes failed: {e:?}");
                 last_err = Some(e);
-                if attempt < retry_policy.size_fetch_retry_limit {
-                    tokio::time::sleep(size_fetch_backoff(attempt, retry_policy)).await;
-                    reconnect(endpoint, addr, &mut connection).await;
-                }
+                let Some(delay) = retry_policy.next_delay(attempt, started.elapsed()) else {
+                    break;
+                };
+                tokio::time::sleep(delay).await;
+                reconnect(endpoint, addr, retry_policy, &mut connection).await;
             }
         }
     }
@@ -525,11 +2079,16 @@ async fn get_sizes_with_retries(
 async fn reconnect(
     endpoint: &Endpoint,
     addr: &iroh::EndpointAddr,
+    retry_policy: ReceiveRetryPolicy,
     connection: &mut iroh::endpoint::Connection,
 ) {
-    match endpoint
-        .connect(addr.clone(), iroh_blobs::protocol::ALPN)
-        .await
+    match connect_within(
+        endpoint,
+        addr,
+        iroh_blobs::protocol::ALPN,
+        retry_policy.connect_timeout,
+    )
+    .await
     {
         Ok(new_connection) => *connection = new_connection,
         Err(conn_err) => tracing::error!("reconnect failed: {conn_err}"),
@@ -541,6 +2100,9 @@ async fn process_get_stream<S>(
     stream: &mut S,
     payload_size: u64,
     app_handle: &AppHandle,
+    trace: Option<&TraceLog>,
+    rate_limiter: Option<&RateLimiter>,
+    stall_timeout: Option<std::time::Duration>,
 ) -> anyhow::Result<()>
 where
     S: n0_future::Stream<Item = GetProgressItem> + Unpin + Send,
@@ -548,10 +2110,29 @@ where
     let mut reporter = ReceiverProgressReporter::new(app_handle.clone(), payload_size);
     reporter.emit_initial_progress();
     let mut seen_done = false;
-    while let Some(item) = stream.next().await {
+    let mut last_offset = 0;
+    loop {
+        let item = match next_progress(stream, stall_timeout).await {
+            Ok(Some(item)) => item,
+            Ok(None) => break,
+            Err(error) => {
+                reporter.emit_failed(receive_failed_message(&error));
+                return Err(error);
+            }
+        };
         trace!("got item {item:?}");
         match item {
             GetProgressItem::Progress(offset) => {
+                if let Some(trace) = trace {
+                    trace.record(TraceKind::Received, 0, 0, offset);
+                }
+                // 暂停读取进度流，使下载按限速推进
+                if let Some(rate_limiter) = rate_limiter {
+                    rate_limiter
+                        .acquire(offset.saturating_sub(last_offset))
+                        .await;
+                }
+                last_offset = offset;
                 reporter.on_progress(offset);
             }
             GetProgressItem::Done(value) => {
@@ -570,13 +2151,31 @@ where
     }
     if !seen_done {
         reporter.emit_failed(receive_stream_ended_message());
+        return Err(
+            CatalogError::new(ErrorKind::ProviderGone, receive_stream_ended_message()).into(),
+        );
     }
-    anyhow::ensure!(seen_done, "download stream ended before completion");
     Ok(())
 }
 
+/// 读取下一个下载进度事件；超过 `stall_timeout` 仍没有新事件时报错，而不是无限等待。
+async fn next_progress<S>(
+    stream: &mut S,
+    stall_timeout: Option<std::time::Duration>,
+) -> anyhow::Result<Option<GetProgressItem>>
+where
+    S: n0_future::Stream<Item = GetProgressItem> + Unpin,
+{
+    let Some(stall_timeout) = stall_timeout else {
+        return Ok(stream.next().await);
+    };
+    tokio::time::timeout(stall_timeout, stream.next())
+        .await
+        .map_err(|_| anyhow::anyhow!("transfer stalled: no data received for {stall_timeout:?}"))
+}
+
 /// 验证单个路径组件是否合法（不应包含分隔符 `/`）。
-fn validate_path_component(component: &str) -> anyhow::Result<()> {
+pub(crate) fn validate_path_component(component: &str) -> anyhow::Result<()> {
     // Check for empty components
     anyhow::ensure!(!component.is_empty(), "path component cannot be empty");
 
@@ -596,6 +2195,13 @@ fn validate_path_component(component: &str) -> anyhow::Result<()> {
         "absolute path components not allowed"
     );
 
+    // 在 Windows 上无法创建、或会被系统改写的名称
+    if cfg!(windows)
+        && let Some(reason) = windows_name_problem(component)
+    {
+        anyhow::bail!("{component:?} is not a valid file name on Windows: {reason}");
+    }
+
     // Optional: Check for hidden files (starting with '.')
     // Uncomment if you want to restrict hidden files
     // anyhow::ensure!(
@@ -606,16 +2212,51 @@ fn validate_path_component(component: &str) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Windows 保留的设备名，带扩展名（如 `CON.txt`）时同样不可用。
+const WINDOWS_RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// 返回 `component` 不能在 Windows 上作为文件名的原因；可用时返回 `None`。
+fn windows_name_problem(component: &str) -> Option<&'static str> {
+    if component
+        .chars()
+        .any(|c| matches!(c, '<' | '>' | ':' | '"' | '|' | '?' | '*') || c.is_control())
+    {
+        return Some("it contains one of < > : \" | ? * or a control character");
+    }
+    if component.ends_with('.') || component.ends_with(' ') {
+        return Some("it ends with a dot or a space");
+    }
+    let stem = component.split('.').next().unwrap_or(component).trim_end();
+    if WINDOWS_RESERVED_NAMES
+        .iter()
+        .any(|reserved| reserved.eq_ignore_ascii_case(stem))
+    {
+        return Some("it is a reserved device name");
+    }
+    None
+}
+
 #[cfg(test)]
 mod tests {
     use super::{
-        completed_local_total_files, completed_local_total_files_from_children,
-        emit_receive_failed, finalize_cleanup, finalize_failed_receive, get_export_path,
-        process_get_stream, receive_failed_message, receive_stream_ended_message,
-        resolve_output_dir, validate_path_component,
+        ChildSelection, CollectionEncoding, Compression, ExportModeOption, cancel_requested,
+        check_disk_space, checksums_content, completed_local_total_files,
+        completed_local_total_files_from_children, emit_receive_failed, export, failed_entries,
+        filter_collection, finalize_cleanup, finalize_failed_receive, flatten_collection,
+        get_export_path, into_subdirs, is_corrupted_data, kept_store_dir, kept_store_tag,
+        partial_path, process_get_stream, receive_failed_message, receive_stream_ended_message,
+        resolve_output_dir, resolve_output_dirs, resumed_payload_bytes, select_children,
+        summarize_collection, validate_path_component, windows_name_problem,
     };
     use crate::core::events::{EventEmitter, Role, TransferEvent};
+    use crate::core::metadata::METADATA_ENTRY;
+    use crate::core::results::VerifiedEntry;
     use iroh_blobs::api::remote::GetProgressItem;
+    use iroh_blobs::get::{GetError, fsm::DecodeError};
+    use iroh_blobs::store::mem::MemStore;
     use n0_future::stream;
     use std::path::Path;
     use std::sync::{Arc, Mutex as StdMutex};
@@ -660,6 +2301,19 @@ mod tests {
         assert!(err.to_string().contains("must not contain path separators"));
     }
 
+    #[test]
+    fn windows_name_problem_flags_reserved_names_and_characters() {
+        assert!(windows_name_problem("report.txt").is_none());
+        assert!(windows_name_problem("console.log").is_none());
+        assert!(windows_name_problem("CON").is_some());
+        assert!(windows_name_problem("nul.txt").is_some());
+        assert!(windows_name_problem("Lpt1 .tar.gz").is_some());
+        assert!(windows_name_problem("notes.").is_some());
+        assert!(windows_name_problem("draft ").is_some());
+        assert!(windows_name_problem("12:30.txt").is_some());
+        assert!(windows_name_problem("what?.md").is_some());
+    }
+
     #[test]
     fn get_export_path_joins_nested_relative_path() {
         let root = Path::new("downloads");
@@ -707,6 +2361,110 @@ mod tests {
         assert!(err.to_string().contains("is not a directory"));
     }
 
+    #[test]
+    fn filter_collection_keeps_matching_entries_in_order() {
+        let collection = [
+            ("docs/a.md", iroh_blobs::Hash::new(b"a")),
+            ("src/main.rs", iroh_blobs::Hash::new(b"b")),
+            ("docs/b.md", iroh_blobs::Hash::new(b"c")),
+        ]
+        .into_iter()
+        .collect::<iroh_blobs::format::collection::Collection>();
+
+        let docs = filter_collection(&collection, |name| name.starts_with("docs/"));
+        let names = docs
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["docs/a.md", "docs/b.md"]);
+    }
+
+    #[test]
+    fn flatten_collection_keeps_file_names_and_rejects_collisions() {
+        let collection = [
+            ("docs/a.md", iroh_blobs::Hash::new(b"a")),
+            ("src/deep/main.rs", iroh_blobs::Hash::new(b"b")),
+        ]
+        .into_iter()
+        .collect::<iroh_blobs::format::collection::Collection>();
+        let flat = flatten_collection(&collection).expect("distinct names");
+        let names = flat
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["a.md", "main.rs"]);
+
+        let colliding = [
+            ("a/readme.md", iroh_blobs::Hash::new(b"a")),
+            ("b/readme.md", iroh_blobs::Hash::new(b"b")),
+        ]
+        .into_iter()
+        .collect::<iroh_blobs::format::collection::Collection>();
+        let err = flatten_collection(&colliding).expect_err("same file name should fail");
+        assert!(err.to_string().contains("a/readme.md"));
+    }
+
+    #[test]
+    fn checksums_content_matches_b3sum_format() {
+        let entries = [VerifiedEntry {
+            name: "docs/a.md".to_string(),
+            hash: iroh_blobs::Hash::new(b"a"),
+            size: 1,
+            verified: true,
+        }];
+        assert_eq!(
+            checksums_content(&entries),
+            format!("{}  docs/a.md\n", iroh_blobs::Hash::new(b"a").to_hex())
+        );
+    }
+
+    #[test]
+    fn summarize_collection_pairs_sizes_and_hides_metadata() {
+        let hash = iroh_blobs::Hash::new(b"collection");
+        let full = [
+            ("a.txt", iroh_blobs::Hash::new(b"a")),
+            (METADATA_ENTRY, iroh_blobs::Hash::new(b"meta")),
+            ("b.txt", iroh_blobs::Hash::new(b"b")),
+        ]
+        .into_iter()
+        .collect::<iroh_blobs::format::collection::Collection>();
+
+        let summary =
+            summarize_collection(hash, &full, &[64, 10, 5, 20]).expect("collection summary");
+        let entries = summary
+            .entries
+            .iter()
+            .map(|entry| (entry.name.as_str(), entry.size))
+            .collect::<Vec<_>>();
+        assert_eq!(entries, vec![("a.txt", 10), ("b.txt", 20)]);
+        assert_eq!(summary.size, 30);
+    }
+
+    #[test]
+    fn summarize_collection_skips_compression_and_metadata_markers() {
+        let hash = iroh_blobs::Hash::new(b"collection");
+        let marker = Compression::Zstd { level: 3 }.marker_name();
+        let full = [
+            (marker.as_str(), iroh_blobs::Hash::new(b"")),
+            ("docs/a.txt", iroh_blobs::Hash::new(b"a")),
+            (METADATA_ENTRY, iroh_blobs::Hash::new(b"meta")),
+            ("docs/b.txt", iroh_blobs::Hash::new(b"b")),
+        ]
+        .into_iter()
+        .collect::<iroh_blobs::format::collection::Collection>();
+
+        let summary =
+            summarize_collection(hash, &full, &[128, 0, 7, 5, 11]).expect("collection summary");
+        let entries = summary
+            .entries
+            .iter()
+            .map(|entry| (entry.name.as_str(), entry.size))
+            .collect::<Vec<_>>();
+        assert_eq!(entries, vec![("docs/a.txt", 7), ("docs/b.txt", 11)]);
+        assert_eq!(summary.size, 18);
+        assert_eq!(summary.hash, hash);
+    }
+
     #[test]
     fn completed_local_total_files_handles_empty_collection() {
         assert_eq!(completed_local_total_files(0), 0);
@@ -721,12 +2479,44 @@ mod tests {
         assert!(err.to_string().contains("missing collection children"));
     }
 
+    #[test]
+    fn resumed_payload_bytes_excludes_hash_seq_and_caps_at_payload() {
+        let sizes = [10, 100, 200];
+        assert_eq!(resumed_payload_bytes(0, &sizes, 310), 0);
+        assert_eq!(resumed_payload_bytes(96 + 110, &sizes, 310), 110);
+        assert_eq!(resumed_payload_bytes(1000, &sizes, 310), 310);
+    }
+
     #[test]
     fn receive_failed_message_wraps_error_with_prefix() {
         let message = receive_failed_message(&anyhow::anyhow!("boom"));
         assert_eq!(message, "error: boom");
     }
 
+    #[test]
+    fn only_verification_failures_count_as_corrupted_data() {
+        let decode = |error: bao_tree::io::DecodeError| {
+            anyhow::Error::from(GetError::from(DecodeError::from(error)))
+        };
+        assert!(is_corrupted_data(&decode(
+            bao_tree::io::DecodeError::LeafHashMismatch(bao_tree::ChunkNum(3))
+        )));
+        assert!(is_corrupted_data(&decode(
+            bao_tree::io::DecodeError::ParentHashMismatch(
+                bao_tree::BaoTree::new(4096, bao_tree::BlockSize::ZERO).root()
+            )
+        )));
+        assert!(!is_corrupted_data(&decode(bao_tree::io::DecodeError::Io(
+            std::io::Error::other("connection reset")
+        ))));
+        assert!(!is_corrupted_data(&anyhow::anyhow!(
+            receive_stream_ended_message()
+        )));
+        assert!(!is_corrupted_data(
+            &anyhow::anyhow!("connection lost").context("download failed")
+        ));
+    }
+
     #[test]
     fn receive_stream_ended_message_is_stable() {
         assert_eq!(
@@ -743,7 +2533,7 @@ mod tests {
         let runtime = tokio::runtime::Runtime::new().expect("runtime");
         runtime.block_on(async {
             let mut s = stream::empty::<GetProgressItem>();
-            let err = process_get_stream(&mut s, 12, &app_handle)
+            let err = process_get_stream(&mut s, 12, &app_handle, None, None, None)
                 .await
                 .expect_err("stream ending early should fail");
             assert!(err.to_string().contains("ended before completion"));
@@ -761,7 +2551,7 @@ mod tests {
         ));
         assert!(events.iter().any(|event| matches!(
             event,
-            TransferEvent::Failed { role: Role::Receiver, message }
+            TransferEvent::Failed { role: Role::Receiver, message, .. }
                 if message == "download stream ended before completion"
         )));
     }
@@ -776,7 +2566,7 @@ mod tests {
         let events = emitter.events();
         assert_eq!(events.len(), 1);
         match &events[0] {
-            TransferEvent::Failed { role, message } => {
+            TransferEvent::Failed { role, message, .. } => {
                 assert_eq!(*role, Role::Receiver);
                 assert_eq!(message, "boom");
             }
@@ -798,6 +2588,32 @@ mod tests {
         assert_eq!(resolved, expected);
     }
 
+    #[test]
+    fn resolve_output_dirs_keeps_primary_first_and_skips_duplicates() {
+        let primary = Path::new("primary").to_path_buf();
+        let mirror = Path::new("mirror").to_path_buf();
+        let (resolved, warnings) = resolve_output_dirs(
+            Some(primary.clone()),
+            vec![mirror.clone(), primary.clone(), mirror.clone()],
+        )
+        .expect("output dirs should resolve");
+        assert_eq!(resolved, vec![primary, mirror]);
+        assert_eq!(warnings.len(), 2);
+    }
+
+    #[test]
+    fn into_subdirs_rejects_existing_and_escaping_directories() {
+        let temp_dir = tempfile::tempdir().expect("temp dir");
+        let output_dirs = vec![temp_dir.path().to_path_buf()];
+
+        let resolved = into_subdirs(&output_dirs, Path::new("photos")).expect("fresh dir");
+        assert_eq!(resolved, vec![temp_dir.path().join("photos")]);
+
+        std::fs::create_dir(temp_dir.path().join("photos")).expect("create dir");
+        assert!(into_subdirs(&output_dirs, Path::new("photos")).is_err());
+        assert!(into_subdirs(&output_dirs, Path::new("../photos")).is_err());
+    }
+
     #[test]
     fn finalize_failed_receive_preserves_primary_error_when_cleanup_fails() {
         let err = finalize_failed_receive(
@@ -825,9 +2641,292 @@ mod tests {
     #[tokio::test]
     async fn process_get_stream_errors_if_stream_ends_before_done() {
         let mut s = stream::empty::<GetProgressItem>();
-        let err = process_get_stream(&mut s, 0, &None)
+        let err = process_get_stream(&mut s, 0, &None, None, None, None)
             .await
             .expect_err("stream ending early should fail");
         assert!(err.to_string().contains("ended before completion"));
     }
+
+    #[tokio::test]
+    async fn export_renames_partial_files_into_place() {
+        let db = MemStore::new();
+        let tag = db
+            .add_bytes(b"hello sendmer".to_vec())
+            .temp_tag()
+            .await
+            .expect("add bytes");
+        let collection = std::iter::once(("dir/hello.txt", tag.hash()))
+            .collect::<iroh_blobs::format::collection::Collection>();
+        let temp_dir = tempfile::tempdir().expect("temp dir");
+        let target = temp_dir.path().join("dir/hello.txt");
+        std::fs::create_dir_all(target.parent().expect("parent")).expect("create dir");
+        std::fs::write(partial_path(&target), b"stale").expect("write stale part");
+
+        export(
+            &db,
+            collection,
+            &[temp_dir.path().to_path_buf()],
+            false,
+            CollectionEncoding::default(),
+            ExportModeOption::Copy,
+        )
+        .await
+        .expect("export should succeed");
+
+        assert_eq!(
+            std::fs::read(&target).expect("read target"),
+            b"hello sendmer"
+        );
+        assert!(!partial_path(&target).exists());
+    }
+
+    #[cfg(any(unix, windows))]
+    #[test]
+    fn check_disk_space_rejects_downloads_that_do_not_fit() {
+        let temp_dir = tempfile::tempdir().expect("temp dir");
+        let output_dirs = vec![temp_dir.path().join("out")];
+
+        check_disk_space(temp_dir.path(), 1, &output_dirs, 1, ExportModeOption::Copy)
+            .expect("small download should fit");
+        let err = check_disk_space(
+            temp_dir.path(),
+            u64::MAX / 2,
+            &output_dirs,
+            u64::MAX / 2,
+            ExportModeOption::Copy,
+        )
+        .expect_err("huge download should not fit");
+        assert!(err.to_string().contains("not enough disk space"));
+        assert!(!err.to_string().contains("--force"));
+        assert!(
+            crate::core::error::remediation_hint(&err).is_some_and(|hint| hint.contains("--force"))
+        );
+    }
+
+    #[tokio::test]
+    async fn export_skips_files_already_exported() {
+        let db = MemStore::new();
+        let tag = db
+            .add_bytes(b"hello sendmer".to_vec())
+            .temp_tag()
+            .await
+            .expect("add bytes");
+        let collection = [("hello.txt", tag.hash()), ("other.txt", tag.hash())]
+            .into_iter()
+            .collect::<iroh_blobs::format::collection::Collection>();
+        let temp_dir = tempfile::tempdir().expect("temp dir");
+        let output_dirs = [temp_dir.path().to_path_buf()];
+        // 上次导出在第二个文件之前中断
+        std::fs::write(temp_dir.path().join("hello.txt"), b"hello sendmer").expect("write");
+
+        export(
+            &db,
+            collection.clone(),
+            &output_dirs,
+            false,
+            CollectionEncoding::default(),
+            ExportModeOption::Copy,
+        )
+        .await
+        .expect("export should skip the finished file");
+        assert_eq!(
+            std::fs::read(temp_dir.path().join("other.txt")).expect("read export"),
+            b"hello sendmer"
+        );
+
+        std::fs::write(temp_dir.path().join("hello.txt"), b"something else").expect("write");
+        let err = export(
+            &db,
+            collection,
+            &output_dirs,
+            false,
+            CollectionEncoding::default(),
+            ExportModeOption::Copy,
+        )
+        .await
+        .expect_err("different content should not be overwritten");
+        assert!(err.to_string().contains("already exists"));
+    }
+
+    #[tokio::test]
+    async fn export_checks_every_output_dir_before_writing() {
+        let db = MemStore::new();
+        let tag = db
+            .add_bytes(b"hello sendmer".to_vec())
+            .temp_tag()
+            .await
+            .expect("add bytes");
+        let collection = [("a.txt", tag.hash()), ("b.txt", tag.hash())]
+            .into_iter()
+            .collect::<iroh_blobs::format::collection::Collection>();
+        let primary = tempfile::tempdir().expect("temp dir");
+        let mirror = tempfile::tempdir().expect("temp dir");
+        std::fs::write(mirror.path().join("b.txt"), b"unrelated").expect("write conflict");
+
+        let err = export(
+            &db,
+            collection,
+            &[primary.path().to_path_buf(), mirror.path().to_path_buf()],
+            false,
+            CollectionEncoding::default(),
+            ExportModeOption::Copy,
+        )
+        .await
+        .expect_err("conflict in the mirror should fail");
+        assert!(err.to_string().contains("already exists"));
+        assert!(!primary.path().join("a.txt").exists());
+        assert!(!mirror.path().join("a.txt").exists());
+    }
+
+    #[test]
+    fn select_children_maps_entries_to_hash_seq_indices_and_sizes() {
+        let hash = |name: &str| iroh_blobs::Hash::new(name.as_bytes());
+        // 完整集合中标记条目排在最前；hash seq 的第 0 个子 blob 是集合自身的元数据
+        let full = [
+            (METADATA_ENTRY, hash("metadata")),
+            ("a.txt", hash("a")),
+            ("dir/b.txt", hash("b")),
+            ("c.txt", hash("c")),
+        ]
+        .into_iter()
+        .collect::<iroh_blobs::format::collection::Collection>();
+        let collection = [
+            ("a.txt", hash("a")),
+            ("dir/b.txt", hash("b")),
+            ("c.txt", hash("c")),
+        ]
+        .into_iter()
+        .collect::<iroh_blobs::format::collection::Collection>();
+        let sizes = [64, 7, 100, 200, 300];
+        let encoding = CollectionEncoding {
+            metadata: Some(hash("metadata")),
+            ..CollectionEncoding::default()
+        };
+
+        let selection = select_children(&full, &collection, encoding, &sizes, |name| {
+            name.starts_with("dir/")
+        })
+        .expect("one entry is selected");
+        assert_eq!(
+            selection,
+            ChildSelection {
+                names: vec!["dir/b.txt".to_string()],
+                children: vec![1, 3],
+                payload_size: 7 + 200,
+            }
+        );
+
+        let selection = select_children(
+            &full,
+            &collection,
+            CollectionEncoding::default(),
+            &sizes,
+            |name| name != "dir/b.txt",
+        )
+        .expect("two entries are selected");
+        assert_eq!(selection.children, [2, 4]);
+        assert_eq!(selection.payload_size, 100 + 300);
+
+        assert!(select_children(&full, &collection, encoding, &sizes, |_| false).is_none());
+    }
+
+    #[tokio::test]
+    async fn failed_entries_lists_missing_and_corrupt_files() {
+        let db = MemStore::new();
+        let collection = [
+            ("good.txt", iroh_blobs::Hash::new(b"good")),
+            ("dir/corrupt.txt", iroh_blobs::Hash::new(b"corrupt")),
+            ("missing.txt", iroh_blobs::Hash::new(b"missing")),
+        ]
+        .into_iter()
+        .collect::<iroh_blobs::format::collection::Collection>();
+        let temp_dir = tempfile::tempdir().expect("temp dir");
+        std::fs::create_dir_all(temp_dir.path().join("dir")).expect("create dir");
+        std::fs::write(temp_dir.path().join("good.txt"), b"good").expect("write");
+        std::fs::write(temp_dir.path().join("dir/corrupt.txt"), b"c0rrupt").expect("write");
+
+        let failed = failed_entries(
+            &db,
+            &collection,
+            temp_dir.path(),
+            CollectionEncoding::default(),
+        )
+        .await
+        .expect("check exported files");
+        let mut failed = failed.into_iter().collect::<Vec<_>>();
+        failed.sort();
+        assert_eq!(failed, ["dir/corrupt.txt", "missing.txt"]);
+    }
+
+    #[tokio::test]
+    async fn reference_export_fills_every_output_dir() {
+        let db = MemStore::new();
+        let tag = db
+            .add_bytes(vec![7u8; 100_000])
+            .temp_tag()
+            .await
+            .expect("add bytes");
+        let collection = std::iter::once(("big.bin", tag.hash()))
+            .collect::<iroh_blobs::format::collection::Collection>();
+        let primary = tempfile::tempdir().expect("temp dir");
+        let mirror = tempfile::tempdir().expect("temp dir");
+
+        export(
+            &db,
+            collection,
+            &[primary.path().to_path_buf(), mirror.path().to_path_buf()],
+            false,
+            CollectionEncoding::default(),
+            ExportModeOption::Reference,
+        )
+        .await
+        .expect("export should succeed");
+
+        for dir in [primary.path(), mirror.path()] {
+            let data = std::fs::read(dir.join("big.bin")).expect("read export");
+            assert_eq!(data, vec![7u8; 100_000]);
+        }
+    }
+
+    #[test]
+    fn kept_store_is_shared_between_receives() {
+        assert_eq!(kept_store_dir(), kept_store_dir());
+        let hash = iroh_blobs::Hash::new(b"demo");
+        assert_eq!(
+            kept_store_tag(hash),
+            format!("sendmer-recv-{}", hash.to_hex())
+        );
+        assert_ne!(
+            kept_store_tag(hash),
+            kept_store_tag(iroh_blobs::Hash::new(b"other"))
+        );
+    }
+
+    #[tokio::test]
+    async fn cancel_requested_returns_once_token_is_cancelled() {
+        let token = tokio_util::sync::CancellationToken::new();
+        token.cancel();
+        tokio::time::timeout(
+            std::time::Duration::from_secs(5),
+            cancel_requested(Some(&token)),
+        )
+        .await
+        .expect("cancelled token should end the wait");
+    }
+
+    #[tokio::test]
+    async fn process_get_stream_fails_when_transfer_stalls() {
+        let mut s = stream::pending::<GetProgressItem>();
+        let err = process_get_stream(
+            &mut s,
+            0,
+            &None,
+            None,
+            None,
+            Some(std::time::Duration::from_millis(10)),
+        )
+        .await
+        .expect_err("stalled stream should fail");
+        assert!(err.to_string().contains("stalled"));
+    }
 }
@@ -0,0 +1,265 @@
+//! 内置的最小 iroh relay 服务，供离线或私有网络使用（需启用 `relay-server` feature）。
+//!
+//! 仅提供明文 HTTP 的 relay 服务，不包含 QUIC 地址发现与 TLS。访问控制使用共享令牌：
+//! iroh 客户端连接 relay 时会保留 relay URL 中的查询参数，因此把令牌写进 URL
+//! （`http://host:3340/?token=<TOKEN>`）即可。relay 本身只监听本机回环地址，
+//! 对外的监听端口先检查 `/relay` 升级请求中的令牌，再把连接转发给它；
+//! 延迟探测等其他路径不携带令牌，直接转发。
+
+use iroh_relay::http::RELAY_PATH;
+use iroh_relay::server::{AccessConfig, Limits, RelayConfig, Server, ServerConfig};
+use n0_future::task::AbortOnDropHandle;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// relay URL 中携带令牌的查询参数名。
+pub const TOKEN_PARAM: &str = "token";
+
+/// 请求头的最大长度，超过后直接断开连接。
+const MAX_REQUEST_HEAD: usize = 16 * 1024;
+
+const UNAUTHORIZED: &[u8] =
+    b"HTTP/1.1 401 Unauthorized\r\ncontent-length: 0\r\nconnection: close\r\n\r\n";
+
+/// 运行中的 relay 服务；丢弃时停止对外监听。
+pub struct RelayServer {
+    addr: SocketAddr,
+    token: String,
+    server: Server,
+    listener: AbortOnDropHandle<()>,
+}
+
+impl RelayServer {
+    /// 对外监听的地址。
+    pub const fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// 客户端通过 `--relay` 使用的 URL，`host` 为客户端访问本机所用的主机名或 IP。
+    pub fn relay_url(&self, host: &str) -> String {
+        format!(
+            "http://{host}:{}/?{TOKEN_PARAM}={}",
+            self.addr.port(),
+            self.token
+        )
+    }
+
+    /// 停止对外监听并关闭 relay。
+    pub async fn shutdown(self) -> anyhow::Result<()> {
+        drop(self.listener);
+        self.server.shutdown().await?;
+        Ok(())
+    }
+}
+
+/// 启动 relay 服务，只为 relay URL 中带有 `token` 的客户端转发数据。
+pub async fn spawn_relay(bind_addr: SocketAddr, token: String) -> anyhow::Result<RelayServer> {
+    anyhow::ensure!(!token.is_empty(), "relay token must not be empty");
+    let config = ServerConfig::<(), ()> {
+        relay: Some(RelayConfig {
+            http_bind_addr: (Ipv4Addr::LOCALHOST, 0).into(),
+            tls: None,
+            limits: Limits::default(),
+            key_cache_capacity: None,
+            access: AccessConfig::Everyone,
+        }),
+        quic: None,
+        metrics_addr: None,
+    };
+    let server = Server::spawn(config).await?;
+    let upstream = server
+        .http_addr()
+        .ok_or_else(|| anyhow::anyhow!("relay server has no HTTP address"))?;
+    let listener = TcpListener::bind(bind_addr).await?;
+    let addr = listener.local_addr()?;
+    let token_hash = Arc::new(blake3::hash(token.as_bytes()));
+    let accept = tokio::spawn(async move {
+        loop {
+            let (client, peer) = match listener.accept().await {
+                Ok(accepted) => accepted,
+                Err(error) => {
+                    tracing::warn!(error = %error, "failed to accept relay connection");
+                    continue;
+                }
+            };
+            let token_hash = token_hash.clone();
+            tokio::spawn(async move {
+                if let Err(error) = forward(client, upstream, &token_hash).await {
+                    tracing::debug!(%peer, error = %error, "relay connection ended");
+                }
+            });
+        }
+    });
+    Ok(RelayServer {
+        addr,
+        token,
+        server,
+        listener: AbortOnDropHandle::new(accept),
+    })
+}
+
+/// 读完请求头并校验令牌，通过后把连接（包括已读取的请求头）转发给 relay。
+async fn forward(
+    mut client: TcpStream,
+    upstream: SocketAddr,
+    token_hash: &blake3::Hash,
+) -> anyhow::Result<()> {
+    let mut head = Vec::new();
+    let mut buf = [0u8; 2048];
+    while !head.windows(4).any(|window| window == b"\r\n\r\n") {
+        anyhow::ensure!(head.len() <= MAX_REQUEST_HEAD, "request head too large");
+        let read = client.read(&mut buf).await?;
+        if read == 0 {
+            return Ok(());
+        }
+        head.extend_from_slice(&buf[..read]);
+    }
+    if !is_authorized(&head, token_hash) {
+        tracing::info!(peer = ?client.peer_addr().ok(), "relay access denied");
+        client.write_all(UNAUTHORIZED).await?;
+        return Ok(());
+    }
+    let mut relay = TcpStream::connect(upstream).await?;
+    relay.write_all(&head).await?;
+    tokio::io::copy_bidirectional(&mut client, &mut relay).await?;
+    Ok(())
+}
+
+/// 只有 relay 升级请求需要令牌；比较的是哈希值，`blake3::Hash` 的相等比较是常数时间的。
+fn is_authorized(head: &[u8], token_hash: &blake3::Hash) -> bool {
+    let Some(target) = head
+        .split(|byte| *byte == b'\n')
+        .next()
+        .and_then(|line| std::str::from_utf8(line).ok())
+        .and_then(|line| line.split_whitespace().nth(1))
+    else {
+        return false;
+    };
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    if path != RELAY_PATH {
+        return true;
+    }
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .any(|(name, value)| name == TOKEN_PARAM && blake3::hash(value.as_bytes()) == *token_hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_authorized, spawn_relay};
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[test]
+    fn only_relay_requests_need_the_token() {
+        let token = blake3::hash(b"s3cret");
+        let request = |target: &str| format!("GET {target} HTTP/1.1\r\nhost: relay\r\n\r\n");
+        assert!(is_authorized(
+            request("/relay?token=s3cret").as_bytes(),
+            &token
+        ));
+        assert!(is_authorized(
+            request("/relay?x=1&token=s3cret").as_bytes(),
+            &token
+        ));
+        assert!(!is_authorized(
+            request("/relay?token=guess").as_bytes(),
+            &token
+        ));
+        assert!(!is_authorized(request("/relay").as_bytes(), &token));
+        assert!(is_authorized(request("/ping").as_bytes(), &token));
+        assert!(!is_authorized(b"\xff\xfe\r\n\r\n", &token));
+    }
+
+    #[tokio::test]
+    async fn relay_rejects_upgrades_without_the_token() {
+        let server = spawn_relay(([127, 0, 0, 1], 0).into(), "s3cret".to_string())
+            .await
+            .expect("spawn relay");
+        assert!(server.relay_url("relay.lan").ends_with("/?token=s3cret"));
+
+        let mut client = tokio::net::TcpStream::connect(server.addr())
+            .await
+            .expect("connect to relay");
+        client
+            .write_all(b"GET /relay?token=guess HTTP/1.1\r\nhost: relay\r\n\r\n")
+            .await
+            .expect("send request");
+        let mut response = String::new();
+        client
+            .read_to_string(&mut response)
+            .await
+            .expect("read response");
+        assert!(response.starts_with("HTTP/1.1 401"), "{response}");
+
+        // 带令牌的请求被转发给 relay，由它拒绝这个不完整的 websocket 升级
+        let mut client = tokio::net::TcpStream::connect(server.addr())
+            .await
+            .expect("connect to relay");
+        client
+            .write_all(
+                b"GET /relay?token=s3cret HTTP/1.1\r\nhost: relay\r\nconnection: close\r\n\r\n",
+            )
+            .await
+            .expect("send request");
+        let mut response = String::new();
+        client
+            .read_to_string(&mut response)
+            .await
+            .expect("read response");
+        assert!(response.starts_with("HTTP/1.1 400"), "{response}");
+
+        server.shutdown().await.expect("shutdown relay");
+    }
+
+    const TEST_ALPN: &[u8] = b"sendmer/relay-test/0";
+
+    async fn relay_endpoint(url: &iroh::RelayUrl) -> iroh::Endpoint {
+        iroh::Endpoint::empty_builder(iroh::RelayMode::Custom(url.clone().into()))
+            .alpns(vec![TEST_ALPN.to_vec()])
+            .bind()
+            .await
+            .expect("bind endpoint")
+    }
+
+    /// 只通过 `url` 上的 relay 连接两个 endpoint，返回是否连接成功。
+    async fn connects_through(url: &str) -> bool {
+        let url: iroh::RelayUrl = url.parse().expect("relay url");
+        let server = relay_endpoint(&url).await;
+        let client = relay_endpoint(&url).await;
+        let accept = {
+            let server = server.clone();
+            tokio::spawn(async move {
+                if let Some(incoming) = server.accept().await
+                    && let Ok(connection) = incoming.await
+                {
+                    connection.closed().await;
+                }
+            })
+        };
+        let addr = iroh::EndpointAddr::new(server.id()).with_relay_url(url);
+        let connected = matches!(
+            tokio::time::timeout(Duration::from_secs(10), client.connect(addr, TEST_ALPN)).await,
+            Ok(Ok(_))
+        );
+        accept.abort();
+        client.close().await;
+        server.close().await;
+        connected
+    }
+
+    #[tokio::test]
+    async fn endpoints_use_the_relay_only_with_the_token() {
+        let server = spawn_relay(([127, 0, 0, 1], 0).into(), "s3cret".to_string())
+            .await
+            .expect("spawn relay");
+
+        assert!(connects_through(&server.relay_url("127.0.0.1")).await);
+        assert!(!connects_through(&format!("http://{}", server.addr())).await);
+
+        server.shutdown().await.expect("shutdown relay");
+    }
+}
@@ -1,30 +1,59 @@
 //! 发送和接收结果定义。
 //!
-//! 本文件定义：SendResult, ReceiveResult。
+//! 本文件定义：ShareHandle, CollectionEntry, VerifiedEntry, CollectionSummary, ReceiveResult。
 
+use crate::core::events::{ConnectionVia, TransferWarning};
+use crate::core::routing::RoutedFile;
+use crate::core::storage::BlobStore;
+use crate::core::ticket::{DnsHint, ShareTicket};
 use crate::core::types::EntryType;
 use iroh_blobs::{Hash, ticket::BlobTicket};
+use serde::{Serialize, Serializer};
 use std::path::PathBuf;
 use tokio::sync::watch;
 
-pub use crate::core::progress::SenderTransferStatus;
+pub use crate::core::progress::{SenderTransferStatus, ShareStats};
 
-/// 发送结果结构体。
-pub struct SendResult {
-    pub ticket: BlobTicket,
-    pub hash: Hash,
-    pub size: u64,
-    pub entry_type: EntryType,
-
-    // CRITICAL: These fields must be kept alive for the duration of the share
-    pub router: iroh::protocol::Router, // Keeps the server running and protocols active
-    pub temp_tag: iroh_blobs::api::TempTag, // Prevents data from being garbage collected
-    pub blobs_data_dir: PathBuf,        // Path for cleanup when share stops
-    pub _progress_handle: n0_future::task::AbortOnDropHandle<anyhow::Result<()>>, // Keeps event channel open
-    pub _store: iroh_blobs::store::fs::FsStore, // Keeps the blob storage alive
+/// 一个正在进行的分享。
+///
+/// 句柄持有 endpoint、blob 存储与后台任务，分享在句柄存活期间持续可用；
+/// 结束时调用 [`ShareHandle::stop`] 有序关闭 endpoint 并清理临时存储。
+pub struct ShareHandle {
+    pub(crate) transfer_id: u64,
+    pub(crate) hash: Hash,
+    pub(crate) size: u64,
+    pub(crate) entry_type: EntryType,
+    pub(crate) router: iroh::protocol::Router,
+    /// 防止集合数据被回收
+    pub(crate) temp_tag: iroh_blobs::api::TempTag,
+    /// 结束时清理的存储目录，内存存储时为 `None`
+    pub(crate) blobs_data_dir: Option<PathBuf>,
+    /// 存储目录由 `SendOptions::store_dir` 指定，结束时保留而不删除。
+    pub(crate) persistent_store: bool,
+    pub(crate) _progress_handle: n0_future::task::AbortOnDropHandle<anyhow::Result<()>>,
+    pub(crate) _store: BlobStore,
     pub(crate) transfer_status_rx: watch::Receiver<SenderTransferStatus>,
+    pub(crate) downloads_rx: watch::Receiver<u64>,
+    pub(crate) stats_rx: watch::Receiver<ShareStats>,
+    /// 分享开始（ticket 生成）的时间，用于计算统计中的持续时间。
+    pub(crate) started_at: tokio::time::Instant,
+    pub(crate) max_downloads: Option<u64>,
+    pub(crate) deadline: Option<tokio::time::Instant>,
+    pub(crate) expires_at: Option<std::time::SystemTime>,
+    pub(crate) dns_hint: Option<DnsHint>,
+    pub(crate) ticket_rx: watch::Receiver<BlobTicket>,
+    pub(crate) _address_refresh_handle: Option<n0_future::task::AbortOnDropHandle<()>>,
+    pub(crate) _watch_handle: Option<n0_future::task::AbortOnDropHandle<()>>,
+    /// 分享码的会合记录在句柄存活期间持续发布
+    pub(crate) _code_publisher: Option<crate::core::code::CodePublisher>,
+    pub(crate) warnings: Vec<TransferWarning>,
+    pub(crate) entries: Vec<CollectionEntry>,
 }
 
+/// 旧名称，保留给升级前的调用方。
+#[deprecated(note = "renamed to `ShareHandle`")]
+pub type SendResult = ShareHandle;
+
 fn normalize_sender_cleanup_result(cleanup_result: std::io::Result<()>) -> anyhow::Result<()> {
     match cleanup_result {
         Ok(()) => Ok(()),
@@ -43,7 +72,36 @@ fn finalize_sender_shutdown(
     shutdown_result
 }
 
-impl SendResult {
+impl ShareHandle {
+    /// 本次分享发出的事件所带的 `transfer_id`。
+    pub const fn transfer_id(&self) -> u64 {
+        self.transfer_id
+    }
+
+    /// 分享开始时的集合 hash；启用 `watch` 时以 [`ShareHandle::ticket`] 中的为准。
+    pub const fn hash(&self) -> Hash {
+        self.hash
+    }
+
+    /// 数据条目的总大小。
+    pub const fn size(&self) -> u64 {
+        self.size
+    }
+
+    pub const fn entry_type(&self) -> EntryType {
+        self.entry_type
+    }
+
+    /// 导入时被跳过的条目等非致命问题。
+    pub fn warnings(&self) -> &[TransferWarning] {
+        &self.warnings
+    }
+
+    /// 分享自动结束的时间（`SendOptions::expire`），便于界面显示倒计时。
+    pub const fn expires_at(&self) -> Option<std::time::SystemTime> {
+        self.expires_at
+    }
+
     pub fn transfer_status(&self) -> SenderTransferStatus {
         *self.transfer_status_rx.borrow()
     }
@@ -52,8 +110,78 @@ impl SendResult {
         self.transfer_status_rx.clone()
     }
 
-    /// Shut down the active share and remove its temporary blob store.
-    pub async fn shutdown(self) -> anyhow::Result<()> {
+    /// 已完成的下载次数（每个接收端连接完整取完数据计一次）。
+    pub fn completed_downloads(&self) -> u64 {
+        *self.downloads_rx.borrow()
+    }
+
+    pub fn subscribe_downloads(&self) -> watch::Receiver<u64> {
+        self.downloads_rx.clone()
+    }
+
+    /// 到目前为止的传输统计。
+    pub fn stats(&self) -> ShareStats {
+        ShareStats {
+            duration: self.started_at.elapsed(),
+            ..*self.stats_rx.borrow()
+        }
+    }
+
+    /// 完成的下载次数达到 `max_downloads` 时返回；未设置上限时永不返回。
+    pub async fn wait_for_max_downloads(&self) {
+        let Some(max_downloads) = self.max_downloads else {
+            return std::future::pending().await;
+        };
+        let mut downloads_rx = self.downloads_rx.clone();
+        if downloads_rx
+            .wait_for(|downloads| *downloads >= max_downloads)
+            .await
+            .is_err()
+        {
+            std::future::pending::<()>().await;
+        }
+    }
+
+    /// 到达 `expires_at` 时返回；未设置有效期时永不返回。
+    pub async fn wait_for_expiry(&self) {
+        match self.deadline {
+            Some(deadline) => tokio::time::sleep_until(deadline).await,
+            None => std::future::pending().await,
+        }
+    }
+
+    /// 距离分享自动结束的剩余时间。
+    pub fn remaining(&self) -> Option<std::time::Duration> {
+        self.deadline
+            .map(|deadline| deadline.saturating_duration_since(tokio::time::Instant::now()))
+    }
+
+    /// 分享内容的清单。
+    pub fn manifest(&self) -> CollectionSummary {
+        CollectionSummary {
+            hash: self.hash,
+            size: self.size,
+            entries: self.entries.clone(),
+            warnings: self.warnings.clone(),
+        }
+    }
+
+    /// 供接收端使用的完整 ticket，包含可选的 DNS 提示。
+    ///
+    /// 启用 `refresh_ticket` 时会随地址变化而更新，启用 `watch` 时会随分享内容变化而更新。
+    pub fn ticket(&self) -> ShareTicket {
+        ShareTicket {
+            ticket: self.ticket_rx.borrow().clone(),
+            dns_hint: self.dns_hint.clone(),
+        }
+    }
+
+    pub fn subscribe_ticket(&self) -> watch::Receiver<BlobTicket> {
+        self.ticket_rx.clone()
+    }
+
+    /// 停止分享：关闭 endpoint 并删除临时 blob 存储（持久化存储保留到下次启动）。
+    pub async fn stop(self) -> anyhow::Result<()> {
         drop(self.temp_tag);
         let shutdown_result =
             match tokio::time::timeout(std::time::Duration::from_secs(2), self.router.shutdown())
@@ -62,22 +190,116 @@ impl SendResult {
                 Ok(result) => result.map_err(anyhow::Error::from),
                 Err(error) => Err(error.into()),
             };
-        let cleanup_result =
-            normalize_sender_cleanup_result(tokio::fs::remove_dir_all(&self.blobs_data_dir).await);
+        let cleanup_result = match &self.blobs_data_dir {
+            Some(dir) if !self.persistent_store => {
+                normalize_sender_cleanup_result(tokio::fs::remove_dir_all(dir).await)
+            }
+            _ => Ok(()),
+        };
         finalize_sender_shutdown(shutdown_result, cleanup_result)
     }
 }
 
+/// 集合中的一个数据条目。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CollectionEntry {
+    pub name: String,
+    #[serde(serialize_with = "serialize_hash")]
+    pub hash: Hash,
+    pub size: u64,
+}
+
+/// 接收端对一个条目的校验结果。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct VerifiedEntry {
+    pub name: String,
+    #[serde(serialize_with = "serialize_hash")]
+    pub hash: Hash,
+    pub size: u64,
+    /// 重新计算的 blake3 hash 与条目 hash 一致
+    pub verified: bool,
+}
+
+/// 集合清单：集合 hash、各条目与总大小。
+///
+/// 由 [`crate::core::sender::compute_collection`] 或 [`ShareHandle::manifest`] 得到，
+/// 序列化为 JSON 后可供脚本和界面向接收端展示将要获得的内容。
+#[derive(Debug, Clone, Serialize)]
+pub struct CollectionSummary {
+    #[serde(serialize_with = "serialize_hash")]
+    pub hash: Hash,
+    pub size: u64,
+    pub entries: Vec<CollectionEntry>,
+    /// 导入时被跳过的条目等非致命问题。
+    #[serde(serialize_with = "serialize_warnings")]
+    pub warnings: Vec<TransferWarning>,
+}
+
+fn serialize_hash<S: Serializer>(hash: &Hash, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&hash.to_hex())
+}
+
+fn serialize_warnings<S: Serializer>(
+    warnings: &[TransferWarning],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(warnings.iter().map(ToString::to_string))
+}
+
 /// 接收结果结构体。
 #[derive(Debug)]
 pub struct ReceiveResult {
+    /// 本次接收发出的事件所带的 `transfer_id`。
+    pub transfer_id: u64,
     pub message: String,
     pub file_path: PathBuf,
+    /// 导出后按路由规则移动的文件（dry-run 时为计划）。
+    pub routed: Vec<RoutedFile>,
+    /// 接收过程中的非致命问题。
+    pub warnings: Vec<TransferWarning>,
+    /// 续传时从上次中断的下载中复用、无需重新传输的字节数。
+    pub resumed_bytes: u64,
+    /// `verify_only` 或 `verify_export` 时每个条目的校验结果，否则为空。
+    pub verified: Vec<VerifiedEntry>,
+    /// 传输结束时连接所走的网络路径；数据已在本地或从多个发送端下载时为 `None`。
+    pub connection: Option<ConnectionInfo>,
+}
+
+/// 与发送端连接的网络路径与往返时延。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConnectionInfo {
+    pub via: ConnectionVia,
+    /// 对端的直连地址或 relay URL
+    pub remote: String,
+    pub rtt: std::time::Duration,
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{finalize_sender_shutdown, normalize_sender_cleanup_result};
+    use super::{
+        CollectionEntry, CollectionSummary, finalize_sender_shutdown,
+        normalize_sender_cleanup_result,
+    };
+    use iroh_blobs::Hash;
+
+    #[test]
+    fn collection_summary_serializes_hashes_as_hex() {
+        let hash = Hash::new(b"alpha");
+        let summary = CollectionSummary {
+            hash,
+            size: 5,
+            entries: vec![CollectionEntry {
+                name: "docs/a.txt".to_string(),
+                hash,
+                size: 5,
+            }],
+            warnings: Vec::new(),
+        };
+        let json = serde_json::to_value(&summary).expect("summary should serialize");
+        assert_eq!(json["hash"], hash.to_hex());
+        assert_eq!(json["entries"][0]["name"], "docs/a.txt");
+        assert_eq!(json["entries"][0]["size"], 5);
+    }
 
     #[test]
     fn normalize_sender_cleanup_result_ignores_not_found() {
@@ -0,0 +1,226 @@
+//! 接收端导出后的路由阶段：按文件名模式把导出的文件移动到不同目录。
+//!
+//! 规则形如 `*.jpg=>~/Pictures`，按声明顺序匹配，第一个命中的规则生效。
+
+use crate::core::error::{CatalogError, ErrorKind};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// 单条路由规则。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RouteRule {
+    /// 通配符模式；不含 `/` 时只匹配文件名，否则匹配集合内的完整相对路径。
+    pub pattern: String,
+    /// 命中后移动到的目标目录。
+    pub dest: PathBuf,
+}
+
+/// 一次路由操作（或 dry-run 时的计划）。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RoutedFile {
+    pub from: PathBuf,
+    pub to: PathBuf,
+}
+
+impl FromStr for RouteRule {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (pattern, dest) = s
+            .split_once("=>")
+            .ok_or_else(|| anyhow::anyhow!("route must look like PATTERN=>DIR"))?;
+        let pattern = pattern.trim();
+        let dest = dest.trim();
+        anyhow::ensure!(!pattern.is_empty(), "route pattern cannot be empty");
+        anyhow::ensure!(!dest.is_empty(), "route destination cannot be empty");
+        Ok(Self {
+            pattern: pattern.to_string(),
+            dest: expand_home(dest),
+        })
+    }
+}
+
+impl RouteRule {
+    /// 判断集合内名称 `name`（以 `/` 分隔）是否命中该规则。
+    pub fn matches(&self, name: &str) -> bool {
+        if self.pattern.contains('/') {
+            glob_match(&self.pattern, name)
+        } else {
+            let file_name = name.rsplit('/').next().unwrap_or(name);
+            glob_match(&self.pattern, file_name)
+        }
+    }
+}
+
+/// 计算集合条目的路由计划。
+///
+/// `names` 为集合内名称，`output_dir` 为条目已导出的根目录。
+/// 两个条目会被移动到同一个目标，或目标已经存在时返回 `TargetExists`，
+/// 因此在移动任何文件之前就能发现冲突。
+pub fn plan_routes(
+    names: &[String],
+    output_dir: &Path,
+    rules: &[RouteRule],
+) -> anyhow::Result<Vec<RoutedFile>> {
+    let mut targets = HashMap::new();
+    let mut routes = Vec::new();
+    for name in names {
+        let Some(rule) = rules.iter().find(|rule| rule.matches(name)) else {
+            continue;
+        };
+        let file_name = name.rsplit('/').next().unwrap_or(name);
+        let to = rule.dest.join(file_name);
+        if let Some(previous) = targets.insert(to.clone(), name) {
+            return Err(CatalogError::new(
+                ErrorKind::TargetExists,
+                format!(
+                    "--route would move both {previous} and {name} to {}",
+                    to.display()
+                ),
+            )
+            .into());
+        }
+        if to.symlink_metadata().is_ok() {
+            return Err(CatalogError::new(
+                ErrorKind::TargetExists,
+                format!("route target {} already exists", to.display()),
+            )
+            .into());
+        }
+        let from = name
+            .split('/')
+            .fold(output_dir.to_path_buf(), |path, part| path.join(part));
+        routes.push(RoutedFile { from, to });
+    }
+    Ok(routes)
+}
+
+/// 执行路由计划，把文件移动到目标目录。目标已存在时返回错误。
+pub fn apply_routes(routes: &[RoutedFile]) -> anyhow::Result<()> {
+    for route in routes {
+        anyhow::ensure!(
+            !route.to.exists(),
+            "route target {} already exists",
+            route.to.display()
+        );
+        if let Some(parent) = route.to.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        if std::fs::rename(&route.from, &route.to).is_err() {
+            // rename 无法跨文件系统，退化为复制后删除
+            std::fs::copy(&route.from, &route.to)?;
+            std::fs::remove_file(&route.from)?;
+        }
+    }
+    Ok(())
+}
+
+/// 简单通配符匹配：`*` 匹配除 `/` 之外的任意字符，`**` 匹配任意字符，`?` 匹配单个字符。
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let text = text.chars().collect::<Vec<_>>();
+    glob_match_from(&pattern, &text)
+}
+
+fn glob_match_from(pattern: &[char], text: &[char]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some(('*', rest)) => {
+            let (cross_separator, rest) = match rest.split_first() {
+                Some(('*', rest)) => (true, rest),
+                _ => (false, rest),
+            };
+            for skip in 0..=text.len() {
+                if glob_match_from(rest, &text[skip..]) {
+                    return true;
+                }
+                if skip < text.len() && !cross_separator && text[skip] == '/' {
+                    return false;
+                }
+            }
+            false
+        }
+        Some(('?', rest)) => text
+            .split_first()
+            .is_some_and(|(c, text)| *c != '/' && glob_match_from(rest, text)),
+        Some((p, rest)) => text
+            .split_first()
+            .is_some_and(|(c, text)| c == p && glob_match_from(rest, text)),
+    }
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    path.strip_prefix("~/")
+        .and_then(|rest| dirs::home_dir().map(|home| home.join(rest)))
+        .unwrap_or_else(|| PathBuf::from(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RouteRule, apply_routes, glob_match, plan_routes};
+    use std::path::Path;
+    use std::str::FromStr;
+
+    #[test]
+    fn glob_match_handles_single_and_double_star() {
+        assert!(glob_match("*.jpg", "photo.jpg"));
+        assert!(!glob_match("*.jpg", "dir/photo.jpg"));
+        assert!(glob_match("docs/**", "docs/a/b.md"));
+        assert!(glob_match("file-?.txt", "file-1.txt"));
+        assert!(!glob_match("file-?.txt", "file-10.txt"));
+    }
+
+    #[test]
+    fn route_rule_parses_pattern_and_destination() {
+        let rule = RouteRule::from_str("*.pdf => /tmp/docs").expect("valid route");
+        assert_eq!(rule.pattern, "*.pdf");
+        assert_eq!(rule.dest, Path::new("/tmp/docs"));
+        assert!(rule.matches("inbox/report.pdf"));
+
+        let err = RouteRule::from_str("*.pdf").expect_err("missing arrow should fail");
+        assert!(err.to_string().contains("PATTERN=>DIR"));
+    }
+
+    #[test]
+    fn routes_move_matching_files_only() {
+        let temp_dir = tempfile::tempdir().expect("temp dir");
+        let output = temp_dir.path().join("out");
+        let pictures = temp_dir.path().join("pictures");
+        std::fs::create_dir_all(output.join("data")).expect("create output");
+        std::fs::write(output.join("data").join("a.jpg"), b"jpg").expect("write jpg");
+        std::fs::write(output.join("data").join("b.txt"), b"txt").expect("write txt");
+
+        let rules = vec![RouteRule {
+            pattern: "*.jpg".to_string(),
+            dest: pictures.clone(),
+        }];
+        let names = vec!["data/a.jpg".to_string(), "data/b.txt".to_string()];
+        let routes = plan_routes(&names, &output, &rules).expect("plan routes");
+        assert_eq!(routes.len(), 1);
+
+        apply_routes(&routes).expect("routes should apply");
+        assert!(pictures.join("a.jpg").exists());
+        assert!(!output.join("data").join("a.jpg").exists());
+        assert!(output.join("data").join("b.txt").exists());
+    }
+
+    #[test]
+    fn plan_routes_rejects_collisions_before_moving_anything() {
+        let temp_dir = tempfile::tempdir().expect("temp dir");
+        let pictures = temp_dir.path().join("pictures");
+        let rules = vec![RouteRule {
+            pattern: "*.jpg".to_string(),
+            dest: pictures.clone(),
+        }];
+
+        let names = vec!["a/cover.jpg".to_string(), "b/cover.jpg".to_string()];
+        let err = plan_routes(&names, temp_dir.path(), &rules).expect_err("same target twice");
+        assert!(err.to_string().contains("both a/cover.jpg and b/cover.jpg"));
+
+        std::fs::create_dir_all(&pictures).expect("create pictures");
+        std::fs::write(pictures.join("cover.jpg"), b"old").expect("write existing");
+        let err = plan_routes(&names[..1], temp_dir.path(), &rules).expect_err("existing target");
+        assert!(err.to_string().contains("already exists"));
+    }
+}
@@ -0,0 +1,123 @@
+//! 沙箱化导出：只打开一次输出目录，之后所有写入都通过相对于该目录句柄的
+//! openat 式操作完成（cap-std），即使文件名校验存在缺陷也无法逃逸出输出目录。
+//!
+//! 需要启用 `sandbox` feature；未启用时导出直接返回错误。
+
+use crate::core::compression::Compression;
+use iroh_blobs::{api::Store, format::collection::Collection};
+use std::path::Path;
+
+/// 在沙箱中把集合中的各个 blob 导出到 `output_dir`，压缩过的集合导出解压后的内容。
+///
+/// 与普通导出一样，每个文件先写到 `<name>.part`，写完后再重命名为目标文件名。
+#[cfg(feature = "sandbox")]
+pub async fn export_collection(
+    db: &Store,
+    collection: &Collection,
+    output_dir: &Path,
+    compression: Option<Compression>,
+) -> anyhow::Result<()> {
+    std::fs::create_dir_all(output_dir)?;
+    let root = cap_std::fs::Dir::open_ambient_dir(output_dir, cap_std::ambient_authority())?;
+    for (name, hash) in collection.iter() {
+        let (file, target, partial) = open_partial(&root, name)?;
+        let mut file = tokio::fs::File::from_std(file.into_std());
+        let copied = match compression {
+            Some(compression) => {
+                tokio::io::copy(&mut compression.decompress(db, *hash), &mut file).await
+            }
+            None => tokio::io::copy(&mut db.reader(*hash), &mut file).await,
+        };
+        let synced = match copied {
+            Ok(_) => file.sync_all().await,
+            Err(error) => Err(error),
+        };
+        drop(file);
+        if let Err(error) = synced {
+            let _ = root.remove_file(&partial);
+            anyhow::bail!("error exporting {name}: {error}");
+        }
+        root.rename(&partial, &root, &target)
+            .map_err(|error| anyhow::anyhow!("failed to move {name} into place: {error}"))?;
+    }
+    Ok(())
+}
+
+/// 在 `root` 下为 `name` 创建临时文件 `<name>.part`（覆盖上次中断留下的），
+/// 返回文件及目标与临时文件的相对路径；目标已存在时返回 `TargetExists`。
+#[cfg(feature = "sandbox")]
+fn open_partial(
+    root: &cap_std::fs::Dir,
+    name: &str,
+) -> anyhow::Result<(cap_std::fs::File, std::path::PathBuf, std::path::PathBuf)> {
+    use crate::core::error::{CatalogError, ErrorKind};
+
+    let mut relative = std::path::PathBuf::new();
+    for part in name.split('/') {
+        super::receiver::validate_path_component(part)?;
+        relative.push(part);
+    }
+    if let Some(parent) = relative
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        root.create_dir_all(parent)?;
+    }
+
+    if root.symlink_metadata(&relative).is_ok() {
+        return Err(CatalogError::new(
+            ErrorKind::TargetExists,
+            format!("target {} already exists", relative.display()),
+        )
+        .into());
+    }
+
+    let partial = super::receiver::partial_path(&relative);
+    let mut options = cap_std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    let file = root.open_with(&partial, &options).map_err(|error| {
+        anyhow::anyhow!(
+            "cannot create {} inside the output directory: {error}",
+            partial.display()
+        )
+    })?;
+    Ok((file, relative, partial))
+}
+
+#[cfg(not(feature = "sandbox"))]
+pub async fn export_collection(
+    _db: &Store,
+    _collection: &Collection,
+    _output_dir: &Path,
+    _compression: Option<Compression>,
+) -> anyhow::Result<()> {
+    anyhow::bail!("sendmer was built without sandboxed export, enable the `sandbox` feature")
+}
+
+#[cfg(all(test, unix, feature = "sandbox"))]
+mod tests {
+    use super::open_partial;
+
+    #[test]
+    fn open_target_cannot_escape_through_symlink() {
+        let temp_dir = tempfile::tempdir().expect("temp dir");
+        let root_path = temp_dir.path().join("inbox");
+        let outside = temp_dir.path().join("outside");
+        std::fs::create_dir_all(&root_path).expect("create root");
+        std::fs::create_dir_all(&outside).expect("create outside");
+        std::os::unix::fs::symlink(&outside, root_path.join("link")).expect("create symlink");
+
+        let root = cap_std::fs::Dir::open_ambient_dir(&root_path, cap_std::ambient_authority())
+            .expect("open root");
+        let (_, target, partial) =
+            open_partial(&root, "nested/file.txt").expect("regular target should open");
+        assert!(root_path.join(&partial).exists());
+        assert!(!root_path.join(&target).exists());
+        assert!(open_partial(&root, "link/escape.txt").is_err());
+        assert!(!outside.join("escape.txt.part").exists());
+
+        std::fs::write(root_path.join(&target), b"done").expect("write target");
+        let err = open_partial(&root, "nested/file.txt").expect_err("existing target should fail");
+        assert!(err.to_string().contains("already exists"));
+    }
+}
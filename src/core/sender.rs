@@ -1,15 +1,42 @@
 //! 发送端功能：将本地文件/目录导入 Blob 存储并通过 iroh 协议对外提供。
 //!
-//! 主要导出 `start_share`，它会导入数据、启动路由器并返回用于后续管理的 `SendResult`。
+//! 主要导出 `send` 与 `send_paths`，它们会导入数据、启动路由器并返回用于后续管理的 `ShareHandle`。
 
+use crate::core::archive::{ARCHIVE_MARKER, pack};
+use crate::core::auth::{AUTH_ALPN, PasswordGate};
+use crate::core::code::{CodePublisher, ShareCode};
+use crate::core::compression::{Compression, read_chunks};
 use crate::core::endpoint::base_endpoint_builder;
-use crate::core::events::AppHandle;
-use crate::core::options::{AddrInfoOptions, SendOptions, apply_options};
-use crate::core::progress::{SenderProgressReporter, SenderTransferStatus, TransferId};
-use crate::core::results::SendResult;
-use crate::core::storage::{load_fs_store, unique_temp_dir};
+use crate::core::error::Error;
+use crate::core::event_queue::QueuedEventEmitter;
+use crate::core::events::{
+    AppHandle, Role, TransferIdEmitter, TransferWarning, WarningCode, next_transfer_id,
+};
+use crate::core::gitignore::IgnoreStack;
+use crate::core::history::{self, Outcome, TransferRecord};
+use crate::core::metadata::{self, METADATA_ENTRY};
+use crate::core::options::{
+    AddrInfoOptions, DiscoveryToggle, ImportModeOption, ImportVerification, SendOptions,
+    SymlinkPolicy, apply_options,
+};
+use crate::core::progress::{
+    ImportProgress, SenderProgressReporter, SenderTransferStatus, TransferEventEmitter, TransferId,
+};
+use crate::core::rate_limit::RateLimiter;
+use crate::core::receiver::validate_path_component;
+use crate::core::results::{CollectionEntry, CollectionSummary, ShareHandle, ShareStats};
+use crate::core::routing::glob_match;
+use crate::core::storage::{
+    BlobStore, load_or_create_secret, save_ticket, unique_temp_dir, unique_temp_dir_in,
+};
+use crate::core::ticket::DnsHint;
+use crate::core::trace::{TraceKind, TraceLog};
+use crate::core::updates::{UPDATES_ALPN, UpdateNotifier};
+use crate::core::verify::verify_blob;
+use crate::core::watch::{WatchContext, spawn_watch_task};
 use anyhow::Context;
-use iroh::{Endpoint, discovery::pkarr::PkarrPublisher};
+use bytes::Bytes;
+use iroh::{Endpoint, EndpointId, Watcher, discovery::pkarr::PkarrPublisher};
 use iroh_blobs::{
     BlobFormat, BlobsProtocol,
     api::{
@@ -17,14 +44,18 @@ use iroh_blobs::{
         blobs::{AddPathOptions, ImportMode},
     },
     format::collection::Collection,
-    provider::events::{ConnectMode, EventMask, EventSender, RequestMode},
-    store::fs::FsStore,
+    provider::events::{
+        AbortReason, ConnectMode, EventMask, EventSender, RequestMode, RequestUpdate, ThrottleMode,
+    },
     ticket::BlobTicket,
 };
 use n0_future::StreamExt;
 use n0_future::{BufferedStreamExt, task::AbortOnDropHandle};
+use rand::seq::IndexedRandom;
 use std::{
+    collections::{HashMap, HashSet},
     path::{Component, Path, PathBuf},
+    sync::Arc,
     time::Duration,
 };
 use tokio::{
@@ -35,94 +66,287 @@ use tracing::{info, trace};
 use walkdir::WalkDir;
 
 const PROVIDER_PROGRESS_TASK_LIMIT: usize = 32;
+const ADDRESS_REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+const VERIFY_IMPORT_SAMPLE_SIZE: usize = 8;
+/// 低于该总大小的分享自动使用内存存储
+const MEMORY_STORE_THRESHOLD: u64 = 16 * 1024 * 1024;
 
 /// Prepare endpoint with the given options
-async fn prepare_endpoint(options: &SendOptions) -> anyhow::Result<Endpoint> {
+pub(crate) async fn prepare_endpoint(options: &SendOptions) -> anyhow::Result<Endpoint> {
     let mut builder = base_endpoint_builder(options, vec![iroh_blobs::protocol::ALPN.to_vec()])?;
-
-    if options.ticket_type == AddrInfoOptions::Id {
+    if let Some(store_dir) = &options.store_dir {
+        // 持久化分享使用固定的节点密钥，重启后 ticket 保持不变
+        builder = builder.secret_key(load_or_create_secret(store_dir)?);
+    }
+    anyhow::ensure!(
+        options.code.is_none() || options.pkarr_publish != DiscoveryToggle::Off,
+        "a share code needs pkarr publishing"
+    );
+    let addressless = options.ticket_type == AddrInfoOptions::Id && options.dns_hint.is_none();
+    let publish_to_pkarr = options
+        .pkarr_publish
+        // 分享码的会合记录只含 endpoint ID，接收端要靠 pkarr 解析发送端地址
+        .enabled(addressless || options.refresh_ticket || options.code.is_some());
+    if publish_to_pkarr {
         builder = builder.discovery(PkarrPublisher::n0_dns());
     }
 
     builder.bind().await.map_err(Into::into)
 }
 
-/// Prepare temporary directory for blob storage
-fn prepare_temp_directory() -> anyhow::Result<PathBuf> {
-    unique_temp_dir(".sendmer-send-")
+/// Prepare temporary directory for blob storage, inside `parent` or the system temp dir
+pub(crate) fn prepare_temp_directory(parent: Option<&Path>) -> anyhow::Result<PathBuf> {
+    parent.map_or_else(
+        || unique_temp_dir(".sendmer-send-"),
+        |parent| unique_temp_dir_in(parent, ".sendmer-send-"),
+    )
 }
 
-/// Validate the path to be shared
-fn validate_share_path(path: &Path) -> anyhow::Result<()> {
-    let cwd = std::env::current_dir()?;
-    let resolved = cwd.join(path);
-    let is_cwd = match (cwd.canonicalize(), resolved.canonicalize()) {
-        (Ok(cwd_canonical), Ok(path_canonical)) => path_canonical == cwd_canonical,
-        _ => resolved == cwd,
+/// Validate the path to be shared: the blob store must not live inside it,
+/// otherwise the import would pick up its own data.
+pub(crate) fn validate_share_path(path: &Path, store_dir: Option<&Path>) -> anyhow::Result<()> {
+    let Some(store_dir) = store_dir else {
+        return Ok(());
     };
-    if is_cwd {
-        anyhow::bail!("can not share from the current directory");
+    let shared = resolve_path(path);
+    if resolve_path(store_dir).starts_with(&shared) {
+        anyhow::bail!(
+            "the blob store {} is inside the shared path {}, use --temp-dir to move it",
+            store_dir.display(),
+            path.display()
+        );
     }
     Ok(())
 }
 
+/// 解析为绝对路径，并尽可能规范化已存在的前缀部分。
+fn resolve_path(path: &Path) -> PathBuf {
+    let absolute = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    absolute
+        .ancestors()
+        .find_map(|ancestor| {
+            let canonical = ancestor.canonicalize().ok()?;
+            Some(canonical.join(absolute.strip_prefix(ancestor).ok()?))
+        })
+        .unwrap_or(absolute)
+}
+
 /// Setup data sharing with progress tracking
 async fn setup_data_sharing(
     endpoint: Endpoint,
-    blobs_data_dir: PathBuf,
+    blobs_data_dir: Option<PathBuf>,
     share_request: ShareRequest,
     wait_for_online: bool,
 ) -> anyhow::Result<SharingSetup> {
     let (progress_tx, progress_rx) = mpsc::channel(32);
     let (transfer_status_tx, transfer_status_rx) = watch::channel(SenderTransferStatus::Idle);
+    let (downloads_tx, downloads_rx) = watch::channel(0);
+    let (stats_tx, stats_rx) = watch::channel(ShareStats::default());
 
     let setup_future = async move {
-        let store = load_fs_store(&blobs_data_dir).await?;
-
-        let blobs = BlobsProtocol::new(&store, Some(create_event_sender(progress_tx)));
+        let store = BlobStore::load(blobs_data_dir.as_deref()).await?;
+
+        let rate_limiter = share_request
+            .rate_limit
+            .map(|rate| Arc::new(RateLimiter::new(rate)));
+        let allow = Arc::new(share_request.allow);
+        let password_gate = share_request.password.as_deref().map(PasswordGate::new);
+        let blobs = BlobsProtocol::new(
+            &store,
+            Some(create_event_sender(
+                progress_tx,
+                !allow.is_empty() || password_gate.is_some(),
+                rate_limiter.is_some(),
+            )),
+        );
 
-        let imported = import(share_request.path, blobs.store()).await?;
+        let imported = import(
+            share_request.source,
+            blobs.store(),
+            &share_request.policy,
+            &share_request.app_handle,
+        )
+        .await?;
+        let emitter = TransferEventEmitter::new(share_request.app_handle.clone(), Role::Sender);
+        for warning in &imported.warnings {
+            tracing::warn!(%warning, "import warning");
+            emitter.emit_warning(warning);
+        }
         let size = imported.size;
         let progress_handle = spawn_provider_progress_task(
             progress_rx,
-            share_request.app_handle,
-            size,
-            share_request.entry_type,
-            transfer_status_tx,
+            ProviderEventContext {
+                app_handle: share_request.app_handle,
+                total_file_size: size,
+                entry_type: share_request.entry_type,
+                transfer_status_tx,
+                downloads_tx,
+                stats_tx,
+                trace: share_request.trace,
+                history: share_request.history,
+                hash: imported.temp_tag.hash(),
+                rate_limiter,
+                allow: allow.clone(),
+                password_gate: password_gate.clone(),
+            },
         );
 
-        let router = iroh::protocol::Router::builder(endpoint)
-            .accept(iroh_blobs::protocol::ALPN, blobs.clone())
-            .spawn();
+        let code_publisher = share_request
+            .code
+            .as_ref()
+            .map(|code| code.publish(endpoint.id(), imported.temp_tag.hash()))
+            .transpose()?;
+        let mut router = iroh::protocol::Router::builder(endpoint)
+            .accept(iroh_blobs::protocol::ALPN, blobs.clone());
+        let updates_tx = if share_request.notify_updates {
+            let (tx, rx) = watch::channel(imported.temp_tag.hash());
+            router = router.accept(
+                UPDATES_ALPN,
+                UpdateNotifier::new(rx, allow.clone(), password_gate.clone()),
+            );
+            Some(tx)
+        } else {
+            None
+        };
+        if let Some(password_gate) = password_gate {
+            router = router.accept(AUTH_ALPN, password_gate);
+        }
+        let router = router.spawn();
 
         wait_until_endpoint_is_online(router.endpoint(), wait_for_online).await?;
 
         anyhow::Ok(SharingSetup {
             router,
+            updates_tx,
+            code_publisher,
             imported,
             blobs_data_dir,
             store,
             progress_handle,
             transfer_status_rx,
+            downloads_rx,
+            stats_rx,
         })
     };
 
     setup_future.await
 }
 
+/// 要分享的数据来源。
+#[derive(Debug)]
+pub(crate) enum ShareSource {
+    /// 本地文件或目录
+    Paths(Vec<PathBuf>),
+    /// 从标准输入读取的数据，以 `name` 作为集合中唯一条目的名称
+    Stdin { name: String },
+}
+
 struct ShareRequest {
-    path: PathBuf,
+    source: ShareSource,
     entry_type: crate::core::types::EntryType,
     app_handle: AppHandle,
+    policy: ImportPolicy,
+    trace: Option<TraceLog>,
+    history: Option<PathBuf>,
+    rate_limit: Option<u64>,
+    allow: HashSet<EndpointId>,
+    password: Option<String>,
+    code: Option<ShareCode>,
+    /// 监听模式下向订阅的接收端推送新的集合 hash
+    notify_updates: bool,
+}
+
+/// 导入阶段的行为配置，由 `SendOptions` 派生。
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ImportPolicy {
+    verification: Option<ImportVerification>,
+    skip_unreadable: bool,
+    symlinks: SymlinkPolicy,
+    one_file_system: bool,
+    exclude: Vec<String>,
+    gitignore: bool,
+    hidden: bool,
+    max_size: Option<u64>,
+    import_mode: ImportModeOption,
+    compression: Option<Compression>,
+    archive: bool,
+    preserve_metadata: bool,
+    root_name: Option<String>,
+    /// 传输记录文件与接收端：省略之前已发送给该接收端的文件
+    skip_published: Option<(PathBuf, EndpointId)>,
+}
+
+impl ImportPolicy {
+    pub(crate) fn from_options(options: &SendOptions) -> Self {
+        Self {
+            verification: options.verify_import,
+            skip_unreadable: options.skip_unreadable,
+            symlinks: options.symlinks,
+            one_file_system: options.one_file_system,
+            exclude: options.exclude.clone(),
+            gitignore: options.gitignore,
+            hidden: options.hidden,
+            max_size: options.max_size,
+            import_mode: options.import_mode,
+            compression: options.compression,
+            archive: options.archive,
+            preserve_metadata: options.preserve_metadata,
+            root_name: options.name.clone(),
+            skip_published: options
+                .skip_published
+                .zip(options.history.clone())
+                .map(|(peer, history)| (history, peer)),
+        }
+    }
+
+    /// 与 rsync 的 `--exclude` 一致：不含 `/` 的模式匹配任意层级的条目名，
+    /// 含 `/` 的模式匹配集合内的完整相对路径。
+    fn is_excluded(&self, path: &Path, root: &Path) -> bool {
+        if self.exclude.is_empty() {
+            return false;
+        }
+        let Ok(name) = path
+            .strip_prefix(root)
+            .map_err(anyhow::Error::from)
+            .and_then(|relative| canonicalized_path_to_string(relative, true))
+        else {
+            return false;
+        };
+        let file_name = name.rsplit('/').next().unwrap_or(&name);
+        self.exclude.iter().any(|pattern| {
+            if pattern.contains('/') {
+                glob_match(pattern.trim_end_matches('/'), &name)
+            } else {
+                glob_match(pattern, file_name)
+            }
+        })
+    }
 }
 
 struct SharePlan {
     entry_type: crate::core::types::EntryType,
     wait_for_online: bool,
-    blobs_data_dir: PathBuf,
+    /// `None` 表示使用内存存储
+    blobs_data_dir: Option<PathBuf>,
+    persistent_store: bool,
+    max_downloads: Option<u64>,
+    expire: Option<Duration>,
     ticket_type: AddrInfoOptions,
+    refresh_ticket: bool,
+    dns_hint: Option<String>,
+    policy: ImportPolicy,
+    trace: Option<TraceLog>,
+    history: Option<PathBuf>,
+    rate_limit: Option<u64>,
+    allow: Vec<EndpointId>,
+    password: Option<String>,
+    code: Option<ShareCode>,
+    /// 监听模式下要监听的路径
+    watch_paths: Option<Vec<PathBuf>>,
+    keep_snapshots: Option<u64>,
 }
 
+#[derive(Debug)]
 struct ImportedSource {
     name: String,
     path: PathBuf,
@@ -136,34 +360,104 @@ struct ImportedBlob {
 
 fn create_event_sender(
     progress_tx: mpsc::Sender<iroh_blobs::provider::events::ProviderMessage>,
+    check_peers: bool,
+    throttle: bool,
 ) -> EventSender {
     EventSender::new(
         progress_tx,
         EventMask {
-            connected: ConnectMode::Notify,
+            connected: if check_peers {
+                ConnectMode::Intercept
+            } else {
+                ConnectMode::Notify
+            },
             get: RequestMode::NotifyLog,
+            throttle: if throttle {
+                ThrottleMode::Intercept
+            } else {
+                ThrottleMode::None
+            },
             ..EventMask::DEFAULT
         },
     )
 }
 
-fn spawn_provider_progress_task(
-    progress_rx: mpsc::Receiver<iroh_blobs::provider::events::ProviderMessage>,
+/// 处理提供者事件所需的状态。
+struct ProviderEventContext {
     app_handle: AppHandle,
     total_file_size: u64,
     entry_type: crate::core::types::EntryType,
     transfer_status_tx: watch::Sender<SenderTransferStatus>,
+    downloads_tx: watch::Sender<u64>,
+    stats_tx: watch::Sender<ShareStats>,
+    trace: Option<TraceLog>,
+    /// 传输记录文件；设置时每个接收端的连接关闭后追加一条记录
+    history: Option<PathBuf>,
+    /// 集合 hash，写入传输记录
+    hash: iroh_blobs::Hash,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    /// 允许下载的接收端；为空时不限制
+    allow: Arc<HashSet<EndpointId>>,
+    /// 设置口令时，只放行已通过口令校验的接收端
+    password_gate: Option<PasswordGate>,
+}
+
+/// 一个接收端连接上的请求结果，连接关闭时写入传输记录。
+#[derive(Debug, Default)]
+struct ConnectionTally {
+    bytes_sent: u64,
+    completed: bool,
+    aborted: bool,
+}
+
+impl ConnectionTally {
+    fn on_request_update(&mut self, update: &RequestUpdate) {
+        match update {
+            RequestUpdate::Completed(m) => {
+                self.bytes_sent += m.stats.payload_bytes_sent;
+                self.completed = true;
+            }
+            RequestUpdate::Aborted(m) => {
+                self.bytes_sent += m.stats.payload_bytes_sent;
+                self.aborted = true;
+            }
+            RequestUpdate::Started(_) | RequestUpdate::Progress(_) => {}
+        }
+    }
+
+    /// 有请求中止或没有任何请求完成时记为失败。
+    fn record(&self, hash: iroh_blobs::Hash, peer: Option<EndpointId>) -> TransferRecord {
+        if self.completed && !self.aborted {
+            TransferRecord::new(
+                Role::Sender,
+                hash,
+                peer,
+                self.bytes_sent,
+                Outcome::Completed,
+            )
+        } else {
+            TransferRecord::new(Role::Sender, hash, peer, self.bytes_sent, Outcome::Failed)
+                .with_message("transfer aborted")
+        }
+    }
+}
+
+/// 判断接收端是否在允许列表中；列表为空时允许所有接收端。
+fn is_peer_allowed(allow: &HashSet<EndpointId>, peer: Option<EndpointId>) -> bool {
+    allow.is_empty() || peer.is_some_and(|peer| allow.contains(&peer))
+}
+
+fn spawn_provider_progress_task(
+    progress_rx: mpsc::Receiver<iroh_blobs::provider::events::ProviderMessage>,
+    context: ProviderEventContext,
 ) -> AbortOnDropHandle<anyhow::Result<()>> {
     AbortOnDropHandle::new(tokio::spawn(show_provide_progress_with_provider_tracker(
         progress_rx,
-        app_handle,
-        total_file_size,
-        entry_type,
-        transfer_status_tx,
+        context,
     )))
 }
 
-async fn wait_until_endpoint_is_online(
+pub(crate) async fn wait_until_endpoint_is_online(
     endpoint: &iroh::Endpoint,
     wait_for_online: bool,
 ) -> anyhow::Result<()> {
@@ -178,101 +472,368 @@ async fn wait_until_endpoint_is_online(
 
 struct SharingSetup {
     router: iroh::protocol::Router,
+    updates_tx: Option<watch::Sender<iroh_blobs::Hash>>,
+    code_publisher: Option<CodePublisher>,
     imported: ImportedCollection,
-    blobs_data_dir: PathBuf,
-    store: FsStore,
+    blobs_data_dir: Option<PathBuf>,
+    store: BlobStore,
     progress_handle: AbortOnDropHandle<anyhow::Result<()>>,
     transfer_status_rx: watch::Receiver<SenderTransferStatus>,
+    downloads_rx: watch::Receiver<u64>,
+    stats_rx: watch::Receiver<ShareStats>,
 }
 
-struct ImportedCollection {
-    temp_tag: TempTag,
-    size: u64,
+pub(crate) struct ImportedCollection {
+    pub(crate) temp_tag: TempTag,
+    pub(crate) size: u64,
+    /// 数据条目（不含标记条目），按名称排序
+    pub(crate) entries: Vec<CollectionEntry>,
     _collection: Collection,
+    pub(crate) warnings: Vec<TransferWarning>,
 }
 
 impl SharePlan {
-    fn new(path: &Path, options: &SendOptions) -> anyhow::Result<Self> {
+    fn new(source: &ShareSource, options: &SendOptions) -> anyhow::Result<Self> {
+        let entry_type = match source {
+            ShareSource::Paths(paths) if paths.len() == 1 => detect_entry_type(&paths[0]),
+            ShareSource::Paths(_) => crate::core::types::EntryType::Directory,
+            ShareSource::Stdin { .. } => crate::core::types::EntryType::File,
+        };
+        let watch_paths = match source {
+            ShareSource::Paths(paths) if options.watch => Some(paths.clone()),
+            ShareSource::Paths(_) => None,
+            ShareSource::Stdin { .. } => {
+                anyhow::ensure!(!options.watch, "stdin cannot be watched for changes");
+                anyhow::ensure!(!options.archive, "stdin cannot be archived");
+                anyhow::ensure!(
+                    options.max_size.is_none(),
+                    "the size of stdin is unknown, --max-size needs paths"
+                );
+                anyhow::ensure!(
+                    !options.preserve_metadata,
+                    "stdin has no file metadata to preserve"
+                );
+                anyhow::ensure!(
+                    options.skip_published.is_none(),
+                    "stdin has no files to compare with earlier shares"
+                );
+                None
+            }
+        };
+        if options.skip_published.is_some() {
+            anyhow::ensure!(
+                options.store_dir.is_some() && options.history.is_some(),
+                "skipping published files needs a persistent store and a transfer history"
+            );
+            anyhow::ensure!(
+                !options.archive,
+                "an archive is a single blob, published files cannot be skipped"
+            );
+        }
         Ok(Self {
-            entry_type: detect_entry_type(path),
+            entry_type,
             wait_for_online: !matches!(
                 options.relay_mode,
                 crate::core::options::RelayModeOption::Disabled
             ),
-            blobs_data_dir: prepare_temp_directory()?,
+            blobs_data_dir: match &options.store_dir {
+                Some(store_dir) => Some(store_dir.clone()),
+                None if options.memory_store || fits_in_memory(source, options.symlinks) => None,
+                None => Some(prepare_temp_directory(options.temp_dir.as_deref())?),
+            },
+            persistent_store: options.store_dir.is_some(),
+            max_downloads: options.max_downloads,
+            expire: options.expire,
+            rate_limit: options.rate_limit,
+            allow: options.allow.clone(),
+            password: options.password.clone(),
+            code: options.code.clone(),
+            watch_paths,
+            keep_snapshots: options.keep_snapshots,
             ticket_type: options.ticket_type,
+            refresh_ticket: options.refresh_ticket,
+            dns_hint: options.dns_hint.clone(),
+            policy: ImportPolicy::from_options(options),
+            trace: options
+                .trace_path
+                .as_deref()
+                .map(TraceLog::create)
+                .transpose()?,
+            history: options.history.clone(),
         })
     }
 
-    fn build_request(&self, path: PathBuf, app_handle: AppHandle) -> ShareRequest {
+    fn build_request(&self, source: ShareSource, app_handle: AppHandle) -> ShareRequest {
         ShareRequest {
-            path,
+            source,
             entry_type: self.entry_type,
             app_handle,
+            policy: self.policy.clone(),
+            trace: self.trace.clone(),
+            history: self.history.clone(),
+            rate_limit: self.rate_limit,
+            allow: self.allow.iter().copied().collect(),
+            password: self.password.clone(),
+            code: self.code.clone(),
+            notify_updates: self.watch_paths.is_some(),
         }
     }
 }
 
 impl SharingSetup {
-    fn into_send_result(
+    fn into_share_handle(
         self,
-        entry_type: crate::core::types::EntryType,
-        ticket_type: AddrInfoOptions,
-    ) -> anyhow::Result<SendResult> {
+        plan: &SharePlan,
+        app_handle: AppHandle,
+        transfer_id: u64,
+    ) -> anyhow::Result<ShareHandle> {
         let Self {
             router,
+            updates_tx,
+            code_publisher,
             imported,
             blobs_data_dir,
             store,
             progress_handle,
             transfer_status_rx,
+            downloads_rx,
+            stats_rx,
         } = self;
-        let ImportedCollection { temp_tag, size, .. } = imported;
+        let ImportedCollection {
+            temp_tag,
+            size,
+            entries,
+            warnings,
+            ..
+        } = imported;
         let hash = temp_tag.hash();
 
-        let mut addr = router.endpoint().addr();
-        apply_options(&mut addr, ticket_type);
+        let endpoint_addr = router.endpoint().addr();
+        let dns_hint = plan
+            .dns_hint
+            .as_deref()
+            .map(|hint| DnsHint::for_endpoint(hint, &endpoint_addr))
+            .transpose()?;
+        let mut addr = endpoint_addr;
+        apply_options(&mut addr, plan.ticket_type);
 
         let ticket = BlobTicket::new(addr, hash, BlobFormat::HashSeq);
-
-        Ok(SendResult {
-            ticket,
+        let (ticket_tx, ticket_rx) = watch::channel(ticket);
+        let watch_handle = plan
+            .watch_paths
+            .clone()
+            .zip(updates_tx)
+            .map(|(paths, updates_tx)| {
+                spawn_watch_task(WatchContext {
+                    paths,
+                    store: Store::clone(&store),
+                    policy: plan.policy.clone(),
+                    code_publisher: code_publisher.clone(),
+                    ticket_tx: ticket_tx.clone(),
+                    app_handle: app_handle.clone(),
+                    keep_snapshots: plan.keep_snapshots,
+                    updates_tx,
+                })
+            })
+            .transpose()?;
+        let address_refresh_handle = plan.refresh_ticket.then(|| {
+            spawn_address_refresh_task(
+                router.endpoint().clone(),
+                plan.ticket_type,
+                ticket_tx,
+                app_handle,
+            )
+        });
+
+        Ok(ShareHandle {
+            transfer_id,
             hash,
             size,
-            entry_type,
+            entry_type: plan.entry_type,
             router,
             temp_tag,
             blobs_data_dir,
+            persistent_store: plan.persistent_store,
             _progress_handle: progress_handle,
             _store: store,
             transfer_status_rx,
+            downloads_rx,
+            stats_rx,
+            started_at: tokio::time::Instant::now(),
+            max_downloads: plan.max_downloads,
+            deadline: plan
+                .expire
+                .map(|expire| tokio::time::Instant::now() + expire),
+            expires_at: plan
+                .expire
+                .map(|expire| std::time::SystemTime::now() + expire),
+            dns_hint,
+            ticket_rx,
+            _address_refresh_handle: address_refresh_handle,
+            _watch_handle: watch_handle,
+            _code_publisher: code_publisher,
+            warnings,
+            entries,
         })
     }
 }
 
+/// 监听 endpoint 的地址更新，变化时重新生成 ticket 并通知订阅者与事件发射器。
+///
+/// 每隔 `ADDRESS_REFRESH_INTERVAL` 仍主动检查一次；地址通知断开后只依赖周期检查。
+/// 当前 ticket 从通道中读取，监听模式更新的集合 hash 因此会被保留。
+fn spawn_address_refresh_task(
+    endpoint: Endpoint,
+    ticket_type: AddrInfoOptions,
+    ticket_tx: watch::Sender<BlobTicket>,
+    app_handle: AppHandle,
+) -> AbortOnDropHandle<()> {
+    AbortOnDropHandle::new(tokio::spawn(async move {
+        let emitter = TransferEventEmitter::new(app_handle, Role::Sender);
+        let mut addr_watcher = endpoint.watch_addr();
+        let mut watching = true;
+        let mut interval = tokio::time::interval(ADDRESS_REFRESH_INTERVAL);
+        interval.tick().await;
+        loop {
+            select! {
+                updated = addr_watcher.updated(), if watching => {
+                    if updated.is_err() {
+                        tracing::debug!("address watcher disconnected, polling only");
+                        watching = false;
+                    }
+                }
+                _ = interval.tick() => {}
+            }
+            let current = ticket_tx.borrow().clone();
+            let Some(fresh) = refreshed_ticket(&current, endpoint.addr(), ticket_type) else {
+                continue;
+            };
+            info!(ticket = %fresh, "endpoint addresses changed, ticket refreshed");
+            emitter.emit_ticket_refreshed(fresh.to_string());
+            let _ = ticket_tx.send(fresh);
+        }
+    }))
+}
+
+/// 若 `addr` 经过 `ticket_type` 过滤后与当前 ticket 中的地址不同，返回新的 ticket。
+fn refreshed_ticket(
+    current: &BlobTicket,
+    mut addr: iroh::EndpointAddr,
+    ticket_type: AddrInfoOptions,
+) -> Option<BlobTicket> {
+    apply_options(&mut addr, ticket_type);
+    if &addr == current.addr() {
+        return None;
+    }
+    let hash_and_format = current.hash_and_format();
+    Some(BlobTicket::new(
+        addr,
+        hash_and_format.hash,
+        hash_and_format.format,
+    ))
+}
+
 /// 开始共享（发送）指定的 `path`（文件或目录）。
 ///
 /// - `path`：要分享的文件或目录路径。
 /// - `options`：发送配置（转发模式、ticket 类型等）。
 /// - `app_handle`：可选的事件发射器句柄，用于 UI/CLI 上报进度。
 ///
-/// 返回 `SendResult`，其中包含票据、hash、大小以及需要保持存活的 router/store 句柄。
+/// 返回分享的 [`ShareHandle`]，通过它获取 ticket 与统计，并在结束时调用 `stop()`。
 pub async fn send(
     path: PathBuf,
     options: SendOptions,
     app_handle: AppHandle,
-) -> anyhow::Result<SendResult> {
+) -> Result<ShareHandle, Error> {
+    send_paths(vec![path], options, app_handle).await
+}
+
+/// 与 [`send`] 相同，但把多个文件或目录合并为一个集合分享。
+///
+/// 每个路径的最后一个组件作为集合中的顶层名称；名称冲突时后出现的条目
+/// 会被重命名为 `name (1).ext` 的形式。
+pub async fn send_paths(
+    paths: Vec<PathBuf>,
+    options: SendOptions,
+    app_handle: AppHandle,
+) -> Result<ShareHandle, Error> {
+    if paths.is_empty() {
+        return Err(anyhow::anyhow!("no path to send").into());
+    }
+    send_source(ShareSource::Paths(paths), options, app_handle)
+        .await
+        .map_err(Error::from)
+}
+
+/// 只导入并计算 `paths` 的集合：返回集合 hash、各条目的 hash 与总大小，
+/// 不绑定 endpoint，也不对外分享。结果与使用相同 `options` 调用 [`send_paths`] 得到的 hash 一致。
+///
+/// 数据导入到一个临时存储中（文件尽可能以引用方式导入），返回前删除。
+pub async fn compute_collection(
+    paths: Vec<PathBuf>,
+    options: &SendOptions,
+) -> anyhow::Result<CollectionSummary> {
+    anyhow::ensure!(!paths.is_empty(), "no path to import");
+    let blobs_data_dir = prepare_temp_directory(options.temp_dir.as_deref())?;
+    let result = async {
+        for path in &paths {
+            validate_share_path(path, Some(&blobs_data_dir))?;
+        }
+        let store = BlobStore::load(Some(&blobs_data_dir)).await?;
+        let policy = ImportPolicy::from_options(options);
+        let imported = import(ShareSource::Paths(paths), &store, &policy, &None).await?;
+        anyhow::Ok(CollectionSummary {
+            hash: imported.temp_tag.hash(),
+            size: imported.size,
+            entries: imported.entries,
+            warnings: imported.warnings,
+        })
+    }
+    .await;
+    if let Err(error) = tokio::fs::remove_dir_all(&blobs_data_dir).await {
+        tracing::warn!(error = %error, "failed to clean temporary import store");
+    }
+    result
+}
+
+/// 将标准输入中的数据作为名为 `name` 的单个文件分享，无需先写入临时文件。
+pub async fn send_stdin(
+    name: String,
+    options: SendOptions,
+    app_handle: AppHandle,
+) -> Result<ShareHandle, Error> {
+    if name.is_empty() || name.contains('/') || name.contains('\\') {
+        return Err(anyhow::anyhow!("invalid name {name:?}, expected a plain file name").into());
+    }
+    send_source(ShareSource::Stdin { name }, options, app_handle)
+        .await
+        .map_err(Error::from)
+}
+
+async fn send_source(
+    source: ShareSource,
+    options: SendOptions,
+    app_handle: AppHandle,
+) -> anyhow::Result<ShareHandle> {
+    let transfer_id = next_transfer_id();
     info!(
-        path = %path.display(),
+        transfer_id,
+        source = ?source,
         relay_mode = ?options.relay_mode,
         ticket_type = ?options.ticket_type,
         "starting send"
     );
-    validate_share_path(&path)?;
+    let app_handle = app_handle
+        .map(|inner| QueuedEventEmitter::wrap(TransferIdEmitter::wrap(inner, transfer_id)));
 
-    let plan = SharePlan::new(&path, &options)?;
+    let plan = SharePlan::new(&source, &options)?;
+    if let ShareSource::Paths(paths) = &source {
+        for path in paths {
+            validate_share_path(path, plan.blobs_data_dir.as_deref())?;
+        }
+    }
     let endpoint = prepare_endpoint(&options).await?;
-    let share_request = plan.build_request(path, app_handle);
+    let share_request = plan.build_request(source, app_handle.clone());
 
     let setup = select! {
         x = setup_data_sharing(
@@ -286,7 +847,14 @@ pub async fn send(
         }
     };
 
-    let result = setup.into_send_result(plan.entry_type, plan.ticket_type)?;
+    let result = setup.into_share_handle(&plan, app_handle, transfer_id)?;
+    if let Some(store_dir) = plan
+        .blobs_data_dir
+        .as_deref()
+        .filter(|_| plan.persistent_store)
+    {
+        save_ticket(store_dir, &result.ticket().to_string())?;
+    }
     info!(
         hash = %result.hash,
         size = result.size,
@@ -296,6 +864,30 @@ pub async fn send(
     Ok(result)
 }
 
+/// 判断待分享的文件总大小是否低于内存存储阈值；标准输入的大小未知，不自动使用内存存储。
+fn fits_in_memory(source: &ShareSource, symlinks: SymlinkPolicy) -> bool {
+    let ShareSource::Paths(paths) = source else {
+        return false;
+    };
+    let mut total = 0u64;
+    for path in paths {
+        let walker = WalkDir::new(path).follow_links(symlinks == SymlinkPolicy::Follow);
+        for entry in walker {
+            let Ok(entry) = entry else {
+                return false;
+            };
+            if entry.file_type().is_file() {
+                let len = entry.metadata().map_or(u64::MAX, |metadata| metadata.len());
+                total = total.saturating_add(len);
+                if total > MEMORY_STORE_THRESHOLD {
+                    return false;
+                }
+            }
+        }
+    }
+    true
+}
+
 fn detect_entry_type(path: &Path) -> crate::core::types::EntryType {
     if path.is_file() {
         crate::core::types::EntryType::File
@@ -304,45 +896,360 @@ fn detect_entry_type(path: &Path) -> crate::core::types::EntryType {
     }
 }
 
-/// 将 `path`（文件或目录）导入到给定的 `Store`，并返回导入后的集合信息。
-async fn import(path: PathBuf, db: &Store) -> anyhow::Result<ImportedCollection> {
-    let parallelism = num_cpus::get();
-    let sources = collect_import_sources(path)?;
-    let imported = import_sources(db, sources, parallelism).await?;
-    build_collection_from_imports(db, imported).await
+/// 将 `source`（文件、目录或标准输入）导入到给定的 `Store`，并返回导入后的集合信息。
+///
+/// 导入文件时通过 `app_handle` 发出 `ImportProgress` 事件；标准输入的大小未知，不发出进度。
+pub(crate) async fn import(
+    source: ShareSource,
+    db: &Store,
+    policy: &ImportPolicy,
+    app_handle: &AppHandle,
+) -> anyhow::Result<ImportedCollection> {
+    // 标记条目告诉接收端导出时需要解压、解包或恢复元数据，它们不计入条目列表
+    let mut markers = Vec::new();
+    let (imported, warnings) = match source {
+        ShareSource::Paths(paths) if policy.archive => {
+            let (sources, warnings) = collect_all_import_sources(paths, policy)?;
+            let progress = ImportProgress::new(app_handle.clone(), total_source_size(&sources)?);
+            (
+                vec![import_archive(db, sources, policy.compression, &progress).await?],
+                warnings,
+            )
+        }
+        ShareSource::Paths(paths) => {
+            let (sources, warnings) = collect_all_import_sources(paths, policy)?;
+            if policy.preserve_metadata {
+                let encoded = metadata::collect(
+                    sources
+                        .iter()
+                        .map(|source| (source.name.as_str(), source.path.as_path())),
+                )?;
+                markers.push((METADATA_ENTRY.to_string(), encoded));
+            }
+            let progress = ImportProgress::new(app_handle.clone(), total_source_size(&sources)?);
+            let mut imported = import_sources(
+                db,
+                sources,
+                num_cpus::get(),
+                policy.import_mode.into(),
+                policy.compression,
+                &progress,
+            )
+            .await?;
+            let mut warnings = warnings;
+            if let Some((history, peer)) = &policy.skip_published {
+                let published = published_hashes(db, history, *peer).await?;
+                imported.retain(|blob| {
+                    let skip = published.contains(&blob.temp_tag.hash());
+                    if skip {
+                        warnings.push(TransferWarning::new(
+                            WarningCode::SkippedPublished,
+                            format!("{} was already sent to {}", blob.name, peer.fmt_short()),
+                        ));
+                    }
+                    !skip
+                });
+                anyhow::ensure!(
+                    !imported.is_empty(),
+                    "nothing new to share, every file was already sent to {peer}"
+                );
+            }
+            (imported, warnings)
+        }
+        ShareSource::Stdin { name } => (
+            vec![import_stdin(db, name, policy.compression).await?],
+            Vec::new(),
+        ),
+    };
+    if let Some(verification) = policy.verification {
+        verify_imports(db, &imported, verification).await?;
+    }
+    if let Some(compression) = policy.compression {
+        markers.push((compression.marker_name(), Bytes::new()));
+    }
+    if policy.archive {
+        markers.push((ARCHIVE_MARKER.to_string(), Bytes::new()));
+    }
+    let mut marker_blobs = Vec::new();
+    for (name, data) in markers {
+        let size = data.len() as u64;
+        marker_blobs.push(ImportedBlob {
+            name,
+            temp_tag: db.add_bytes(data).temp_tag().await?,
+            size,
+        });
+    }
+    let mut collection = build_collection_from_imports(db, imported, marker_blobs).await?;
+    collection.warnings = warnings;
+    Ok(collection)
 }
 
-fn collect_import_sources(path: PathBuf) -> anyhow::Result<Vec<ImportedSource>> {
+/// 之前完整发送给 `peer` 的分享中所有条目的 hash。
+///
+/// 已发送的集合从传输记录中查找，条目从存储中读取；存储中已没有的集合（例如换过存储目录）被忽略。
+async fn published_hashes(
+    db: &Store,
+    history: &Path,
+    peer: EndpointId,
+) -> anyhow::Result<HashSet<iroh_blobs::Hash>> {
+    let mut hashes = HashSet::new();
+    if !history.exists() {
+        return Ok(hashes);
+    }
+    let peer = peer.to_string();
+    let shared = history::read(history)?
+        .into_iter()
+        .filter(|record| {
+            record.role == Role::Sender.as_str()
+                && record.outcome == Outcome::Completed
+                && record.peer.as_deref() == Some(peer.as_str())
+        })
+        .filter_map(|record| record.hash.parse::<iroh_blobs::Hash>().ok())
+        .collect::<HashSet<_>>();
+    for hash in shared {
+        match Collection::load(hash, db).await {
+            Ok(collection) => hashes.extend(collection.iter().map(|(_, hash)| *hash)),
+            Err(error) => {
+                tracing::debug!(%hash, error = %error, "published collection is not in the store");
+            }
+        }
+    }
+    Ok(hashes)
+}
+
+/// 导入后抽查（或全部校验）存储中的数据，尽早发现源端磁盘上的静默损坏。
+async fn verify_imports(
+    db: &Store,
+    imported: &[ImportedBlob],
+    verification: ImportVerification,
+) -> anyhow::Result<()> {
+    let selected = match verification {
+        ImportVerification::All => imported.iter().collect::<Vec<_>>(),
+        ImportVerification::Sample => imported
+            .choose_multiple(&mut rand::rng(), VERIFY_IMPORT_SAMPLE_SIZE)
+            .collect(),
+    };
+    info!(count = selected.len(), "verifying imported data");
+    for blob in selected {
+        verify_blob(db, &blob.name, blob.temp_tag.hash()).await?;
+    }
+    Ok(())
+}
+
+/// 依次收集每个路径下的文件，并为重名的顶层条目分配不冲突的名称；
+/// 指定了 `root_name` 时，唯一路径的顶层条目改用该名称。
+fn collect_all_import_sources(
+    paths: Vec<PathBuf>,
+    policy: &ImportPolicy,
+) -> anyhow::Result<(Vec<ImportedSource>, Vec<TransferWarning>)> {
+    if let Some(name) = &policy.root_name {
+        anyhow::ensure!(paths.len() == 1, "a custom name needs exactly one path");
+        validate_path_component(name).with_context(|| format!("invalid share name {name:?}"))?;
+    }
+    let mut sources = Vec::new();
+    let mut warnings = Vec::new();
+    let mut used_roots = HashSet::new();
+    for path in paths {
+        let (mut path_sources, path_warnings) = collect_import_sources(path, policy)?;
+        warnings.extend(path_warnings);
+        let Some(root) = path_sources
+            .first()
+            .and_then(|source| source.name.split('/').next())
+            .map(str::to_string)
+        else {
+            continue;
+        };
+        let unique = policy
+            .root_name
+            .clone()
+            .unwrap_or_else(|| unique_root_name(&root, &mut used_roots));
+        if unique != root {
+            info!(from = %root, to = %unique, "renamed colliding entry");
+            for source in &mut path_sources {
+                source.name = format!("{unique}{}", &source.name[root.len()..]);
+            }
+        }
+        sources.extend(path_sources);
+    }
+    if let Some(max_size) = policy.max_size {
+        ensure_within_max_size(&sources, max_size)?;
+    }
+    Ok((sources, warnings))
+}
+
+/// 在导入之前按文件系统中的大小检查总量是否超过上限。
+fn ensure_within_max_size(sources: &[ImportedSource], max_size: u64) -> anyhow::Result<()> {
+    let total = total_source_size(sources)?;
+    anyhow::ensure!(
+        total <= max_size,
+        "the files to share add up to {}, more than the limit of {}",
+        indicatif::HumanBytes(total),
+        indicatif::HumanBytes(max_size)
+    );
+    Ok(())
+}
+
+/// 按文件系统中的大小累计 `sources` 的总字节数。
+fn total_source_size(sources: &[ImportedSource]) -> anyhow::Result<u64> {
+    let mut total = 0u64;
+    for source in sources {
+        let len = std::fs::metadata(&source.path)
+            .with_context(|| format!("failed to read metadata of {}", source.path.display()))?
+            .len();
+        total = total.saturating_add(len);
+    }
+    Ok(total)
+}
+
+/// 返回未被占用的顶层名称：`name`、`name (1)`、`name (2)`……扩展名保留在末尾。
+fn unique_root_name(root: &str, used: &mut HashSet<String>) -> String {
+    if used.insert(root.to_string()) {
+        return root.to_string();
+    }
+    let (stem, extension) = match root.rfind('.') {
+        Some(index) if index > 0 => root.split_at(index),
+        _ => (root, ""),
+    };
+    (1..)
+        .map(|n| format!("{stem} ({n}){extension}"))
+        .find(|candidate| used.insert(candidate.clone()))
+        .unwrap_or_else(|| root.to_string())
+}
+
+/// 遍历 `path` 收集要导入的文件。
+///
+/// 符号链接按 `symlinks` 策略处理：跳过时记录警告，跟随时遇到成环的链接跳过并记录警告，
+/// 设为 `Error` 时直接返回错误；特殊文件会被跳过并以警告的形式返回。
+/// 启用 `skip_unreadable` 时，无权限读取的子条目同样跳过并记录警告，否则直接返回错误。
+/// 命中 `exclude` 模式或（启用 `gitignore` 时）被忽略规则忽略的条目，
+/// 目录连同其内容被静默排除；未启用 `hidden` 时，隐藏条目同样被排除，只在日志中记录数量。
+fn collect_import_sources(
+    path: PathBuf,
+    policy: &ImportPolicy,
+) -> anyhow::Result<(Vec<ImportedSource>, Vec<TransferWarning>)> {
     let path = path.canonicalize()?;
     anyhow::ensure!(path.exists(), "path {} does not exist", path.display());
     let root = path.parent().context("context get parent")?;
 
-    WalkDir::new(path.clone())
+    let mut sources = Vec::new();
+    let mut warnings = Vec::new();
+    let mut ignores = policy.gitignore.then(|| IgnoreStack::new(path.clone()));
+    let mut hidden_skipped = 0usize;
+    let walker = WalkDir::new(path.clone())
+        .follow_links(policy.symlinks == SymlinkPolicy::Follow)
+        .same_file_system(policy.one_file_system)
         .into_iter()
-        .map(|entry| {
-            let entry = entry?;
-            if !entry.file_type().is_file() {
-                return Ok(None);
+        .filter_entry(|entry| {
+            if entry.depth() == 0 {
+                return true;
+            }
+            if !policy.hidden && is_hidden(entry.file_name()) {
+                hidden_skipped += 1;
+                return false;
             }
+            !(policy.is_excluded(entry.path(), root)
+                || ignores.as_mut().is_some_and(|ignores| {
+                    ignores.is_ignored(entry.path(), entry.file_type().is_dir())
+                }))
+        });
+    for entry in walker {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(error)
+                if policy.skip_unreadable && error.depth() > 0 && is_permission_denied(&error) =>
+            {
+                let detail = error
+                    .path()
+                    .map_or_else(|| error.to_string(), |path| path.display().to_string());
+                warnings.push(TransferWarning::new(WarningCode::UnreadableEntry, detail));
+                continue;
+            }
+            Err(error) if error.loop_ancestor().is_some() => {
+                let detail = error
+                    .path()
+                    .map_or_else(|| error.to_string(), |path| path.display().to_string());
+                warnings.push(TransferWarning::new(WarningCode::SymlinkLoop, detail));
+                continue;
+            }
+            Err(error) => return Err(error.into()),
+        };
+        let file_type = entry.file_type();
+        if file_type.is_dir() {
+            continue;
+        }
+        if file_type.is_symlink() && policy.symlinks == SymlinkPolicy::Error {
+            anyhow::bail!(
+                "{} is a symlink, use --symlinks skip or --symlinks follow",
+                entry.path().display()
+            );
+        }
+        if !file_type.is_file() {
+            let code = if file_type.is_symlink() {
+                WarningCode::SkippedSymlink
+            } else {
+                WarningCode::SkippedSpecialFile
+            };
+            warnings.push(TransferWarning::new(
+                code,
+                entry.path().display().to_string(),
+            ));
+            continue;
+        }
 
-            let path = entry.into_path();
-            let relative = path.strip_prefix(root)?;
-            let name = canonicalized_path_to_string(relative, true)?;
-            anyhow::Ok(Some(ImportedSource { name, path }))
-        })
-        .filter_map(Result::transpose)
-        .collect::<anyhow::Result<Vec<_>>>()
+        let path = entry.into_path();
+        if policy.skip_unreadable
+            && let Err(error) = std::fs::File::open(&path)
+            && error.kind() == std::io::ErrorKind::PermissionDenied
+        {
+            warnings.push(TransferWarning::new(
+                WarningCode::UnreadableEntry,
+                path.display().to_string(),
+            ));
+            continue;
+        }
+        let relative = path.strip_prefix(root)?;
+        let name = canonicalized_path_to_string(relative, true)?;
+        sources.push(ImportedSource { name, path });
+    }
+    if hidden_skipped > 0 {
+        info!(
+            count = hidden_skipped,
+            "skipped hidden entries, use --hidden to include them"
+        );
+    }
+    Ok((sources, warnings))
+}
+
+/// 以 `.` 开头的名称视为隐藏条目。
+fn is_hidden(name: &std::ffi::OsStr) -> bool {
+    name.as_encoded_bytes().starts_with(b".")
+}
+
+fn is_permission_denied(error: &walkdir::Error) -> bool {
+    error
+        .io_error()
+        .is_some_and(|error| error.kind() == std::io::ErrorKind::PermissionDenied)
 }
 
 async fn import_sources(
     db: &Store,
     sources: Vec<ImportedSource>,
     parallelism: usize,
+    mode: ImportMode,
+    compression: Option<Compression>,
+    progress: &ImportProgress,
 ) -> anyhow::Result<Vec<ImportedBlob>> {
     n0_future::stream::iter(sources)
         .map(|source| {
             let db = db.clone();
-            async move { import_source(&db, source).await }
+            async move {
+                match compression {
+                    Some(compression) => {
+                        import_compressed(&db, source, compression, progress).await
+                    }
+                    None => import_source(&db, source, mode, progress).await,
+                }
+            }
         })
         .buffered_unordered(parallelism)
         .collect::<Vec<_>>()
@@ -351,14 +1258,21 @@ async fn import_sources(
         .collect::<anyhow::Result<Vec<_>>>()
 }
 
-async fn import_source(db: &Store, source: ImportedSource) -> anyhow::Result<ImportedBlob> {
+/// 以 `mode` 指定的方式导入单个文件，按计算 outboard 的进度上报已哈希的字节数。
+async fn import_source(
+    db: &Store,
+    source: ImportedSource,
+    mode: ImportMode,
+    progress: &ImportProgress,
+) -> anyhow::Result<ImportedBlob> {
     let import = db.add_path_with_opts(AddPathOptions {
         path: source.path,
-        mode: ImportMode::TryReference,
+        mode,
         format: BlobFormat::Raw,
     });
     let mut stream = import.stream().await;
     let mut item_size = 0;
+    let mut hashed = 0;
     let temp_tag = loop {
         let item = stream
             .next()
@@ -371,11 +1285,15 @@ async fn import_source(db: &Store, source: ImportedSource) -> anyhow::Result<Imp
             }
             iroh_blobs::api::blobs::AddProgressItem::CopyProgress(_) => {}
             iroh_blobs::api::blobs::AddProgressItem::CopyDone => {}
-            iroh_blobs::api::blobs::AddProgressItem::OutboardProgress(_) => {}
+            iroh_blobs::api::blobs::AddProgressItem::OutboardProgress(offset) => {
+                progress.advance(offset.saturating_sub(hashed));
+                hashed = hashed.max(offset);
+            }
             iroh_blobs::api::blobs::AddProgressItem::Error(cause) => {
                 anyhow::bail!("error importing {}: {}", source.name, cause);
             }
             iroh_blobs::api::blobs::AddProgressItem::Done(tt) => {
+                progress.advance(item_size.saturating_sub(hashed));
                 break tt;
             }
         }
@@ -388,12 +1306,129 @@ async fn import_source(db: &Store, source: ImportedSource) -> anyhow::Result<Imp
     })
 }
 
+/// 以流的方式把标准输入导入存储，不在内存或磁盘上保留完整副本。
+async fn import_stdin(
+    db: &Store,
+    name: String,
+    compression: Option<Compression>,
+) -> anyhow::Result<ImportedBlob> {
+    let stdin = tokio::io::stdin();
+    match compression {
+        Some(compression) => import_stream(db, name, compression.compress(stdin), None).await,
+        None => import_stream(db, name, read_chunks(stdin), None).await,
+    }
+}
+
+/// 把所有文件打包为一个 tar blob 导入。
+///
+/// 归档条目以共同的顶层名称命名，多个顶层条目时命名为 `archive`。
+/// 进度按写入存储的归档字节数上报，不超过文件的总大小。
+async fn import_archive(
+    db: &Store,
+    sources: Vec<ImportedSource>,
+    compression: Option<Compression>,
+    progress: &ImportProgress,
+) -> anyhow::Result<ImportedBlob> {
+    let roots = sources
+        .iter()
+        .filter_map(|source| source.name.split('/').next())
+        .collect::<HashSet<_>>();
+    let name = match roots.iter().next() {
+        Some(root) if roots.len() == 1 => (*root).to_string(),
+        _ => "archive".to_string(),
+    };
+    let files = sources
+        .into_iter()
+        .map(|source| (source.name, source.path))
+        .collect();
+    let (reader, packer) = pack(files);
+    let imported = match compression {
+        Some(compression) => {
+            import_stream(db, name, compression.compress(reader), Some(progress)).await
+        }
+        None => import_stream(db, name, read_chunks(reader), Some(progress)).await,
+    };
+    packer.await??;
+    imported
+}
+
+/// 导入 `path` 压缩后的内容；压缩数据只存在于存储中，因此无法引用源文件。
+///
+/// 压缩后的字节数与源文件大小不对应，整个文件导入完成后才上报进度。
+async fn import_compressed(
+    db: &Store,
+    source: ImportedSource,
+    compression: Compression,
+    progress: &ImportProgress,
+) -> anyhow::Result<ImportedBlob> {
+    let file = tokio::fs::File::open(&source.path)
+        .await
+        .with_context(|| format!("failed to open {}", source.path.display()))?;
+    let len = file.metadata().await?.len();
+    let imported = import_stream(db, source.name, compression.compress(file), None).await?;
+    progress.advance(len);
+    Ok(imported)
+}
+
+async fn import_stream(
+    db: &Store,
+    name: String,
+    chunks: impl n0_future::Stream<Item = std::io::Result<Bytes>> + Send + Sync + 'static,
+    progress: Option<&ImportProgress>,
+) -> anyhow::Result<ImportedBlob> {
+    let mut stream = db.add_stream(chunks).await.stream().await;
+    let mut item_size = 0;
+    let temp_tag = loop {
+        let item = stream
+            .next()
+            .await
+            .context("import stream ended without a tag")?;
+        trace!("importing {name} {item:?}");
+        match item {
+            iroh_blobs::api::blobs::AddProgressItem::Size(size) => {
+                item_size = size;
+            }
+            iroh_blobs::api::blobs::AddProgressItem::CopyProgress(offset) => {
+                if let Some(progress) = progress {
+                    progress.advance(offset.saturating_sub(item_size));
+                }
+                item_size = offset;
+            }
+            iroh_blobs::api::blobs::AddProgressItem::CopyDone => {}
+            iroh_blobs::api::blobs::AddProgressItem::OutboardProgress(_) => {}
+            iroh_blobs::api::blobs::AddProgressItem::Error(cause) => {
+                anyhow::bail!("error importing {name}: {cause}");
+            }
+            iroh_blobs::api::blobs::AddProgressItem::Done(tt) => {
+                break tt;
+            }
+        }
+    };
+
+    Ok(ImportedBlob {
+        name,
+        temp_tag,
+        size: item_size,
+    })
+}
+
 async fn build_collection_from_imports(
     db: &Store,
     mut imported: Vec<ImportedBlob>,
+    markers: Vec<ImportedBlob>,
 ) -> anyhow::Result<ImportedCollection> {
     imported.sort_by(|a, b| a.name.cmp(&b.name));
     let size = imported.iter().map(|item| item.size).sum::<u64>();
+    let entries = imported
+        .iter()
+        .map(|item| CollectionEntry {
+            name: item.name.clone(),
+            hash: item.temp_tag.hash(),
+            size: item.size,
+        })
+        .collect();
+    imported.extend(markers);
+    imported.sort_by(|a, b| a.name.cmp(&b.name));
     let (collection, tags) = imported
         .into_iter()
         .map(|item| ((item.name, item.temp_tag.hash()), item.temp_tag))
@@ -403,7 +1438,9 @@ async fn build_collection_from_imports(
     Ok(ImportedCollection {
         temp_tag,
         size,
+        entries,
         _collection: collection,
+        warnings: Vec::new(),
     })
 }
 
@@ -452,20 +1489,94 @@ pub fn canonicalized_path_to_string(
 /// 该函数使用ProviderProgressTracker来管理多个并发传输的进度，并根据完成状态发射相应的事件。
 async fn show_provide_progress_with_provider_tracker(
     mut recv: mpsc::Receiver<iroh_blobs::provider::events::ProviderMessage>,
-    app_handle: AppHandle,
-    total_file_size: u64,
-    entry_type: crate::core::types::EntryType,
-    transfer_status_tx: watch::Sender<SenderTransferStatus>,
+    context: ProviderEventContext,
 ) -> anyhow::Result<()> {
-    let reporter = SenderProgressReporter::new(app_handle, entry_type, transfer_status_tx);
-    let request_task_limit = std::sync::Arc::new(Semaphore::new(PROVIDER_PROGRESS_TASK_LIMIT));
+    let ProviderEventContext {
+        app_handle,
+        total_file_size,
+        entry_type,
+        transfer_status_tx,
+        downloads_tx,
+        stats_tx,
+        trace,
+        history,
+        hash,
+        rate_limiter,
+        allow,
+        password_gate,
+    } = context;
+    let emitter = TransferEventEmitter::new(app_handle.clone(), Role::Sender);
+    let reporter = SenderProgressReporter::new(app_handle, entry_type, transfer_status_tx)
+        .with_downloads(downloads_tx)
+        .with_stats(stats_tx);
+    let request_task_limit = Arc::new(Semaphore::new(PROVIDER_PROGRESS_TASK_LIMIT));
+    // 已放行的连接及其对端，关闭时据此发出 `PeerDisconnected`
+    let mut peers = HashMap::new();
+    // 启用传输记录时，按连接累计已发送的字节数与请求结果
+    let tallies = history
+        .as_ref()
+        .map(|_| Arc::new(std::sync::Mutex::new(HashMap::<u64, ConnectionTally>::new())));
 
     while let Some(item) = recv.recv().await {
         match item {
-            iroh_blobs::provider::events::ProviderMessage::ClientConnectedNotify(_msg) => {}
-            iroh_blobs::provider::events::ProviderMessage::ConnectionClosed(_msg) => {}
+            iroh_blobs::provider::events::ProviderMessage::ClientConnectedNotify(msg) => {
+                if let Some(peer) = msg.endpoint_id {
+                    peers.insert(msg.connection_id, peer);
+                    reporter.on_peer_connected(peer).await;
+                    emitter.emit_peer_connected(peer, msg.connection_id);
+                }
+            }
+            iroh_blobs::provider::events::ProviderMessage::ClientConnected(msg) => {
+                let peer = msg.endpoint_id;
+                let rejection = if !is_peer_allowed(&allow, peer) {
+                    Some("endpoint not in allowlist")
+                } else if password_gate
+                    .as_ref()
+                    .is_some_and(|gate| peer.is_none_or(|peer| !gate.is_authorized(&peer)))
+                {
+                    Some("missing or wrong password")
+                } else {
+                    None
+                };
+                let verdict = match rejection {
+                    None => {
+                        if let Some(peer) = peer {
+                            peers.insert(msg.connection_id, peer);
+                            reporter.on_peer_connected(peer).await;
+                            emitter.emit_peer_connected(peer, msg.connection_id);
+                        }
+                        Ok(())
+                    }
+                    Some(reason) => {
+                        let peer =
+                            peer.map_or_else(|| "unknown peer".to_string(), |id| id.to_string());
+                        tracing::warn!(%peer, reason, "rejected connection");
+                        emitter.emit_failed(format!("rejected connection from {peer}: {reason}"));
+                        Err(AbortReason::Permission)
+                    }
+                };
+                msg.tx.send(verdict).await.ok();
+            }
+            iroh_blobs::provider::events::ProviderMessage::ConnectionClosed(msg) => {
+                let peer = peers.remove(&msg.connection_id);
+                if let Some(peer) = peer {
+                    emitter.emit_peer_disconnected(peer, msg.connection_id);
+                }
+                if let (Some(path), Some(tallies)) = (&history, &tallies)
+                    && let Some(tally) = tallies
+                        .lock()
+                        .unwrap_or_else(|error| error.into_inner())
+                        .remove(&msg.connection_id)
+                {
+                    history::append(path, &tally.record(hash, peer));
+                }
+                reporter.on_connection_closed(msg.connection_id).await;
+            }
             iroh_blobs::provider::events::ProviderMessage::GetRequestReceivedNotify(msg) => {
                 let transfer_id = TransferId::new(msg.connection_id, msg.request_id);
+                if let Some(trace) = &trace {
+                    trace.record(TraceKind::Requested, msg.connection_id, msg.request_id, 0);
+                }
                 reporter
                     .on_request_received(transfer_id, total_file_size)
                     .await;
@@ -473,13 +1584,44 @@ async fn show_provide_progress_with_provider_tracker(
                 let reporter_clone = reporter.clone();
                 let mut rx = msg.rx;
                 let task_limit = request_task_limit.clone();
+                let trace = trace.clone();
+                let tallies = tallies.clone();
                 tokio::spawn(async move {
                     let Ok(_permit) = task_limit.acquire_owned().await else {
                         return;
                     };
                     while let Ok(Some(update)) = rx.recv().await {
+                        if let (Some(trace), RequestUpdate::Progress(progress)) = (&trace, &update)
+                        {
+                            trace.record(
+                                TraceKind::Served,
+                                transfer_id.connection,
+                                transfer_id.request,
+                                progress.end_offset,
+                            );
+                        }
+                        if let Some(tallies) = &tallies {
+                            tallies
+                                .lock()
+                                .unwrap_or_else(|error| error.into_inner())
+                                .entry(transfer_id.connection)
+                                .or_default()
+                                .on_request_update(&update);
+                        }
                         reporter_clone.on_request_update(transfer_id, update).await;
                     }
+                    if let Some(trace) = &trace {
+                        trace.flush();
+                    }
+                });
+            }
+            iroh_blobs::provider::events::ProviderMessage::Throttle(msg) => {
+                let rate_limiter = rate_limiter.clone();
+                tokio::spawn(async move {
+                    if let Some(rate_limiter) = rate_limiter {
+                        rate_limiter.acquire(msg.size).await;
+                    }
+                    msg.tx.send(Ok(())).await.ok();
                 });
             }
             _ => {
@@ -494,12 +1636,17 @@ async fn show_provide_progress_with_provider_tracker(
 #[cfg(test)]
 mod tests {
     use super::{
-        canonicalized_path_to_string, collect_import_sources, detect_entry_type,
+        ImportPolicy, MEMORY_STORE_THRESHOLD, ShareSource, canonicalized_path_to_string,
+        collect_all_import_sources, collect_import_sources, compute_collection, detect_entry_type,
+        fits_in_memory, import, is_peer_allowed, refreshed_ticket, unique_root_name,
         validate_share_path,
     };
-    use crate::core::options::{AddrInfoOptions, apply_options};
+    use crate::core::events::{Role, WarningCode};
+    use crate::core::history::{self, Outcome, TransferRecord};
+    use crate::core::options::{AddrInfoOptions, SendOptions, SymlinkPolicy, apply_options};
     use crate::core::types::EntryType;
     use iroh::{EndpointAddr, RelayUrl, SecretKey, TransportAddr};
+    use iroh_blobs::{BlobFormat, Hash, ticket::BlobTicket};
     use std::path::Path;
     use std::str::FromStr;
 
@@ -545,6 +1692,37 @@ mod tests {
         assert_eq!(full.addrs.len(), base.addrs.len());
     }
 
+    #[test]
+    fn refreshed_ticket_only_changes_when_addresses_change() {
+        let base = sample_addr();
+        let ticket = BlobTicket::new(base.clone(), Hash::new(b"demo"), BlobFormat::HashSeq);
+
+        assert!(
+            refreshed_ticket(&ticket, base.clone(), AddrInfoOptions::RelayAndAddresses).is_none()
+        );
+
+        let moved = base.with_ip_addr("127.0.0.1:8888".parse().expect("valid socket addr"));
+        let fresh = refreshed_ticket(&ticket, moved.clone(), AddrInfoOptions::RelayAndAddresses)
+            .expect("changed addresses should refresh the ticket");
+        assert_eq!(fresh.addr(), &moved);
+        assert_eq!(fresh.hash(), ticket.hash());
+    }
+
+    #[test]
+    fn is_peer_allowed_checks_allowlist() {
+        let allowed = SecretKey::generate(&mut rand::rng()).public();
+        let other = SecretKey::generate(&mut rand::rng()).public();
+
+        let open = std::collections::HashSet::new();
+        assert!(is_peer_allowed(&open, Some(other)));
+        assert!(is_peer_allowed(&open, None));
+
+        let allow = std::collections::HashSet::from([allowed]);
+        assert!(is_peer_allowed(&allow, Some(allowed)));
+        assert!(!is_peer_allowed(&allow, Some(other)));
+        assert!(!is_peer_allowed(&allow, None));
+    }
+
     #[test]
     fn disabled_relay_skips_online_wait() {
         let wait_for_online = !matches!(
@@ -585,6 +1763,122 @@ mod tests {
         assert_eq!(detect_entry_type(temp_dir.path()), EntryType::Directory);
     }
 
+    #[test]
+    fn fits_in_memory_respects_threshold_and_stdin() {
+        let temp_dir = tempfile::tempdir().expect("temp dir");
+        let small = temp_dir.path().join("small.txt");
+        std::fs::write(&small, b"demo").expect("write small file");
+        let large = temp_dir.path().join("large.bin");
+        std::fs::File::create(&large)
+            .and_then(|file| file.set_len(MEMORY_STORE_THRESHOLD + 1))
+            .expect("create large file");
+
+        let source = |path: &Path| ShareSource::Paths(vec![path.to_path_buf()]);
+        assert!(fits_in_memory(&source(&small), SymlinkPolicy::Skip));
+        assert!(!fits_in_memory(&source(&large), SymlinkPolicy::Skip));
+        assert!(!fits_in_memory(
+            &source(temp_dir.path()),
+            SymlinkPolicy::Skip
+        ));
+        let stdin = ShareSource::Stdin {
+            name: "stdin".to_string(),
+        };
+        assert!(!fits_in_memory(&stdin, SymlinkPolicy::Skip));
+    }
+
+    #[tokio::test]
+    async fn skip_published_leaves_out_files_already_sent_to_the_peer() {
+        let temp_dir = tempfile::tempdir().expect("temp dir");
+        let root = temp_dir.path().join("docs");
+        std::fs::create_dir_all(&root).expect("create dir");
+        std::fs::write(root.join("a.txt"), b"alpha").expect("write a");
+        std::fs::write(root.join("b.txt"), b"bravo").expect("write b");
+        let history_path = temp_dir.path().join("transfers.log");
+        let peer = SecretKey::generate(&mut rand::rng()).public();
+        let other = SecretKey::generate(&mut rand::rng()).public();
+        let db = iroh_blobs::store::mem::MemStore::new();
+        let source = || ShareSource::Paths(vec![root.clone()]);
+
+        let first = import(
+            source(),
+            &db,
+            &ImportPolicy::from_options(&SendOptions::default()),
+            &None,
+        )
+        .await
+        .expect("import first share");
+        history::append(
+            &history_path,
+            &TransferRecord::new(
+                Role::Sender,
+                first.temp_tag.hash(),
+                Some(peer),
+                10,
+                Outcome::Completed,
+            ),
+        );
+        std::fs::write(root.join("b.txt"), b"bravo v2").expect("change b");
+        std::fs::write(root.join("c.txt"), b"charlie").expect("write c");
+
+        let policy = |peer| {
+            ImportPolicy::from_options(&SendOptions {
+                skip_published: Some(peer),
+                history: Some(history_path.clone()),
+                ..Default::default()
+            })
+        };
+        let second = import(source(), &db, &policy(peer), &None)
+            .await
+            .expect("import second share");
+        let names = second
+            .entries
+            .iter()
+            .map(|entry| entry.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["docs/b.txt", "docs/c.txt"]);
+        assert_eq!(second.warnings.len(), 1);
+        assert_eq!(second.warnings[0].code, WarningCode::SkippedPublished);
+        assert!(second.warnings[0].detail.contains("docs/a.txt"));
+
+        let fresh = import(source(), &db, &policy(other), &None)
+            .await
+            .expect("import share for another peer");
+        assert_eq!(fresh.entries.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn compute_collection_lists_entries_and_cleans_up() {
+        let temp_dir = tempfile::tempdir().expect("temp dir");
+        let root = temp_dir.path().join("docs");
+        std::fs::create_dir_all(&root).expect("create dir");
+        std::fs::write(root.join("a.txt"), b"alpha").expect("write a");
+        std::fs::write(root.join("b.txt"), b"bravo!").expect("write b");
+        let store_parent = temp_dir.path().join("stores");
+        std::fs::create_dir_all(&store_parent).expect("create store parent");
+        let options = SendOptions {
+            temp_dir: Some(store_parent.clone()),
+            ..Default::default()
+        };
+
+        let summary = compute_collection(vec![root], &options)
+            .await
+            .expect("compute collection");
+        let names = summary
+            .entries
+            .iter()
+            .map(|entry| entry.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["docs/a.txt", "docs/b.txt"]);
+        assert_eq!(summary.entries[0].hash, Hash::new(b"alpha"));
+        assert_eq!(summary.size, 11);
+        assert_eq!(
+            std::fs::read_dir(&store_parent)
+                .expect("read stores")
+                .count(),
+            0
+        );
+    }
+
     #[test]
     fn collect_import_sources_returns_relative_sorted_names_after_sorting() {
         let temp_dir = tempfile::tempdir().expect("temp dir");
@@ -594,8 +1888,10 @@ mod tests {
         std::fs::write(root.join("alpha.txt"), b"a").expect("write alpha");
         std::fs::write(nested.join("beta.txt"), b"b").expect("write beta");
 
-        let mut names = collect_import_sources(root)
-            .expect("sources")
+        let (sources, warnings) =
+            collect_import_sources(root, &ImportPolicy::default()).expect("sources");
+        assert!(warnings.is_empty());
+        let mut names = sources
             .into_iter()
             .map(|source| source.name)
             .collect::<Vec<_>>();
@@ -605,22 +1901,237 @@ mod tests {
     }
 
     #[test]
-    fn validate_share_path_rejects_current_directory_aliases() {
-        let dot_err = validate_share_path(Path::new("."))
-            .expect_err("`.` should be treated as current directory");
-        assert!(dot_err.to_string().contains("current directory"));
+    fn collect_all_import_sources_renames_colliding_roots() {
+        let temp_dir = tempfile::tempdir().expect("temp dir");
+        let first = temp_dir.path().join("a");
+        let second = temp_dir.path().join("b");
+        std::fs::create_dir_all(first.join("photos")).expect("create first");
+        std::fs::create_dir_all(&second).expect("create second");
+        std::fs::write(first.join("notes.md"), b"a").expect("write first notes");
+        std::fs::write(first.join("photos").join("cat.jpg"), b"c").expect("write cat");
+        std::fs::write(second.join("notes.md"), b"b").expect("write second notes");
+
+        let (sources, _) = collect_all_import_sources(
+            vec![
+                first.join("notes.md"),
+                first.join("photos"),
+                second.join("notes.md"),
+            ],
+            &ImportPolicy::default(),
+        )
+        .expect("sources");
+        let names = sources
+            .into_iter()
+            .map(|source| source.name)
+            .collect::<Vec<_>>();
+
+        assert_eq!(names, vec!["notes.md", "photos/cat.jpg", "notes (1).md"]);
+    }
+
+    #[test]
+    fn collect_all_import_sources_applies_custom_root_name() {
+        let temp_dir = tempfile::tempdir().expect("temp dir");
+        let out = temp_dir.path().join("build-1234").join("out");
+        std::fs::create_dir_all(out.join("bin")).expect("create out");
+        std::fs::write(out.join("bin").join("app"), b"app").expect("write app");
+        let policy = ImportPolicy {
+            root_name: Some("release-v2.1".to_string()),
+            ..ImportPolicy::default()
+        };
+
+        let (sources, _) = collect_all_import_sources(vec![out.clone()], &policy).expect("sources");
+        assert_eq!(sources[0].name, "release-v2.1/bin/app");
+
+        assert!(collect_all_import_sources(vec![out.clone(), out.join("bin")], &policy).is_err());
+        let nested = ImportPolicy {
+            root_name: Some("a/b".to_string()),
+            ..ImportPolicy::default()
+        };
+        assert!(collect_all_import_sources(vec![out], &nested).is_err());
+    }
 
-        let dot_slash_err = validate_share_path(Path::new("./"))
-            .expect_err("`./` should be treated as current directory");
-        assert!(dot_slash_err.to_string().contains("current directory"));
+    #[test]
+    fn collect_all_import_sources_enforces_max_size() {
+        let temp_dir = tempfile::tempdir().expect("temp dir");
+        let root = temp_dir.path().join("data");
+        std::fs::create_dir_all(&root).expect("create root");
+        std::fs::write(root.join("a.bin"), [0u8; 600]).expect("write a");
+        std::fs::write(root.join("b.bin"), [0u8; 600]).expect("write b");
+
+        let within = ImportPolicy {
+            max_size: Some(1200),
+            ..ImportPolicy::default()
+        };
+        collect_all_import_sources(vec![root.clone()], &within).expect("exactly at the limit");
+
+        let over = ImportPolicy {
+            max_size: Some(1024),
+            ..ImportPolicy::default()
+        };
+        let error = collect_all_import_sources(vec![root], &over).expect_err("over the limit");
+        assert!(error.to_string().contains("more than the limit"));
     }
 
     #[test]
-    fn validate_share_path_rejects_current_directory_absolute_path() {
-        let cwd = std::env::current_dir().expect("current dir");
-        let err =
-            validate_share_path(&cwd).expect_err("absolute current directory should be rejected");
-        assert!(err.to_string().contains("current directory"));
+    fn unique_root_name_keeps_extension_and_handles_dotfiles() {
+        let mut used = std::collections::HashSet::new();
+        assert_eq!(unique_root_name("report.pdf", &mut used), "report.pdf");
+        assert_eq!(unique_root_name("report.pdf", &mut used), "report (1).pdf");
+        assert_eq!(unique_root_name("report.pdf", &mut used), "report (2).pdf");
+        assert_eq!(unique_root_name(".env", &mut used), ".env");
+        assert_eq!(unique_root_name(".env", &mut used), ".env (1)");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn collect_import_sources_warns_about_skipped_symlinks() {
+        let temp_dir = tempfile::tempdir().expect("temp dir");
+        let root = temp_dir.path().join("data");
+        std::fs::create_dir_all(&root).expect("create dirs");
+        std::fs::write(root.join("alpha.txt"), b"a").expect("write alpha");
+        std::os::unix::fs::symlink(root.join("alpha.txt"), root.join("link.txt"))
+            .expect("create symlink");
+
+        let (sources, warnings) =
+            collect_import_sources(root, &ImportPolicy::default()).expect("sources");
+
+        assert_eq!(sources.len(), 1);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].code, WarningCode::SkippedSymlink);
+        assert!(warnings[0].detail.ends_with("link.txt"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn collect_import_sources_applies_symlink_policy() {
+        let temp_dir = tempfile::tempdir().expect("temp dir");
+        let root = temp_dir.path().join("data");
+        let nested = root.join("nested");
+        std::fs::create_dir_all(&nested).expect("create dirs");
+        std::fs::write(nested.join("alpha.txt"), b"a").expect("write alpha");
+        std::os::unix::fs::symlink(&root, nested.join("loop")).expect("create loop symlink");
+
+        let follow = ImportPolicy {
+            symlinks: SymlinkPolicy::Follow,
+            ..Default::default()
+        };
+        let (sources, warnings) =
+            collect_import_sources(root.clone(), &follow).expect("loops are skipped");
+        assert_eq!(sources.len(), 1);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].code, WarningCode::SymlinkLoop);
+
+        let error = ImportPolicy {
+            symlinks: SymlinkPolicy::Error,
+            ..Default::default()
+        };
+        let err = collect_import_sources(root, &error).expect_err("symlink should fail");
+        assert!(err.to_string().contains("is a symlink"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn collect_import_sources_skips_unreadable_files_only_when_asked() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = tempfile::tempdir().expect("temp dir");
+        let root = temp_dir.path().join("data");
+        std::fs::create_dir_all(&root).expect("create dirs");
+        std::fs::write(root.join("alpha.txt"), b"a").expect("write alpha");
+        let secret = root.join("secret.txt");
+        std::fs::write(&secret, b"s").expect("write secret");
+        std::fs::set_permissions(&secret, std::fs::Permissions::from_mode(0o000))
+            .expect("chmod secret");
+        if std::fs::File::open(&secret).is_ok() {
+            // running as root, permissions are not enforced
+            return;
+        }
+
+        let skipping = ImportPolicy {
+            skip_unreadable: true,
+            ..Default::default()
+        };
+        let (sources, warnings) = collect_import_sources(root.clone(), &skipping)
+            .expect("unreadable file should be skipped");
+        assert_eq!(sources.len(), 1);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].code, WarningCode::UnreadableEntry);
+
+        let (sources, warnings) =
+            collect_import_sources(root, &ImportPolicy::default()).expect("sources");
+        assert_eq!(sources.len(), 2);
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn collect_import_sources_applies_rsync_style_excludes() {
+        let temp_dir = tempfile::tempdir().expect("temp dir");
+        let root = temp_dir.path().join("data");
+        std::fs::create_dir_all(root.join("target").join("debug")).expect("create dirs");
+        std::fs::create_dir_all(root.join("src")).expect("create dirs");
+        std::fs::write(root.join("src").join("main.rs"), b"fn main() {}").expect("write main");
+        std::fs::write(root.join("src").join("main.rs.bak"), b"old").expect("write backup");
+        std::fs::write(root.join("target").join("debug").join("app"), b"bin").expect("write bin");
+
+        let policy = ImportPolicy {
+            exclude: vec!["target".to_string(), "*.bak".to_string()],
+            ..Default::default()
+        };
+        let (sources, _warnings) = collect_import_sources(root, &policy).expect("sources");
+
+        let names = sources
+            .into_iter()
+            .map(|source| source.name)
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["data/src/main.rs"]);
+    }
+
+    #[test]
+    fn collect_import_sources_skips_hidden_entries_unless_asked() {
+        let temp_dir = tempfile::tempdir().expect("temp dir");
+        let root = temp_dir.path().join(".config");
+        std::fs::create_dir_all(root.join(".git")).expect("create git dir");
+        std::fs::write(root.join(".git").join("HEAD"), b"ref").expect("write head");
+        std::fs::write(root.join(".env"), b"SECRET=1").expect("write env");
+        std::fs::write(root.join("app.toml"), b"x = 1").expect("write config");
+
+        let (sources, _) =
+            collect_import_sources(root.clone(), &ImportPolicy::default()).expect("sources");
+        let names = sources
+            .into_iter()
+            .map(|source| source.name)
+            .collect::<Vec<_>>();
+        // 直接指定的隐藏目录本身仍然被分享
+        assert_eq!(names, vec![".config/app.toml"]);
+
+        let policy = ImportPolicy {
+            hidden: true,
+            ..Default::default()
+        };
+        let (sources, _) = collect_import_sources(root, &policy).expect("sources");
+        assert_eq!(sources.len(), 3);
+    }
+
+    #[test]
+    fn validate_share_path_accepts_current_directory() {
+        let store = tempfile::tempdir().expect("temp dir");
+        let store_dir = store.path().join(".sendmer-send-demo");
+        validate_share_path(Path::new("."), Some(&store_dir))
+            .expect("current directory should be accepted");
+        validate_share_path(Path::new("./"), None).expect("no store dir to check");
+    }
+
+    #[test]
+    fn validate_share_path_rejects_store_inside_shared_path() {
+        let temp_dir = tempfile::tempdir().expect("temp dir");
+        let store_dir = temp_dir.path().join("share").join(".sendmer-send-demo");
+        std::fs::create_dir_all(temp_dir.path().join("share")).expect("create share dir");
+
+        let err = validate_share_path(&temp_dir.path().join("share"), Some(&store_dir))
+            .expect_err("store inside the shared path should be rejected");
+        assert!(err.to_string().contains("inside the shared path"));
+        let relative = temp_dir.path().join("share").join("..");
+        assert!(validate_share_path(&relative, Some(&store_dir)).is_err());
     }
 
     #[test]
@@ -628,6 +2139,7 @@ mod tests {
         let temp_dir = tempfile::tempdir().expect("temp dir");
         let nested = temp_dir.path().join("nested").join("share");
         std::fs::create_dir_all(&nested).expect("create nested dir");
-        validate_share_path(&nested).expect("nested path should be accepted");
+        let store_dir = temp_dir.path().join(".sendmer-send-demo");
+        validate_share_path(&nested, Some(&store_dir)).expect("nested path should be accepted");
     }
 }
@@ -0,0 +1,329 @@
+//! 常驻分享模式：一个长期运行的 endpoint 与 blob 存储同时托管多个分享。
+//!
+//! 每个分享都是存储中的一个集合，拥有各自的名称和 ticket，并以
+//! `sendmer/share/<name>` 的持久 tag 保存，重启后重新导入相同内容时无需再次写入数据。
+//! 分享可以在运行期间增删；删除分享只移除 tag 和分享记录，
+//! 已发出的 ticket 在数据被存储回收之前仍可能可用。
+//!
+//! 未指定存储目录时使用临时存储，结束或句柄被丢弃时删除。库用户可以使用
+//! [`Sharer`]，按路径逐个新增分享，复用同一个 endpoint 与中继连接。
+
+use crate::core::events::TransferWarning;
+use crate::core::options::{AddrInfoOptions, RelayModeOption, SendOptions, apply_options};
+use crate::core::sender::{
+    ImportPolicy, ShareSource, import, prepare_endpoint, prepare_temp_directory,
+    validate_share_path, wait_until_endpoint_is_online,
+};
+use crate::core::storage::BlobStore;
+use anyhow::Context;
+use iroh::EndpointId;
+use iroh::protocol::Router;
+use iroh_blobs::{BlobFormat, BlobsProtocol, Hash, HashAndFormat, ticket::BlobTicket};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use tokio::sync::Mutex;
+use tracing::info;
+
+const SHARE_TAG_PREFIX: &str = "sendmer/share/";
+
+/// 常驻模式中的一个分享。
+#[derive(Debug, Clone)]
+pub struct ServedShare {
+    pub name: String,
+    pub hash: Hash,
+    pub size: u64,
+    pub ticket: BlobTicket,
+    /// 导入时被跳过的条目等非致命问题。
+    pub warnings: Vec<TransferWarning>,
+}
+
+/// 常驻分享的句柄；持有 endpoint、router 与 blob 存储，丢弃前应调用 [`ServeHandle::shutdown`]。
+///
+/// 未调用 `shutdown` 就被丢弃时，临时存储目录同样会被删除。
+pub struct ServeHandle {
+    router: Router,
+    store: BlobStore,
+    store_dir: PathBuf,
+    /// 存储目录是否为临时目录，是则在结束时删除
+    temporary: bool,
+    ticket_type: AddrInfoOptions,
+    policy: ImportPolicy,
+    shares: Mutex<Shares>,
+}
+
+#[derive(Default)]
+struct Shares {
+    served: BTreeMap<String, ServedShare>,
+    /// 正在导入的分享名称；导入期间不持有锁，用它防止同名分享被并发添加
+    importing: BTreeSet<String>,
+}
+
+/// 在库 API 中复用同一个 endpoint 分享多个路径。
+///
+/// 每次调用 [`Sharer::add_share`] 导入一个路径并得到它的 ticket，而不必为每个分享
+/// 重新绑定 endpoint、建立中继连接。分享以路径的最后一个组件命名。
+pub struct Sharer {
+    handle: ServeHandle,
+}
+
+impl Sharer {
+    /// 绑定 endpoint；`options` 的含义与 [`serve`] 相同，未设置 `store_dir` 时使用临时存储。
+    pub async fn new(options: SendOptions) -> anyhow::Result<Self> {
+        Ok(Self {
+            handle: serve(options).await?,
+        })
+    }
+
+    pub fn endpoint_id(&self) -> EndpointId {
+        self.handle.endpoint_id()
+    }
+
+    /// 导入 `path` 并新增一个分享；同名分享已存在时返回错误。
+    pub async fn add_share(&self, path: impl Into<PathBuf>) -> anyhow::Result<ServedShare> {
+        self.handle.add_share(None, vec![path.into()]).await
+    }
+
+    /// 移除名为 `name` 的分享；不存在时返回 `None`。
+    pub async fn remove_share(&self, name: &str) -> anyhow::Result<Option<ServedShare>> {
+        self.handle.remove_share(name).await
+    }
+
+    /// 当前的所有分享，按名称排序。
+    pub async fn shares(&self) -> Vec<ServedShare> {
+        self.handle.shares().await
+    }
+
+    /// 停止 endpoint 并删除临时存储。
+    pub async fn shutdown(self) -> anyhow::Result<()> {
+        self.handle.shutdown().await
+    }
+}
+
+/// 启动常驻分享：绑定 endpoint 并打开 blob 存储，初始时不含任何分享。
+///
+/// 设置了 `options.store_dir` 时使用其中的持久化存储，节点密钥同样保存在其中，
+/// 重启后 endpoint ID 不变；否则在 `options.temp_dir`（默认系统临时目录）中创建临时存储，
+/// 并在 [`ServeHandle::shutdown`] 时删除。
+pub async fn serve(options: SendOptions) -> anyhow::Result<ServeHandle> {
+    let (store_dir, temporary) = match options.store_dir.clone() {
+        Some(store_dir) => (store_dir, false),
+        None => (prepare_temp_directory(options.temp_dir.as_deref())?, true),
+    };
+    info!(store_dir = %store_dir.display(), relay_mode = ?options.relay_mode, "starting serve");
+    let endpoint = prepare_endpoint(&options).await?;
+    let store = BlobStore::load(Some(&store_dir)).await?;
+    let blobs = BlobsProtocol::new(&store, None);
+    let router = Router::builder(endpoint)
+        .accept(iroh_blobs::protocol::ALPN, blobs)
+        .spawn();
+    wait_until_endpoint_is_online(
+        router.endpoint(),
+        !matches!(options.relay_mode, RelayModeOption::Disabled),
+    )
+    .await?;
+    Ok(ServeHandle {
+        router,
+        store,
+        store_dir,
+        temporary,
+        ticket_type: options.ticket_type,
+        policy: ImportPolicy::from_options(&options),
+        shares: Mutex::default(),
+    })
+}
+
+impl ServeHandle {
+    pub fn endpoint_id(&self) -> EndpointId {
+        self.router.endpoint().id()
+    }
+
+    /// 导入 `paths` 并以 `name` 新增一个分享；未指定名称时使用唯一路径的最后一个组件。
+    pub async fn add_share(
+        &self,
+        name: Option<String>,
+        paths: Vec<PathBuf>,
+    ) -> anyhow::Result<ServedShare> {
+        anyhow::ensure!(!paths.is_empty(), "no path to share");
+        for path in &paths {
+            validate_share_path(path, Some(&self.store_dir))?;
+        }
+        let name = match name {
+            Some(name) => name,
+            None => default_share_name(&paths)?,
+        };
+        anyhow::ensure!(!name.is_empty(), "share name must not be empty");
+
+        {
+            let mut shares = self.shares.lock().await;
+            anyhow::ensure!(
+                !shares.served.contains_key(&name) && !shares.importing.contains(&name),
+                "a share named {name:?} already exists"
+            );
+            shares.importing.insert(name.clone());
+        }
+        let imported = self.import_share(&name, paths).await;
+        let mut shares = self.shares.lock().await;
+        shares.importing.remove(&name);
+        let share = imported?;
+        info!(name = %name, hash = %share.hash, size = share.size, "share added");
+        shares.served.insert(name, share.clone());
+        drop(shares);
+        Ok(share)
+    }
+
+    async fn import_share(&self, name: &str, paths: Vec<PathBuf>) -> anyhow::Result<ServedShare> {
+        let imported = import(ShareSource::Paths(paths), &self.store, &self.policy, &None).await?;
+        let hash = imported.temp_tag.hash();
+        // 持久 tag 保证数据在临时 tag 释放后依然保留
+        self.store
+            .tags()
+            .set(share_tag(name), HashAndFormat::hash_seq(hash))
+            .await?;
+        Ok(ServedShare {
+            name: name.to_string(),
+            hash,
+            size: imported.size,
+            ticket: self.ticket(hash),
+            warnings: imported.warnings,
+        })
+    }
+
+    /// 移除名为 `name` 的分享；不存在时返回 `None`。
+    pub async fn remove_share(&self, name: &str) -> anyhow::Result<Option<ServedShare>> {
+        let Some(share) = self.shares.lock().await.served.remove(name) else {
+            return Ok(None);
+        };
+        self.store.tags().delete(share_tag(name)).await?;
+        info!(name = %name, hash = %share.hash, "share removed");
+        Ok(Some(share))
+    }
+
+    /// 当前托管的所有分享，按名称排序。
+    pub async fn shares(&self) -> Vec<ServedShare> {
+        self.shares.lock().await.served.values().cloned().collect()
+    }
+
+    fn ticket(&self, hash: Hash) -> BlobTicket {
+        let mut addr = self.router.endpoint().addr();
+        apply_options(&mut addr, self.ticket_type);
+        BlobTicket::new(addr, hash, BlobFormat::HashSeq)
+    }
+
+    /// 停止 endpoint；持久化存储与其中的 tag 保留到下次启动，临时存储被删除。
+    pub async fn shutdown(mut self) -> anyhow::Result<()> {
+        tokio::time::timeout(std::time::Duration::from_secs(2), self.router.shutdown()).await??;
+        if self.temporary {
+            // 已经清理过，drop 时不再重复
+            self.temporary = false;
+            if let Err(error) = tokio::fs::remove_dir_all(&self.store_dir).await
+                && error.kind() != std::io::ErrorKind::NotFound
+            {
+                tracing::warn!(error = %error, "failed to clean temporary serve store");
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for ServeHandle {
+    fn drop(&mut self) {
+        if self.temporary
+            && let Err(error) = std::fs::remove_dir_all(&self.store_dir)
+            && error.kind() != std::io::ErrorKind::NotFound
+        {
+            tracing::warn!(error = %error, "failed to clean temporary serve store");
+        }
+    }
+}
+
+fn share_tag(name: &str) -> String {
+    format!("{SHARE_TAG_PREFIX}{name}")
+}
+
+fn default_share_name(paths: &[PathBuf]) -> anyhow::Result<String> {
+    let [path] = paths else {
+        anyhow::bail!("a share with several paths needs an explicit name");
+    };
+    path_name(path)
+}
+
+/// 路径的最后一个组件，`.` 等没有名称的路径先解析为绝对路径。
+fn path_name(path: &Path) -> anyhow::Result<String> {
+    let absolute = std::path::absolute(path)?;
+    absolute
+        .components()
+        .next_back()
+        .and_then(|component| component.as_os_str().to_str())
+        .filter(|name| !name.is_empty() && *name != "/")
+        .map(str::to_string)
+        .with_context(|| format!("cannot derive a share name from {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Sharer, default_share_name, share_tag};
+    use crate::core::options::{RelayModeOption, SendOptions};
+    use std::path::PathBuf;
+
+    #[test]
+    fn share_names_come_from_the_single_path() {
+        assert_eq!(
+            default_share_name(&[PathBuf::from("photos/2025")]).expect("named path"),
+            "2025"
+        );
+        assert!(default_share_name(&[PathBuf::from("a"), PathBuf::from("b")]).is_err());
+        assert_eq!(share_tag("docs"), "sendmer/share/docs");
+    }
+
+    fn sharer_options(store_parent: PathBuf) -> SendOptions {
+        SendOptions {
+            relay_mode: RelayModeOption::Disabled,
+            temp_dir: Some(store_parent),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn sharer_adds_shares_on_one_endpoint_and_cleans_up() {
+        let temp_dir = tempfile::tempdir().expect("temp dir");
+        let store_parent = temp_dir.path().join("stores");
+        std::fs::create_dir_all(&store_parent).expect("create store parent");
+        for name in ["a", "b"] {
+            let dir = temp_dir.path().join(name);
+            std::fs::create_dir_all(&dir).expect("create share dir");
+            std::fs::write(dir.join("file.txt"), name).expect("write file");
+        }
+
+        let sharer = Sharer::new(sharer_options(store_parent.clone()))
+            .await
+            .expect("create sharer");
+        let (a, b) = tokio::join!(
+            sharer.add_share(temp_dir.path().join("a")),
+            sharer.add_share(temp_dir.path().join("b"))
+        );
+        let (a, b) = (a.expect("share a"), b.expect("share b"));
+        assert_eq!(a.name, "a");
+        assert_ne!(a.hash, b.hash);
+        assert_eq!(a.ticket.addr().id, sharer.endpoint_id());
+        assert_eq!(b.ticket.addr().id, sharer.endpoint_id());
+        assert!(sharer.add_share(temp_dir.path().join("a")).await.is_err());
+        assert!(sharer.remove_share("a").await.expect("remove").is_some());
+        assert_eq!(sharer.shares().await.len(), 1);
+
+        sharer.shutdown().await.expect("shutdown");
+        let leftover = std::fs::read_dir(&store_parent)
+            .expect("read stores")
+            .count();
+        assert_eq!(leftover, 0);
+
+        // 未调用 shutdown 直接丢弃时也会删除临时存储
+        let sharer = Sharer::new(sharer_options(store_parent.clone()))
+            .await
+            .expect("create sharer");
+        drop(sharer);
+        let leftover = std::fs::read_dir(&store_parent)
+            .expect("read stores")
+            .count();
+        assert_eq!(leftover, 0);
+    }
+}
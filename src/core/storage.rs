@@ -1,13 +1,22 @@
 //! Shared temporary-directory and blob-store helpers.
 
+use anyhow::Context;
 use data_encoding::HEXLOWER;
-use iroh_blobs::store::fs::FsStore;
+use iroh::SecretKey;
+use iroh_blobs::api::Store;
+use iroh_blobs::store::{fs::FsStore, mem::MemStore};
 use rand::Rng;
+use std::io::Write;
+use std::ops::Deref;
 use std::path::{Path, PathBuf};
 
 pub fn unique_temp_dir(prefix: &str) -> anyhow::Result<PathBuf> {
+    unique_temp_dir_in(&std::env::temp_dir(), prefix)
+}
+
+pub fn unique_temp_dir_in(parent: &Path, prefix: &str) -> anyhow::Result<PathBuf> {
     let suffix = rand::rng().random::<[u8; 16]>();
-    let path = std::env::temp_dir().join(format!("{prefix}{}", HEXLOWER.encode(&suffix)));
+    let path = parent.join(format!("{prefix}{}", HEXLOWER.encode(&suffix)));
 
     if path.exists() {
         anyhow::bail!(
@@ -24,9 +33,184 @@ pub async fn load_fs_store(path: &Path) -> anyhow::Result<FsStore> {
     FsStore::load(path).await
 }
 
+/// 发送端使用的 blob 存储：磁盘存储或纯内存存储。
+#[derive(Debug, Clone)]
+pub enum BlobStore {
+    Fs(FsStore),
+    Mem(MemStore),
+}
+
+impl BlobStore {
+    /// `dir` 为 `None` 时使用内存存储，不在磁盘上留下任何数据。
+    pub async fn load(dir: Option<&Path>) -> anyhow::Result<Self> {
+        match dir {
+            Some(dir) => Ok(Self::Fs(load_fs_store(dir).await?)),
+            None => Ok(Self::Mem(MemStore::new())),
+        }
+    }
+}
+
+impl Deref for BlobStore {
+    type Target = Store;
+
+    fn deref(&self) -> &Store {
+        match self {
+            Self::Fs(store) => store,
+            Self::Mem(store) => store,
+        }
+    }
+}
+
+const SECRET_FILE: &str = "secret.key";
+const TICKET_FILE: &str = "ticket";
+
+/// 读取持久化存储目录中的节点密钥，不存在时生成并写入。
+pub fn load_or_create_secret(dir: &Path) -> anyhow::Result<SecretKey> {
+    let path = dir.join(SECRET_FILE);
+    match std::fs::read_to_string(&path) {
+        Ok(text) => {
+            let bytes: [u8; 32] = HEXLOWER
+                .decode(text.trim().as_bytes())
+                .ok()
+                .and_then(|bytes| bytes.try_into().ok())
+                .with_context(|| format!("invalid secret key in {}", path.display()))?;
+            Ok(SecretKey::from_bytes(&bytes))
+        }
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+            std::fs::create_dir_all(dir)?;
+            let secret = SecretKey::generate(&mut rand::rng());
+            let mut options = std::fs::OpenOptions::new();
+            options.write(true).create_new(true);
+            #[cfg(unix)]
+            std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+            let mut file = options
+                .open(&path)
+                .with_context(|| format!("cannot create {}", path.display()))?;
+            file.write_all(HEXLOWER.encode(&secret.to_bytes()).as_bytes())?;
+            Ok(secret)
+        }
+        Err(error) => Err(error).with_context(|| format!("cannot read {}", path.display())),
+    }
+}
+
+/// 把当前 ticket 写入持久化存储目录，便于重启后或其他脚本读取。
+pub fn save_ticket(dir: &Path, ticket: &str) -> anyhow::Result<()> {
+    let path = dir.join(TICKET_FILE);
+    std::fs::write(&path, format!("{ticket}\n"))
+        .with_context(|| format!("cannot write {}", path.display()))
+}
+
+/// 某个路径所在文件系统的剩余空间。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiskSpace {
+    /// 文件系统所在的设备号，用于判断多个路径是否共享同一块空间
+    pub device: u64,
+    /// 当前用户可用的字节数
+    pub available: u64,
+}
+
+/// 查询 `path` 所在文件系统的剩余空间；路径尚不存在时查询最近的已存在的上级目录。
+///
+/// 无法查询（包括 Unix 与 Windows 之外的平台）时返回 `None`，调用方应跳过检查。
+pub fn disk_space(path: &Path) -> Option<DiskSpace> {
+    let existing = path
+        .ancestors()
+        .map(|ancestor| {
+            if ancestor.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                ancestor
+            }
+        })
+        .find(|ancestor| ancestor.exists())?;
+    platform_disk_space(existing)
+}
+
+#[cfg(unix)]
+fn platform_disk_space(path: &Path) -> Option<DiskSpace> {
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::fs::MetadataExt;
+
+    let device = std::fs::metadata(path).ok()?.dev();
+    let c_path = std::ffi::CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat = std::mem::MaybeUninit::<libc::statvfs>::uninit();
+    // SAFETY: c_path 是以 NUL 结尾的字符串，stat 指向一块足够大的未初始化缓冲区，
+    // statvfs 返回 0 时已完整写入该缓冲区。
+    let stat = unsafe {
+        if libc::statvfs(c_path.as_ptr(), stat.as_mut_ptr()) != 0 {
+            return None;
+        }
+        stat.assume_init()
+    };
+    #[allow(clippy::useless_conversion)]
+    let available = u64::from(stat.f_bavail).saturating_mul(u64::from(stat.f_frsize));
+    Some(DiskSpace { device, available })
+}
+
+/// Windows 上以卷序列号区分文件系统，剩余空间取当前用户可用的字节数（考虑磁盘配额）。
+#[cfg(windows)]
+fn platform_disk_space(path: &Path) -> Option<DiskSpace> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::Storage::FileSystem::{
+        GetDiskFreeSpaceExW, GetVolumeInformationW, GetVolumePathNameW,
+    };
+
+    let wide = path
+        .as_os_str()
+        .encode_wide()
+        .chain(std::iter::once(0))
+        .collect::<Vec<u16>>();
+    let mut root = vec![0u16; wide.len().max(261)];
+    let mut available = 0u64;
+    let mut serial = 0u32;
+    // SAFETY: wide 与 root 都以 NUL 结尾且在调用期间有效，root 的长度如实传入；
+    // 其余输出参数指向有效的局部变量，不需要的输出传空指针，这是 API 允许的。
+    unsafe {
+        if GetVolumePathNameW(
+            wide.as_ptr(),
+            root.as_mut_ptr(),
+            u32::try_from(root.len()).ok()?,
+        ) == 0
+        {
+            return None;
+        }
+        if GetDiskFreeSpaceExW(
+            root.as_ptr(),
+            &mut available,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        ) == 0
+        {
+            return None;
+        }
+        if GetVolumeInformationW(
+            root.as_ptr(),
+            std::ptr::null_mut(),
+            0,
+            &mut serial,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            0,
+        ) == 0
+        {
+            return None;
+        }
+    }
+    Some(DiskSpace {
+        device: u64::from(serial),
+        available,
+    })
+}
+
+#[cfg(not(any(unix, windows)))]
+fn platform_disk_space(_path: &Path) -> Option<DiskSpace> {
+    None
+}
+
 #[cfg(test)]
 mod tests {
-    use super::unique_temp_dir;
+    use super::{disk_space, load_or_create_secret, unique_temp_dir};
 
     #[test]
     fn unique_temp_dir_generates_prefixed_path() {
@@ -40,6 +224,29 @@ mod tests {
         assert!(file_name.starts_with(".sendmer-send-"));
     }
 
+    #[test]
+    fn load_or_create_secret_reuses_persisted_key() {
+        let temp_dir = tempfile::tempdir().expect("temp dir");
+        let store_dir = temp_dir.path().join("store");
+
+        let first = load_or_create_secret(&store_dir).expect("create secret");
+        let second = load_or_create_secret(&store_dir).expect("load secret");
+        assert_eq!(first.public(), second.public());
+
+        std::fs::write(store_dir.join("secret.key"), "not hex").expect("corrupt secret");
+        assert!(load_or_create_secret(&store_dir).is_err());
+    }
+
+    #[cfg(any(unix, windows))]
+    #[test]
+    fn disk_space_falls_back_to_existing_ancestor() {
+        let temp_dir = tempfile::tempdir().expect("temp dir");
+        let existing = disk_space(temp_dir.path()).expect("disk space");
+        let missing = disk_space(&temp_dir.path().join("not/yet/created")).expect("disk space");
+        assert_eq!(existing.device, missing.device);
+        assert!(existing.available > 0);
+    }
+
     #[test]
     fn unique_temp_dir_returns_distinct_paths() {
         let first = unique_temp_dir(".sendmer-recv-").expect("first path");
@@ -0,0 +1,443 @@
+//! sendmer 的 ticket 表示：在 `BlobTicket` 基础上可附带 DNS 地址提示。
+//!
+//! 带提示的 ticket 字符串形如 `<blob-ticket>@<host>:<port>`，接收端解析该域名
+//! 得到直连地址，从而在使用最短的 ID ticket 时也无需依赖 pkarr 等外部基础设施。
+//! 接收端也可以用 [`StaticAddr`] 直接写死某个 endpoint 的地址，完全跳过 pkarr/DNS。
+//! 命令行上的 ticket 还可以来自标准输入、文件或 URL，见 [`TicketSource`]；
+//! 从 URL 下载需要启用 `ticket-url` feature（默认启用）。
+
+use crate::core::error::{CatalogError, ErrorKind};
+use anyhow::Context;
+use iroh_blobs::ticket::BlobTicket;
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// 接收端用于解析直连地址的 DNS 提示。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DnsHint {
+    pub host: String,
+    pub port: u16,
+}
+
+/// 接收端的静态地址覆盖，形如 `<endpoint-id>=<ip:port>`。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StaticAddr {
+    pub id: iroh::EndpointId,
+    pub addr: std::net::SocketAddr,
+}
+
+impl FromStr for StaticAddr {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (id, addr) = s
+            .split_once('=')
+            .context("static address must look like node-id=ip:port")?;
+        Ok(Self {
+            id: id.parse().context("invalid endpoint ID")?,
+            addr: addr.parse().context("invalid socket address")?,
+        })
+    }
+}
+
+/// 带可选 DNS 提示的 ticket。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShareTicket {
+    pub ticket: BlobTicket,
+    pub dns_hint: Option<DnsHint>,
+}
+
+impl DnsHint {
+    /// 由用户给出的 `host[:port]` 构造提示；未给出端口时使用 endpoint 当前直连地址的端口。
+    pub fn for_endpoint(hint: &str, addr: &iroh::EndpointAddr) -> anyhow::Result<Self> {
+        if let Some((host, port)) = hint.rsplit_once(':')
+            && let Ok(port) = port.parse::<u16>()
+        {
+            return Self::new(host, port);
+        }
+
+        let port =
+            addr.ip_addrs().next().map(|addr| addr.port()).context(
+                "no direct address available to derive the DNS hint port, use host:port",
+            )?;
+        Self::new(hint, port)
+    }
+
+    fn new(host: &str, port: u16) -> anyhow::Result<Self> {
+        anyhow::ensure!(!host.is_empty(), "DNS hint host cannot be empty");
+        anyhow::ensure!(
+            !host.contains('@') && !host.contains('/'),
+            "invalid DNS hint host {host:?}"
+        );
+        Ok(Self {
+            host: host.to_string(),
+            port,
+        })
+    }
+}
+
+impl ShareTicket {
+    /// 解析 DNS 提示（若存在）并把得到的地址加入 ticket。
+    pub async fn resolve(self) -> anyhow::Result<BlobTicket> {
+        let Some(hint) = self.dns_hint else {
+            return Ok(self.ticket);
+        };
+
+        let resolved = tokio::net::lookup_host((hint.host.as_str(), hint.port))
+            .await
+            .with_context(|| format!("failed to resolve DNS hint {}", hint.host))?;
+        let addr = resolved.fold(self.ticket.addr().clone(), |addr, ip| addr.with_ip_addr(ip));
+        let hash_and_format = self.ticket.hash_and_format();
+        Ok(BlobTicket::new(
+            addr,
+            hash_and_format.hash,
+            hash_and_format.format,
+        ))
+    }
+
+    /// 与 [`Self::resolve`] 相同，但 `overrides` 中有该 endpoint 的地址时
+    /// 只使用这些地址，丢弃 ticket 中的 relay 与 DNS 提示。
+    pub async fn resolve_with(self, overrides: &[StaticAddr]) -> anyhow::Result<BlobTicket> {
+        let id = self.ticket.addr().id;
+        // 先收集成 Vec：借用 `overrides` 的迭代器若跨越 await，返回的 future 将不满足 `Send`
+        let addrs = overrides
+            .iter()
+            .filter(|entry| entry.id == id)
+            .map(|entry| entry.addr)
+            .collect::<Vec<_>>();
+        if addrs.is_empty() {
+            return self.resolve().await;
+        }
+
+        let addr = addrs
+            .into_iter()
+            .fold(iroh::EndpointAddr::new(id), |addr, ip| {
+                addr.with_ip_addr(ip)
+            });
+        let hash_and_format = self.ticket.hash_and_format();
+        Ok(BlobTicket::new(
+            addr,
+            hash_and_format.hash,
+            hash_and_format.format,
+        ))
+    }
+}
+
+impl From<BlobTicket> for ShareTicket {
+    fn from(ticket: BlobTicket) -> Self {
+        Self {
+            ticket,
+            dns_hint: None,
+        }
+    }
+}
+
+impl FromStr for ShareTicket {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((ticket, hint)) = s.rsplit_once('@') else {
+            return Ok(BlobTicket::from_str(s)?.into());
+        };
+        let (host, port) = hint
+            .rsplit_once(':')
+            .context("DNS hint must look like host:port")?;
+        let port = port.parse::<u16>().context("invalid DNS hint port")?;
+        Ok(Self {
+            ticket: BlobTicket::from_str(ticket)?,
+            dns_hint: Some(DnsHint::new(host, port)?),
+        })
+    }
+}
+
+impl TryFrom<&str> for ShareTicket {
+    type Error = anyhow::Error;
+
+    /// 与 `FromStr` 相同，但解析失败时返回带 [`ErrorKind::InvalidTicket`] 分类的错误。
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        Self::from_str(s).map_err(|error| {
+            CatalogError::new(ErrorKind::InvalidTicket, format!("invalid ticket: {error}")).into()
+        })
+    }
+}
+
+impl Display for ShareTicket {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.dns_hint {
+            Some(hint) => write!(f, "{}@{}:{}", self.ticket, hint.host, hint.port),
+            None => write!(f, "{}", self.ticket),
+        }
+    }
+}
+
+/// ticket 的来源：直接给出、从标准输入或文件读取，或从 URL 下载。
+///
+/// 读取到的文本中第一个能解析为 ticket 的词即为 ticket，因此粘贴板上的
+/// `sendmer receive <ticket>` 整行也可以直接使用。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TicketSource {
+    Ticket(ShareTicket),
+    /// 命令行上写作 `-`
+    Stdin,
+    File(PathBuf),
+    /// `http://` 或 `https://` 链接，响应内容中包含 ticket
+    Url(String),
+}
+
+impl TicketSource {
+    /// 读取来源中的 ticket。
+    pub async fn resolve(&self) -> anyhow::Result<ShareTicket> {
+        let text = match self {
+            Self::Ticket(ticket) => return Ok(ticket.clone()),
+            Self::Stdin => {
+                let mut text = String::new();
+                tokio::io::AsyncReadExt::read_to_string(&mut tokio::io::stdin(), &mut text)
+                    .await
+                    .context("cannot read the ticket from stdin")?;
+                text
+            }
+            Self::File(path) => tokio::fs::read_to_string(path)
+                .await
+                .with_context(|| format!("cannot read the ticket from {}", path.display()))?,
+            Self::Url(url) => fetch_ticket_text(url).await?,
+        };
+        find_ticket(&text)
+    }
+}
+
+impl From<ShareTicket> for TicketSource {
+    fn from(ticket: ShareTicket) -> Self {
+        Self::Ticket(ticket)
+    }
+}
+
+impl FromStr for TicketSource {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "-" {
+            return Ok(Self::Stdin);
+        }
+        if s.starts_with("https://") || s.starts_with("http://") {
+            return Ok(Self::Url(s.to_string()));
+        }
+        Ok(Self::Ticket(ShareTicket::try_from(s)?))
+    }
+}
+
+/// 下载 ticket 时建立连接的超时时间。
+#[cfg(feature = "ticket-url")]
+const TICKET_CONNECT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// 下载 ticket 的总超时时间，包括读取响应。
+#[cfg(feature = "ticket-url")]
+const TICKET_FETCH_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// 下载 ticket 时响应内容的上限；ticket 只有几百字节，更大的响应不会是 ticket 页面。
+#[cfg(feature = "ticket-url")]
+const MAX_TICKET_RESPONSE: usize = 8 * 1024;
+
+#[cfg(feature = "ticket-url")]
+async fn fetch_ticket_text(url: &str) -> anyhow::Result<String> {
+    let client = reqwest::Client::builder()
+        .connect_timeout(TICKET_CONNECT_TIMEOUT)
+        .timeout(TICKET_FETCH_TIMEOUT)
+        .build()?;
+    let mut response = client
+        .get(url)
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .with_context(|| format!("cannot fetch the ticket from {url}"))?;
+    let too_large =
+        || anyhow::anyhow!("the response from {url} is larger than {MAX_TICKET_RESPONSE} bytes");
+    if response
+        .content_length()
+        .is_some_and(|length| length > MAX_TICKET_RESPONSE as u64)
+    {
+        return Err(too_large());
+    }
+    let mut body = Vec::new();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .with_context(|| format!("cannot read the ticket from {url}"))?
+    {
+        if body.len() + chunk.len() > MAX_TICKET_RESPONSE {
+            return Err(too_large());
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(String::from_utf8_lossy(&body).into_owned())
+}
+
+#[cfg(not(feature = "ticket-url"))]
+async fn fetch_ticket_text(url: &str) -> anyhow::Result<String> {
+    anyhow::bail!("cannot fetch the ticket from {url}: built without the `ticket-url` feature")
+}
+
+/// 返回 `text` 中第一个能解析为 ticket 的词。
+fn find_ticket(text: &str) -> anyhow::Result<ShareTicket> {
+    text.split_whitespace()
+        .find_map(|word| ShareTicket::from_str(word).ok())
+        .ok_or_else(|| {
+            CatalogError::new(ErrorKind::InvalidTicket, "no ticket found in the input").into()
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DnsHint, ShareTicket, StaticAddr, TicketSource, find_ticket};
+    use iroh::{EndpointAddr, SecretKey};
+    use iroh_blobs::{BlobFormat, Hash, ticket::BlobTicket};
+    use std::str::FromStr;
+
+    fn id_ticket() -> BlobTicket {
+        let node_id = SecretKey::generate(&mut rand::rng()).public();
+        BlobTicket::new(
+            EndpointAddr::new(node_id),
+            Hash::new(b"demo"),
+            BlobFormat::HashSeq,
+        )
+    }
+
+    #[test]
+    fn share_ticket_round_trips_with_dns_hint() {
+        let ticket = ShareTicket {
+            ticket: id_ticket(),
+            dns_hint: Some(DnsHint {
+                host: "myhost.example.com".to_string(),
+                port: 4433,
+            }),
+        };
+
+        let parsed = ShareTicket::from_str(&ticket.to_string()).expect("ticket should parse");
+        assert_eq!(parsed, ticket);
+    }
+
+    #[test]
+    fn share_ticket_parses_plain_blob_ticket() {
+        let ticket = id_ticket();
+        let parsed = ShareTicket::from_str(&ticket.to_string()).expect("ticket should parse");
+        assert_eq!(parsed.ticket, ticket);
+        assert!(parsed.dns_hint.is_none());
+    }
+
+    #[test]
+    fn share_ticket_try_from_classifies_invalid_ticket() {
+        let err = ShareTicket::try_from("not-a-ticket").expect_err("garbage should fail");
+        assert_eq!(
+            crate::core::error::classify(&err),
+            Some(crate::core::error::ErrorKind::InvalidTicket)
+        );
+    }
+
+    #[test]
+    fn dns_hint_prefers_explicit_port() {
+        let addr = EndpointAddr::new(SecretKey::generate(&mut rand::rng()).public())
+            .with_ip_addr("10.0.0.1:7000".parse().expect("valid socket addr"));
+
+        let explicit = DnsHint::for_endpoint("host.example:9000", &addr).expect("explicit port");
+        assert_eq!(explicit.port, 9000);
+
+        let derived = DnsHint::for_endpoint("host.example", &addr).expect("derived port");
+        assert_eq!(derived.port, 7000);
+        assert_eq!(derived.host, "host.example");
+    }
+
+    #[tokio::test]
+    async fn static_addr_replaces_ticket_addresses() {
+        let ticket = ShareTicket {
+            ticket: id_ticket(),
+            dns_hint: Some(DnsHint {
+                host: "unresolvable.invalid".to_string(),
+                port: 4433,
+            }),
+        };
+        let id = ticket.ticket.addr().id;
+        let entry: StaticAddr = format!("{id}=10.0.0.5:7000")
+            .parse()
+            .expect("static addr should parse");
+
+        let resolved = ticket
+            .resolve_with(&[entry])
+            .await
+            .expect("override applies");
+        let addrs = resolved.addr().ip_addrs().copied().collect::<Vec<_>>();
+        assert_eq!(addrs, vec![entry.addr]);
+        assert!(resolved.addr().relay_urls().next().is_none());
+
+        let err = "10.0.0.5:7000"
+            .parse::<StaticAddr>()
+            .expect_err("missing node id should fail");
+        assert!(err.to_string().contains("node-id=ip:port"));
+    }
+
+    #[test]
+    fn ticket_source_recognizes_stdin_and_urls() {
+        assert_eq!(
+            TicketSource::from_str("-").expect("stdin source"),
+            TicketSource::Stdin
+        );
+        assert_eq!(
+            TicketSource::from_str("https://paste.example/raw/abc").expect("url source"),
+            TicketSource::Url("https://paste.example/raw/abc".to_string())
+        );
+        let ticket = id_ticket();
+        assert_eq!(
+            TicketSource::from_str(&ticket.to_string()).expect("ticket source"),
+            TicketSource::Ticket(ticket.into())
+        );
+        assert!(TicketSource::from_str("not-a-ticket").is_err());
+    }
+
+    #[test]
+    fn find_ticket_skips_surrounding_text() {
+        let ticket = id_ticket();
+        let text = format!("to get this data, use\nsendmer receive {ticket}\n");
+        assert_eq!(find_ticket(&text).expect("ticket in text").ticket, ticket);
+        assert!(find_ticket("nothing to see here").is_err());
+    }
+
+    /// 在本地端口上对一个请求返回 `body`，返回对应的 URL。
+    #[cfg(feature = "ticket-url")]
+    async fn serve_once(body: String) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("bind listener");
+        let addr = listener.local_addr().expect("listener addr");
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.expect("accept request");
+            let mut request = [0u8; 1024];
+            let _ = stream.read(&mut request).await;
+            let response = format!(
+                "HTTP/1.1 200 OK\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            let _ = stream.write_all(response.as_bytes()).await;
+        });
+        format!("http://{addr}/ticket.txt")
+    }
+
+    #[cfg(feature = "ticket-url")]
+    #[tokio::test]
+    async fn url_source_fetches_ticket_and_rejects_large_responses() {
+        let ticket = id_ticket();
+        let url = serve_once(format!("sendmer receive {ticket}\n")).await;
+        let resolved = TicketSource::Url(url)
+            .resolve()
+            .await
+            .expect("ticket from url");
+        assert_eq!(resolved.ticket, ticket);
+
+        let url = serve_once("x".repeat(super::MAX_TICKET_RESPONSE + 1)).await;
+        let error = TicketSource::Url(url)
+            .resolve()
+            .await
+            .expect_err("oversized response should fail");
+        assert!(error.to_string().contains("larger than"), "{error:#}");
+    }
+}
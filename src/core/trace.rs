@@ -0,0 +1,301 @@
+//! 分块级传输审计日志：记录请求、发送、接收的范围及时间戳，用于排查慢传输。
+//!
+//! 日志为紧凑的二进制格式：8 字节文件头 `SMTRACE1`，之后是定长记录，
+//! 每条记录依次为时间（微秒）、类型、连接 ID、请求 ID、偏移量，整数均为小端序。
+//! `sendmer trace analyze` 读取该文件并报告停顿与重传。
+
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::io::{BufWriter, Read, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const TRACE_MAGIC: &[u8; 8] = b"SMTRACE1";
+const RECORD_SIZE: usize = 8 + 1 + 8 + 8 + 8;
+
+/// 记录类型。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TraceKind {
+    /// 接收端发出或发送端收到的请求
+    Requested,
+    /// 发送端已发送到的偏移量
+    Served,
+    /// 接收端已接收到的偏移量
+    Received,
+}
+
+impl TraceKind {
+    const fn to_byte(self) -> u8 {
+        match self {
+            Self::Requested => 0,
+            Self::Served => 1,
+            Self::Received => 2,
+        }
+    }
+
+    fn from_byte(byte: u8) -> anyhow::Result<Self> {
+        match byte {
+            0 => Ok(Self::Requested),
+            1 => Ok(Self::Served),
+            2 => Ok(Self::Received),
+            _ => anyhow::bail!("invalid trace record kind {byte}"),
+        }
+    }
+
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Requested => "requested",
+            Self::Served => "served",
+            Self::Received => "received",
+        }
+    }
+}
+
+/// 单条审计记录。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceRecord {
+    /// 相对日志创建时间的微秒数
+    pub at_micros: u64,
+    pub kind: TraceKind,
+    pub connection: u64,
+    pub request: u64,
+    pub offset: u64,
+}
+
+impl TraceRecord {
+    fn encode(&self) -> [u8; RECORD_SIZE] {
+        let mut buf = [0u8; RECORD_SIZE];
+        buf[0..8].copy_from_slice(&self.at_micros.to_le_bytes());
+        buf[8] = self.kind.to_byte();
+        buf[9..17].copy_from_slice(&self.connection.to_le_bytes());
+        buf[17..25].copy_from_slice(&self.request.to_le_bytes());
+        buf[25..33].copy_from_slice(&self.offset.to_le_bytes());
+        buf
+    }
+
+    fn decode(buf: &[u8; RECORD_SIZE]) -> anyhow::Result<Self> {
+        let u64_at = |start: usize| {
+            let mut bytes = [0u8; 8];
+            bytes.copy_from_slice(&buf[start..start + 8]);
+            u64::from_le_bytes(bytes)
+        };
+        Ok(Self {
+            at_micros: u64_at(0),
+            kind: TraceKind::from_byte(buf[8])?,
+            connection: u64_at(9),
+            request: u64_at(17),
+            offset: u64_at(25),
+        })
+    }
+}
+
+/// 可在多个任务间共享的审计日志写入端。
+#[derive(Clone)]
+pub struct TraceLog {
+    start: Instant,
+    writer: Arc<Mutex<BufWriter<std::fs::File>>>,
+}
+
+impl std::fmt::Debug for TraceLog {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TraceLog").finish_non_exhaustive()
+    }
+}
+
+impl TraceLog {
+    /// 创建（或覆盖）`path` 处的日志文件并写入文件头。
+    pub fn create(path: &Path) -> anyhow::Result<Self> {
+        let mut writer = BufWriter::new(std::fs::File::create(path)?);
+        writer.write_all(TRACE_MAGIC)?;
+        Ok(Self {
+            start: Instant::now(),
+            writer: Arc::new(Mutex::new(writer)),
+        })
+    }
+
+    /// 追加一条记录；写入失败只记录日志，不影响传输本身。
+    pub fn record(&self, kind: TraceKind, connection: u64, request: u64, offset: u64) {
+        let record = TraceRecord {
+            at_micros: u64::try_from(self.start.elapsed().as_micros()).unwrap_or(u64::MAX),
+            kind,
+            connection,
+            request,
+            offset,
+        };
+        let Ok(mut writer) = self.writer.lock() else {
+            return;
+        };
+        if let Err(error) = writer.write_all(&record.encode()) {
+            tracing::warn!(error = %error, "failed to write trace record");
+        }
+    }
+
+    /// 将缓冲区中的记录写入磁盘。
+    pub fn flush(&self) {
+        if let Ok(mut writer) = self.writer.lock()
+            && let Err(error) = writer.flush()
+        {
+            tracing::warn!(error = %error, "failed to flush trace log");
+        }
+    }
+}
+
+/// 读取日志文件中的全部记录。
+pub fn read_trace(path: &Path) -> anyhow::Result<Vec<TraceRecord>> {
+    let mut reader = std::io::BufReader::new(std::fs::File::open(path)?);
+    let mut magic = [0u8; 8];
+    reader.read_exact(&mut magic)?;
+    anyhow::ensure!(
+        &magic == TRACE_MAGIC,
+        "{} is not a trace log",
+        path.display()
+    );
+
+    let mut records = Vec::new();
+    let mut buf = [0u8; RECORD_SIZE];
+    loop {
+        match reader.read_exact(&mut buf) {
+            Ok(()) => records.push(TraceRecord::decode(&buf)?),
+            // 进程被中断时最后一条记录可能不完整，直接忽略
+            Err(error) if error.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(error) => return Err(error.into()),
+        }
+    }
+    Ok(records)
+}
+
+/// 一次停顿：同一数据流中两条相邻记录的间隔超过阈值。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stall {
+    pub kind: TraceKind,
+    pub connection: u64,
+    pub request: u64,
+    pub offset: u64,
+    pub at: Duration,
+    pub duration: Duration,
+}
+
+/// 审计日志的分析结果。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TraceReport {
+    pub records: usize,
+    pub requests: usize,
+    pub duration: Duration,
+    pub stalls: Vec<Stall>,
+    /// 偏移量没有前进（重复或回退）的记录数，通常意味着重传或重新请求
+    pub retransmissions: usize,
+}
+
+/// 按 (类型, 连接, 请求) 分组检查停顿与偏移量回退。
+pub fn analyze(records: &[TraceRecord], stall_threshold: Duration) -> TraceReport {
+    let mut report = TraceReport {
+        records: records.len(),
+        ..Default::default()
+    };
+    let mut last_seen: HashMap<(TraceKind, u64, u64), &TraceRecord> = HashMap::new();
+
+    for record in records {
+        if record.kind == TraceKind::Requested {
+            report.requests += 1;
+        }
+        report.duration = report.duration.max(Duration::from_micros(record.at_micros));
+
+        let key = (record.kind, record.connection, record.request);
+        if let Some(previous) = last_seen.insert(key, record) {
+            let gap = Duration::from_micros(record.at_micros.saturating_sub(previous.at_micros));
+            if gap >= stall_threshold {
+                report.stalls.push(Stall {
+                    kind: record.kind,
+                    connection: record.connection,
+                    request: record.request,
+                    offset: previous.offset,
+                    at: Duration::from_micros(previous.at_micros),
+                    duration: gap,
+                });
+            }
+            if record.kind != TraceKind::Requested && record.offset <= previous.offset {
+                report.retransmissions += 1;
+            }
+        }
+    }
+    report
+}
+
+impl Display for TraceReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{} records, {} requests over {:.3}s",
+            self.records,
+            self.requests,
+            self.duration.as_secs_f64()
+        )?;
+        writeln!(f, "retransmissions: {}", self.retransmissions)?;
+        writeln!(f, "stalls: {}", self.stalls.len())?;
+        for stall in &self.stalls {
+            writeln!(
+                f,
+                "  {:>10.3}s  {:<8} conn {} req {} at offset {} stalled for {:.3}s",
+                stall.at.as_secs_f64(),
+                stall.kind.as_str(),
+                stall.connection,
+                stall.request,
+                stall.offset,
+                stall.duration.as_secs_f64()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TraceKind, TraceLog, TraceRecord, analyze, read_trace};
+    use std::time::Duration;
+
+    fn record(at_ms: u64, kind: TraceKind, offset: u64) -> TraceRecord {
+        TraceRecord {
+            at_micros: at_ms * 1000,
+            kind,
+            connection: 1,
+            request: 0,
+            offset,
+        }
+    }
+
+    #[test]
+    fn trace_log_round_trips_records() {
+        let temp_dir = tempfile::tempdir().expect("temp dir");
+        let path = temp_dir.path().join("transfer.trace");
+
+        let log = TraceLog::create(&path).expect("create trace log");
+        log.record(TraceKind::Requested, 3, 1, 0);
+        log.record(TraceKind::Received, 3, 1, 4096);
+        log.flush();
+
+        let records = read_trace(&path).expect("read trace log");
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].kind, TraceKind::Requested);
+        assert_eq!(records[1].offset, 4096);
+        assert_eq!(records[1].connection, 3);
+    }
+
+    #[test]
+    fn analyze_reports_stalls_and_retransmissions() {
+        let records = vec![
+            record(0, TraceKind::Requested, 0),
+            record(10, TraceKind::Served, 1024),
+            record(20, TraceKind::Served, 2048),
+            record(2020, TraceKind::Served, 2048),
+            record(2030, TraceKind::Served, 4096),
+        ];
+
+        let report = analyze(&records, Duration::from_secs(1));
+        assert_eq!(report.requests, 1);
+        assert_eq!(report.retransmissions, 1);
+        assert_eq!(report.stalls.len(), 1);
+        assert_eq!(report.stalls[0].offset, 2048);
+        assert_eq!(report.stalls[0].duration, Duration::from_secs(2));
+    }
+}
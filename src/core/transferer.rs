@@ -0,0 +1,469 @@
+//! 基于通道的传输调度器：GUI 等宿主只需持有一个长期运行的任务。
+//!
+//! [`Transferer`] 通过 mpsc 通道接收 [`TransfererCommand`]（开始分享、开始下载、取消、查询），
+//! 并把每个传输的事件与生命周期变化以 [`TransfererEvent`] 的形式发送到另一个通道。
+
+use crate::core::error::{Error, ErrorKind};
+use crate::core::events::{EventEmitter, TransferEvent};
+use crate::core::options::{ReceiveOptions, SendOptions};
+use crate::core::receiver::receive_ticket;
+use crate::core::sender::send;
+use crate::core::ticket::ShareTicket;
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::sync::{mpsc, oneshot};
+
+const COMMAND_CHANNEL_CAPACITY: usize = 32;
+/// 保留供查询的已结束传输数量上限，超出后丢弃最早结束的记录。
+const MAX_FINISHED_TASKS: usize = 64;
+
+/// 调度器内单个传输的标识。
+pub type TaskId = u64;
+
+/// 发送给调度器的命令。
+#[derive(Debug)]
+pub enum TransfererCommand {
+    /// 开始分享 `path`
+    StartShare {
+        id: TaskId,
+        path: PathBuf,
+        options: SendOptions,
+    },
+    /// 开始下载 `ticket` 指向的数据
+    StartDownload {
+        id: TaskId,
+        ticket: ShareTicket,
+        options: ReceiveOptions,
+    },
+    /// 取消下载或停止分享
+    Cancel { id: TaskId },
+    /// 查询所有已知传输的状态
+    Query {
+        reply: oneshot::Sender<Vec<TaskStatus>>,
+    },
+}
+
+/// 传输类型。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskKind {
+    Share,
+    Download,
+}
+
+/// 传输的生命周期状态。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TaskState {
+    /// 正在导入数据或正在下载
+    Running,
+    /// 分享已就绪，正在对外提供数据；`expires_at` 为分享自动结束的时间
+    Sharing {
+        ticket: String,
+        expires_at: Option<std::time::SystemTime>,
+    },
+    /// 下载完成，或分享到期、达到下载次数上限后结束
+    Finished { message: String, file_path: PathBuf },
+    /// 传输失败；`kind` 为失败的分类，未归类时为 `None`
+    Failed {
+        message: String,
+        kind: Option<ErrorKind>,
+    },
+    /// 已被取消
+    Cancelled,
+}
+
+impl TaskState {
+    const fn is_active(&self) -> bool {
+        matches!(self, Self::Running | Self::Sharing { .. })
+    }
+}
+
+/// [`TransfererCommand::Query`] 返回的单个传输状态。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaskStatus {
+    pub id: TaskId,
+    pub kind: TaskKind,
+    pub state: TaskState,
+}
+
+/// 调度器发出的事件。
+#[derive(Debug, Clone)]
+pub enum TransfererEvent {
+    /// 传输过程中的进度等事件
+    Transfer { id: TaskId, event: TransferEvent },
+    /// 传输状态发生变化
+    StateChanged { id: TaskId, state: TaskState },
+}
+
+/// 调度器句柄，克隆后可在多处发送命令。
+#[derive(Clone)]
+pub struct Transferer {
+    commands: mpsc::Sender<TransfererCommand>,
+    next_id: Arc<AtomicU64>,
+}
+
+impl Transferer {
+    /// 启动调度器任务，返回命令句柄与事件接收端。
+    ///
+    /// 所有句柄被丢弃后调度器会停止全部传输并退出。
+    pub fn spawn() -> (Self, mpsc::UnboundedReceiver<TransfererEvent>) {
+        let (commands_tx, commands_rx) = mpsc::channel(COMMAND_CHANNEL_CAPACITY);
+        let (events_tx, events_rx) = mpsc::unbounded_channel();
+        tokio::spawn(run(commands_rx, events_tx));
+        (
+            Self {
+                commands: commands_tx,
+                next_id: Arc::new(AtomicU64::new(1)),
+            },
+            events_rx,
+        )
+    }
+
+    /// 分配一个新的传输 ID，用于自行构造 [`TransfererCommand`]。
+    pub fn next_id(&self) -> TaskId {
+        self.next_id.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// 发送原始命令。
+    pub async fn send(&self, command: TransfererCommand) -> anyhow::Result<()> {
+        self.commands
+            .send(command)
+            .await
+            .map_err(|_| anyhow::anyhow!("transferer has stopped"))
+    }
+
+    /// 开始分享 `path`，返回分配的传输 ID。
+    pub async fn start_share(&self, path: PathBuf, options: SendOptions) -> anyhow::Result<TaskId> {
+        let id = self.next_id();
+        self.send(TransfererCommand::StartShare { id, path, options })
+            .await?;
+        Ok(id)
+    }
+
+    /// 开始下载，返回分配的传输 ID。
+    pub async fn start_download(
+        &self,
+        ticket: impl Into<ShareTicket>,
+        options: ReceiveOptions,
+    ) -> anyhow::Result<TaskId> {
+        let id = self.next_id();
+        self.send(TransfererCommand::StartDownload {
+            id,
+            ticket: ticket.into(),
+            options,
+        })
+        .await?;
+        Ok(id)
+    }
+
+    /// 取消下载或停止分享。
+    pub async fn cancel(&self, id: TaskId) -> anyhow::Result<()> {
+        self.send(TransfererCommand::Cancel { id }).await
+    }
+
+    /// 查询所有已知传输的状态。
+    pub async fn query(&self) -> anyhow::Result<Vec<TaskStatus>> {
+        let (reply, rx) = oneshot::channel();
+        self.send(TransfererCommand::Query { reply }).await?;
+        rx.await
+            .map_err(|_| anyhow::anyhow!("transferer has stopped"))
+    }
+}
+
+struct Task {
+    kind: TaskKind,
+    state: TaskState,
+    cancel: Option<oneshot::Sender<()>>,
+    handle: tokio::task::JoinHandle<()>,
+}
+
+/// 调度器持有的传输：进行中的传输，以及最近结束的传输状态。
+///
+/// 传输进入终止状态后即从 `active` 中移除，其任务不再被中止，
+/// 以便取消后仍能关闭存储、清理临时目录；只保留最近 [`MAX_FINISHED_TASKS`] 个结束状态。
+#[derive(Default)]
+struct Tasks {
+    active: HashMap<TaskId, Task>,
+    finished: VecDeque<TaskStatus>,
+}
+
+impl Tasks {
+    fn finish(&mut self, id: TaskId, state: TaskState) {
+        let Some(task) = self.active.remove(&id) else {
+            return;
+        };
+        self.finished.push_back(TaskStatus {
+            id,
+            kind: task.kind,
+            state,
+        });
+        if self.finished.len() > MAX_FINISHED_TASKS {
+            self.finished.pop_front();
+        }
+    }
+
+    fn statuses(&self) -> Vec<TaskStatus> {
+        let mut statuses = self
+            .active
+            .iter()
+            .map(|(id, task)| TaskStatus {
+                id: *id,
+                kind: task.kind,
+                state: task.state.clone(),
+            })
+            .chain(self.finished.iter().cloned())
+            .collect::<Vec<_>>();
+        statuses.sort_by_key(|status| status.id);
+        statuses
+    }
+}
+
+/// 把单个传输的事件转发到调度器的事件通道。
+struct TaskEmitter {
+    id: TaskId,
+    events: mpsc::UnboundedSender<TransfererEvent>,
+}
+
+impl EventEmitter for TaskEmitter {
+    fn emit(&self, event: &TransferEvent) {
+        let _ = self.events.send(TransfererEvent::Transfer {
+            id: self.id,
+            event: event.clone(),
+        });
+    }
+}
+
+async fn run(
+    mut commands: mpsc::Receiver<TransfererCommand>,
+    events: mpsc::UnboundedSender<TransfererEvent>,
+) {
+    let (state_tx, mut state_rx) = mpsc::unbounded_channel::<(TaskId, TaskState)>();
+    let mut tasks = Tasks::default();
+
+    loop {
+        tokio::select! {
+            command = commands.recv() => {
+                let Some(command) = command else {
+                    break;
+                };
+                handle_command(command, &mut tasks, &events, &state_tx);
+            }
+            Some((id, state)) = state_rx.recv() => {
+                let Some(task) = tasks.active.get_mut(&id) else {
+                    continue;
+                };
+                if state.is_active() {
+                    task.state = state.clone();
+                } else {
+                    tasks.finish(id, state.clone());
+                }
+                let _ = events.send(TransfererEvent::StateChanged { id, state });
+            }
+        }
+    }
+    // 所有句柄都已丢弃：停止仍在进行的传输
+    for task in tasks.active.values() {
+        task.handle.abort();
+    }
+}
+
+fn failed_state(error: &Error) -> TaskState {
+    TaskState::Failed {
+        message: error.to_string(),
+        kind: error.kind(),
+    }
+}
+
+fn handle_command(
+    command: TransfererCommand,
+    tasks: &mut Tasks,
+    events: &mpsc::UnboundedSender<TransfererEvent>,
+    state_tx: &mpsc::UnboundedSender<(TaskId, TaskState)>,
+) {
+    match command {
+        TransfererCommand::StartShare { id, path, options } => {
+            let (cancel, cancel_rx) = oneshot::channel();
+            let app_handle = task_app_handle(id, events);
+            let state_tx = state_tx.clone();
+            let handle = tokio::spawn(async move {
+                let result = match send(path.clone(), options, app_handle).await {
+                    Ok(result) => result,
+                    Err(error) => {
+                        let _ = state_tx.send((id, failed_state(&error)));
+                        return;
+                    }
+                };
+                let ticket = result.ticket().to_string();
+                let expires_at = result.expires_at();
+                let _ = state_tx.send((id, TaskState::Sharing { ticket, expires_at }));
+                let finished = tokio::select! {
+                    _ = cancel_rx => None,
+                    () = result.wait_for_expiry() => Some("share expired"),
+                    () = result.wait_for_max_downloads() => Some("download limit reached"),
+                };
+                if let Some(message) = finished {
+                    let _ = state_tx.send((
+                        id,
+                        TaskState::Finished {
+                            message: message.to_string(),
+                            file_path: path,
+                        },
+                    ));
+                }
+                if let Err(error) = result.stop().await {
+                    tracing::warn!(id, error = %error, "failed to shutdown share");
+                }
+            });
+            insert_task(tasks, events, id, TaskKind::Share, cancel, handle);
+        }
+        TransfererCommand::StartDownload {
+            id,
+            ticket,
+            mut options,
+        } => {
+            let (cancel, cancel_rx) = oneshot::channel::<()>();
+            let app_handle = task_app_handle(id, events);
+            let state_tx = state_tx.clone();
+            let token = options.cancel.get_or_insert_with(Default::default).clone();
+            let handle = tokio::spawn(async move {
+                let receive = receive_ticket(ticket, options, app_handle);
+                tokio::pin!(receive);
+                let state = tokio::select! {
+                    result = &mut receive => match result {
+                        Ok(result) => TaskState::Finished {
+                            message: result.message,
+                            file_path: result.file_path,
+                        },
+                        Err(error) => failed_state(&error),
+                    },
+                    _ = cancel_rx => {
+                        // 通知下载自行停止，等它关闭存储并清理临时目录
+                        token.cancel();
+                        let _ = receive.await;
+                        return;
+                    }
+                };
+                let _ = state_tx.send((id, state));
+            });
+            insert_task(tasks, events, id, TaskKind::Download, cancel, handle);
+        }
+        TransfererCommand::Cancel { id } => {
+            if let Some(task) = tasks.active.get_mut(&id) {
+                if let Some(cancel) = task.cancel.take() {
+                    let _ = cancel.send(());
+                }
+                tasks.finish(id, TaskState::Cancelled);
+                let _ = events.send(TransfererEvent::StateChanged {
+                    id,
+                    state: TaskState::Cancelled,
+                });
+            }
+        }
+        TransfererCommand::Query { reply } => {
+            let _ = reply.send(tasks.statuses());
+        }
+    }
+}
+
+fn insert_task(
+    tasks: &mut Tasks,
+    events: &mpsc::UnboundedSender<TransfererEvent>,
+    id: TaskId,
+    kind: TaskKind,
+    cancel: oneshot::Sender<()>,
+    handle: tokio::task::JoinHandle<()>,
+) {
+    let task = Task {
+        kind,
+        state: TaskState::Running,
+        cancel: Some(cancel),
+        handle,
+    };
+    if let Some(replaced) = tasks.active.insert(id, task) {
+        tracing::warn!(id, "replacing transfer with duplicate id");
+        replaced.handle.abort();
+    }
+    let _ = events.send(TransfererEvent::StateChanged {
+        id,
+        state: TaskState::Running,
+    });
+}
+
+fn task_app_handle(
+    id: TaskId,
+    events: &mpsc::UnboundedSender<TransfererEvent>,
+) -> crate::core::events::AppHandle {
+    Some(Arc::new(TaskEmitter {
+        id,
+        events: events.clone(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        MAX_FINISHED_TASKS, Task, TaskKind, TaskState, Tasks, Transferer, TransfererEvent,
+    };
+    use crate::core::options::ReceiveOptions;
+
+    #[tokio::test]
+    async fn cancel_marks_download_cancelled() {
+        let (transferer, mut events) = Transferer::spawn();
+        let node = iroh::SecretKey::generate(&mut rand::rng()).public();
+        let ticket = iroh_blobs::ticket::BlobTicket::new(
+            iroh::EndpointAddr::new(node),
+            iroh_blobs::Hash::new(b"demo"),
+            iroh_blobs::BlobFormat::HashSeq,
+        );
+
+        let id = transferer
+            .start_download(ticket, ReceiveOptions::default())
+            .await
+            .expect("start download");
+        transferer.cancel(id).await.expect("cancel download");
+
+        let statuses = transferer.query().await.expect("query");
+        assert_eq!(statuses.len(), 1);
+        assert_eq!(statuses[0].kind, TaskKind::Download);
+        assert_eq!(statuses[0].state, TaskState::Cancelled);
+
+        let mut states = Vec::new();
+        while let Ok(event) = events.try_recv() {
+            if let TransfererEvent::StateChanged { state, .. } = event {
+                states.push(state);
+            }
+        }
+        assert_eq!(states, vec![TaskState::Running, TaskState::Cancelled]);
+    }
+
+    #[tokio::test]
+    async fn finished_tasks_leave_the_active_set_and_are_capped() {
+        let mut tasks = Tasks::default();
+        let count = MAX_FINISHED_TASKS as u64 + 2;
+        for id in 1..=count {
+            tasks.active.insert(
+                id,
+                Task {
+                    kind: TaskKind::Share,
+                    state: TaskState::Running,
+                    cancel: None,
+                    handle: tokio::spawn(async {}),
+                },
+            );
+        }
+        for id in 1..=count {
+            tasks.finish(id, TaskState::Cancelled);
+        }
+
+        assert!(tasks.active.is_empty());
+        let statuses = tasks.statuses();
+        assert_eq!(statuses.len(), MAX_FINISHED_TASKS);
+        assert_eq!(statuses[0].id, 3);
+        assert!(
+            statuses
+                .iter()
+                .all(|status| status.state == TaskState::Cancelled)
+        );
+    }
+}
@@ -0,0 +1,196 @@
+//! 监听模式的内容更新通知。
+//!
+//! 发送端在独立的 ALPN 上为每个连接打开一条单向流，先写出当前集合的 hash，
+//! 之后每次重新导入得到新集合时再写出新的 hash。持有旧 ticket 的接收端订阅后，
+//! 无需轮询即可得知有新内容，并用新 hash 重新同步。
+
+use crate::core::auth::PasswordGate;
+use crate::core::connect::connect;
+use iroh::endpoint::{Connection, RecvStream};
+use iroh::protocol::{AcceptError, ProtocolHandler};
+use iroh::{Endpoint, EndpointAddr, EndpointId};
+use iroh_blobs::Hash;
+use std::collections::HashSet;
+use std::sync::Arc;
+use tokio::sync::watch;
+
+/// 更新通知使用的 ALPN。
+pub const UPDATES_ALPN: &[u8] = b"sendmer/updates/0";
+
+/// 发送端：把当前集合 hash 的变化推送给订阅的接收端。
+///
+/// 与 blob 连接使用相同的准入规则：设置了允许列表或口令时，只为已放行的 endpoint 推送。
+#[derive(Debug, Clone)]
+pub struct UpdateNotifier {
+    hashes: watch::Receiver<Hash>,
+    allow: Arc<HashSet<EndpointId>>,
+    password_gate: Option<PasswordGate>,
+}
+
+impl UpdateNotifier {
+    pub const fn new(
+        hashes: watch::Receiver<Hash>,
+        allow: Arc<HashSet<EndpointId>>,
+        password_gate: Option<PasswordGate>,
+    ) -> Self {
+        Self {
+            hashes,
+            allow,
+            password_gate,
+        }
+    }
+
+    fn admits(&self, peer: &EndpointId) -> bool {
+        (self.allow.is_empty() || self.allow.contains(peer))
+            && self
+                .password_gate
+                .as_ref()
+                .is_none_or(|gate| gate.is_authorized(peer))
+    }
+
+    async fn handle(&self, connection: Connection) -> anyhow::Result<()> {
+        let peer = connection.remote_id();
+        if !self.admits(&peer) {
+            tracing::warn!(%peer, "rejected update subscription");
+            connection.close(1u32.into(), b"not allowed");
+            return Ok(());
+        }
+        let mut hashes = self.hashes.clone();
+        let mut send = connection.open_uni().await?;
+        loop {
+            let hash = *hashes.borrow_and_update();
+            send.write_all(hash.as_bytes()).await?;
+            tokio::select! {
+                changed = hashes.changed() => {
+                    if changed.is_err() {
+                        // 分享已停止，不会再有新内容
+                        break;
+                    }
+                }
+                _ = connection.closed() => return Ok(()),
+            }
+        }
+        send.finish()?;
+        connection.closed().await;
+        Ok(())
+    }
+}
+
+impl ProtocolHandler for UpdateNotifier {
+    async fn accept(&self, connection: Connection) -> Result<(), AcceptError> {
+        self.handle(connection)
+            .await
+            .map_err(|error| AcceptError::from_err(std::io::Error::other(error)))
+    }
+}
+
+/// 接收端：对一个监听中分享的更新订阅。
+pub struct ShareUpdates {
+    connection: Connection,
+    recv: RecvStream,
+}
+
+impl ShareUpdates {
+    /// 下一个集合 hash；第一个是订阅时的当前内容。发送端停止分享后返回 `None`。
+    pub async fn next(&mut self) -> anyhow::Result<Option<Hash>> {
+        let mut bytes = [0u8; 32];
+        match self.recv.read_exact(&mut bytes).await {
+            Ok(()) => Ok(Some(Hash::from_bytes(bytes))),
+            Err(iroh::endpoint::ReadExactError::FinishedEarly(0)) => Ok(None),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// 取消订阅并关闭连接。
+    pub fn close(self) {
+        self.connection.close(0u32.into(), b"done");
+    }
+}
+
+/// 订阅 `addr` 上监听中分享的内容更新；口令保护的分享需要先调用
+/// [`crate::core::auth::authenticate`]。
+pub async fn subscribe(endpoint: &Endpoint, addr: &EndpointAddr) -> anyhow::Result<ShareUpdates> {
+    let connection = connect(endpoint, addr, UPDATES_ALPN).await?;
+    let recv = connection.accept_uni().await?;
+    Ok(ShareUpdates { connection, recv })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{UPDATES_ALPN, UpdateNotifier, subscribe};
+    use iroh::protocol::Router;
+    use iroh::{Endpoint, EndpointAddr, RelayMode};
+    use iroh_blobs::Hash;
+    use std::sync::Arc;
+    use tokio::sync::watch;
+
+    async fn local_endpoint() -> Endpoint {
+        Endpoint::builder()
+            .relay_mode(RelayMode::Disabled)
+            .bind()
+            .await
+            .expect("bind endpoint")
+    }
+
+    /// 关闭 relay 时 `addr()` 未必带上直连地址，直接使用绑定的本地端口。
+    fn loopback_addr(endpoint: &Endpoint) -> EndpointAddr {
+        let port = endpoint
+            .bound_sockets()
+            .into_iter()
+            .find(std::net::SocketAddr::is_ipv4)
+            .expect("bound IPv4 socket")
+            .port();
+        EndpointAddr::new(endpoint.id()).with_ip_addr(([127, 0, 0, 1], port).into())
+    }
+
+    #[tokio::test]
+    async fn subscribers_receive_current_and_new_hashes() {
+        let (hash_tx, hash_rx) = watch::channel(Hash::new(b"first"));
+        let server = local_endpoint().await;
+        let addr = loopback_addr(&server);
+        let router = Router::builder(server)
+            .accept(
+                UPDATES_ALPN,
+                UpdateNotifier::new(hash_rx, Arc::default(), None),
+            )
+            .spawn();
+        let client = local_endpoint().await;
+
+        let mut updates = subscribe(&client, &addr).await.expect("subscribe");
+        assert_eq!(
+            updates.next().await.expect("first update"),
+            Some(Hash::new(b"first"))
+        );
+        hash_tx.send_replace(Hash::new(b"second"));
+        assert_eq!(
+            updates.next().await.expect("second update"),
+            Some(Hash::new(b"second"))
+        );
+        drop(hash_tx);
+        assert_eq!(updates.next().await.expect("end of updates"), None);
+
+        updates.close();
+        client.close().await;
+        router.shutdown().await.expect("shutdown router");
+    }
+
+    #[tokio::test]
+    async fn unauthorized_peers_are_not_subscribed() {
+        let (_hash_tx, hash_rx) = watch::channel(Hash::new(b"secret"));
+        let server = local_endpoint().await;
+        let addr = loopback_addr(&server);
+        let gate = crate::core::auth::PasswordGate::new("open sesame");
+        let router = Router::builder(server)
+            .accept(
+                UPDATES_ALPN,
+                UpdateNotifier::new(hash_rx, Arc::default(), Some(gate)),
+            )
+            .spawn();
+        let client = local_endpoint().await;
+
+        assert!(subscribe(&client, &addr).await.is_err());
+
+        client.close().await;
+        router.shutdown().await.expect("shutdown router");
+    }
+}
@@ -0,0 +1,92 @@
+//! Blob 完整性校验：重新读取存储中的数据或导出的文件并计算 blake3，与期望 hash 比对。
+
+use anyhow::Context;
+use iroh_blobs::{Hash, api::Store};
+use std::path::Path;
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+const VERIFY_BUFFER_SIZE: usize = 64 * 1024;
+
+/// 读取 `hash` 对应 blob 的全部数据并重新计算其 blake3 hash。
+pub async fn rehash_blob(db: &Store, hash: Hash) -> anyhow::Result<Hash> {
+    Ok(rehash_blob_with_size(db, hash).await?.0)
+}
+
+/// 与 [`rehash_blob`] 相同，同时返回读取到的字节数。
+pub async fn rehash_blob_with_size(db: &Store, hash: Hash) -> anyhow::Result<(Hash, u64)> {
+    hash_reader(db.reader(hash)).await
+}
+
+/// 计算磁盘上文件内容的 blake3 hash 与大小。
+pub async fn hash_file(path: &Path) -> anyhow::Result<(Hash, u64)> {
+    let file = tokio::fs::File::open(path)
+        .await
+        .with_context(|| format!("failed to open {}", path.display()))?;
+    hash_reader(file).await
+}
+
+/// 读完 `reader` 并返回其内容的 blake3 hash 与字节数。
+pub async fn hash_reader(mut reader: impl AsyncRead + Unpin) -> anyhow::Result<(Hash, u64)> {
+    let mut hasher = blake3::Hasher::new();
+    let mut buf = vec![0u8; VERIFY_BUFFER_SIZE];
+    let mut size = 0;
+    loop {
+        let read = reader.read(&mut buf).await?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buf[..read]);
+        size += read as u64;
+    }
+    Ok((Hash::from_bytes(*hasher.finalize().as_bytes()), size))
+}
+
+/// 校验 `hash` 对应的 blob，数据与 hash 不一致时返回错误。
+pub async fn verify_blob(db: &Store, name: &str, hash: Hash) -> anyhow::Result<()> {
+    let actual = rehash_blob(db, hash).await?;
+    anyhow::ensure!(
+        actual == hash,
+        "integrity check failed for {name}: expected {}, got {}",
+        hash.to_hex(),
+        actual.to_hex()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{rehash_blob, rehash_blob_with_size, verify_blob};
+    use iroh_blobs::{Hash, store::mem::MemStore};
+
+    #[tokio::test]
+    async fn rehash_blob_matches_stored_hash() {
+        let store = MemStore::new();
+        let tag = store
+            .add_bytes(b"hello sendmer".to_vec())
+            .temp_tag()
+            .await
+            .expect("add bytes");
+
+        let hash = rehash_blob(&store, tag.hash()).await.expect("rehash");
+        assert_eq!(hash, Hash::new(b"hello sendmer"));
+        verify_blob(&store, "hello", tag.hash())
+            .await
+            .expect("stored blob should verify");
+    }
+
+    #[tokio::test]
+    async fn rehash_blob_with_size_counts_bytes() {
+        let store = MemStore::new();
+        let tag = store
+            .add_bytes(vec![7u8; 100_000])
+            .temp_tag()
+            .await
+            .expect("add bytes");
+
+        let (hash, size) = rehash_blob_with_size(&store, tag.hash())
+            .await
+            .expect("rehash");
+        assert_eq!(hash, tag.hash());
+        assert_eq!(size, 100_000);
+    }
+}
@@ -0,0 +1,192 @@
+//! 监听模式：分享的路径发生变化时重新导入，生成新的集合并刷新 ticket。
+//!
+//! 文件系统事件经过短暂的合并后才触发导入，避免一次保存产生多次导入。
+//! 新 ticket 通过 `ShareHandle` 的 ticket 通道发布，与地址刷新共用同一个通道；
+//! 新集合的 hash 同时推送给通过 [`crate::core::updates`] 订阅的接收端。
+//!
+//! 设置了保留快照数时，每个集合都以带时间戳的 tag 保存，最近 N 个快照的旧 ticket
+//! 在分享期间继续有效；否则只保留当前集合。
+
+use crate::core::code::CodePublisher;
+use crate::core::events::AppHandle;
+use crate::core::events::Role;
+use crate::core::progress::TransferEventEmitter;
+use crate::core::sender::{ImportPolicy, ShareSource, import};
+use iroh_blobs::api::{Store, TempTag};
+use iroh_blobs::{BlobFormat, Hash, HashAndFormat, ticket::BlobTicket};
+use n0_future::StreamExt;
+use n0_future::task::AbortOnDropHandle;
+use notify::{EventKind, RecursiveMode, Watcher};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::{mpsc, watch};
+use tracing::info;
+
+/// 最后一次变化之后等待这么久没有新事件，才开始重新导入。
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// 快照 tag 的前缀，后接补零的毫秒时间戳，按名称排序即按时间排序。
+const SNAPSHOT_TAG_PREFIX: &str = "sendmer/snapshot/";
+
+/// 监听任务所需的状态。
+pub struct WatchContext {
+    pub paths: Vec<PathBuf>,
+    pub store: Store,
+    pub policy: ImportPolicy,
+    /// 使用分享码时，让会合记录指向新集合
+    pub code_publisher: Option<CodePublisher>,
+    pub ticket_tx: watch::Sender<BlobTicket>,
+    pub app_handle: AppHandle,
+    /// 保留的快照数；`None` 时不创建快照
+    pub keep_snapshots: Option<u64>,
+    /// 向订阅更新的接收端推送新集合的 hash
+    pub updates_tx: watch::Sender<Hash>,
+}
+
+/// 开始监听 `context.paths`；返回的句柄被丢弃时停止监听。
+pub fn spawn_watch_task(context: WatchContext) -> anyhow::Result<AbortOnDropHandle<()>> {
+    let (change_tx, mut change_rx) = mpsc::unbounded_channel();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        if event.is_ok_and(|event| is_content_change(&event.kind)) {
+            let _ = change_tx.send(());
+        }
+    })?;
+    for path in &context.paths {
+        watcher.watch(path, RecursiveMode::Recursive)?;
+    }
+
+    Ok(AbortOnDropHandle::new(tokio::spawn(async move {
+        let _watcher = watcher;
+        let emitter = TransferEventEmitter::new(context.app_handle.clone(), Role::Sender);
+        // 持有当前集合的临时 tag，替换时释放上一个
+        let mut current: Option<TempTag> = None;
+        if let Some(keep) = context.keep_snapshots {
+            let initial = context.ticket_tx.borrow().hash();
+            if let Err(error) = save_snapshot(&context.store, initial, keep).await {
+                tracing::warn!(error = %error, "failed to save the initial snapshot");
+            }
+        }
+        while change_rx.recv().await.is_some() {
+            loop {
+                match tokio::time::timeout(WATCH_DEBOUNCE, change_rx.recv()).await {
+                    Ok(Some(())) => {}
+                    Ok(None) => return,
+                    Err(_) => break,
+                }
+            }
+            let source = ShareSource::Paths(context.paths.clone());
+            let imported =
+                match import(source, &context.store, &context.policy, &context.app_handle).await {
+                    Ok(imported) => imported,
+                    Err(error) => {
+                        tracing::warn!(error = %error, "re-import after change failed");
+                        continue;
+                    }
+                };
+            let hash = imported.temp_tag.hash();
+            if context.ticket_tx.borrow().hash() == hash {
+                continue;
+            }
+            if let Some(keep) = context.keep_snapshots
+                && let Err(error) = save_snapshot(&context.store, hash, keep).await
+            {
+                tracing::warn!(error = %error, "failed to save snapshot");
+            }
+            for warning in &imported.warnings {
+                emitter.emit_warning(warning);
+            }
+            if let Some(publisher) = &context.code_publisher
+                && let Err(error) = publisher.update(hash)
+            {
+                tracing::warn!(error = %error, "failed to republish share code");
+            }
+            let ticket = BlobTicket::new(
+                context.ticket_tx.borrow().addr().clone(),
+                hash,
+                BlobFormat::HashSeq,
+            );
+            info!(%hash, size = imported.size, "shared content changed, ticket refreshed");
+            emitter.emit_ticket_refreshed(ticket.to_string());
+            let _ = context.ticket_tx.send(ticket);
+            context.updates_tx.send_replace(hash);
+            drop(current.replace(imported.temp_tag));
+        }
+    })))
+}
+
+/// 以当前时间为名保存集合 `hash` 的快照，并删除最近 `keep` 个之外的旧快照。
+///
+/// 快照 tag 让旧 ticket 指向的数据不被回收；使用持久化存储时也会清理之前运行留下的快照。
+async fn save_snapshot(store: &Store, hash: Hash, keep: u64) -> anyhow::Result<()> {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis());
+    let name = format!("{SNAPSHOT_TAG_PREFIX}{millis:020}");
+    store
+        .tags()
+        .set(&name, HashAndFormat::hash_seq(hash))
+        .await?;
+    info!(tag = %name, %hash, "saved snapshot");
+
+    let mut names = Vec::new();
+    let mut tags = store.tags().list_prefix(SNAPSHOT_TAG_PREFIX).await?;
+    while let Some(tag) = tags.next().await {
+        names.push(tag?.name);
+    }
+    let keep = usize::try_from(keep.max(1)).unwrap_or(usize::MAX);
+    for name in names.iter().rev().skip(keep) {
+        store.tags().delete(name).await?;
+    }
+    Ok(())
+}
+
+/// 只读访问不会改变内容，其余事件都可能改变集合。
+const fn is_content_change(kind: &EventKind) -> bool {
+    !matches!(kind, EventKind::Access(_))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SNAPSHOT_TAG_PREFIX, is_content_change, save_snapshot};
+    use iroh_blobs::store::mem::MemStore;
+    use n0_future::StreamExt;
+    use notify::EventKind;
+    use notify::event::{AccessKind, CreateKind, ModifyKind};
+
+    #[test]
+    fn only_non_access_events_trigger_reimport() {
+        assert!(!is_content_change(&EventKind::Access(AccessKind::Any)));
+        assert!(is_content_change(&EventKind::Create(CreateKind::File)));
+        assert!(is_content_change(&EventKind::Modify(ModifyKind::Any)));
+    }
+
+    #[tokio::test]
+    async fn save_snapshot_keeps_only_the_newest_snapshots() {
+        let store = MemStore::new();
+        let mut hashes = Vec::new();
+        for content in [b"one".as_slice(), b"two", b"three"] {
+            let tag = store
+                .add_bytes(content.to_vec())
+                .temp_tag()
+                .await
+                .expect("add bytes");
+            save_snapshot(&store, tag.hash(), 2)
+                .await
+                .expect("save snapshot");
+            hashes.push(tag.hash());
+            // 快照名精确到毫秒
+            tokio::time::sleep(std::time::Duration::from_millis(2)).await;
+        }
+
+        let mut kept = Vec::new();
+        let mut tags = store
+            .tags()
+            .list_prefix(SNAPSHOT_TAG_PREFIX)
+            .await
+            .expect("list tags");
+        while let Some(tag) = tags.next().await {
+            kept.push(tag.expect("tag").hash);
+        }
+        assert_eq!(kept, hashes[1..]);
+    }
+}
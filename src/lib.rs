@@ -7,11 +7,26 @@
 //! 同时也包含用于命令行工具的包装（`src/bin`）。
 pub mod core;
 
+#[allow(deprecated)]
+pub use core::results::SendResult;
 pub use core::{
     args::{Args, Commands, ReceiveArgs, SendArgs},
-    events::{AppHandle, EventEmitter, Role, TransferEvent, emit_event},
+    blocking,
+    error::{Error, ErrorKind},
+    event_stream::{EventStream, event_stream, receive_with_events, send_with_events},
+    events::{
+        AppHandle, ConnectionVia, EventEmitter, FnEmitter, Role, TransferEvent, TransferWarning,
+        WarningCode, emit_event, on_event,
+    },
     options::{AddrInfoOptions, ReceiveOptions, RelayModeOption, SendOptions, apply_options},
-    receiver::receive,
-    results::{ReceiveResult, SendResult, SenderTransferStatus},
-    sender::send,
+    receiver::{list, receive, receive_ticket},
+    results::{
+        CollectionEntry, CollectionSummary, ConnectionInfo, ReceiveResult, SenderTransferStatus,
+        ShareHandle, ShareStats,
+    },
+    sender::{compute_collection, send, send_paths, send_stdin},
+    serve::{ServeHandle, ServedShare, Sharer, serve},
+    ticket::ShareTicket,
+    transferer::{TaskId, TaskState, TaskStatus, Transferer, TransfererCommand, TransfererEvent},
 };
+pub use tokio_util::sync::CancellationToken;
@@ -152,7 +152,7 @@ fn send_recv_file() {
         relay_mode: Default::default(),
         magic_ipv4_addr: None,
         magic_ipv6_addr: None,
-        retry_policy: Default::default(),
+        ..Default::default()
     };
     let res = rt
         .block_on(async { sendmer::receive(ticket.to_string(), opts, None).await })
@@ -202,10 +202,10 @@ fn send_recv_dir() {
         relay_mode: Default::default(),
         magic_ipv4_addr: None,
         magic_ipv6_addr: None,
-        retry_policy: Default::default(),
+        ..Default::default()
     };
     let res = rt
-        .block_on(async { sendmer::receive(ticket.to_string(), opts, None).await })
+        .block_on(async { sendmer::receive_ticket(ticket, opts, None).await })
         .unwrap();
     send.cleanup();
     assert!(res.message.contains("Downloaded"));
@@ -246,7 +246,7 @@ fn receive_fails_on_existing_target_and_cleans_temp_dir() {
         relay_mode: Default::default(),
         magic_ipv4_addr: None,
         magic_ipv6_addr: None,
-        retry_policy: Default::default(),
+        ..Default::default()
     };
     let err = rt
         .block_on(async { sendmer::receive(ticket.to_string(), opts, None).await })
@@ -292,7 +292,7 @@ fn receive_defaults_to_current_directory_when_output_dir_is_missing() {
         relay_mode: Default::default(),
         magic_ipv4_addr: None,
         magic_ipv6_addr: None,
-        retry_policy: Default::default(),
+        ..Default::default()
     };
     let result = rt.block_on(async { sendmer::receive(ticket.to_string(), opts, None).await });
 